@@ -0,0 +1,230 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use amp_client::client::Client;
+use amp_client::playbooks::PlaybookPayload;
+use amp_common::http::HTTPError;
+use amp_common::resource::PlaybookSpec;
+use reqwest::{Method, RequestBuilder, Response};
+
+use crate::recorder;
+
+type Result<T> = std::result::Result<T, HTTPError>;
+
+/// The idle connections kept open per host by the shared HTTP client.
+const POOL_MAX_IDLE_PER_HOST: usize = 10;
+
+/// The TCP keep-alive interval of the shared HTTP client.
+const TCP_KEEPALIVE: Duration = Duration::from_secs(20);
+
+/// The header the server identifies each request with, worth quoting in bug reports.
+pub const REQUEST_ID: &str = "x-request-id";
+
+/// Build the HTTP client shared by the requests made outside the API client,
+/// such as event streams and remote manifests. It pools and keeps alive its
+/// connections, and clones share the same pool so it can be cheaply handed to
+/// concurrent tasks.
+pub fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .pool_max_idle_per_host(POOL_MAX_IDLE_PER_HOST)
+        .tcp_keepalive(TCP_KEEPALIVE)
+        .build()
+        .unwrap_or_default()
+}
+
+/// The API of a server for the requests made outside the API client, each
+/// of them authorized with the token of the context, if any.
+#[derive(Clone, Debug)]
+pub struct Api {
+    http: reqwest::Client,
+    server: String,
+    token: Option<String>,
+}
+
+impl Api {
+    pub fn new(http: reqwest::Client, server: &str, token: Option<&str>) -> Self {
+        Self { http, server: server.trim_end_matches('/').to_string(), token: token.map(String::from) }
+    }
+
+    /// The URL of the path of the `/v1` API, such as `/playbooks/42`.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/v1{}", self.server, path)
+    }
+
+    /// A request to the path of the `/v1` API.
+    pub fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self.http.request(method, self.url(path));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    pub fn get(&self, path: &str) -> RequestBuilder {
+        self.request(Method::GET, path)
+    }
+
+    pub fn post(&self, path: &str) -> RequestBuilder {
+        self.request(Method::POST, path)
+    }
+
+    pub fn patch(&self, path: &str) -> RequestBuilder {
+        self.request(Method::PATCH, path)
+    }
+
+    pub fn delete(&self, path: &str) -> RequestBuilder {
+        self.request(Method::DELETE, path)
+    }
+}
+
+/// Send the request, noting the status and the request ID of the response for
+/// the recorder of the API call.
+pub async fn send(request: RequestBuilder) -> reqwest::Result<Response> {
+    let response = request.send().await?;
+    let request_id = response.headers().get(REQUEST_ID).and_then(|v| v.to_str().ok());
+    recorder::observe(response.status().as_u16(), request_id);
+    Ok(response)
+}
+
+/// Async counterparts of the `Playbooks` methods of the client.
+///
+/// The client performs blocking I/O, so each call is moved to the blocking
+/// thread pool instead of stalling the async executor.
+pub struct AsyncPlaybooks {
+    client: Arc<Client>,
+}
+
+impl AsyncPlaybooks {
+    pub fn new(client: Arc<Client>) -> Self {
+        Self { client }
+    }
+
+    /// List the playbooks, filtered by the given options.
+    pub async fn list_async(&self, options: Option<HashMap<String, String>>) -> Result<Vec<PlaybookSpec>> {
+        self.blocking(move |client| client.playbooks().list(options)).await
+    }
+
+    /// Create a playbook from the given payload. The commands send the enriched
+    /// payload instead, see `ops::playbooks::create`.
+    #[allow(dead_code)]
+    pub async fn create_async(&self, payload: PlaybookPayload) -> Result<PlaybookSpec> {
+        self.blocking(move |client| client.playbooks().create(payload)).await
+    }
+
+    /// Get the playbook with the given ID.
+    pub async fn get_async(&self, pid: &str) -> Result<PlaybookSpec> {
+        let pid = pid.to_string();
+        self.blocking(move |client| client.playbooks().get(&pid)).await
+    }
+
+    /// Delete the playbook with the given ID, returns the status code.
+    pub async fn delete_async(&self, pid: &str) -> Result<u16> {
+        let pid = pid.to_string();
+        let status = self.blocking(move |client| client.playbooks().delete(&pid)).await?;
+        recorder::observe(status, None);
+        Ok(status)
+    }
+
+    /// Start the playbook with the given ID, returns the status code.
+    pub async fn start_async(&self, pid: &str) -> Result<u16> {
+        let pid = pid.to_string();
+        let status = self.blocking(move |client| client.playbooks().start(&pid)).await?;
+        recorder::observe(status, None);
+        Ok(status)
+    }
+
+    /// Stop the playbook with the given ID, returns the status code.
+    pub async fn stop_async(&self, pid: &str) -> Result<u16> {
+        let pid = pid.to_string();
+        let status = self.blocking(move |client| client.playbooks().stop(&pid)).await?;
+        recorder::observe(status, None);
+        Ok(status)
+    }
+
+    async fn blocking<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Client) -> Result<T> + Send + 'static,
+    {
+        let client = self.client.clone();
+        match tokio::task::spawn_blocking(move || f(&client)).await {
+            Ok(result) => result,
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amp_common::resource::Preface;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn playbooks(server: &MockServer) -> AsyncPlaybooks {
+        let client = Client::new(&format!("{}/v1", server.uri()), None);
+        AsyncPlaybooks::new(Arc::new(client))
+    }
+
+    #[tokio::test]
+    async fn authorize_the_api_requests() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/playbooks"))
+            .and(header("authorization", "Bearer secret"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let api = Api::new(reqwest::Client::new(), &format!("{}/", server.uri()), Some("secret"));
+        assert!(api.get("/playbooks").send().await.unwrap().status().is_success());
+    }
+
+    #[tokio::test]
+    async fn create_async_returns_server_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/playbooks"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let payload = PlaybookPayload {
+            title: "Untitled".to_string(),
+            description: "".to_string(),
+            preface: Preface::registry("demo", "hub", "latest"),
+        };
+        assert!(playbooks(&server).create_async(payload).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn delete_async_returns_status() {
+        let server = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .and(path("/v1/playbooks/42"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert_eq!(playbooks(&server).delete_async("42").await.unwrap(), 204);
+    }
+}
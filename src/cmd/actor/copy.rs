@@ -0,0 +1,107 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use clap::{Args, ValueEnum};
+use reqwest::Method;
+use tracing::info;
+
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::copy::{self, Location, Transferred};
+use crate::ops::discover;
+
+/// Copy files between the local machine and an actor
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// The files to copy, a local path or `<actor>:<path>`
+    source: String,
+
+    /// Where to copy the files, a local directory or `<actor>:<path>`
+    #[arg(required_unless_present = "output")]
+    destination: Option<String>,
+
+    /// The ID of the playbook, discovered from the manifest if omitted
+    #[arg(long)]
+    pid: Option<String>,
+
+    /// Stream the tarball of the actor files to stdout instead of extracting it
+    #[arg(long, value_enum, conflicts_with = "destination")]
+    output: Option<Output>,
+
+    /// Don't show the progress, for CI and scripts
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    no_progress: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Output {
+    /// Write the tarball to stdout
+    Pipe,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let destination = self.destination.as_deref().map(Location::parse);
+        let (name, path, upload) = match (Location::parse(&self.source), &destination) {
+            (Location::Actor { name, path }, Some(Location::Local(_)) | None) => (name, path, false),
+            (Location::Local(_), Some(Location::Actor { name, path })) => (name.clone(), path.clone(), true),
+            _ => return Err(Errors::FailedCopy("exactly one side must be an actor, as <actor>:<path>".into())),
+        };
+
+        let pid = discover::playbook_id(&ctx, self.pid.as_deref()).await?;
+        let method = if upload { Method::PUT } else { Method::GET };
+        let request = copy::request(&ctx.api().await, method, &pid, &name, &path);
+
+        let transferred = match destination {
+            _ if upload => {
+                let payload = copy::pack(self.source.as_ref(), !self.no_progress)?;
+                let files = format!("/actors/{}/{}/files?path={}", pid, name, path);
+                let body = Some(serde_json::json!({ "tarball": format!("<{} bytes>", payload.len()) }));
+                ctx.recorder
+                    .mutate_async("PUT", &files, body, copy::upload(request, payload, !self.no_progress))
+                    .await?
+            }
+            Some(Location::Local(dir)) => {
+                let mut payload = vec![];
+                let transferred = copy::download(
+                    request,
+                    |chunk| {
+                        payload.extend_from_slice(chunk);
+                        Ok(())
+                    },
+                    !self.no_progress,
+                )
+                .await?;
+                copy::unpack(&payload, &dir)?;
+                transferred
+            }
+            _ => return copy::download(request, |chunk| ctx.stdout.write(chunk), false).await.map(|_| ()),
+        };
+
+        report(&transferred);
+        Ok(())
+    }
+}
+
+fn report(transferred: &Transferred) {
+    info!(
+        "Copied {:.1} MB in {:.1}s ({:.1} MB/s)",
+        transferred.bytes as f64 / 1_000_000.0,
+        transferred.elapsed.as_secs_f64(),
+        transferred.rate()
+    );
+}
@@ -0,0 +1,183 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::Args;
+use serde_json::Value;
+use tokio::time::sleep;
+
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::{actors, discover};
+use crate::output;
+use crate::utils;
+
+/// Print the details of an actor, or compare them against a snapshot
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// The name of the actor
+    name: String,
+
+    /// The ID of the playbook, discovered from the manifest if omitted
+    #[arg(long)]
+    pid: Option<String>,
+
+    /// Save the current state of the actor to the given JSON file
+    #[arg(long, value_name = "FILE", conflicts_with = "diff")]
+    snapshot: Option<PathBuf>,
+
+    /// Print a unified diff between the given snapshot and the current state
+    #[arg(long, value_name = "FILE")]
+    diff: Option<PathBuf>,
+
+    /// Keep comparing the state against the initial snapshot until interrupted
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "snapshot")]
+    watch: bool,
+
+    /// The number of seconds between comparisons when watching
+    #[arg(long, value_name = "SECONDS", default_value = "5", requires = "watch")]
+    diff_interval: u64,
+
+    /// Show the recent events of the actor below its details
+    #[arg(long, visible_alias = "events", action = clap::ArgAction::SetTrue, conflicts_with_all = ["snapshot", "diff", "watch"])]
+    show_events: bool,
+
+    /// The number of events to show, the most recent first
+    #[arg(long, value_name = "N", default_value = "10", requires = "show_events")]
+    event_count: usize,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let pid = discover::playbook_id(&ctx, self.pid.as_deref()).await?;
+        let current = self.fetch(&ctx, &pid)?;
+
+        if let Some(path) = &self.snapshot {
+            let content = output::json(&current)?;
+            return utils::atomic_write(path, content.as_bytes()).map_err(Errors::FailedSaveSnapshot);
+        }
+
+        let baseline = match &self.diff {
+            Some(path) => read_snapshot(path)?,
+            None if self.watch => current.clone(),
+            None if self.show_events => {
+                let events = actors::events(&ctx.api().await, &pid, &self.name).await?;
+                return ctx.stdout.print(describe(&current, &events, self.event_count)?);
+            }
+            None => return ctx.stdout.println(ctx.json(&current)?),
+        };
+
+        let mut printed = diff(&baseline, &current);
+        match &printed {
+            Some(diff) => ctx.stdout.print(diff)?,
+            None => ctx.stdout.println("No changes")?,
+        }
+        if !self.watch {
+            return Ok(());
+        }
+
+        loop {
+            tokio::select! {
+                _ = sleep(Duration::from_secs(self.diff_interval)) => {}
+                _ = ctx.shutdown.triggered() => return Ok(()),
+            }
+
+            let changes = diff(&baseline, &self.fetch(&ctx, &pid)?);
+            if changes != printed {
+                ctx.stdout.print(changes.as_deref().unwrap_or("No changes\n"))?;
+                printed = changes;
+            }
+        }
+    }
+
+    fn fetch(&self, ctx: &Context, pid: &str) -> Result<Value> {
+        let path = format!("/actors/{}/{}", pid, self.name);
+        let actor = ctx.recorder.call("GET", &path, || ctx.client.actors().get(pid, &self.name));
+        let actor = actor.map_err(Errors::ActorFetchFailed)?;
+        serde_json::to_value(actor).map_err(Errors::JsonSerializeError)
+    }
+}
+
+fn read_snapshot(path: &Path) -> Result<Value> {
+    let content = fs::read_to_string(path).map_err(Errors::SnapshotReadFailed)?;
+    serde_json::from_str(&content).map_err(|e| Errors::SnapshotReadFailed(e.into()))
+}
+
+/// The details of the actor followed by its recent events, as `<timestamp> <severity> <message>`.
+fn describe(actor: &Value, events: &[Value], count: usize) -> Result<String> {
+    let mut content = output::json(actor)? + "\n\nEvents:\n";
+    let recent = actors::recent_events(events, count);
+    if recent.is_empty() {
+        content.push_str("  <none>\n");
+    }
+    for event in recent {
+        content.push_str(&format!("  {} {} {}\n", event.timestamp, event.severity, event.message));
+    }
+    Ok(content)
+}
+
+/// The colored unified diff from the snapshot to the current state, None if unchanged.
+fn diff(snapshot: &Value, current: &Value) -> Option<String> {
+    let old = serde_json::to_string_pretty(snapshot).unwrap_or_default() + "\n";
+    let new = serde_json::to_string_pretty(current).unwrap_or_default() + "\n";
+    output::diff(&old, &new, "snapshot", "current")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn diff_changed_fields() {
+        let snapshot = json!({"name": "web", "image": "web:1", "replicas": 1});
+        let current = json!({"name": "web", "image": "web:2", "replicas": 1, "port": 80});
+
+        let diff = diff(&snapshot, &current).unwrap();
+        assert!(diff.contains("--- snapshot") && diff.contains("+++ current"));
+        assert!(diff.contains("-  \"image\": \"web:1\","));
+        assert!(diff.contains("+  \"image\": \"web:2\","));
+        assert!(diff.contains("+  \"port\": 80,"));
+        assert!(!diff.contains("-  \"name\""));
+    }
+
+    #[test]
+    fn append_events_after_the_details() {
+        let actor = json!({"name": "web"});
+        let events = vec![
+            json!({"timestamp": "2025-01-02T03:04:05Z", "severity": "Normal", "message": "Pulled image"}),
+            json!({"timestamp": "2025-01-02T03:05:00Z", "severity": "Warning", "message": "Back-off restarting"}),
+        ];
+
+        let content = describe(&actor, &events, 10).unwrap();
+        let expected = format!(
+            "{}\n\nEvents:\n  2025-01-02T03:05:00Z Warning Back-off restarting\n  2025-01-02T03:04:05Z Normal Pulled image\n",
+            output::json(&actor).unwrap()
+        );
+        assert_eq!(content, expected);
+        assert!(describe(&actor, &[], 10).unwrap().ends_with("Events:\n  <none>\n"));
+    }
+
+    #[test]
+    fn diff_unchanged_state() {
+        let value = json!({"name": "web"});
+        assert_eq!(diff(&value, &value), None);
+    }
+}
@@ -0,0 +1,78 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use clap::Args;
+
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::{actors, discover};
+
+/// Run a command in an actor, streaming its output
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// The name of the actor
+    name: String,
+
+    /// The command to run, after `--`
+    #[arg(last = true, required = true)]
+    command: Vec<String>,
+
+    /// Kill the command after the seconds elapsed
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: Option<u64>,
+
+    /// Kill the command at an RFC 3339 timestamp
+    #[arg(long, value_name = "RFC3339", value_parser = parse_deadline, conflicts_with = "timeout")]
+    deadline: Option<DateTime<Utc>>,
+
+    /// The ID of the playbook, discovered from the manifest if omitted
+    #[arg(long)]
+    pid: Option<String>,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let pid = discover::playbook_id(&ctx, self.pid.as_deref()).await?;
+        let api = ctx.api().await;
+
+        let limit = match (self.timeout, self.deadline) {
+            (Some(seconds), _) => Some(Duration::from_secs(seconds)),
+            (_, Some(deadline)) => Some((deadline - Utc::now()).to_std().unwrap_or_default()),
+            _ => None,
+        };
+        if limit == Some(Duration::ZERO) {
+            return Err(Errors::ExecTimeout(self.name.clone()));
+        }
+
+        let path = format!("/playbooks/{}/actors/{}/exec", pid, self.name);
+        let body = serde_json::json!({"command": self.command});
+        let run = actors::exec(&api, &pid, &self.name, &self.command, limit, |chunk| ctx.stdout.write(chunk));
+        ctx.recorder.mutate_async("POST", &path, Some(body), run).await
+    }
+}
+
+fn parse_deadline(s: &str) -> std::result::Result<DateTime<Utc>, String> {
+    let deadline = DateTime::parse_from_rfc3339(s)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| format!("invalid RFC 3339 timestamp `{}`: {}", s, e))?;
+    if deadline <= Utc::now() {
+        return Err(format!("the deadline `{}` is already past", s));
+    }
+    Ok(deadline)
+}
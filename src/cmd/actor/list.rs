@@ -0,0 +1,193 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::IsTerminal;
+use std::sync::Arc;
+
+use clap::Args;
+use serde_json::Value;
+use tabled::Tabled;
+use tokio::time::Duration;
+use tracing::warn;
+
+use crate::context::Context;
+use crate::errors::Result;
+use crate::ops::actors::{self, State, Status};
+use crate::ops::discover;
+use crate::output::{self, Format};
+use crate::refresh::{Refresh, Ticker};
+use crate::tui;
+use crate::utils::selector::{self, Selector};
+
+/// List the actors of a playbook
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// The ID of the playbook, discovered from the manifest if omitted
+    pid: Option<String>,
+
+    /// Find the running playbook matching the name of the manifest, the default without an ID
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "pid")]
+    playbook_from_manifest: bool,
+
+    /// Output format, one of: table, json, csv, name
+    #[arg(short, long, value_enum, default_value_t = Format::Table, env = "AMP_OUTPUT")]
+    output: Format,
+
+    /// Only list the actors in the given state
+    #[arg(long, value_enum, default_value_t)]
+    status: Status,
+
+    /// Only list the actors whose labels match the selector, such as
+    /// `app=backend,version!=v1` or `tier in (web,api)`
+    #[arg(short = 'l', long, value_name = "SELECTOR", value_parser = selector::parse_selector)]
+    selector: Option<Selector>,
+
+    /// List the actors again every given number of seconds, until interrupted
+    #[arg(long, value_name = "SECONDS")]
+    interval: Option<u64>,
+
+    /// Keep refreshing the actors every 2 seconds, or `--interval`, until interrupted
+    #[arg(short, long, action = clap::ArgAction::SetTrue)]
+    watch: bool,
+
+    /// Watch the failed actors, a shorthand for `--status failed --interval 5`
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["status", "interval"])]
+    watching: bool,
+
+    /// Open a full-screen dashboard of the playbooks, their actors and logs,
+    /// refreshed every 2 seconds
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["watch", "watching", "interval"])]
+    tui: bool,
+
+    /// Pick the columns of the table among name, image, status, labels and detail,
+    /// `output.columns.actors` in the configuration
+    #[clap(flatten)]
+    columns: output::Columns,
+
+    #[clap(flatten)]
+    table: output::TableArgs,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        if self.tui {
+            return tui::run(ctx, self.pid.clone()).await;
+        }
+        let pid = discover::playbook_id(&ctx, self.pid.as_deref()).await?;
+        let (status, interval) = match self.watching {
+            true => (Status::Failed, Some(5)),
+            false => (self.status, self.interval.or(self.watch.then_some(2))),
+        };
+        let selector = self.selector.as_ref();
+        let Some(interval) = interval else {
+            let response = actors::list_if_changed(&ctx.api().await, &pid, status, selector, None).await?;
+            let actors = response.data.unwrap_or_default();
+            return self.render(&ctx, actors, status, None);
+        };
+
+        // Poll the actors with conditional requests, so the unchanged lists
+        // are neither downloaded nor printed again, unless to clear the flags.
+        let mut refresh = Refresh::new(std::io::stdout().is_terminal());
+        let mut ticker = Ticker::new(&ctx.shutdown, Duration::from_secs(interval));
+        let (mut etag, mut actors) = (None, None);
+        while ticker.tick().await {
+            let api = ctx.api().await;
+            let response = actors::list_if_changed(&api, &pid, status, selector, etag.as_deref()).await?;
+            etag = response.etag;
+            let changed = response.data.is_some_and(|data| actors.replace(data.clone()) != Some(data));
+            if changed || refresh.flagged() {
+                self.render(&ctx, actors.clone().unwrap_or_default(), status, Some(&mut refresh))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Render the actors, the tables refreshed in place when watching.
+    fn render(&self, ctx: &Context, actors: Vec<Value>, status: Status, refresh: Option<&mut Refresh>) -> Result<()> {
+        let selected = |actor: &Value| self.selector.as_ref().map_or(true, |s| s.matches(&actors::labels(actor)));
+        let actors: Vec<Value> =
+            actors.into_iter().filter(|a| status.matches(&State::from(a)) && selected(a)).collect();
+
+        if actors.is_empty() && refresh.is_none() && self.output == Format::Table {
+            warn!("No actors found");
+            return Ok(());
+        }
+
+        let rows: Vec<ActorTable> = actors.iter().map(|a| ActorTable::new(a, status)).collect();
+        let columns = self.columns.or_configured("actors");
+        match (self.output, refresh) {
+            (Format::Table, Some(refresh)) => {
+                let (headers, rows) =
+                    columns.select_keyed(rows.into_iter().map(|row| (row.name.clone(), row)).collect())?;
+                refresh.show(&ctx.stdout, &refresh.records(headers, rows, self.table.layout()))
+            }
+            (Format::Table, None) => ctx.stdout.println(columns.table(rows, self.table.layout())?),
+            (Format::Json, _) => ctx.stdout.println(ctx.json(&actors)?),
+            (Format::Csv, _) => ctx.stdout.print(output::csv_with_header(&rows, !self.table.no_header)?),
+            (Format::Name, _) => ctx.stdout.print(output::names(rows)),
+        }
+    }
+}
+
+#[derive(Tabled)]
+struct ActorTable {
+    name: String,
+    image: String,
+    status: String,
+    /// The labels as comma-separated `key=value` pairs
+    labels: String,
+    /// The last error of the failed actors, or the estimated wait of the pending ones
+    detail: String,
+}
+
+impl ActorTable {
+    fn new(actor: &Value, filter: Status) -> Self {
+        let string = |key: &str| actor[key].as_str().unwrap_or_default().to_string();
+        let state = State::from(actor);
+        let detail = match filter {
+            Status::Failed => state.error.unwrap_or_default(),
+            Status::Pending => state.wait.map(|s| format!("~{}s", s)).unwrap_or_default(),
+            _ => String::new(),
+        };
+        let mut labels: Vec<String> = actors::labels(actor).into_iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        labels.sort();
+        Self { name: string("name"), image: string("image"), status: state.status, labels: labels.join(","), detail }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn show_details_of_the_filtered_state() {
+        let failed = json!({"name": "api", "image": "api:1", "status": {"state": "Failed", "message": "crash loop"}});
+        assert_eq!(ActorTable::new(&failed, Status::Failed).detail, "crash loop");
+        assert_eq!(ActorTable::new(&failed, Status::All).detail, "");
+
+        let pending = json!({"name": "db", "status": {"phase": "Pending", "estimated_wait": 45}});
+        let row = ActorTable::new(&pending, Status::Pending);
+        assert_eq!((row.status.as_str(), row.detail.as_str()), ("pending", "~45s"));
+    }
+
+    #[test]
+    fn show_the_sorted_labels() {
+        let actor = json!({"name": "db", "labels": {"tier": "db", "app": "shop", "replicas": 2}});
+        assert_eq!(ActorTable::new(&actor, Status::All).labels, "app=shop,tier=db");
+    }
+}
@@ -0,0 +1,155 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use clap::Args;
+
+use crate::context::Context;
+use crate::errors::Result;
+use crate::ops::discover;
+use crate::ops::logger::{self, Filter, Format, Levels, Options, Timestamps};
+use crate::utils::time::parse_time_flag;
+
+/// Stream the logs of an actor
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// The name of the actor
+    #[arg(required_unless_present = "all")]
+    name: Option<String>,
+
+    /// Stream the logs of every actor of the playbook, prefixed with their names
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "name")]
+    all: bool,
+
+    /// The ID of the playbook, discovered from the manifest if omitted
+    #[arg(long)]
+    pid: Option<String>,
+
+    /// Only show lines containing the pattern, may be given multiple times
+    #[arg(long, value_name = "PATTERN", action = clap::ArgAction::Append)]
+    grep: Vec<String>,
+
+    /// Treat the patterns as regular expressions
+    #[arg(long, action = clap::ArgAction::SetTrue, requires = "grep")]
+    regex: bool,
+
+    /// Only show lines NOT matching any of the patterns
+    #[arg(long, action = clap::ArgAction::SetTrue, requires = "grep")]
+    invert_match: bool,
+
+    /// Highlight the matching portions of the lines
+    #[arg(long, action = clap::ArgAction::SetTrue, requires = "grep", conflicts_with = "invert_match")]
+    color: bool,
+
+    /// Show the timestamps in the local timezone, the one named by `TZ` if set, implies --timestamps
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    local_time: bool,
+
+    /// Show the seconds elapsed since the first line instead of the timestamps, implies --timestamps
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "local_time")]
+    relative: bool,
+
+    /// Only show the lines logged since an RFC 3339 timestamp, a duration ago such as 5m, 2h or 1d, or now
+    #[arg(long, value_name = "TIME")]
+    since: Option<String>,
+
+    /// Only show the last N lines logged so far
+    #[arg(long, value_name = "N")]
+    tail: Option<u64>,
+
+    /// Keep streaming the new lines, or exit once the lines logged so far are shown
+    #[arg(long, action = clap::ArgAction::Set, default_value = "true")]
+    follow: bool,
+
+    /// The most memory kept for the last lines of `--tail` without following,
+    /// the oldest lines are dropped beyond it, such as 512KiB or 10MiB
+    #[arg(long, value_name = "BYTES", default_value = "10MiB", value_parser = parse_size)]
+    max_buffer: usize,
+
+    /// Fail rather than drop the oldest lines once `--max-buffer` is reached
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    fail_on_buffer_overflow: bool,
+
+    /// The output format, ndjson wraps each line in a JSON object for the log aggregators
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    output: Format,
+
+    /// The regular expression inferring the level of the lines in the ndjson format,
+    /// its first capture group or else the whole match is the level
+    #[arg(long, value_name = "REGEX")]
+    level_pattern: Option<String>,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let options = Options {
+            filter: Filter::new(&self.grep, self.regex, self.invert_match, self.color && self.output == Format::Text)?,
+            timestamps: self.timestamps(ctx.timestamps),
+            format: self.output,
+            levels: Levels::new(self.level_pattern.as_deref())?,
+            since: self.since.as_deref().map(parse_time_flag).transpose()?,
+            tail: self.tail,
+            follow: self.follow,
+            max_buffer: self.max_buffer,
+            fail_on_overflow: self.fail_on_buffer_overflow,
+        };
+        let pid = discover::playbook_id(&ctx, self.pid.as_deref()).await?;
+        match &self.name {
+            Some(name) => logger::tail(&ctx, &pid, name, &options).await,
+            None => logger::tail_playbook(&ctx, &pid, &options, true).await,
+        }
+    }
+
+    fn timestamps(&self, enabled: bool) -> Timestamps {
+        match (self.relative, self.local_time, enabled) {
+            (true, _, _) => Timestamps::Relative,
+            (_, true, _) => Timestamps::Local,
+            (_, _, true) => Timestamps::Utc,
+            _ => Timestamps::Off,
+        }
+    }
+}
+
+/// Parse a size in bytes, optionally in KiB, MiB or GiB.
+fn parse_size(s: &str) -> std::result::Result<usize, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let unit = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kib" => 1024,
+        "m" | "mib" => 1024 * 1024,
+        "g" | "gib" => 1024 * 1024 * 1024,
+        _ => return Err(format!("invalid size `{}`: expected bytes, or a number of KiB, MiB or GiB", s)),
+    };
+    let number: usize = number.parse().map_err(|_| format!("invalid size `{}`: expected a number of bytes", s))?;
+    number.checked_mul(unit).ok_or_else(|| format!("invalid size `{}`: too large", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_the_buffer_sizes() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("512KiB"), Ok(512 * 1024));
+        assert_eq!(parse_size("10MiB"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("1 gib"), Ok(1024 * 1024 * 1024));
+        assert!(parse_size("MiB").is_err());
+        assert!(parse_size("10MB/s").is_err());
+    }
+}
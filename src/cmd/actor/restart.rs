@@ -0,0 +1,194 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::future::Future;
+use std::sync::Arc;
+
+use clap::Args;
+use tokio::task::JoinSet;
+use tracing::{error, info, warn};
+
+use crate::client::Api;
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::actors::{self, Status};
+use crate::ops::discover;
+
+/// Restart an actor, or every actor of the playbook
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// The name of the actor
+    #[arg(required_unless_present = "all")]
+    name: Option<String>,
+
+    /// Restart every actor of the playbook, all at once unless `--rolling`
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "name")]
+    all: bool,
+
+    /// Restart the actors one at a time, the dependencies first
+    #[arg(long, action = clap::ArgAction::SetTrue, requires = "all")]
+    rolling: bool,
+
+    /// The number of actors to restart at a time when rolling
+    #[arg(long, default_value = "1", requires = "rolling", value_parser = clap::value_parser!(u16).range(1..))]
+    batch_size: u16,
+
+    /// The ID of the playbook, discovered from the manifest if omitted
+    #[arg(long)]
+    pid: Option<String>,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let pid = discover::playbook_id(&ctx, self.pid.as_deref()).await?;
+        let api = ctx.api().await;
+        if let Some(name) = &self.name {
+            restart(&ctx, &api, &pid, name).await?;
+            info!("Restarted actor {}", name);
+            return Ok(());
+        }
+
+        let names = actors::names(&actors::list(&api, &pid, Status::All).await?);
+        if names.is_empty() {
+            warn!("No actors found in playbook {}", pid);
+            return Ok(());
+        }
+        let (names, batch) = match self.rolling {
+            true => {
+                let graph = actors::dependency_graph(&api, &pid).await?;
+                (actors::dependency_order(&names, &graph), self.batch_size as usize)
+            }
+            false => {
+                let batch = names.len();
+                (names, batch)
+            }
+        };
+
+        let mut errors = restart_all(names, batch, |name| {
+            let (ctx, api, pid) = (ctx.clone(), api.clone(), pid.clone());
+            async move { restart(&ctx, &api, &pid, &name).await }
+        })
+        .await;
+        // Every restart is planned in a dry run, none of them failed.
+        errors.retain(|(_, err)| !matches!(err, Errors::DryRun(_)));
+        if !errors.is_empty() {
+            let errors = errors.into_iter().map(|(name, err)| format!("{}: {}", name, err)).collect();
+            return Err(Errors::RollingRestartFailed(errors));
+        }
+
+        Ok(())
+    }
+}
+
+/// Restart the actor, through the recorder.
+pub(crate) async fn restart(ctx: &Context, api: &Api, pid: &str, name: &str) -> Result<()> {
+    let path = format!("/playbooks/{}/actors/{}/actions/restart", pid, name);
+    ctx.recorder.mutate_async("POST", &path, None, actors::restart(api, pid, name)).await
+}
+
+/// Restart the actors in order, `batch` at a time, and collect the errors of
+/// each actor instead of bailing out on the first failure.
+async fn restart_all<F, Fut>(names: Vec<String>, batch: usize, restart: F) -> Vec<(String, Errors)>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let total = names.len();
+    let mut done = 0;
+    let mut errors = vec![];
+
+    for chunk in names.chunks(batch.max(1)) {
+        let mut set = JoinSet::new();
+        for name in chunk {
+            let restart = restart(name.clone());
+            let name = name.clone();
+            set.spawn(async move { (name, restart.await) });
+        }
+
+        while let Some(joined) = set.join_next().await {
+            done += 1;
+            match joined {
+                Ok((name, Ok(()))) => info!("Restarted actor {} ({}/{})", name, done, total),
+                Ok((name, Err(err))) => {
+                    error!("{}", err);
+                    errors.push((name, err));
+                }
+                Err(err) => errors.push((String::from("unknown"), Errors::FailedJoinTask(err))),
+            }
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Restart the actors, recording the peak number of restarts at a time and
+    /// the order they finished in. The restarts of `api` fail.
+    async fn restart(names: &[&str], batch: usize) -> (usize, Vec<String>, Vec<(String, Errors)>) {
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let finished = Arc::new(Mutex::new(vec![]));
+
+        let (running1, peak1, finished1) = (running.clone(), peak.clone(), finished.clone());
+        let names = names.iter().map(|n| n.to_string()).collect();
+        let errors = restart_all(names, batch, move |name| {
+            let (running, peak, finished) = (running1.clone(), peak1.clone(), finished1.clone());
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                finished.lock().unwrap().push(name.clone());
+
+                match name.as_str() {
+                    "api" => Err(Errors::FailedRestartActor(name, "500".into())),
+                    _ => Ok(()),
+                }
+            }
+        })
+        .await;
+
+        let finished = finished.lock().unwrap().clone();
+        (peak.load(Ordering::SeqCst), finished, errors)
+    }
+
+    #[tokio::test]
+    async fn restart_simultaneously() {
+        let (peak, finished, errors) = restart(&["web", "api", "db", "cache"], 4).await;
+        assert_eq!(peak, 4);
+        assert_eq!(finished.len(), 4);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, "api");
+    }
+
+    #[tokio::test]
+    async fn restart_rolling_in_order() {
+        let (peak, finished, errors) = restart(&["db", "cache", "api", "web"], 1).await;
+        assert_eq!(peak, 1);
+        assert_eq!(finished, vec!["db", "cache", "api", "web"]);
+        assert_eq!(errors.len(), 1, "the failure of api doesn't stop the others");
+
+        let (peak, finished, _) = restart(&["db", "cache", "api", "web", "worker"], 2).await;
+        assert_eq!(peak, 2);
+        assert_eq!(finished[4], "worker");
+    }
+}
@@ -0,0 +1,61 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use clap::Args;
+use tracing::info;
+
+use crate::context::Context;
+use crate::errors::Result;
+use crate::utils::{self, cache};
+
+/// Remove the entries of the cache
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// Remove the entries not accessed for the given duration, such as 30d
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, required_unless_present = "all")]
+    older_than: Option<Duration>,
+
+    /// Remove all the entries
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "older_than")]
+    all: bool,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let dir = utils::remote_cache_dir()?;
+        let now = Utc::now();
+        let entries: Vec<cache::Entry> = cache::entries(&dir)?
+            .into_iter()
+            .filter(|e| self.all || self.older_than.is_some_and(|age| e.older_than(age, now)))
+            .collect();
+        if entries.is_empty() {
+            info!("Nothing to clean in {}", dir.display());
+            return Ok(());
+        }
+
+        let size = super::format_size(entries.iter().map(|e| e.size).sum());
+        let message = format!("Remove {} cached repositories ({})?", entries.len(), size);
+        if !ctx.prompter.confirm(&message, false)? {
+            return Ok(());
+        }
+
+        let freed = cache::remove(&dir, &entries)?;
+        ctx.stdout.println(format!("Removed {} entries, freed {}", entries.len(), super::format_size(freed)))
+    }
+}
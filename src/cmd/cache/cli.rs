@@ -0,0 +1,45 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use clap::{Args, Subcommand};
+
+use crate::context::Context;
+use crate::errors::Result;
+
+/// Inspect and prune the cache of remote git repositories
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    Clean(super::clean::Cli),
+    Info(super::info::Cli),
+    Verify(super::verify::Cli),
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        match &self.command {
+            Commands::Clean(cli) => cli.exec(ctx).await,
+            Commands::Info(cli) => cli.exec(ctx).await,
+            Commands::Verify(cli) => cli.exec(ctx).await,
+        }
+    }
+}
@@ -0,0 +1,66 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use chrono::Utc;
+use clap::Args;
+use tabled::Tabled;
+
+use crate::context::Context;
+use crate::errors::Result;
+use crate::output;
+use crate::utils::{self, cache};
+
+/// Show the location, entries and size of the cache
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let dir = utils::remote_cache_dir()?;
+        let entries = cache::entries(&dir)?;
+        let total = entries.iter().map(|e| e.size).sum();
+
+        ctx.stdout.println(format!("Location: {}", dir.display()))?;
+        ctx.stdout.println(format!("Entries:  {}", entries.len()))?;
+        ctx.stdout.println(format!("Size:     {}", super::format_size(total)))?;
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let rows: Vec<EntryTable> = entries
+            .iter()
+            .map(|e| EntryTable {
+                origin: match &e.record {
+                    Some(r) => format!("{}#{}", r.repository, r.reference.as_deref().unwrap_or("HEAD")),
+                    None => format!("{} (unindexed)", e.key),
+                },
+                size: super::format_size(e.size),
+                age: super::format_age(e.accessed, now),
+            })
+            .collect();
+        ctx.stdout.println(format!("\n{}", output::table_with_header(rows, true)))
+    }
+}
+
+#[derive(Tabled)]
+struct EntryTable {
+    origin: String,
+    size: String,
+    /// The time since the last access
+    age: String,
+}
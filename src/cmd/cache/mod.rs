@@ -0,0 +1,70 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+pub mod clean;
+pub mod cli;
+pub mod info;
+pub mod verify;
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+/// The size in a human readable unit.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{}B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", size, UNITS[unit])
+}
+
+/// The time since the last access, to the minute.
+pub(crate) fn format_age(accessed: Option<DateTime<Utc>>, now: DateTime<Utc>) -> String {
+    let Some(age) = accessed.and_then(|a| now.signed_duration_since(a).to_std().ok()) else {
+        return "-".to_string();
+    };
+    if age.as_secs() < 60 {
+        return "just now".to_string();
+    }
+    humantime::format_duration(Duration::from_secs(age.as_secs() / 60 * 60)).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    #[test]
+    fn format_sizes() {
+        assert_eq!(format_size(512), "512B");
+        assert_eq!(format_size(1536), "1.5KiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0GiB");
+    }
+
+    #[test]
+    fn format_ages() {
+        let now = Utc::now();
+        assert_eq!(format_age(None, now), "-");
+        assert_eq!(format_age(Some(now), now), "just now");
+        assert_eq!(format_age(Some(now - TimeDelta::seconds(3 * 86400 + 3725)), now), "3days 1h 2m");
+    }
+}
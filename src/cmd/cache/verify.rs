@@ -0,0 +1,56 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use clap::Args;
+use tracing::{info, warn};
+
+use crate::context::Context;
+use crate::errors::Result;
+use crate::utils::{self, cache};
+
+/// Check the entries of the cache, and fetch the broken ones again
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let dir = utils::remote_cache_dir()?;
+        let entries = cache::entries(&dir)?;
+        let (mut sound, mut refetched, mut removed) = (0, 0, vec![]);
+
+        for entry in entries {
+            if cache::check(&entry).await {
+                sound += 1;
+                continue;
+            }
+            match &entry.record {
+                Some(record) => {
+                    info!("{} is broken, fetching it again", record.repository);
+                    cache::refetch(&dir, &entry.key, record).await?;
+                    refetched += 1;
+                }
+                None => {
+                    warn!("{} is broken and its origin is unknown, removing it", entry.key);
+                    removed.push(entry);
+                }
+            }
+        }
+        cache::remove(&dir, &removed)?;
+
+        ctx.stdout.println(format!("{} sound, {} fetched again, {} removed", sound, refetched, removed.len()))
+    }
+}
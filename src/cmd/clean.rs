@@ -12,13 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::fmt::Display;
 use std::sync::Arc;
 
-use amp_client::client::Client;
 use clap::Args;
-use inquire::Select;
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::context::Context;
 use crate::errors::{Errors, Result};
@@ -30,64 +27,47 @@ pub struct Cli {
     /// The ID of the playbook to delete
     id: Option<String>,
 
-    /// If true, amp will skip yes/no confirmation from the user
-    #[arg(long, action = clap::ArgAction::Set, default_value = "true", env = "AMP_ASSUME_YES")]
-    assume_yes: bool,
-
-    /// If true, amp will skip yes/no confirmation from the user and default to yes
-    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_DRY_RUN")]
-    dry_run: bool,
-
     /// If true, amp will delete all playbooks
-    #[arg(long, action = clap::ArgAction::SetTrue, default_value = "false")]
+    #[arg(long, action = clap::ArgAction::SetTrue, default_value = "false", conflicts_with = "id")]
     all: bool,
 }
 
 impl Cli {
     pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
         if let Some(id) = &self.id {
-            return delete(&ctx.client, id).await;
+            return delete(&ctx, id).await;
         }
 
-        let playbooks = ctx.client.playbooks().list(None).map_err(Errors::ClientError)?;
+        let playbooks = ctx.recorder.call_async("GET", "/playbooks", ctx.playbooks().list_async(None)).await?;
         if playbooks.is_empty() {
-            println!("No playbooks found");
+            warn!("No playbooks found");
             return Ok(());
         }
 
         if self.all {
-            if self.dry_run {
-                println!("Would delete all playbooks");
-                return Ok(());
-            }
-
             for playbook in playbooks {
-                delete(&ctx.client, &playbook.id).await?;
+                match delete(&ctx, &playbook.id).await {
+                    // Plan the deletion of every playbook in a dry run.
+                    Err(Errors::DryRun(_)) => {}
+                    result => result?,
+                }
             }
 
             return Ok(());
         }
 
         // create a options list for the user to select from
-        let options: Vec<OptionItem> = playbooks.iter().map(|p| OptionItem(p.id.clone(), p.title.clone())).collect();
-        let answer = Select::new("Select playbook to delete: ", options).prompt().map_err(Errors::InquireError)?;
-        delete(&ctx.client, answer.0.as_str()).await?;
+        let options: Vec<String> = playbooks.iter().map(|p| format!("#{} {}", p.id, p.title)).collect();
+        let index = ctx.prompter.select("Select playbook to delete: ", &options, None)?;
+        delete(&ctx, &playbooks[index].id).await?;
 
         Ok(())
     }
 }
 
-#[derive(PartialEq)]
-struct OptionItem(String, String);
-
-impl Display for OptionItem {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "#{} {}", self.0, self.1)
-    }
-}
-
-async fn delete(client: &Client, id: &str) -> Result<()> {
-    let status = client.playbooks().delete(id).map_err(Errors::ClientError)?;
+async fn delete(ctx: &Context, id: &str) -> Result<()> {
+    let path = format!("/playbooks/{}", id);
+    let status = ctx.recorder.mutate_async("DELETE", &path, None, ctx.playbooks().delete_async(id)).await?;
     if status != 204 {
         return Err(Errors::FailedDeletePlaybook(id.to_string()));
     }
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use clap::{Parser, Subcommand};
@@ -19,6 +20,8 @@ use clap_verbosity_flag::{InfoLevel, Verbosity};
 
 use crate::context::Context;
 use crate::errors::Result;
+use crate::recorder::Mode;
+use crate::utils::{parse_key_value, CachePolicy};
 
 pub const AFTER_HELP_STRING: &str =
     "Use \"amp options\" for a list of global command-line options (applies to all commands).";
@@ -38,6 +41,10 @@ pub struct Cli {
     #[command(subcommand)]
     command: Commands,
 
+    /// If true, amp will skip yes/no confirmation from the user
+    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_ASSUME_YES", global = true)]
+    pub assume_yes: bool,
+
     /// File for global configurations
     #[arg(short, long, default_value = DEFAULT_CONFIG_FILEPATH, env = "AMP_CONFIG", global=true)]
     config: Option<String>,
@@ -48,32 +55,83 @@ pub struct Cli {
 
     /// Print timestamps in logs
     #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_TIMESTAMPS", global=true)]
-    timestamps: bool,
+    pub timestamps: bool,
 
     /// Check for a more recent version of Amphitheatre
     #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_UPDATE_CHECK", global=true)]
     update_check: bool,
 
+    /// Print each API call to stderr, use `full` to include redacted bodies
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "summary", env = "AMP_DEBUG_HTTP", global = true)]
+    pub debug_http: Option<Mode>,
+
+    /// The directory the manifest paths are relative to, defaults to the working directory
+    #[arg(long, env = "AMP_WORKSPACE", global = true)]
+    pub workspace: Option<PathBuf>,
+
+    /// When to sync the cached clones of remote git manifests
+    #[arg(long, value_enum, default_value_t, env = "AMP_SYNC_REMOTE_CACHE", global = true)]
+    pub sync_remote_cache: CachePolicy,
+
+    /// Set a variable for the `${VAR}` placeholders of the manifest, may be given multiple times
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_key_value, global = true)]
+    pub set: Vec<(String, String)>,
+
+    /// Apply the `[env.<name>]` overrides of the manifest, after the profiles
+    #[arg(long, value_name = "NAME", env = "AMP_ENV", global = true)]
+    pub env: Option<String>,
+
+    /// Send the manifest as is, without expanding its `${VAR}` placeholders
+    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_NO_SUBSTITUTION", global = true)]
+    pub no_substitution: bool,
+
+    /// Go through the local work, then print the API calls that would change
+    /// anything instead of sending them
+    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_DRY_RUN", global = true)]
+    pub dry_run: bool,
+
+    /// Validate the current context before running the command
+    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_VALIDATE_CONTEXT", global = true)]
+    pub validate_context: bool,
+
     /// Log level: one of [panic fatal error warning info debug trace]
-    #[arg(long, default_value = "warning", env = "AMP_VERBOSITY", global = true)]
+    #[arg(
+        long,
+        default_value = "warning",
+        env = "AMP_VERBOSITY",
+        global = true,
+        conflicts_with_all = ["verbose", "quiet"]
+    )]
     verbosity: String,
 }
 
 #[derive(Subcommand, Debug)]
 enum Commands {
+    Actor(super::actor::cli::Cli),
+    Cache(super::cache::cli::Cli),
     Clean(super::clean::Cli),
     Context(super::context::cli::Cli),
     Completion(super::completion::Cli),
     Config(super::config::cli::Cli),
+    Create(super::create::Cli),
     Debug(super::debug::Cli),
     Deploy(super::deploy::Cli),
     Dev(super::dev::Cli),
+    #[command(alias = "doctor")]
     Diagnose(super::diagnose::Cli),
+    Diff(super::diff::Cli),
+    Fix(super::fix::Cli),
+    #[command(alias = "scaffold")]
     Init(super::init::Cli),
-    List(super::list::Cli),
+    #[command(alias = "validate")]
+    Lint(super::lint::Cli),
+    List(super::playbook::list::Cli),
     Options(super::options::Cli),
+    Playbook(super::playbook::cli::Cli),
     Render(super::render::Cli),
     Run(super::run::Cli),
+    Schema(super::schema::Cli),
+    Status(super::actor::list::Cli),
     Test(super::test::Cli),
     Version(super::version::Cli),
 }
@@ -81,19 +139,28 @@ enum Commands {
 impl Cli {
     pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
         match &self.command {
+            Commands::Actor(cli) => cli.exec(ctx).await,
+            Commands::Cache(cli) => cli.exec(ctx).await,
             Commands::Clean(cli) => cli.exec(ctx).await,
             Commands::Context(cli) => cli.exec(ctx).await,
             Commands::Completion(cli) => cli.exec(),
             Commands::Config(cli) => cli.exec(ctx).await,
+            Commands::Create(cli) => cli.exec(ctx).await,
             Commands::Debug(cli) => cli.exec(ctx).await,
             Commands::Deploy(cli) => cli.exec(ctx).await,
             Commands::Dev(cli) => cli.exec(ctx).await,
             Commands::Diagnose(cli) => cli.exec(ctx).await,
+            Commands::Diff(cli) => cli.exec(ctx).await,
+            Commands::Fix(cli) => cli.exec(ctx).await,
             Commands::Init(cli) => cli.exec(ctx).await,
+            Commands::Lint(cli) => cli.exec(ctx).await,
             Commands::List(cli) => cli.exec(ctx).await,
             Commands::Options(cli) => cli.exec(),
+            Commands::Playbook(cli) => cli.exec(ctx).await,
             Commands::Render(cli) => cli.exec(ctx).await,
             Commands::Run(cli) => cli.exec(ctx).await,
+            Commands::Schema(cli) => cli.exec(ctx).await,
+            Commands::Status(cli) => cli.exec(ctx).await,
             Commands::Test(cli) => cli.exec(ctx).await,
             Commands::Version(cli) => cli.exec(),
         }
@@ -105,3 +172,189 @@ fn verify_cli() {
     use clap::CommandFactory;
     Cli::command().debug_assert()
 }
+
+#[test]
+fn verify_conflicting_flags() {
+    let forbidden: &[&[&str]] = &[
+        &["amp", "dev", "-q", "-v"],
+        &["amp", "dev", "--verbosity", "debug", "-v"],
+        &["amp", "list", "--quiet", "--verbose"],
+        &["amp", "list", "-q", "--verbosity", "info"],
+        &["amp", "clean", "42", "--all"],
+        &["amp", "run", "--git", "https://github.com/amphitheatre-app/amp-example-go", "--name", "demo"],
+        &["amp", "run", "--name", "demo", "--filename", ".amp.toml"],
+        &["amp", "actor", "list", "--watching", "--status", "running"],
+        &["amp", "list", "--output", "template"],
+        &["amp", "cache", "clean"],
+        &["amp", "actor", "copy", "web:/app"],
+        &["amp", "playbook", "list", "--interval", "5"],
+        &["amp", "list", "--wide", "--output", "json"],
+        &["amp", "actor", "copy", "web:/app", "./out", "--output", "pipe"],
+        &["amp", "cache", "clean", "--all", "--older-than", "30d"],
+        &["amp", "dev", "--only", "web"],
+        &["amp", "dev", "--all", "--filename", ".amp.toml"],
+        &["amp", "playbook", "create", "--timeout", "60"],
+        &["amp", "playbook", "create", "--wait", "--poll-interval", "0"],
+        &["amp", "run", "--timeout", "0"],
+        &["amp", "actor", "describe", "web", "--event-count", "5"],
+        &["amp", "actor", "describe", "web", "--show-events", "--watch"],
+        &["amp", "deploy", "--images", "web"],
+        &["amp", "deploy", "-i", "web=Web:v1"],
+        &["amp", "run", "--name", "demo", "--images", "web=web:v1"],
+        &["amp", "context", "add", "--skip-validation", "--validate-connection", "true"],
+        &["amp", "actor", "logs"],
+        &["amp", "actor", "logs", "web", "--all"],
+        &["amp", "playbook", "logs"],
+        &["amp", "create"],
+        &["amp", "playbook", "stop", "--all", "--wait"],
+        &["amp", "playbook", "stop", "42", "--force"],
+        &["amp", "playbook", "stop", "42", "--timeout", "30"],
+        &["amp", "actor", "restart"],
+        &["amp", "actor", "restart", "web", "--all"],
+        &["amp", "actor", "restart", "web", "--rolling"],
+        &["amp", "actor", "restart", "--all", "--batch-size", "2"],
+        &["amp", "actor", "restart", "--all", "--rolling", "--batch-size", "0"],
+        &["amp", "actor", "exec", "web"],
+        &["amp", "actor", "exec", "web", "--timeout", "0", "--", "ls"],
+        &["amp", "actor", "exec", "web", "--deadline", "tomorrow", "--", "ls"],
+        &["amp", "actor", "exec", "web", "--deadline", "2000-01-01T00:00:00Z", "--", "ls"],
+        &["amp", "actor", "exec", "web", "--timeout", "30", "--deadline", "2030-01-01T00:00:00Z", "--", "ls"],
+        &["amp", "dev", "--no-live", "--trigger", "polling"],
+        &["amp", "dev", "--no-live", "--tail", "true"],
+        &["amp", "dev", "--no-live", "--all"],
+        &["amp", "playbook", "start", "42", "--actor", "web"],
+        &["amp", "playbook", "start", "42", "--wait", "--timeout", "0"],
+        &["amp", "create", "--list", "--from-template", "hello-world"],
+        &["amp", "list", "--sort-by"],
+        &["amp", "status", "--wide", "-o", "csv"],
+        &["amp", "playbook", "list", "--no-columns", "--columns", "id"],
+        &["amp", "init", "--var", "NAME=demo"],
+        &["amp", "playbook", "create", "--ref", "main"],
+        &["amp", "playbook", "create", "--from-git", "https://github.com/org/repo", "--filename", ".amp.toml"],
+        &["amp", "init", "--template", "todo", "--name", "demo"],
+        &["amp", "init", "--interactive", "--non-interactive"],
+        &["amp", "init", "--template", "todo", "--interactive"],
+        &["amp", "init", "--template", "todo", "--port", "8080"],
+        &["amp", "status", "--tui", "--watch"],
+        &["amp", "list", "--fields", "id", "--watch"],
+        &["amp", "context", "show", "--mask-token", "--unmask-token"],
+        &["amp", "playbook", "events", "42", "--replay", "--follow"],
+        &["amp", "playbook", "events", "42", "--speed", "2"],
+        &["amp", "playbook", "events", "42", "--replay", "--speed", "-1"],
+    ];
+    for args in forbidden {
+        assert!(Cli::try_parse_from(*args).is_err(), "expected {:?} to be rejected", args);
+    }
+
+    let allowed: &[&[&str]] = &[
+        &["amp", "dev", "-v"],
+        &["amp", "list", "-q"],
+        &["amp", "clean", "--all", "--dry-run"],
+        &["amp", "clean", "--dry-run"],
+        &["amp", "--dry-run", "dev"],
+        &["amp", "playbook", "stop", "42", "--dry-run"],
+        &["amp", "validate", "--schema", "--remote"],
+        &["amp", "actor", "list", "--status", "pending", "--interval", "10"],
+        &["amp", "playbook", "list", "--output", "template", "{{id}} {{title}}"],
+        &["amp", "playbook", "events", "42", "--format", "tap"],
+        &["amp", "playbook", "rename", "42", "Storefront", "--confirm"],
+        &["amp", "playbook", "events", "42", "--replay", "--speed", "0", "--stop-at", "7"],
+        &["amp", "cache", "clean", "--older-than", "30d"],
+        &["amp", "actor", "copy", "web:/app", "--output", "pipe"],
+        &["amp", "actor", "exec", "web", "--timeout", "30", "--", "ls", "-la"],
+        &["amp", "actor", "exec", "web", "--deadline", "2030-01-01T00:00:00Z", "--", "ls"],
+        &["amp", "list", "--watch", "--interval", "1"],
+        &["amp", "list", "--output", "wide"],
+        &["amp", "playbook", "list", "--wide"],
+        &["amp", "actor", "copy", "./dist", "web:/app", "--no-progress"],
+        &["amp", "dev", "--all", "--only", "web,api"],
+        &["amp", "init", "--template", "todo", "--var", "NAME=demo", "--var", "REPO=https://github.com/org"],
+        &[
+            "amp",
+            "init",
+            "--non-interactive",
+            "--language",
+            "node",
+            "--build",
+            "dockerfile",
+            "--port",
+            "80",
+            "--port",
+            "443",
+            "--env-var",
+            "LOG=info",
+            "--env",
+            "staging",
+        ],
+        &["amp", "init", "--interactive", "--description", "The shop"],
+        &["amp", "playbook", "create", "--from-git", "git@github.com:org/repo.git", "--ref", "v1", "--subpath", "api"],
+        &["amp", "playbook", "create", "--wait", "--poll-interval", "5", "--timeout", "60"],
+        &["amp", "run", "--follow", "--timeout", "60"],
+        &["amp", "actor", "describe", "web", "--events", "--event-count", "5"],
+        &[
+            "amp",
+            "deploy",
+            "-i",
+            "web=ghcr.io/org/web:v1",
+            "-i",
+            "api=api@sha256:9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+        ],
+        &["amp", "run", "--images", "web=web:v1,api=api:v2", "--file-input", "build.json"],
+        &["amp", "dev", "--file-input", "build.json"],
+        &["amp", "context", "add", "--skip-validation"],
+        &["amp", "context", "add", "--validate-connection", "false"],
+        &["amp", "actor", "logs", "--all", "--tail", "100", "--follow", "false"],
+        &["amp", "actor", "logs", "web", "-o", "ndjson", "--level-pattern", "level=(\\w+)"],
+        &[
+            "amp",
+            "actor",
+            "logs",
+            "web",
+            "--tail",
+            "100",
+            "--follow",
+            "false",
+            "--max-buffer",
+            "1MiB",
+            "--fail-on-buffer-overflow",
+        ],
+        &["amp", "create", "--list"],
+        &["amp", "playbook", "stop", "42", "--wait", "--timeout", "30", "--force"],
+        &["amp", "status", "-w"],
+        &["amp", "status", "42", "--tui"],
+        &["amp", "playbook", "watch"],
+        &["amp", "playbook", "watch", "42"],
+        &["amp", "playbook", "export", "42"],
+        &["amp", "playbook", "export", "42", "--format", "helm", "--output-dir", "chart"],
+        &["amp", "playbook", "export", "--format", "docker-compose"],
+        &["amp", "playbook", "import", "web.json", "--dry-run"],
+        &["amp", "status", "42", "--selector", "app=backend,tier in (web,api)"],
+        &["amp", "status", "42", "--watch", "--interval", "5"],
+        &["amp", "actor", "list", "--watching", "-w"],
+        &["amp", "actor", "restart", "web", "--pid", "42"],
+        &["amp", "actor", "restart", "--all"],
+        &["amp", "actor", "restart", "--all", "--rolling", "--batch-size", "2"],
+        &["amp", "dev", "--no-live", "--no-partners", "--per-branch"],
+        &["amp", "playbook", "start", "42"],
+        &["amp", "playbook", "start", "42", "--wait", "--actor", "web", "--timeout", "300"],
+        &["amp", "create", "--from-template", "hello-world", "--set", "GREETING=hi", "--timeout", "60"],
+        &["amp", "playbook", "logs", "42", "--tail", "10", "--since", "5m", "--grep", "error", "--no-prefix"],
+        &["amp", "list", "--columns", "id,title,status,age", "--sort-by", "age"],
+        &["amp", "list", "--sort", "status:asc,created_at:desc"],
+        &["amp", "list", "--fields", "id,title,status", "-o", "json"],
+        &["amp", "context", "list", "--columns", "name,server"],
+        &["amp", "status", "-w", "--columns", "name,status", "--sort-by", "status"],
+        &["amp", "list", "-o", "name"],
+        &["amp", "list", "-o", "count"],
+        &["amp", "playbook", "list", "--output", "none", "--search", "web"],
+        &["amp", "list", "--no-headers", "--wide"],
+        &["amp", "context", "list", "--no-header", "--wide"],
+        &["amp", "context", "show", "-o", "yaml", "--unmask-token"],
+        &["amp", "actor", "list", "42", "-o", "name"],
+        &["amp", "playbook", "list", "--output", "table", "--columns", "id,title,status,created_at"],
+        &["amp", "playbook", "list", "--no-columns"],
+    ];
+    for args in allowed {
+        assert!(Cli::try_parse_from(*args).is_ok(), "expected {:?} to be accepted", args);
+    }
+}
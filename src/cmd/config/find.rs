@@ -26,9 +26,9 @@ use crate::errors::{Errors, Result};
 pub struct Cli {}
 
 impl Cli {
-    pub async fn exec(&self, _ctx: Arc<Context>) -> Result<()> {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
         let path = Configuration::path().map_err(Errors::InvalidConfigPath)?.display().to_string();
-        println!("{}", path.replace(' ', r"\ "));
+        ctx.stdout.println(path.replace(' ', r"\ "))?;
 
         Ok(())
     }
@@ -0,0 +1,179 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use amp_common::config::Cluster;
+use clap::Args;
+use tracing::{error, info, warn};
+
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::connection;
+use crate::prompt::Prompter;
+use crate::utils;
+use crate::utils::kubeconfig::{Credentials, Kubeconfig};
+
+/// Add a context, interactively or from a Kubernetes kubeconfig
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// The name of the context, defaults to the name of the kubeconfig context
+    #[arg(requires = "from_kubeconfig")]
+    name: Option<String>,
+
+    /// Derive the context from a kubeconfig, the one of kubectl if no path is given
+    #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "")]
+    from_kubeconfig: Option<PathBuf>,
+
+    /// Check the server is reachable and accepts the token before saving the context
+    #[arg(long, action = clap::ArgAction::Set, default_value = "true")]
+    validate_connection: bool,
+
+    /// Save the context without checking the connection, such as in CI before the server is up
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "validate_connection")]
+    skip_validation: bool,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let (name, cluster) = match &self.from_kubeconfig {
+            Some(path) => {
+                let path = match path.as_os_str().is_empty() {
+                    true => Kubeconfig::default_path().ok_or_else(|| {
+                        Errors::KubeconfigParseFailed(String::from("no kubeconfig found, pass its path"))
+                    })?,
+                    false => path.clone(),
+                };
+                let kubeconfig = Kubeconfig::load(&path)?;
+                from_kubeconfig(ctx.prompter.as_ref(), &kubeconfig, self.name.as_deref())?
+            }
+            None => super::using::inquire(ctx.prompter.as_ref())?,
+        };
+
+        if self.validate_connection && !self.skip_validation {
+            let result = connection::validate(&ctx.http, &cluster).await;
+            confirm_save(ctx.prompter.as_ref(), result)?;
+        }
+
+        let mut configuration = ctx.configuration.write().await;
+        let context = configuration.context.as_mut().ok_or(Errors::NotFoundContexts)?;
+        context.add(&name, cluster).map_err(Errors::FailedAddContext)?;
+        utils::save_configuration(&configuration)?;
+
+        info!("Added the context {}", name);
+        Ok(())
+    }
+}
+
+/// Keep saving the context when the connection is valid, or when the user
+/// confirms despite the error.
+fn confirm_save(prompter: &dyn Prompter, result: Result<()>) -> Result<()> {
+    let Err(err) = result else {
+        info!("The connection to the server is valid");
+        return Ok(());
+    };
+
+    error!("{}", err);
+    match prompter.confirm("Save anyway?", false)? {
+        true => Ok(()),
+        false => Err(err),
+    }
+}
+
+/// Pick a context of the kubeconfig, the current one by default, and derive
+/// the cluster from its server and credentials. Amphitheatre authenticates
+/// with a token, so it is asked for when the user has none.
+fn from_kubeconfig(prompter: &dyn Prompter, kubeconfig: &Kubeconfig, name: Option<&str>) -> Result<(String, Cluster)> {
+    let contexts = kubeconfig.contexts();
+    if contexts.is_empty() {
+        return Err(Errors::KubeconfigParseFailed(String::from("no contexts are defined")));
+    }
+    let current = kubeconfig.current_context.as_ref().and_then(|c| contexts.iter().position(|n| n == c));
+    let selected = &contexts[prompter.select("Select the kubeconfig context:", &contexts, current)?];
+    let resolved = kubeconfig.resolve(selected)?;
+
+    let token = match resolved.credentials {
+        Credentials::Token(token) => Some(token),
+        Credentials::Certificate { .. } => {
+            warn!("The {} context uses a client certificate, Amphitheatre needs a token instead", selected);
+            Some(prompter.password("What is the token of the cluster?")?)
+        }
+        Credentials::None => Some(prompter.password("What is the token of the cluster?")?),
+    };
+    let cluster = Cluster { title: selected.clone(), server: resolved.server, token };
+
+    Ok((name.unwrap_or(selected).to_string(), cluster))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::prompt::{Answer, ScriptedPrompter};
+
+    fn kubeconfig() -> Kubeconfig {
+        let content = r#"
+current-context: production
+clusters:
+  - {name: staging, cluster: {server: "https://staging:6443"}}
+  - {name: production, cluster: {server: "https://production:6443"}}
+contexts:
+  - {name: staging, context: {cluster: staging, user: ci}}
+  - {name: production, context: {cluster: production, user: admin}}
+users:
+  - {name: ci, user: {token: secret}}
+  - {name: admin, user: {client-certificate-data: Y2VydA==, client-key-data: a2V5}}
+"#;
+        let path = std::env::temp_dir().join(format!("amp-context-add-{}", std::process::id()));
+        fs::write(&path, content).unwrap();
+        let kubeconfig = Kubeconfig::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        kubeconfig
+    }
+
+    #[test]
+    fn derive_token_contexts() {
+        let prompter = ScriptedPrompter::new(vec![Answer::Select(0)]);
+        let (name, cluster) = from_kubeconfig(&prompter, &kubeconfig(), Some("ci")).unwrap();
+        assert_eq!(name, "ci");
+        assert_eq!((cluster.title.as_str(), cluster.server.as_str()), ("staging", "https://staging:6443"));
+        assert_eq!(cluster.token.as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn ask_a_token_for_certificate_contexts() {
+        let prompter = ScriptedPrompter::new(vec![Answer::Default, Answer::Password("token".into())]);
+        let (name, cluster) = from_kubeconfig(&prompter, &kubeconfig(), None).unwrap();
+        assert_eq!(name, "production");
+        assert_eq!(cluster.server, "https://production:6443");
+        assert_eq!(cluster.token.as_deref(), Some("token"));
+    }
+
+    #[test]
+    fn save_anyway_once_confirmed() {
+        let failed = || Err(Errors::ConnectionValidationFailed("https://amp.example.com: timed out".into()));
+
+        let prompter = ScriptedPrompter::new(vec![Answer::Confirm(true)]);
+        assert!(confirm_save(&prompter, failed()).is_ok());
+
+        let prompter = ScriptedPrompter::new(vec![Answer::Default]);
+        assert!(matches!(confirm_save(&prompter, failed()), Err(Errors::ConnectionValidationFailed(_))));
+
+        let prompter = ScriptedPrompter::new(vec![]);
+        assert!(confirm_save(&prompter, Ok(())).is_ok());
+    }
+}
@@ -29,6 +29,7 @@ pub struct Cli {
 
 #[derive(Subcommand, Debug)]
 enum Commands {
+    Add(super::add::Cli),
     Init(super::init::Cli),
     Show(super::show::Cli),
     List(super::list::Cli),
@@ -39,6 +40,7 @@ enum Commands {
 impl Cli {
     pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
         match &self.command {
+            Commands::Add(cli) => cli.exec(ctx).await,
             Commands::Init(cli) => cli.exec(ctx).await,
             Commands::Show(cli) => cli.exec(ctx).await,
             Commands::List(cli) => cli.exec(ctx).await,
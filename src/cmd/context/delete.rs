@@ -12,15 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::fmt::Display;
 use std::sync::Arc;
 
-use amp_common::config::Configuration;
 use clap::Args;
-use inquire::Select;
 
 use crate::context::Context;
 use crate::errors::{Errors, Result};
+use crate::utils;
 
 /// Delete a context
 #[derive(Args, Debug)]
@@ -39,34 +37,25 @@ impl Cli {
 
         // display the available contexts for selection
         let answer = select_context(&ctx).await?;
-        delete(&ctx, answer.0.as_str()).await?;
+        delete(&ctx, &answer).await?;
 
         Ok(())
     }
 }
 
 /// Select the context with the given name
-async fn select_context(ctx: &Arc<Context>) -> Result<OptionItem> {
+async fn select_context(ctx: &Arc<Context>) -> Result<String> {
     let configuration = ctx.configuration.read().await;
     let context = configuration.context.as_ref().ok_or(Errors::NotFoundContexts)?;
 
     // create a options with the available contexts
-    let options: Vec<OptionItem> =
-        context.iter().map(|(name, ctx)| OptionItem(String::from(name), ctx.title.clone())).collect();
+    let (names, titles): (Vec<String>, Vec<String>) =
+        context.iter().map(|(name, ctx)| (String::from(name), ctx.title.clone())).unzip();
 
     // run the select prompt
-    let answer = Select::new("Select the context:", options).prompt().map_err(Errors::InquireError)?;
+    let index = ctx.prompter.select("Select the context:", &titles, None)?;
 
-    Ok(answer)
-}
-
-#[derive(PartialEq)]
-struct OptionItem(String, String);
-
-impl Display for OptionItem {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.1)
-    }
+    Ok(names[index].clone())
 }
 
 async fn delete(ctx: &Arc<Context>, name: &str) -> Result<()> {
@@ -74,9 +63,7 @@ async fn delete(ctx: &Arc<Context>, name: &str) -> Result<()> {
     let context = configuration.context.as_mut().ok_or(Errors::NotFoundContexts)?;
 
     context.delete(name).map_err(Errors::FailedDeleteContext)?;
-    configuration
-        .save(Configuration::path().map_err(Errors::InvalidConfigPath)?)
-        .map_err(Errors::FailedSaveConfiguration)?;
+    utils::save_configuration(&configuration)?;
 
     Ok(())
 }
@@ -18,7 +18,8 @@ use amp_common::config::Configuration;
 use clap::Args;
 
 use crate::context::Context;
-use crate::errors::{Errors, Result};
+use crate::errors::Result;
+use crate::utils;
 
 /// Init the context with default configuration
 #[derive(Args, Debug)]
@@ -29,9 +30,7 @@ impl Cli {
     pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
         let mut configuration = ctx.configuration.write().await;
         *configuration = Configuration::default();
-        configuration
-            .save(Configuration::path().map_err(Errors::InvalidConfigPath)?)
-            .map_err(Errors::FailedSaveConfiguration)?;
+        utils::save_configuration(&configuration)?;
         Ok(())
     }
 }
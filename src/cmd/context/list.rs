@@ -15,17 +15,43 @@
 use std::sync::Arc;
 
 use amp_common::config::Cluster;
-use clap::Args;
-use tabled::settings::Style;
+use clap::{Args, ValueEnum};
+use serde::Serialize;
 use tabled::Tabled;
 
 use crate::context::Context;
 use crate::errors::{Errors, Result};
+use crate::output::{self, Format};
 
 /// List all available contexts
 #[derive(Args, Debug)]
 #[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
-pub struct Cli {}
+pub struct Cli {
+    /// Output format, one of: table, json, csv, name
+    #[arg(short, long, value_enum, default_value_t = Format::Table, env = "AMP_OUTPUT")]
+    output: Format,
+
+    /// Print one line per context instead, for shell prompts and pipes
+    #[arg(long, value_enum, conflicts_with = "output")]
+    format: Option<LineFormat>,
+
+    /// Pick the columns of the table among active, name, title and server,
+    /// `output.columns.contexts` in the configuration
+    #[clap(flatten)]
+    columns: output::Columns,
+
+    #[clap(flatten)]
+    table: output::TableArgs,
+}
+
+/// The line-oriented formats of the context list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum LineFormat {
+    /// `<name> <server> [current]`
+    Compact,
+    /// Only the name of the context
+    NameOnly,
+}
 
 impl Cli {
     pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
@@ -37,26 +63,95 @@ impl Cli {
             let mut row = ContextTable::from(cluster);
             row.name.clone_from(name);
             if let Some((current, _)) = &context.current() {
-                row.default = name.eq(current);
+                row.active = name.eq(current);
             }
             table.push(row);
         }
-        println!("{}", tabled::Table::new(table).with(Style::modern()));
+        if let Some(format) = self.format {
+            return ctx.stdout.print(lines(&table, format));
+        }
+        match self.output {
+            Format::Table => {
+                let columns = self.columns.or_configured("contexts");
+                ctx.stdout.println(columns.table(table, self.table.layout())?)?
+            }
+            Format::Json => ctx.stdout.println(ctx.json(&table)?)?,
+            Format::Csv => ctx.stdout.print(output::csv_with_header(&table, !self.table.no_header)?)?,
+            Format::Name => ctx.stdout.print(output::names(table))?,
+        }
 
         Ok(())
     }
 }
 
-#[derive(Tabled)]
+/// Render the contexts one per line in the given format.
+fn lines(rows: &[ContextTable], format: LineFormat) -> String {
+    rows.iter()
+        .map(|row| match format {
+            LineFormat::Compact if row.active => format!("{} {} current\n", row.name, row.server),
+            LineFormat::Compact => format!("{} {}\n", row.name, row.server),
+            LineFormat::NameOnly => format!("{}\n", row.name),
+        })
+        .collect()
+}
+
+#[derive(Tabled, Serialize)]
 struct ContextTable {
+    /// `*` for the current context, like `kubectl config get-contexts`
+    #[tabled(display_with = "indicator")]
+    active: bool,
     name: String,
     title: String,
     server: String,
-    default: bool,
+}
+
+fn indicator(active: &bool) -> String {
+    String::from(if *active { "*" } else { "" })
 }
 
 impl From<&Cluster> for ContextTable {
     fn from(ctx: &Cluster) -> Self {
-        Self { name: String::new(), title: ctx.title.clone(), server: ctx.server.clone(), default: false }
+        Self { active: false, name: String::new(), title: ctx.title.clone(), server: ctx.server.clone() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows() -> Vec<ContextTable> {
+        vec![
+            ContextTable { active: true, name: "prod".into(), title: "Prod".into(), server: "https://a".into() },
+            ContextTable { active: false, name: "dev".into(), title: "Dev".into(), server: "https://b".into() },
+            ContextTable { active: false, name: "ci".into(), title: "CI".into(), server: "https://c".into() },
+        ]
+    }
+
+    #[test]
+    fn compact_matches_table() {
+        let rows = rows();
+        let table = output::table(rows());
+        let compact = lines(&rows, LineFormat::Compact);
+        assert_eq!(compact, "prod https://a current\ndev https://b\nci https://c\n");
+
+        for (row, line) in rows.iter().zip(compact.lines()) {
+            assert!(table.contains(&row.name) && table.contains(&row.server));
+            assert_eq!(line.ends_with(" current"), row.active);
+        }
+    }
+
+    #[test]
+    fn only_the_current_context_is_active() {
+        let records = output::Columns::default().or_headers::<ContextTable>().select(rows()).unwrap();
+        assert_eq!(records[0][0], "active");
+        let active: Vec<&str> = records[1..].iter().map(|r| r[0].as_str()).collect();
+        assert_eq!(active, vec!["*", "", ""]);
+    }
+
+    #[test]
+    fn name_only_matches_table() {
+        let names = lines(&rows(), LineFormat::NameOnly);
+        let expected: Vec<String> = rows().into_iter().map(|r| r.name).collect();
+        assert_eq!(names.lines().collect::<Vec<_>>(), expected);
     }
 }
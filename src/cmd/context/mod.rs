@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod add;
 pub mod cli;
 pub mod delete;
 pub mod init;
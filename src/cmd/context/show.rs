@@ -14,27 +14,82 @@
 
 use std::sync::Arc;
 
-use clap::Args;
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use tabled::Tabled;
+use tracing::warn;
 
 use crate::context::Context;
-use crate::errors::{Errors, Result};
+use crate::errors::Result;
+use crate::output;
+use crate::utils;
 
 /// Print the current context
 #[derive(Args, Debug)]
 #[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
-pub struct Cli {}
+pub struct Cli {
+    /// Output format, one of: table, json, yaml
+    #[arg(short, long, value_enum, default_value_t = ShowFormat::Table)]
+    output: ShowFormat,
+
+    /// Show only the last 4 characters of the token, the default unless
+    /// `output.default_token_display = "unmasked"` in the configuration
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "unmask_token")]
+    mask_token: bool,
+
+    /// Show the whole token
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    unmask_token: bool,
+}
+
+/// The output formats of the context.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ShowFormat {
+    /// Print the context as a human readable table
+    Table,
+    /// Print the context as pretty JSON
+    Json,
+    /// Print the context as YAML
+    Yaml,
+}
 
 impl Cli {
     pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
-        let configuration = ctx.configuration.read().await;
-        let context = configuration.context.as_ref().ok_or(Errors::NotFoundContexts)?;
+        let name = ctx.context_name().await;
+        let cluster = ctx.cluster.read().await;
+        if name.is_none() && cluster.server.is_empty() {
+            warn!("No current context");
+            return Ok(());
+        }
 
-        if let Some((_, current)) = context.current() {
-            println!("{:#?}", current);
-        } else {
-            println!("No current context");
+        let unmasked = match (self.mask_token, self.unmask_token) {
+            (true, _) => false,
+            (_, true) => true,
+            _ => utils::setting(&["output", "default_token_display"]).is_some_and(|v| v.as_str() == Some("unmasked")),
+        };
+        if unmasked {
+            warn!("The token is shown in full, keep it out of shared logs and screenshots");
         }
+        let token = cluster.token.as_deref().unwrap_or_default();
+        let view = ContextView {
+            name: name.unwrap_or_default(),
+            title: cluster.title.clone(),
+            server: cluster.server.clone(),
+            token: if unmasked { token.to_string() } else { utils::mask_token(token) },
+        };
 
-        Ok(())
+        match self.output {
+            ShowFormat::Table => ctx.stdout.println(output::table([view])),
+            ShowFormat::Json => ctx.stdout.println(ctx.json(&view)?),
+            ShowFormat::Yaml => ctx.stdout.print(output::yaml(&view)?),
+        }
     }
 }
+
+#[derive(Tabled, Serialize)]
+struct ContextView {
+    name: String,
+    title: String,
+    server: String,
+    token: String,
+}
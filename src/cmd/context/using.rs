@@ -12,16 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::fmt::Display;
 use std::sync::Arc;
 
-use amp_common::config::{Cluster, Configuration};
+use amp_common::config::Cluster;
 use clap::Args;
-use inquire::error::InquireResult;
-use inquire::{Password, Select, Text};
 
 use crate::context::Context;
 use crate::errors::{Errors, Result};
+use crate::prompt::Prompter;
+use crate::utils;
 
 const CREATE_KEY: &str = "$$CREATE$$";
 
@@ -42,13 +41,13 @@ impl Cli {
         let answer = select_context(ctx.clone()).await?;
 
         // if the user selects "create new context", create a new context
-        if answer.0 == CREATE_KEY {
+        if answer == CREATE_KEY {
             return create_context(ctx.clone()).await;
         }
 
         // if the user selects a context, set it as the current context
         // and set it as the current context
-        use_context(ctx.clone(), answer.0.as_str()).await?;
+        use_context(ctx.clone(), &answer).await?;
 
         Ok(())
     }
@@ -60,36 +59,26 @@ async fn use_context(ctx: Arc<Context>, name: &str) -> Result<()> {
     let context = configuration.context.as_mut().ok_or(Errors::NotFoundContexts)?;
 
     context.select(name).map_err(Errors::FailedSelectContext)?;
-    configuration
-        .save(Configuration::path().map_err(Errors::InvalidConfigPath)?)
-        .map_err(Errors::FailedSaveConfiguration)?;
+    utils::save_configuration(&configuration)?;
 
     Ok(())
 }
 
 /// Select the context with the given name
-async fn select_context(ctx: Arc<Context>) -> Result<OptionItem> {
+async fn select_context(ctx: Arc<Context>) -> Result<String> {
     let configuration = ctx.configuration.read().await;
     let context = configuration.context.as_ref().ok_or(Errors::NotFoundContexts)?;
 
     // create a options with the available contexts
-    let mut options: Vec<OptionItem> =
-        context.iter().map(|(name, ctx)| OptionItem(String::from(name), ctx.title.clone())).collect();
-    options.push(OptionItem(CREATE_KEY.into(), "Create new context".into()));
+    let (mut names, mut titles): (Vec<String>, Vec<String>) =
+        context.iter().map(|(name, ctx)| (String::from(name), ctx.title.clone())).unzip();
+    names.push(CREATE_KEY.into());
+    titles.push("Create new context".into());
 
     // run the select prompt
-    let answer = Select::new("Select the context:", options).prompt().map_err(Errors::InquireError)?;
+    let index = ctx.prompter.select("Select the context:", &titles, None)?;
 
-    Ok(answer)
-}
-
-#[derive(PartialEq)]
-struct OptionItem(String, String);
-
-impl Display for OptionItem {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.1)
-    }
+    Ok(names[index].clone())
 }
 
 /// Create a new context
@@ -97,22 +86,42 @@ async fn create_context(ctx: Arc<Context>) -> Result<()> {
     let mut configuration = ctx.configuration.write().await;
     let context = configuration.context.as_mut().ok_or(Errors::NotFoundContexts)?;
 
-    let (name, cluster) = inquire().map_err(Errors::InquireError)?;
+    let (name, cluster) = inquire(ctx.prompter.as_ref())?;
     context.add(&name, cluster).map_err(Errors::FailedAddContext)?;
-    configuration
-        .save(Configuration::path().map_err(Errors::InvalidConfigPath)?)
-        .map_err(Errors::FailedSaveConfiguration)?;
+    utils::save_configuration(&configuration)?;
 
     Ok(())
 }
 
-fn inquire() -> InquireResult<(String, Cluster)> {
+pub(super) fn inquire(prompter: &dyn Prompter) -> Result<(String, Cluster)> {
     let mut cluster = Cluster::default();
 
-    let name = Text::new("What is the name of the context?").prompt()?;
-    cluster.title = Text::new("What is the title of the context?").prompt()?;
-    cluster.server = Text::new("What is the server address of the cluster?").prompt()?;
-    cluster.token = Some(Password::new("What is the token of the cluster?").prompt()?);
+    let name = prompter.text("What is the name of the context?", None)?;
+    cluster.title = prompter.text("What is the title of the context?", None)?;
+    cluster.server = prompter.text("What is the server address of the cluster?", None)?;
+    cluster.token = Some(prompter.password("What is the token of the cluster?")?);
 
     Ok((name, cluster))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt::{Answer, ScriptedPrompter};
+
+    #[test]
+    fn inquire_new_context() {
+        let prompter = ScriptedPrompter::new(vec![
+            Answer::Text("staging".into()),
+            Answer::Text("Staging".into()),
+            Answer::Text("https://staging.amphitheatre.app".into()),
+            Answer::Password("secret".into()),
+        ]);
+
+        let (name, cluster) = inquire(&prompter).unwrap();
+        assert_eq!(name, "staging");
+        assert_eq!(cluster.title, "Staging");
+        assert_eq!(cluster.server, "https://staging.amphitheatre.app");
+        assert_eq!(cluster.token.as_deref(), Some("secret"));
+    }
+}
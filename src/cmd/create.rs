@@ -0,0 +1,79 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::Args;
+use tabled::Tabled;
+use tracing::{info, warn};
+
+use crate::context::Context;
+use crate::errors::Result;
+use crate::ops::templates;
+use crate::output;
+
+/// Create a playbook from a template, without a local checkout
+#[derive(Args, Debug)]
+#[command(after_help = super::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// The git URL of a repository containing `.amp.toml`, the URL of a manifest, or the name of a template
+    #[arg(long, value_name = "TEMPLATE", required_unless_present = "list")]
+    from_template: Option<String>,
+
+    /// List the templates of the index, along with their descriptions
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "from_template")]
+    list: bool,
+
+    /// Skip the lint rules with the given IDs, see `amp lint --rules`
+    #[arg(long, value_name = "RULE", value_delimiter = ',')]
+    allow: Vec<String>,
+
+    /// The seconds to wait for the actors to be ready before giving up
+    #[arg(long, default_value = "600", value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: u64,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let Some(reference) = &self.from_template else {
+            return self.list(&ctx).await;
+        };
+
+        let endpoints = templates::create(&ctx, reference, &self.allow, Duration::from_secs(self.timeout)).await?;
+        info!("The playbook of the template {} is ready", reference);
+        for endpoint in endpoints {
+            ctx.stdout.println(endpoint)?;
+        }
+
+        Ok(())
+    }
+
+    async fn list(&self, ctx: &Context) -> Result<()> {
+        let templates = templates::index(&ctx.api().await).await?;
+        if templates.is_empty() {
+            warn!("No templates found");
+            return Ok(());
+        }
+
+        let rows = templates.into_iter().map(|t| TemplateTable { name: t.name, description: t.description });
+        ctx.stdout.println(output::table_with_header(rows, true))
+    }
+}
+
+#[derive(Tabled)]
+struct TemplateTable {
+    name: String,
+    description: String,
+}
@@ -23,10 +23,6 @@ use crate::errors::Result;
 #[derive(Args, Debug)]
 #[command(after_help = super::cli::AFTER_HELP_STRING)]
 pub struct Cli {
-    /// If true, amp will skip yes/no confirmation from the user
-    #[arg(long, action = clap::ArgAction::Set, default_value = "true", env = "AMP_ASSUME_YES")]
-    assume_yes: bool,
-
     /// When set to false, builds wait for API request instead of running automatically
     #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_AUTO_BUILD")]
     auto_build: bool,
@@ -57,8 +53,8 @@ pub struct Cli {
     force: bool,
 
     /// Activate profiles by name (prefixed with `-` to disable a profile)
-    #[arg(short, long, default_value = "[]", env = "AMP_PROFILE")]
-    profile: Option<Vec<String>>,
+    #[arg(short, long, env = "AMP_PROFILE", value_delimiter = ',', allow_hyphen_values = true)]
+    profile: Vec<String>,
 
     /// Priority sorted order of debugger protocols to support
     #[arg(long, default_value = "[]", env = "AMP_PROTOCOLS")]
@@ -12,21 +12,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use clap::Args;
 
 use crate::context::Context;
 use crate::errors::Result;
+use crate::ops::images::{self, ImageOverride};
+use crate::ops::pipeline::{self, Options};
+use crate::ops::playbook::{self, CreateOptions};
+use crate::utils;
+use crate::utils::git::{self, Source};
 
 /// Deploy pre-built artifacts
 #[derive(Args, Debug)]
 #[command(after_help = super::cli::AFTER_HELP_STRING)]
 pub struct Cli {
-    /// If true, amp will skip yes/no confirmation from the user
-    #[arg(long, action = clap::ArgAction::Set, default_value = "true", env = "AMP_ASSUME_YES")]
-    assume_yes: bool,
-
     /// Path or URL to the Amphitheatre config file
     #[arg(short, long, env = "AMP_FILENAME")]
     filename: Option<String>,
@@ -37,8 +39,8 @@ pub struct Cli {
     force: bool,
 
     /// Activate profiles by name (prefixed with `-` to disable a profile)
-    #[arg(short, long, default_value = "[]", env = "AMP_PROFILE")]
-    profile: Option<Vec<String>>,
+    #[arg(short, long, env = "AMP_PROFILE", value_delimiter = ',', allow_hyphen_values = true)]
+    profile: Vec<String>,
 
     /// Don't render the manifests, just deploy them
     #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_SKIP_RENDER")]
@@ -47,10 +49,59 @@ pub struct Cli {
     /// Stream logs from deployed objects
     #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_TAIL")]
     tail: bool,
+
+    /// Deploy the lead character alone, ignoring the partners of the manifest
+    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_NO_PARTNERS")]
+    no_partners: bool,
+
+    /// Skip the lint rules with the given IDs, see `amp lint --rules`
+    #[arg(long, value_name = "RULE", value_delimiter = ',')]
+    allow: Vec<String>,
+
+    /// Suffix the character name with the git branch, so previews of parallel branches don't clash
+    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_PER_BRANCH")]
+    per_branch: bool,
+
+    /// Skip the warnings about the uncommitted and unpushed changes of the workspace
+    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_NO_GIT_CHECKS")]
+    no_git_checks: bool,
+
+    /// Don't run the hooks of the manifest
+    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_NO_HOOKS")]
+    no_hooks: bool,
+
+    /// Deploy the image instead of building the character from source, as NAME=REFERENCE
+    #[arg(short, long, value_name = "NAME=REFERENCE", value_parser = images::parse_override, value_delimiter = ',')]
+    images: Vec<ImageOverride>,
+
+    /// Deploy the images of the artifacts file written by a build with `--file-output`
+    #[arg(long, value_name = "FILE")]
+    file_input: Option<PathBuf>,
 }
 
 impl Cli {
-    pub async fn exec(&self, _ctx: Arc<Context>) -> Result<()> {
-        Ok(())
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let opt = Options {
+            cleanup: false, // keep the deployment running
+            tail: self.tail,
+            live: false, // deploy the pre-built artifacts, without syncing sources
+            once: true,
+            partners: !self.no_partners,
+            allow: self.allow.clone(),
+            per_branch: self.per_branch,
+            hooks: !self.no_hooks,
+            timeout: None,
+            follow: false,
+            images: images::collect(&self.images, self.file_input.as_deref())?,
+            preview: false,
+        };
+        if !self.no_git_checks {
+            let workspace = utils::manifest_path(".", ctx.workspace.as_deref());
+            git::check(ctx.prompter.as_ref(), &workspace, Source::Workspace)?;
+        }
+        let source = playbook::Source::Manifest { filename: self.filename.clone(), profiles: self.profile.clone() };
+        let playbook = playbook::create(&ctx, &CreateOptions::new(source, &opt)).await?.playbook;
+
+        pipeline::run(&ctx, playbook, opt).await
     }
 }
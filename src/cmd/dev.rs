@@ -15,31 +15,34 @@
 use clap::Args;
 use std::path::PathBuf;
 use std::sync::Arc;
+use tracing::info;
 
+use crate::cmd::playbook::list::PlaybookTable;
 use crate::context::Context;
 use crate::errors::Result;
+use crate::ops::images::{self, ImageOverride};
 use crate::ops::pipeline::Options;
-use crate::ops::{cleaner, pipeline};
+use crate::ops::playbook::{self, CreateOptions, Source};
+use crate::ops::{monorepo, pipeline};
+use crate::output;
+use crate::utils::git::Revision;
+use crate::utils::{self, naming};
 
 /// Run a pipeline in development mode
 #[derive(Args, Debug)]
 #[command(after_help = super::cli::AFTER_HELP_STRING)]
 pub struct Cli {
-    /// If true, amp will skip yes/no confirmation from the user
-    #[arg(long, action = clap::ArgAction::Set, default_value = "true", env = "AMP_ASSUME_YES")]
-    assume_yes: bool,
-
     /// Delete deployments after dev or debug mode is interrupted
     #[arg(long, action = clap::ArgAction::Set, default_value = "true", env = "AMP_CLEANUP")]
     cleanup: bool,
 
     /// Path or URL to the Amphitheatre config file
     #[arg(short, long, env = "AMP_FILENAME")]
-    filename: Option<PathBuf>,
+    filename: Option<String>,
 
     /// Activate profiles by name (prefixed with `-` to disable a profile)
-    #[arg(short, long, env = "AMP_PROFILE")]
-    profile: Option<Vec<String>>,
+    #[arg(short, long, env = "AMP_PROFILE", value_delimiter = ',', allow_hyphen_values = true)]
+    profile: Vec<String>,
 
     /// Stream logs from deployed objects
     #[arg(long, action = clap::ArgAction::Set, default_value = "true", env = "AMP_TAIL")]
@@ -48,24 +51,89 @@ pub struct Cli {
     /// How is change detection triggered? (polling, notify, or manual)
     #[arg(long, default_value = "notify", env = "AMP_TRIGGER")]
     trigger: Option<String>,
+
+    /// Deploy the lead character alone, ignoring the partners of the manifest
+    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_NO_PARTNERS")]
+    no_partners: bool,
+
+    /// Skip the lint rules with the given IDs, see `amp lint --rules`
+    #[arg(long, value_name = "RULE", value_delimiter = ',')]
+    allow: Vec<String>,
+
+    /// Suffix the character name with the git branch, so previews of parallel branches don't clash
+    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_PER_BRANCH")]
+    per_branch: bool,
+
+    /// Don't run the hooks of the manifest
+    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_NO_HOOKS")]
+    no_hooks: bool,
+
+    /// Run every character found under the workspace root, in a single playbook
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["filename", "per_branch"])]
+    all: bool,
+
+    /// Run only the characters with the given names, along with `--all`
+    #[arg(long, value_name = "NAME", value_delimiter = ',', requires = "all")]
+    only: Vec<String>,
+
+    /// Deploy the image instead of building the character from source, as NAME=REFERENCE
+    #[arg(short, long, value_name = "NAME=REFERENCE", value_parser = images::parse_override, value_delimiter = ',', conflicts_with = "all")]
+    images: Vec<ImageOverride>,
+
+    /// Deploy the images of the artifacts file written by a build with `--file-output`
+    #[arg(long, value_name = "FILE", conflicts_with = "all")]
+    file_input: Option<PathBuf>,
+
+    /// Create a static preview the server owns: upload the sources once, print the playbook and exit
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["trigger", "tail", "all"])]
+    no_live: bool,
 }
 
 impl Cli {
     pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
-        // Setup handler for for handling Ctrl-C signals.
-        cleaner::setup_signal_handler(ctx.clone(), self.cleanup);
-
         // Define the options for the pipeline.
         let opt = Options {
-            cleanup: self.cleanup,
-            tail: self.tail, // toggle log streaming
-            live: true,      // sync the sources from local to server
-            once: false,     // watch for changes and sync them incrementally
+            cleanup: self.cleanup && !self.no_live,
+            tail: self.tail && !self.no_live, // toggle log streaming
+            live: !self.no_live,              // sync the sources from local to server
+            once: self.no_live,               // exit after the upload of a preview, or keep watching
+            partners: !self.no_partners,
+            allow: self.allow.clone(),
+            per_branch: self.per_branch,
+            hooks: !self.no_hooks,
+            timeout: None,
+            follow: false,
+            images: images::collect(&self.images, self.file_input.as_deref())?,
+            preview: self.no_live,
         };
-        let playbook = pipeline::load(&ctx, &self.filename, opt.once).await?;
+        if self.all {
+            return self.exec_all(ctx, opt).await;
+        }
+        let source = Source::Manifest { filename: self.filename.clone(), profiles: self.profile.clone() };
+        let playbook = playbook::create(&ctx, &CreateOptions::new(source, &opt)).await?.playbook;
+        if self.no_live {
+            let details = output::table(vec![PlaybookTable::from(&playbook)]);
+            pipeline::run(&ctx, playbook, opt).await?;
+            return ctx.stdout.println(details);
+        }
 
         // Run dev mode. This will sync the full sources into the server,
         // and then watch for changes and sync them incrementally.
         pipeline::run(&ctx, playbook, opt).await
     }
+
+    /// Run every character under the workspace root, multiplexing their output.
+    async fn exec_all(&self, ctx: Arc<Context>, opt: Options) -> Result<()> {
+        let root = utils::manifest_path("", ctx.workspace.as_deref());
+        let members = monorepo::discover(&root, &self.only)?;
+        let names: Vec<&str> = members.iter().map(|m| m.name.as_str()).collect();
+        info!("Found {} character(s): {}", members.len(), names.join(", "));
+
+        let characters = monorepo::load(&ctx, &members, &self.profile, &opt.allow).await?;
+        let name = root.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let title = naming::title(&name, &Revision::detect(&root));
+        let (playbook, created) = monorepo::create_or_reuse(&ctx, &title, characters).await?;
+
+        monorepo::run(&ctx, playbook, created, members, opt).await
+    }
 }
@@ -15,29 +15,40 @@
 use std::sync::Arc;
 
 use clap::Args;
+use colored::Colorize;
 
 use crate::context::Context;
-use crate::errors::Result;
+use crate::errors::{Errors, Result};
 
 /// Run a diagnostic on Amphitheatre
 #[derive(Args, Debug)]
 #[command(after_help = super::cli::AFTER_HELP_STRING)]
 pub struct Cli {
-    /// If true, amp will skip yes/no confirmation from the user
-    #[arg(long, action = clap::ArgAction::Set, default_value = "true", env = "AMP_ASSUME_YES")]
-    assume_yes: bool,
-
     /// Path or URL to the Amphitheatre config file
     #[arg(short, long, env = "AMP_FILENAME")]
     filename: Option<String>,
 
     /// Activate profiles by name (prefixed with `-` to disable a profile)
-    #[arg(short, long, default_value = "[]", env = "AMP_PROFILE")]
-    profile: Option<Vec<String>>,
+    #[arg(short, long, env = "AMP_PROFILE", value_delimiter = ',', allow_hyphen_values = true)]
+    profile: Vec<String>,
+
+    /// Skip the checks that require network access, such as server reachability
+    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_NO_NETWORK")]
+    no_network: bool,
 }
 
 impl Cli {
-    pub async fn exec(&self, _ctx: Arc<Context>) -> Result<()> {
-        Ok(())
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let errors = ctx.validate(!self.no_network).await;
+        if errors.is_empty() {
+            ctx.stdout.println("The context is valid".green())?;
+            return Ok(());
+        }
+
+        for error in &errors {
+            ctx.stdout.println(format_args!("{} {}", "✗".red(), error))?;
+        }
+
+        Err(Errors::InvalidContext(errors.len()))
     }
 }
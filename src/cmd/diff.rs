@@ -0,0 +1,93 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use amp_common::resource::CharacterSpec;
+use amp_common::schema::Character;
+use clap::Args;
+use colored::Colorize;
+
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::{discover, pipeline};
+use crate::output::{self, DIFF_CONTEXT};
+
+/// Show the differences between the local manifest and the deployed one
+#[derive(Args, Debug)]
+#[command(after_help = super::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// The ID of the playbook, discovered from the manifest if omitted
+    pid: Option<String>,
+
+    /// Path or URL to the Amphitheatre config file
+    #[arg(short, long, env = "AMP_FILENAME")]
+    filename: Option<String>,
+
+    /// Activate profiles by name (prefixed with `-` to disable a profile)
+    #[arg(short, long, env = "AMP_PROFILE", value_delimiter = ',', allow_hyphen_values = true)]
+    profile: Vec<String>,
+
+    /// The lines of context around the changes
+    #[arg(short = 'U', long, value_name = "N", default_value_t = DIFF_CONTEXT)]
+    unified: usize,
+
+    /// Only print the number of lines added and removed
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    stat: bool,
+
+    /// Exit with 1 if there are differences, like `git diff --exit-code`
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    exit_code: bool,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let manifest = pipeline::read(&ctx, &self.filename, &self.profile).await?;
+        let origin = manifest.origin.to_string();
+        let character: Character =
+            toml::from_str(&manifest.content).map_err(|e| Errors::FailedParseManifest(origin.clone(), e))?;
+
+        let pid = discover::playbook_id(&ctx, self.pid.as_deref()).await?;
+        let path = format!("/playbooks/{}", pid);
+        let playbook = ctx.recorder.call_async("GET", &path, ctx.playbooks().get_async(&pid)).await?;
+        let name = &character.meta.name;
+        let deployed = playbook.characters.iter().flatten().find(|c| &c.meta.name == name);
+        let deployed = deployed.ok_or_else(|| Errors::CharacterNotDeployed(name.clone(), pid.clone()))?;
+
+        let local = render(&CharacterSpec::from(&character))?;
+        let remote = render(deployed)?;
+
+        if self.stat {
+            let (added, removed) = output::diff_stat(&remote, &local);
+            ctx.stdout.println(format!("{} lines added, {} removed", added, removed))?;
+        } else {
+            let label = format!("playbook {}", pid);
+            match output::diff_with_context(&remote, &local, &label, &origin, self.unified) {
+                Some(diff) => ctx.stdout.print(diff)?,
+                None => ctx.stdout.println(format!("{} is up to date with {}", origin, label).green())?,
+            }
+        }
+
+        if self.exit_code && local != remote {
+            std::process::exit(1);
+        }
+        Ok(())
+    }
+}
+
+/// Render the character as TOML, so both sides are compared in the same layout.
+fn render(character: &CharacterSpec) -> Result<String> {
+    toml::to_string(character).map_err(|e| Errors::DiffRenderFailed(e.to_string()))
+}
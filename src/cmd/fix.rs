@@ -0,0 +1,69 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use clap::Args;
+use colored::Colorize;
+use tracing::{info, warn};
+
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::utils::manifest_edit;
+use crate::{migrate, output, schema, utils};
+
+/// Migrate the character manifest to the current schema
+#[derive(Args, Debug)]
+#[command(after_help = super::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// Path to the Amphitheatre config file
+    #[arg(short, long, env = "AMP_FILENAME")]
+    filename: Option<String>,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let location = utils::resolve_manifest(self.filename.as_deref(), ctx.workspace.as_deref())?;
+        let path = PathBuf::from(&location);
+        if !path.is_file() {
+            return Err(Errors::ManifestNotLocal(location));
+        }
+
+        let content = std::fs::read_to_string(&path).map_err(|e| Errors::FailedReadManifest(location.clone(), e))?;
+        let known: Vec<String> =
+            schema::manifest().schema.object.map(|o| o.properties.into_keys().collect()).unwrap_or_default();
+        let migrated =
+            migrate::migrate(&content, &known).map_err(|e| Errors::FailedEditManifest(location.clone(), e))?;
+
+        for name in &migrated.applied {
+            info!("Migration: {}", name);
+        }
+        for key in &migrated.follow_ups {
+            warn!("Unknown key `{}` can't be migrated automatically, please update it by hand", key);
+        }
+
+        let Some(diff) = output::diff(&content, &migrated.content, &location, &location) else {
+            ctx.stdout.println(format!("{} is up to date", location).green())?;
+            return Ok(());
+        };
+        ctx.stdout.print(diff)?;
+
+        if ctx.dry_run || !ctx.prompter.confirm("Write the changes?", false)? {
+            return Ok(());
+        }
+        manifest_edit::write(&path, &migrated.content)?;
+        ctx.stdout.println(format!("{} was migrated", location).green())
+    }
+}
@@ -12,25 +12,35 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::fs;
+use std::io::IsTerminal;
+use std::path::Path;
 use std::sync::Arc;
 
 use crate::context::Context;
+use crate::detect::{self, BuildMethod, Candidate};
 use crate::errors::{Errors, Result};
+use crate::ops::templates;
+use crate::prompt::Prompter;
+use crate::utils::manifest_edit::{self, ManifestEdit};
+use crate::utils::{self, parse_key_value};
 use amp_common::schema::Character;
-use clap::Args;
+use clap::{Args, ValueEnum};
 use colored::Colorize;
-use tracing::error;
+use toml_edit::{Array, InlineTable};
+use tracing::{error, warn};
 
 const FILE_NAME: &str = ".amp.toml";
 
+/// The builder of the characters built by Cloud Native Buildpacks.
+const BUILDER: &str = "heroku/buildpacks:20";
+
+/// The ports offered by the wizard, along with the detected ones.
+const PORTS: [u16; 6] = [80, 443, 3000, 5173, 8000, 8080];
+
 /// Create a new Amphitheatre character in an existing directory
 #[derive(Args, Debug)]
 #[command(after_help = super::cli::AFTER_HELP_STRING)]
 pub struct Cli {
-    /// If true, amp will skip yes/no confirmation from the user
-    #[arg(long, action = clap::ArgAction::Set, default_value = "true", env = "AMP_ASSUME_YES")]
-    assume_yes: bool,
     /// File to write generated manifests to
     #[arg(short, long, default_value = ".amp.toml", env = "AMP_FILENAME")]
     filename: Option<String>,
@@ -40,40 +50,400 @@ pub struct Cli {
     /// Set the character name. Defaults to the directory name.
     #[arg(long, env = "AMP_NAME")]
     name: Option<String>,
+    /// Start from the manifest of a template, a name of the index, a git repository or a manifest URL
+    #[arg(long, value_name = "NAME", conflicts_with = "name")]
+    template: Option<String>,
+    /// Fill in a `{{NAME}}` placeholder of the template instead of prompting, may be given multiple times
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_key_value, requires = "template")]
+    vars: Vec<(String, String)>,
+    /// Set the description of the character. Defaults to the detected one.
+    #[arg(long, conflicts_with = "template")]
+    description: Option<String>,
+    /// Scaffold for one of the detected languages. Defaults to the most likely one.
+    #[arg(long, conflicts_with = "template")]
+    language: Option<String>,
+    /// How the character is built. Defaults to the detected strategy.
+    #[arg(long, value_enum, conflicts_with = "template")]
+    build: Option<Strategy>,
+    /// A port the character exposes, may be given multiple times
+    #[arg(long = "port", value_name = "PORT", conflicts_with = "template")]
+    ports: Vec<u16>,
+    /// An environment variable of the character, may be given multiple times
+    #[arg(id = "variables", long = "env-var", value_name = "KEY=VALUE", value_parser = parse_key_value, conflicts_with = "template")]
+    variables: Vec<(String, String)>,
+    /// Ask for the inputs in a wizard, the default in a terminal
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["non_interactive", "template"])]
+    interactive: bool,
+    /// Take the inputs from the flags and the detection only, without asking
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    non_interactive: bool,
+}
+
+/// How the character is built.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Strategy {
+    /// Built by Cloud Native Buildpacks from the sources
+    Buildpacks,
+    /// Built from the Dockerfile of the workspace
+    Dockerfile,
+    /// Built by the user, the manifest has no build section
+    Manual,
+}
+
+impl Strategy {
+    const ALL: [Strategy; 3] = [Strategy::Buildpacks, Strategy::Dockerfile, Strategy::Manual];
+}
+
+impl From<BuildMethod> for Strategy {
+    fn from(method: BuildMethod) -> Self {
+        match method {
+            BuildMethod::Buildpacks => Strategy::Buildpacks,
+            BuildMethod::Dockerfile => Strategy::Dockerfile,
+        }
+    }
+}
+
+/// What the manifest is generated from: the detection, overridden by the
+/// flags, then by the answers of the wizard.
+#[derive(Debug)]
+struct Inputs {
+    name: String,
+    description: Option<String>,
+    version: Option<String>,
+    /// The candidate the character is built and run as
+    candidate: Option<Candidate>,
+    build: Strategy,
+    ports: Vec<u16>,
+    env: Vec<(String, String)>,
+    test_command: Option<String>,
+}
+
+impl Inputs {
+    /// The inputs as the most likely candidate of the workspace suggests.
+    fn detected(name: &str, candidates: &[Candidate]) -> Self {
+        let candidate = candidates.first().cloned();
+        Self {
+            name: name.to_string(),
+            description: candidates.iter().find_map(|c| c.description.clone()),
+            version: candidates.iter().find_map(|c| c.version.clone()),
+            build: candidate.as_ref().map_or(Strategy::Manual, |c| c.build.into()),
+            candidate,
+            ports: vec![],
+            env: vec![],
+            test_command: candidates.iter().find_map(|c| c.test_command.clone()),
+        }
+    }
 }
 
 impl Cli {
-    pub async fn exec(&self, _ctx: Arc<Context>) -> Result<()> {
-        let path = std::env::current_dir().unwrap();
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let filename = self.filename.as_deref().unwrap_or(FILE_NAME);
+        let path = utils::manifest_path(filename, ctx.workspace.as_deref());
+        let dir = path.parent().ok_or_else(|| Errors::InvalidManifestPath(path.clone()))?;
 
-        let name = self.name.as_deref().unwrap_or_else(|| path.file_name().unwrap().to_str().unwrap());
+        let dirname = dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
 
-        if !self.force && path.join(FILE_NAME).exists() {
-            error!("`amp init` cannot be run on existing Amphitheatre character");
+        if !self.force && path.exists() {
+            error!("`amp init` cannot be run on existing Amphitheatre character: {}", path.display());
             std::process::exit(1);
         }
 
-        if let Err(e) = create(name) {
+        if let Some(reference) = &self.template {
+            let location = templates::resolve(&ctx.api().await, reference).await?;
+            let manifest = utils::read_manifest(&ctx.http, &location, ctx.remote_cache).await?;
+            let content = templates::fill(&manifest.content, &self.vars, ctx.prompter.as_ref())?;
+            manifest_edit::write(&path, &content)?;
+            eprintln!("Configuration {} was created from the template {}", path.display(), reference);
+            return Ok(());
+        }
+
+        let candidates = detect::detect(dir);
+        match candidates.first() {
+            Some(candidate) => eprintln!("Detected {} from {}", candidate, candidate.manifest.display()),
+            None => eprintln!("No known language or framework detected"),
+        }
+
+        if let Some(candidate) = candidates.iter().find(|c| !c.members.is_empty()) {
+            eprintln!(
+                "Detected a workspace of {} members: {}, run `amp init` in the directory of each of them \
+                 to run them all with `amp dev --all`",
+                candidate.members.len(),
+                candidate.members.join(", ")
+            );
+        }
+
+        let detected = candidates.iter().find_map(|c| c.name.clone());
+        let name = self.name.clone().or(detected).unwrap_or(dirname);
+        let mut inputs = self.inputs(&name, &candidates)?;
+
+        let interactive = self.interactive || (!self.non_interactive && std::io::stdin().is_terminal());
+        if interactive {
+            inputs = wizard(ctx.prompter.as_ref(), inputs, &candidates)?;
+        }
+
+        let manifest = render(&inputs)?;
+        eprintln!("{}", manifest);
+        if interactive && !ctx.prompter.confirm(&format!("Write the manifest to {}?", path.display()), true)? {
+            eprintln!("Nothing was written");
+            return Ok(());
+        }
+        if let Err(e) = manifest_edit::write(&path, &manifest) {
             error!("Failed to create the character: {}", e.to_string());
             std::process::exit(1);
         }
 
-        println!("Configuration .amp.toml was created successfully");
-        println!("{}", "You can now run [amp run] to build and deploy your character".green());
-        println!("{}", "or [amp dev] to enter development mode, with hot reloading".green());
+        eprintln!("Configuration {} was created successfully", path.display());
+        eprintln!("{}", "You can now run [amp run] to build and deploy your character".green());
+        eprintln!("{}", "or [amp dev] to enter development mode, with hot reloading".green());
 
         Ok(())
     }
+
+    /// The detected inputs, overridden by the flags.
+    fn inputs(&self, name: &str, candidates: &[Candidate]) -> Result<Inputs> {
+        let mut inputs = Inputs::detected(name, candidates);
+        if let Some(language) = &self.language {
+            let candidate = pick(candidates, language)?;
+            inputs.build = candidate.build.into();
+            inputs.candidate = Some(candidate.clone());
+        }
+        inputs.description = self.description.clone().or(inputs.description);
+        inputs.build = self.build.unwrap_or(inputs.build);
+        inputs.ports = self.ports.clone();
+        inputs.env = self.variables.clone();
+        Ok(inputs)
+    }
+}
+
+/// The candidate of the detected language.
+fn pick<'a>(candidates: &'a [Candidate], language: &str) -> Result<&'a Candidate> {
+    let candidate = candidates.iter().find(|c| c.language.eq_ignore_ascii_case(language));
+    candidate.ok_or_else(|| {
+        let detected: Vec<&str> = candidates.iter().map(|c| c.language).collect();
+        Errors::LanguageNotDetected(language.to_string(), detected.join(", "))
+    })
+}
+
+/// Ask for the inputs in turn, the given ones being the defaults.
+fn wizard(prompter: &dyn Prompter, mut inputs: Inputs, candidates: &[Candidate]) -> Result<Inputs> {
+    inputs.name = prompter.text("Project name", Some(&inputs.name))?;
+    let description = prompter.text("Description", Some(inputs.description.as_deref().unwrap_or_default()))?;
+    inputs.description = (!description.trim().is_empty()).then_some(description);
+
+    if !candidates.is_empty() {
+        let options: Vec<String> = candidates.iter().map(|c| c.to_string()).collect();
+        let current = candidates.iter().position(|c| Some(c) == inputs.candidate.as_ref());
+        let index = prompter.select("Language", &options, current.or(Some(0)))?;
+        if current != Some(index) {
+            inputs.build = candidates[index].build.into();
+            inputs.candidate = Some(candidates[index].clone());
+        }
+    }
+
+    let options: Vec<String> = Strategy::ALL.iter().map(|s| format!("{:?}", s).to_lowercase()).collect();
+    let current = Strategy::ALL.iter().position(|s| *s == inputs.build);
+    inputs.build = Strategy::ALL[prompter.select("Build strategy", &options, current)?];
+
+    let detected = candidates.iter().filter_map(|c| c.port);
+    let mut ports: Vec<u16> = PORTS.into_iter().chain(detected).chain(inputs.ports.iter().copied()).collect();
+    ports.sort_unstable();
+    ports.dedup();
+    let mut selected = inputs.ports.clone();
+    selected.extend(inputs.candidate.as_ref().and_then(|c| c.port));
+    let defaults: Vec<usize> = ports.iter().enumerate().filter(|(_, p)| selected.contains(p)).map(|(i, _)| i).collect();
+    let options: Vec<String> = ports.iter().map(u16::to_string).collect();
+    let indexes = prompter.multi_select("Exposed ports", &options, &defaults)?;
+    inputs.ports = indexes.into_iter().filter_map(|i| ports.get(i).copied()).collect();
+
+    loop {
+        let variable = prompter.text("Environment variable as KEY=VALUE, empty to finish", Some(""))?;
+        if variable.trim().is_empty() {
+            break;
+        }
+        match parse_key_value(variable.trim()) {
+            Ok(pair) => inputs.env.push(pair),
+            Err(e) => warn!("{}", e),
+        }
+    }
+
+    Ok(inputs)
+}
+
+/// The manifest of the character, built and run as the most likely candidate
+/// of the workspace suggests.
+#[cfg(test)]
+fn scaffold(name: &str, candidates: &[Candidate]) -> Result<String> {
+    render(&Inputs::detected(name, candidates))
+}
+
+/// The manifest of the character, built and run as the inputs say.
+fn render(inputs: &Inputs) -> Result<String> {
+    // Init the Manifest fields, then fill them from the inputs.
+    let serialized = manifest_edit::serialize(&Character::new(&inputs.name))?;
+    let mut manifest = ManifestEdit::parse(FILE_NAME, &serialized)?;
+    if let Some(version) = &inputs.version {
+        manifest.set(&["meta", "version"], version.as_str())?;
+    }
+    if let Some(description) = &inputs.description {
+        manifest.set(&["meta", "description"], description.as_str())?;
+    }
+
+    let candidate = inputs.candidate.as_ref();
+    match inputs.build {
+        Strategy::Dockerfile => manifest.set(&["build", "dockerfile"], "Dockerfile")?,
+        Strategy::Buildpacks => {
+            manifest.set(&["build", "buildpacks", "builder"], candidate.and_then(|c| c.builder).unwrap_or(BUILDER))?;
+            if let Some(command) = candidate.and_then(|c| c.build_command.as_deref()) {
+                manifest.set(&["build", "command"], command)?;
+            }
+        }
+        Strategy::Manual => {}
+    }
+    if let Some(command) = candidate.and_then(|c| c.command.as_deref()) {
+        manifest.set(&["deploy", "command"], command)?;
+    }
+    if !inputs.env.is_empty() {
+        let env: InlineTable = inputs.env.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        manifest.set(&["deploy", "env"], env)?;
+    }
+    if !inputs.ports.is_empty() {
+        let ports: Array = inputs
+            .ports
+            .iter()
+            .map(|port| {
+                InlineTable::from_iter([("port", toml_edit::Value::from(*port as i64)), ("expose", true.into())])
+            })
+            .collect();
+        let service = InlineTable::from_iter([("kind", toml_edit::Value::from("ClusterIP")), ("ports", ports.into())]);
+        manifest.set(&["deploy", "services"], Array::from_iter([service]))?;
+    }
+
+    // The manifest has no place for the tests, suggest running them as a hook.
+    let mut serialized = manifest.to_string();
+    if let Some(command) = &inputs.test_command {
+        serialized
+            .push_str(&format!("\n# Run the tests before each deployment:\n# [hooks]\n# pre-deploy = {:?}\n", command));
+    }
+    Ok(serialized)
 }
 
-fn create(name: &str) -> Result<()> {
-    // Init and fill the Manifest fields.
-    let manifest = Character::new(name);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt::{Answer, ScriptedPrompter};
 
-    // Convert the Manifest to a TOML String.
-    let serialized = toml::to_string(&manifest).map_err(Errors::TomlSerializeError)?;
-    println!("{}", serialized);
-    fs::write(FILE_NAME, serialized).map_err(Errors::FailedSaveManifest)?;
+    fn fixture(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/detect").join(name)
+    }
+
+    #[test]
+    fn scaffold_go_modules() {
+        let candidates = detect::detect(&fixture("go"));
+        let manifest = scaffold("greeter", &candidates).unwrap();
+        assert!(manifest.contains(r#"name = "greeter""#), "{}", manifest);
+        assert!(manifest.contains(r#"description = "A Go application""#));
+        assert!(manifest.contains(r#"builder = "heroku/buildpacks:20""#));
+        assert!(manifest.contains(r#"command = "go run .""#));
+
+        let candidates = detect::detect(&fixture("go-docker"));
+        let manifest = scaffold("api", &candidates).unwrap();
+        assert!(manifest.contains(r#"dockerfile = "Dockerfile""#), "{}", manifest);
+        assert!(!manifest.contains("builder"));
+    }
+
+    #[test]
+    fn scaffold_node_packages() {
+        let candidates = detect::detect(&fixture("node-minimal"));
+        let name = candidates.iter().find_map(|c| c.name.clone()).unwrap();
+        let manifest = scaffold(&name, &candidates).unwrap();
+        assert!(manifest.contains(r#"name = "greeter""#), "{}", manifest);
+        assert!(manifest.contains(r#"builder = "heroku/buildpacks:20""#));
+        assert!(manifest.contains(r#"command = "node index.js""#));
+        assert!(!manifest.contains("hooks"));
+
+        let candidates = detect::detect(&fixture("node-full"));
+        let manifest = scaffold("storefront", &candidates).unwrap();
+        assert!(manifest.contains(r#"version = "2.3.1""#), "{}", manifest);
+        assert!(manifest.contains(r#"dockerfile = "Dockerfile""#), "the Dockerfile is preferred");
+        assert!(!manifest.contains("builder"));
+        assert!(manifest.contains(r#"command = "node server.js""#));
+        assert!(manifest.contains("# pre-deploy = \"npm test\""));
+        assert!(manifest.parse::<toml::Table>().is_ok());
+    }
+
+    #[test]
+    fn scaffold_cargo_packages() {
+        let candidates = detect::detect(&fixture("rust-bin"));
+        let manifest = scaffold("hello", &candidates).unwrap();
+        assert!(manifest.contains(r#"builder = "paketobuildpacks/builder:base""#), "{}", manifest);
+        assert!(manifest.contains(r#"command = "cargo build --release""#));
+        assert!(manifest.contains(r#"command = "./target/release/hello""#));
+    }
+
+    #[test]
+    fn parse_the_variables_apart_from_the_global_env() {
+        use clap::{CommandFactory, FromArgMatches};
 
-    Ok(())
+        let args = ["amp", "init", "--env-var", "LOG=info", "--env-var", "PORT=80", "--env", "staging"];
+        let matches = crate::cmd::cli::Cli::command().try_get_matches_from(args).unwrap();
+        let (name, init) = matches.subcommand().unwrap();
+        assert_eq!(name, "init");
+        assert_eq!(init.get_one::<String>("env").map(String::as_str), Some("staging"));
+
+        let cli = Cli::from_arg_matches(init).unwrap();
+        assert_eq!(cli.variables, vec![("LOG".into(), "info".into()), ("PORT".into(), "80".into())]);
+    }
+
+    #[test]
+    fn wizard_asks_for_the_inputs() {
+        let candidates = detect::detect(&fixture("node-full"));
+        let answers = vec![
+            Answer::Default,
+            Answer::Text("The shop".into()),
+            Answer::Select(1),
+            Answer::Default,
+            Answer::MultiSelect(vec![2, 5]),
+            Answer::Text("LOG_LEVEL=info".into()),
+            Answer::Text("oops".into()),
+            Answer::Text("".into()),
+        ];
+        let inputs = Inputs::detected("storefront", &candidates);
+        let inputs = wizard(&ScriptedPrompter::new(answers), inputs, &candidates).unwrap();
+        assert_eq!(inputs.name, "storefront");
+        assert_eq!(inputs.description.as_deref(), Some("The shop"));
+        assert_eq!(inputs.candidate.as_ref().map(|c| c.language), Some("node"));
+        assert_eq!(inputs.build, Strategy::Buildpacks, "the build of the selected language");
+        assert_eq!(inputs.ports, vec![3000, 8080]);
+        assert_eq!(inputs.env, vec![("LOG_LEVEL".into(), "info".into())]);
+
+        let manifest = render(&inputs).unwrap();
+        let table: toml::Table = manifest.parse().unwrap();
+        assert_eq!(table["build"]["buildpacks"]["builder"].as_str(), Some("heroku/buildpacks:20"));
+        assert_eq!(table["deploy"]["command"].as_str(), Some("npm start"));
+        assert_eq!(table["deploy"]["env"]["LOG_LEVEL"].as_str(), Some("info"));
+        let ports = table["deploy"]["services"][0]["ports"].as_array().unwrap();
+        assert_eq!(ports.iter().map(|p| p["port"].as_integer().unwrap()).collect::<Vec<_>>(), vec![3000, 8080]);
+    }
+
+    #[test]
+    fn wizard_defaults_to_the_detection() {
+        let candidates = detect::detect(&fixture("node-full"));
+        let answers = vec![Answer::Default, Answer::Default, Answer::Default, Answer::Default, Answer::Default];
+        let inputs = Inputs::detected("storefront", &candidates);
+        let prompter = ScriptedPrompter::new([answers, vec![Answer::Text("".into())]].concat());
+        let inputs = wizard(&prompter, inputs, &candidates).unwrap();
+        assert_eq!(inputs.description.as_deref(), Some("The storefront of the shop"));
+        assert_eq!(inputs.candidate.as_ref().map(|c| c.language), Some("docker"));
+        assert_eq!(inputs.build, Strategy::Dockerfile);
+        assert_eq!(inputs.ports, vec![3000], "the detected port is selected");
+    }
+
+    #[test]
+    fn pick_a_detected_language() {
+        let candidates = detect::detect(&fixture("node-full"));
+        assert_eq!(pick(&candidates, "Node").unwrap().language, "node");
+        let result = pick(&candidates, "python");
+        assert!(matches!(result, Err(Errors::LanguageNotDetected(language, detected))
+            if language == "python" && detected == "docker, node"));
+    }
 }
@@ -0,0 +1,113 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use amp_common::schema::Character;
+use clap::Args;
+use colored::Colorize;
+use serde_json::Value;
+
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::lint::{self, Severity};
+use crate::ops::{pipeline, validate};
+use crate::schema;
+
+/// Check the character manifest for errors
+#[derive(Args, Debug)]
+#[command(after_help = super::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// Path or URL to the Amphitheatre config file
+    #[arg(short, long, env = "AMP_FILENAME")]
+    filename: Option<String>,
+
+    /// Activate profiles by name (prefixed with `-` to disable a profile)
+    #[arg(short, long, env = "AMP_PROFILE", value_delimiter = ',', allow_hyphen_values = true)]
+    profile: Vec<String>,
+
+    /// Print the effective manifest, with the active profiles merged
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    effective: bool,
+
+    /// Also validate against the JSON Schema, reporting unknown keys and type mismatches
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    schema: bool,
+
+    /// Also send the manifest to the server, which checks what can't be checked locally
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    remote: bool,
+
+    /// Skip the lint rules with the given IDs
+    #[arg(long, value_name = "RULE", value_delimiter = ',')]
+    allow: Vec<String>,
+
+    /// List the lint rules and exit
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    rules: bool,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        if self.rules {
+            for rule in &lint::RULES {
+                let severity = format!("{:?}", rule.severity).to_lowercase();
+                ctx.stdout.println(format_args!("{:<18} {:<7} {}", rule.id, severity, rule.description))?;
+            }
+            return Ok(());
+        }
+
+        let manifest = pipeline::read(&ctx, &self.filename, &self.profile).await?;
+        let origin = manifest.origin.to_string();
+        if self.effective {
+            ctx.stdout.print(&manifest.content)?;
+        }
+
+        let mut issues = vec![];
+        if self.schema {
+            let value: Value =
+                toml::from_str(&manifest.content).map_err(|e| Errors::FailedParseManifest(origin.clone(), e))?;
+            issues = schema::validate(&schema::manifest(), &value).iter().map(|i| i.to_string()).collect();
+        }
+        let parsed = toml::from_str::<Character>(&manifest.content);
+        let diagnostics = lint::run(&manifest.content, &self.allow, None)?;
+
+        let remote = match self.remote {
+            true => validate::remote(&ctx, &manifest.content).await?.unwrap_or_default(),
+            false => vec![],
+        };
+
+        for (source, issue) in issues.iter().map(|i| ("client", i)).chain(remote.iter().map(|i| ("server", i))) {
+            match self.remote {
+                true => ctx.stdout.println(format_args!("{} [{}] {}", "✗".red(), source, issue))?,
+                false => ctx.stdout.println(format_args!("{} {}", "✗".red(), issue))?,
+            }
+        }
+
+        for diagnostic in &diagnostics {
+            ctx.stdout.println(format_args!("{}:{}", origin, diagnostic))?;
+        }
+
+        let errors = issues.len() + diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+        if errors > 0 {
+            return Err(Errors::InvalidManifest(origin, errors));
+        }
+        parsed.map_err(|e| Errors::FailedParseManifest(origin.clone(), e))?;
+        if !remote.is_empty() {
+            return Err(Errors::RemoteValidationFailed(remote));
+        }
+
+        ctx.stdout.println(format!("{} is valid", origin).green())
+    }
+}
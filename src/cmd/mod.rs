@@ -12,19 +12,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod actor;
+pub mod cache;
 pub mod clean;
 pub mod cli;
 pub mod completion;
 pub mod config;
 pub mod context;
+pub mod create;
 pub mod debug;
 pub mod deploy;
 pub mod dev;
 pub mod diagnose;
+pub mod diff;
+pub mod fix;
 pub mod init;
-pub mod list;
+pub mod lint;
 pub mod options;
+pub mod playbook;
 pub mod render;
 pub mod run;
+pub mod schema;
 pub mod test;
 pub mod version;
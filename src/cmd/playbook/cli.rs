@@ -0,0 +1,61 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use clap::{Args, Subcommand};
+
+use crate::context::Context;
+use crate::errors::Result;
+
+/// Manage the playbooks on the current cluster
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    Create(super::create::Cli),
+    Events(super::events::Cli),
+    Export(super::export::Cli),
+    Get(super::get::Cli),
+    Import(super::import::Cli),
+    List(super::list::Cli),
+    Logs(super::logs::Cli),
+    Rename(super::rename::Cli),
+    Start(super::start::Cli),
+    Stop(super::stop::Cli),
+    Watch(super::watch::Cli),
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        match &self.command {
+            Commands::Create(cli) => cli.exec(ctx).await,
+            Commands::Events(cli) => cli.exec(ctx).await,
+            Commands::Export(cli) => cli.exec(ctx).await,
+            Commands::Get(cli) => cli.exec(ctx).await,
+            Commands::Import(cli) => cli.exec(ctx).await,
+            Commands::List(cli) => cli.exec(ctx).await,
+            Commands::Logs(cli) => cli.exec(ctx).await,
+            Commands::Rename(cli) => cli.exec(ctx).await,
+            Commands::Start(cli) => cli.exec(ctx).await,
+            Commands::Stop(cli) => cli.exec(ctx).await,
+            Commands::Watch(cli) => cli.exec(ctx).await,
+        }
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use clap::Args;
+use tokio::time::Duration;
+use tracing::info;
+
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::actors::State;
+use crate::ops::events;
+use crate::ops::metadata::GitSource;
+use crate::ops::playbook::{self, CreateOptions, Source};
+use crate::ops::playbooks;
+use crate::ops::readiness::{self, Outcome};
+use crate::progress::Progress;
+
+/// Create a playbook from the manifest
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// Path or URL to the Amphitheatre config file
+    #[arg(short, long, env = "AMP_FILENAME")]
+    filename: Option<String>,
+
+    /// Activate profiles by name (prefixed with `-` to disable a profile)
+    #[arg(short, long, env = "AMP_PROFILE", value_delimiter = ',', allow_hyphen_values = true)]
+    profile: Vec<String>,
+
+    /// Create the playbook from the manifest of a remote git repository, which the server reads itself
+    #[arg(long, value_name = "URL", conflicts_with_all = ["filename", "profile"])]
+    from_git: Option<String>,
+
+    /// The branch, tag or commit of the repository, its default branch if omitted
+    #[arg(long = "ref", value_name = "REF", requires = "from_git")]
+    reference: Option<String>,
+
+    /// The directory of the manifest in the repository, its root if omitted
+    #[arg(long, value_name = "PATH", requires = "from_git")]
+    subpath: Option<String>,
+
+    /// Deploy the lead character alone, ignoring the partners of the manifest
+    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_NO_PARTNERS")]
+    no_partners: bool,
+
+    /// Skip the lint rules with the given IDs, see `amp lint --rules`
+    #[arg(long, value_name = "RULE", value_delimiter = ',')]
+    allow: Vec<String>,
+
+    /// Wait until the playbook is running, or failed
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    wait: bool,
+
+    /// The seconds between two polls of the playbook status when waiting
+    #[arg(long, default_value = "2", requires = "wait", value_parser = clap::value_parser!(u64).range(1..))]
+    poll_interval: u64,
+
+    /// The seconds to wait for the playbook before giving up
+    #[arg(long, default_value = "300", requires = "wait", value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: u64,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let source = match &self.from_git {
+            Some(url) => Source::Repository(GitSource {
+                url: url.clone(),
+                reference: self.reference.clone(),
+                subpath: self.subpath.clone(),
+            }),
+            None => Source::Manifest { filename: self.filename.clone(), profiles: self.profile.clone() },
+        };
+        let opt = CreateOptions {
+            source,
+            once: true,
+            partners: !self.no_partners,
+            allow: self.allow.clone(),
+            hooks: true,
+            ..CreateOptions::default()
+        };
+        let playbook = playbook::create(&ctx, &opt).await?.playbook;
+        ctx.stdout.println(&playbook.id)?;
+        if !self.wait {
+            return Ok(());
+        }
+
+        let api = ctx.api().await;
+        let poll = || async {
+            let (value, _) = playbooks::get(&api, &playbook.id).await?;
+            Ok(State::from(&value))
+        };
+
+        let spinner = Progress::spinner("Waiting for the playbook to be running...");
+        let every = Duration::from_secs(self.poll_interval);
+        let timeout = Duration::from_secs(self.timeout);
+        let tick = |state: &State| spinner.set_message(format!("Waiting for the playbook, it is {}...", state.status));
+        let outcome = readiness::wait(&ctx.shutdown, every, timeout, poll, tick).await;
+        spinner.finish();
+
+        match outcome? {
+            Outcome::Running => {
+                info!("The playbook is running");
+                ctx.stdout.println(api.url(&format!("/playbooks/{}", playbook.id)))
+            }
+            Outcome::Failed(error) => {
+                let error = match error {
+                    Some(error) => Some(error),
+                    None => events::last_error(&ctx, &playbook.id).await,
+                };
+                Err(Errors::PlaybookFailed(playbook.id, error.unwrap_or_else(|| "unknown error".into())))
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use clap::Args;
+use tracing::warn;
+
+use crate::context::Context;
+use crate::errors::Result;
+use crate::ops::events;
+
+/// Stream the events of a playbook
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// The ID of the playbook
+    id: String,
+
+    /// Only stream the events after the given event ID
+    #[arg(long, value_name = "EVENT_ID")]
+    after: Option<String>,
+
+    /// Resume after the last event received by a previous run
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "after")]
+    resume: bool,
+
+    /// Keep streaming, reconnecting after interruptions
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    follow: bool,
+
+    /// Replay all the events from the creation of the playbook, as they were
+    /// spaced in time, for the post-mortems of failed deployments
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["after", "resume", "follow"])]
+    replay: bool,
+
+    /// The speed of the replay relative to the original timing, 2 for twice as
+    /// fast, 0 to replay every event at once
+    #[arg(long, value_name = "FACTOR", default_value_t = 1.0, value_parser = parse_speed, requires = "replay")]
+    speed: f64,
+
+    /// Stop the replay after the event with the given ID
+    #[arg(long, value_name = "EVENT_ID", requires = "replay")]
+    stop_at: Option<String>,
+
+    /// The output format, `tap` prints the test results as TAP
+    #[arg(long, value_enum, default_value_t)]
+    format: events::Format,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        if self.replay {
+            return events::replay(&ctx, &self.id, self.speed, self.stop_at.as_deref(), self.format).await;
+        }
+
+        let mut after = self.after.clone();
+        if self.resume {
+            after = events::last_id(&self.id);
+            if after.is_none() {
+                warn!("No stored event ID found for playbook {}, streaming from the start", self.id);
+            }
+        }
+
+        events::stream(&ctx, &self.id, after, self.follow, self.format).await
+    }
+}
+
+/// Parse the replay speed, a finite and non-negative factor.
+fn parse_speed(s: &str) -> std::result::Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(speed) if speed.is_finite() && speed >= 0.0 => Ok(speed),
+        _ => Err(format!("invalid speed `{}`: expected a non-negative number such as 0.5, 1 or 2", s)),
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use clap::{Args, ValueEnum};
+use tracing::{info, warn};
+
+use crate::context::Context;
+use crate::errors::Result;
+use crate::ops::{compose, discover, helm, playbooks};
+use crate::output;
+
+/// Export a playbook, to import it again or to deploy it without Amphitheatre
+///
+/// The Helm chart deploys the image of each character, exposes its ports and
+/// holds its environment. Building from source, the live sync, the partners
+/// and the playbook lifecycle have no Helm equivalent and are left out. The
+/// Docker Compose file runs the images as well, depending on the partners.
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// The ID of the playbook, discovered from the workspace if omitted
+    id: Option<String>,
+
+    /// What to export the playbook as
+    #[arg(short, long, value_enum, default_value_t)]
+    format: ExportFormat,
+
+    /// The directory of the Helm chart or the Compose file, otherwise the chart is named after the
+    /// playbook in the current directory, and the Compose file is written in the current directory
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+enum ExportFormat {
+    /// The JSON of the playbook, for `amp playbook import`
+    #[default]
+    Json,
+    /// A Helm chart of Deployments, Services, ConfigMaps and Secrets
+    Helm,
+    /// A docker-compose.yml of a service for each character
+    DockerCompose,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let pid = discover::playbook_id(&ctx, self.id.as_deref()).await?;
+        let path = format!("/playbooks/{}", pid);
+        let api = ctx.api().await;
+        let request = playbooks::get(&api, &pid);
+        let (value, _) = ctx.recorder.call_async("GET", &path, request).await?;
+
+        match self.format {
+            ExportFormat::Json => ctx.stdout.println(output::json(&value)?),
+            ExportFormat::Helm => {
+                let chart = helm::chart(&value)?;
+                for feature in &chart.unsupported {
+                    warn!("Left out of the chart: {}", feature);
+                }
+                let dir = self.output_dir.clone().unwrap_or_else(|| PathBuf::from(&chart.name));
+                chart.write(&dir)?;
+                info!("Exported the playbook as the Helm chart {}", chart.name);
+                ctx.stdout.println(dir.display())
+            }
+            ExportFormat::DockerCompose => {
+                let compose = compose::compose(&value)?;
+                for feature in &compose.unsupported {
+                    warn!("Left out of the Compose file: {}", feature);
+                }
+                let dir = self.output_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+                compose.write(&dir)?;
+                info!("Exported the playbook as a Docker Compose file");
+                ctx.stdout.println(dir.join(compose::FILE).display())
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use amp_common::resource::{ActorSpec, PlaybookSpec};
+use clap::Args;
+use serde::Serialize;
+use serde_json::Value;
+use tabled::Tabled;
+
+use crate::cmd::playbook::list::PlaybookTable;
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::metadata::Metadata;
+use crate::ops::playbooks;
+use crate::output::{self, Format};
+use crate::utils::git::Revision;
+
+/// Print the details of a playbook
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// The ID of the playbook
+    id: String,
+
+    /// Also fetch the actors of the playbook and include them in the output
+    #[arg(long, action = clap::ArgAction::SetTrue, default_value = "false")]
+    actors: bool,
+
+    /// Output format, one of: table, json, csv, name
+    #[arg(short, long, value_enum, default_value_t = Format::Table, env = "AMP_OUTPUT")]
+    output: Format,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let path = format!("/playbooks/{}", self.id);
+        let api = ctx.api().await;
+        let request = playbooks::get(&api, &self.id);
+        let (value, metadata) = ctx.recorder.call_async("GET", &path, request).await?;
+        let playbook: PlaybookSpec =
+            serde_json::from_value(value.clone()).map_err(|e| Errors::FailedFetchPlaybook(e.to_string()))?;
+
+        let mut actors = None;
+        if self.actors {
+            let path = format!("/actors/{}", self.id);
+            let list = ctx.recorder.call("GET", &path, || ctx.client.actors().list(&self.id, None));
+            actors = Some(list.map_err(Errors::ActorFetchFailed)?);
+        }
+
+        match self.output {
+            Format::Table => {
+                ctx.stdout.println(render_table(&playbook, &metadata, actors.as_deref()))?;
+                if let Some(revision) = Revision::from_attributes(&value["attributes"]) {
+                    ctx.stdout.println(format!("Last synced from {}", revision))?;
+                }
+            }
+            Format::Json => ctx.stdout.println(ctx.json(&render_json(&value, actors.as_deref()))?)?,
+            Format::Csv => ctx.stdout.print(output::csv(&[PlaybookTable::from(&playbook)])?)?,
+            Format::Name => ctx.stdout.println(&playbook.id)?,
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Tabled)]
+pub(crate) struct ActorTable {
+    name: String,
+    image: String,
+}
+
+impl From<&ActorSpec> for ActorTable {
+    fn from(value: &ActorSpec) -> Self {
+        Self { name: value.name.clone(), image: value.image.clone() }
+    }
+}
+
+#[derive(Tabled)]
+struct MetadataTable {
+    maintainers: String,
+    repository: String,
+    icon: String,
+}
+
+impl From<&Metadata> for MetadataTable {
+    fn from(value: &Metadata) -> Self {
+        Self {
+            maintainers: value.maintainers.as_deref().unwrap_or_default().join(", "),
+            repository: value.repository.clone().unwrap_or_default(),
+            icon: value.icon.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// Render the playbook, its metadata if any, and the actors if fetched, as tables.
+fn render_table(playbook: &PlaybookSpec, metadata: &Metadata, actors: Option<&[ActorSpec]>) -> String {
+    let mut details = output::table(vec![PlaybookTable::from(playbook)]);
+    if !metadata.is_empty() {
+        details = format!("{}\n{}", details, output::table(vec![MetadataTable::from(metadata)]));
+    }
+    match actors {
+        Some(actors) => format!("{}\n{}", details, actors_table(actors.iter().map(ActorTable::from).collect())),
+        None => details,
+    }
+}
+
+fn actors_table(rows: Vec<ActorTable>) -> String {
+    if rows.is_empty() {
+        return String::from("No actors found");
+    }
+    output::table(rows)
+}
+
+/// Render the playbook as JSON, embedding the actors under the `actors` key if fetched.
+fn render_json<P: Serialize, A: Serialize>(playbook: &P, actors: Option<&[A]>) -> Value {
+    let mut value = serde_json::to_value(playbook).unwrap_or(Value::Null);
+    if let (Some(actors), Value::Object(map)) = (actors, &mut value) {
+        map.insert(String::from("actors"), serde_json::to_value(actors).unwrap_or(Value::Null));
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn embed_actors_in_json() {
+        let playbook = json!({"id": "1", "title": "demo"});
+        let actors = vec![json!({"name": "web"}), json!({"name": "db"})];
+
+        let value = render_json(&playbook, Some(actors.as_slice()));
+        assert_eq!(value["actors"], json!([{"name": "web"}, {"name": "db"}]));
+
+        let value = render_json::<_, Value>(&playbook, None);
+        assert!(value.get("actors").is_none());
+    }
+
+    #[test]
+    fn embed_actors_in_table() {
+        let rows = vec![ActorTable { name: "web".into(), image: "nginx:latest".into() }];
+        let table = actors_table(rows);
+        assert!(table.contains("web"));
+        assert!(table.contains("nginx:latest"));
+
+        assert_eq!(actors_table(vec![]), "No actors found");
+    }
+
+    #[test]
+    fn render_metadata_table() {
+        let metadata = Metadata {
+            maintainers: Some(vec!["jane".into(), "joe".into()]),
+            repository: None,
+            icon: Some("https://example.com/web.png".into()),
+        };
+        let table = output::table(vec![MetadataTable::from(&metadata)]);
+        assert!(table.contains("jane, joe"));
+        assert!(table.contains("https://example.com/web.png"));
+    }
+}
@@ -0,0 +1,58 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+use std::sync::Arc;
+
+use clap::Args;
+use tracing::info;
+
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::importer::{self, Exported, Strategy};
+use crate::ops::metadata::Payload;
+
+/// Import a playbook exported with `amp playbook export`
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// The JSON file of the exported playbook
+    file: String,
+
+    /// What to do when a playbook with the same title already exists
+    #[arg(long, value_enum, default_value_t)]
+    on_conflict: Strategy,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let content =
+            fs::read_to_string(&self.file).map_err(|e| Errors::FailedReadPlaybook(self.file.clone(), e.to_string()))?;
+        let exported: Exported =
+            serde_json::from_str(&content).map_err(|e| Errors::FailedReadPlaybook(self.file.clone(), e.to_string()))?;
+
+        let payload: Payload = exported.into();
+        if ctx.dry_run {
+            let source = importer::validate(&self.file, &payload)?;
+            info!("Would import the playbook {} from {}", payload.playbook.title, source);
+            ctx.stdout.println(importer::preview(&payload)?.trim_end())?;
+        }
+
+        let api = ctx.api().await;
+        let playbook = importer::import(&api, &ctx.recorder, payload, self.on_conflict, ctx.prompter.as_ref()).await?;
+
+        info!("Imported the playbook as {}", playbook.title);
+        ctx.stdout.println(&playbook.id)
+    }
+}
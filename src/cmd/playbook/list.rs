@@ -0,0 +1,593 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::sync::Arc;
+
+use amp_common::resource::PlaybookSpec;
+use chrono::{DateTime, Utc};
+use clap::{Args, ValueEnum};
+use colored::Colorize;
+use regex::Regex;
+use serde_json::Value;
+use tabled::Tabled;
+use tokio::time::Duration;
+use tracing::{debug, warn};
+
+use crate::client::{self, Api};
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::actors::State;
+use crate::ops::listing::{self, RequestOptions};
+use crate::ops::poller::Poller;
+use crate::output::{self, Layout};
+use crate::refresh::{Refresh, Ticker};
+use crate::template;
+
+/// List all running instances
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// Output format, one of: table, wide, json, csv, name (or id-only), title-only, template, count, none
+    #[arg(short, long, value_enum, default_value_t = ListFormat::Table, env = "AMP_OUTPUT")]
+    output: ListFormat,
+
+    /// The Handlebars template rendered for each playbook with `--output template`,
+    /// with the upper, lower, truncate and format_date helpers. For example:
+    ///
+    ///   amp playbook list --output template '{{id}} {{title}}'
+    ///   amp playbook list --output template '{{upper title}}: {{truncate description 50}}'
+    ///   amp playbook list --output template '{{id}} {{format_date created_at "%Y-%m-%d"}}'
+    #[arg(value_name = "TEMPLATE", required_if_eq("output", "template"), verbatim_doc_comment)]
+    template: Option<String>,
+
+    /// Only list the playbooks created by the given user (email)
+    #[arg(long, env = "AMP_CREATED_BY")]
+    created_by: Option<String>,
+
+    /// Only list the playbooks created by the current user
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "created_by")]
+    me: bool,
+
+    /// Only list the playbooks whose title or description contains the text
+    #[arg(long, value_name = "TEXT")]
+    search: Option<String>,
+
+    /// Only request the given fields of the playbooks from the server, among id,
+    /// title, description, status, created_at, updated_at, owner, created_by,
+    /// labels and characters, printed as a table of them or as JSON
+    #[arg(long, value_name = "FIELD", value_delimiter = ',', conflicts_with_all = ["watch", "search"])]
+    fields: Vec<String>,
+
+    /// Keep polling the playbooks, printing them again when they change, until interrupted
+    #[arg(short, long, action = clap::ArgAction::SetTrue)]
+    watch: bool,
+
+    /// The seconds between two polls of `--watch`
+    #[arg(long, value_name = "SECONDS", default_value_t = 2, requires = "watch")]
+    interval: u64,
+
+    /// Pick the columns of the table among id, title, status, created_at, last_updated,
+    /// age, owner, labels and actors, `id,title,status` by default, or
+    /// `output.columns.playbooks` in the configuration
+    #[clap(flatten)]
+    columns: output::Columns,
+
+    // `--wide` also shows the creation and update times, owner, labels and
+    // actor count, a shorthand for `--output wide`.
+    #[clap(flatten)]
+    table: output::TableArgs,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let options = self.options(&ctx).await?;
+        if self.watch {
+            return self.watch(&ctx, options.unwrap_or_default()).await;
+        }
+        if !self.fields.is_empty() {
+            return self.sparse(&ctx, options.unwrap_or_default()).await;
+        }
+        if matches!(self.output, ListFormat::Count | ListFormat::None) {
+            let count = self.count(&ctx, options.unwrap_or_default()).await?;
+            return match self.output {
+                ListFormat::None if count == 0 => Err(Errors::NoMatch),
+                ListFormat::None => Ok(()),
+                _ => ctx.stdout.println(count),
+            };
+        }
+
+        let playbooks = ctx.recorder.call_async("GET", "/playbooks", ctx.playbooks().list_async(options)).await;
+        let playbooks = match &self.search {
+            Some(_) => playbooks.map_err(Errors::SearchFailed)?,
+            None => playbooks?,
+        };
+        self.render(&ctx, playbooks, None)
+    }
+
+    /// Poll the playbooks with conditional requests, so the unchanged lists
+    /// are neither downloaded nor printed again, unless to clear the flags of
+    /// the playbooks added or removed.
+    async fn watch(&self, ctx: &Context, query: HashMap<String, String>) -> Result<()> {
+        let mut poller = Poller::new(query);
+        let mut refresh = Refresh::new(std::io::stdout().is_terminal());
+        let mut ticker = Ticker::new(&ctx.shutdown, Duration::from_secs(self.interval));
+        while ticker.tick().await {
+            let changed = poller.poll(&ctx.api().await).await?;
+            if changed || refresh.flagged() {
+                self.render(ctx, poller.playbooks().to_vec(), Some(&mut refresh))?;
+            }
+        }
+
+        debug!("Received {} bytes of playbooks, {} spared by the unchanged lists", poller.received, poller.spared);
+        Ok(())
+    }
+
+    /// List only the fields of the playbooks, asking the server for a sparse
+    /// fieldset and picking them locally if it sends the whole playbooks.
+    async fn sparse(&self, ctx: &Context, mut query: HashMap<String, String>) -> Result<()> {
+        if let Some(field) = self.fields.iter().find(|f| !FIELDS.contains(&f.as_str())) {
+            return Err(Errors::InvalidFieldName(field.clone()));
+        }
+        query.insert(String::from("fields"), self.fields.join(","));
+        let options = RequestOptions { query, if_none_match: None };
+        let response = listing::list::<Value>(&ctx.api().await, &options).await?;
+
+        let playbooks = response.data.unwrap_or_default();
+        let (playbooks, ignored) = project(playbooks, &self.fields);
+        if ignored {
+            warn!("The server ignored the sparse fieldset, the fields were picked locally");
+        }
+        if self.output == ListFormat::Json {
+            return ctx.stdout.println(ctx.json(&playbooks)?);
+        }
+
+        let cell = |value: &Value| match value {
+            Value::Null => String::new(),
+            Value::String(s) => s.clone(),
+            value => value.to_string(),
+        };
+        let rows = playbooks.iter().map(|p| self.fields.iter().map(|f| cell(&p[f])).collect());
+        let records = std::iter::once(self.fields.clone()).chain(rows).collect();
+        ctx.stdout.println(output::records_table(records, self.table.layout()))
+    }
+
+    /// Count the matching playbooks, trusting the total reported by the server
+    /// unless searching, as the server may ignore the search. Otherwise the
+    /// playbooks of every page are counted.
+    async fn count(&self, ctx: &Context, query: HashMap<String, String>) -> Result<usize> {
+        let api = ctx.api().await;
+        let mut options = RequestOptions { query, if_none_match: None };
+        let mut count = 0;
+        loop {
+            let response = listing::list::<PlaybookSpec>(&api, &options).await?;
+            let mut playbooks = response.data.unwrap_or_default();
+            match (&self.search, response.total) {
+                (Some(text), _) => {
+                    let fields = |p: &PlaybookSpec| [p.title.clone(), p.description.clone().unwrap_or_default()];
+                    search(&mut playbooks, &search_pattern(text), fields);
+                }
+                (None, Some(total)) => return Ok(total),
+                (None, None) => {}
+            }
+            count += playbooks.len();
+
+            let Some(next) = response.next else { return Ok(count) };
+            let mut query = options.query.clone();
+            query.extend(next);
+            if query == options.query {
+                return Ok(count);
+            }
+            options.query = query;
+        }
+    }
+
+    /// Render the playbooks, the tables refreshed in place when watching.
+    fn render(&self, ctx: &Context, mut playbooks: Vec<PlaybookSpec>, refresh: Option<&mut Refresh>) -> Result<()> {
+        let pattern = self.search.as_deref().map(search_pattern);
+        if let Some(pattern) = &pattern {
+            let fields = |p: &PlaybookSpec| [p.title.clone(), p.description.clone().unwrap_or_default()];
+            if search(&mut playbooks, pattern, fields) {
+                warn!("The server ignored the search, the playbooks were filtered locally");
+            }
+        }
+
+        let format = if self.table.wide { ListFormat::Wide } else { self.output };
+        if playbooks.is_empty() && refresh.is_none() && matches!(format, ListFormat::Table | ListFormat::Wide) {
+            warn!("No playbooks found");
+            return Ok(());
+        }
+
+        let highlighted = || {
+            playbooks.iter().map(Playbook::from).map(|playbook| match &pattern {
+                Some(pattern) => playbook.highlight(pattern),
+                None => playbook,
+            })
+        };
+        let rows: Vec<PlaybookTable> = playbooks.iter().map(PlaybookTable::from).collect();
+        let keyed = |playbook: Playbook| (playbook.id.clone(), playbook);
+        let layout = match format {
+            ListFormat::Wide => Layout::new(!self.table.no_header).wrap(Some(WRAP_WIDTH)),
+            _ => self.table.layout(),
+        };
+        let columns = self.columns.or_configured("playbooks");
+        if !columns.is_empty() && matches!(format, ListFormat::Table | ListFormat::Wide) {
+            let columns = match format {
+                ListFormat::Wide => columns.or_headers::<WidePlaybookRow>(),
+                _ => columns.or_headers::<PlaybookRow>(),
+            };
+            let (headers, rows) = columns.select_keyed(highlighted().map(keyed).collect())?;
+            return match refresh {
+                Some(refresh) => refresh.show(&ctx.stdout, &refresh.records(headers, rows, layout)),
+                None => {
+                    let records = std::iter::once(headers).chain(rows.into_iter().map(|(_, fields)| fields));
+                    ctx.stdout.println(output::records_table(records.collect(), layout))
+                }
+            };
+        }
+        match (format, refresh) {
+            (ListFormat::Table, Some(refresh)) => {
+                let rows = highlighted().map(keyed).map(|(id, p)| (id, PlaybookRow::from(p))).collect();
+                refresh.show(&ctx.stdout, &refresh.table(rows, layout))?
+            }
+            (ListFormat::Wide, Some(refresh)) => {
+                let rows = highlighted().map(keyed).map(|(id, p)| (id, WidePlaybookRow::from(p))).collect();
+                refresh.show(&ctx.stdout, &refresh.table(rows, layout))?
+            }
+            (ListFormat::Table, None) => {
+                let rows = highlighted().map(PlaybookRow::from);
+                ctx.stdout.println(output::table_with_layout(rows, layout))?
+            }
+            (ListFormat::Wide, None) => {
+                let rows = highlighted().map(WidePlaybookRow::from);
+                ctx.stdout.println(output::table_with_layout(rows, layout))?
+            }
+            (ListFormat::Json, _) => ctx.stdout.println(ctx.json(&playbooks)?)?,
+            (ListFormat::Csv, _) => ctx.stdout.print(output::csv_with_header(&rows, !self.table.no_header)?)?,
+            (ListFormat::Name, _) => ctx.stdout.print(output::names(rows))?,
+            (ListFormat::TitleOnly, _) => ctx.stdout.print(output::names(playbooks.iter().map(PlaybookTitle::from)))?,
+            (ListFormat::Template, _) => {
+                ctx.stdout.print(template::render(self.template.as_deref().unwrap_or_default(), &playbooks)?)?
+            }
+            (ListFormat::Count, _) => ctx.stdout.println(playbooks.len())?,
+            (ListFormat::None, _) => {}
+        }
+
+        Ok(())
+    }
+
+    /// Build the request options from the filters.
+    async fn options(&self, ctx: &Context) -> Result<Option<HashMap<String, String>>> {
+        let mut options = HashMap::new();
+
+        if self.me {
+            let me = ctx.recorder.call_async("GET", "/me", email(&ctx.api().await)).await;
+            options.insert(String::from("created_by"), me.map_err(Errors::UserFilterFailed)?);
+        } else if let Some(user) = &self.created_by {
+            options.insert(String::from("created_by"), user.clone());
+        }
+        if let Some(text) = &self.search {
+            options.insert(String::from("q"), text.clone());
+        }
+
+        Ok((!options.is_empty()).then_some(options))
+    }
+}
+
+/// The email of the current user.
+async fn email(api: &Api) -> std::result::Result<String, String> {
+    let response = client::send(api.get("/me")).await.and_then(|r| r.error_for_status()).map_err(|e| e.to_string())?;
+    let account: Value = response.json().await.map_err(|e| e.to_string())?;
+    account["email"].as_str().map(String::from).ok_or_else(|| String::from("the account has no email"))
+}
+
+/// The fields of the playbooks which can be requested with `--fields`.
+const FIELDS: [&str; 10] =
+    ["id", "title", "description", "status", "created_at", "updated_at", "owner", "created_by", "labels", "characters"];
+
+/// Keep only the fields of the playbooks. Returns true if others were dropped,
+/// meaning the server didn't apply the sparse fieldset itself.
+fn project(playbooks: Vec<Value>, fields: &[String]) -> (Vec<Value>, bool) {
+    let mut ignored = false;
+    let playbooks = playbooks
+        .into_iter()
+        .map(|playbook| match playbook {
+            Value::Object(mut map) => {
+                let len = map.len();
+                map.retain(|key, _| fields.contains(key));
+                ignored |= map.len() != len;
+                Value::Object(map)
+            }
+            other => other,
+        })
+        .collect();
+    (playbooks, ignored)
+}
+
+/// The cells of the wide table longer than this are wrapped.
+const WRAP_WIDTH: usize = 32;
+
+/// The output formats of the playbook list, the common ones, a wide table and a template.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ListFormat {
+    /// Print resources as a human readable table
+    Table,
+    /// Print resources as a table with additional columns
+    Wide,
+    /// Print resources as pretty JSON
+    Json,
+    /// Print resources as comma-separated values, with a header row
+    Csv,
+    /// Print the ID of each resource, one per line
+    #[value(alias = "id-only", alias = "name-only")]
+    Name,
+    /// Print the title of each resource, one per line
+    TitleOnly,
+    /// Print each resource with the given Handlebars template
+    Template,
+    /// Print only the number of matching resources
+    Count,
+    /// Print nothing, exiting with 1 when no resource matches
+    None,
+}
+
+#[derive(Tabled)]
+pub(crate) struct PlaybookTable {
+    id: String,
+    title: String,
+    description: String,
+}
+
+/// The fields of a playbook shown by the tables, read leniently from the
+/// serialized playbook as they depend on the version of the server. All of
+/// them are the columns to pick from with `--columns`.
+#[derive(Debug, Default, Tabled)]
+struct Playbook {
+    id: String,
+    title: String,
+    status: String,
+    #[tabled(rename = "created at")]
+    created_at: String,
+    #[tabled(rename = "last updated")]
+    updated_at: String,
+    /// The time since the creation, to the minute
+    age: String,
+    owner: String,
+    /// The labels as comma-separated `key=value` pairs
+    labels: String,
+    actors: usize,
+}
+
+impl Playbook {
+    /// Bold the occurrences of the search in the title.
+    fn highlight(self, pattern: &Regex) -> Self {
+        let bold = |s: &str| pattern.replace_all(s, |c: &regex::Captures| c[0].bold().to_string()).into_owned();
+        Self { title: bold(&self.title), ..self }
+    }
+}
+
+impl From<&Value> for Playbook {
+    fn from(value: &Value) -> Self {
+        let string = |key: &str| value[key].as_str().unwrap_or_default().to_string();
+        let created = DateTime::parse_from_rfc3339(&string("created_at")).ok().map(|t| t.with_timezone(&Utc));
+        let time = |key: &str| match DateTime::parse_from_rfc3339(&string(key)) {
+            Ok(time) => time.format("%Y-%m-%d %H:%M").to_string(),
+            Err(_) => string(key),
+        };
+        let mut labels: Vec<String> = value["labels"]
+            .as_object()
+            .map(|labels| labels.iter().map(|(k, v)| format!("{}={}", k, v.as_str().unwrap_or_default())).collect())
+            .unwrap_or_default();
+        labels.sort();
+
+        Self {
+            id: string("id"),
+            title: string("title"),
+            status: State::from(value).status,
+            created_at: time("created_at"),
+            updated_at: time("updated_at"),
+            age: crate::cmd::cache::format_age(created, Utc::now()),
+            owner: [string("owner"), string("created_by")].into_iter().find(|o| !o.is_empty()).unwrap_or_default(),
+            labels: labels.join(","),
+            actors: value["characters"].as_array().map(Vec::len).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<&PlaybookSpec> for Playbook {
+    fn from(playbook: &PlaybookSpec) -> Self {
+        Playbook::from(&serde_json::to_value(playbook).unwrap_or_default())
+    }
+}
+
+/// The title of a playbook, the one field of `--output title-only`.
+#[derive(Tabled)]
+struct PlaybookTitle {
+    title: String,
+}
+
+impl From<&PlaybookSpec> for PlaybookTitle {
+    fn from(playbook: &PlaybookSpec) -> Self {
+        Self { title: playbook.title.clone() }
+    }
+}
+
+#[derive(Tabled)]
+struct PlaybookRow {
+    id: String,
+    title: String,
+    status: String,
+}
+
+impl From<Playbook> for PlaybookRow {
+    fn from(playbook: Playbook) -> Self {
+        Self { id: playbook.id, title: playbook.title, status: playbook.status }
+    }
+}
+
+#[derive(Tabled)]
+struct WidePlaybookRow {
+    id: String,
+    title: String,
+    status: String,
+    #[tabled(rename = "created at")]
+    created_at: String,
+    #[tabled(rename = "last updated")]
+    updated_at: String,
+    owner: String,
+    labels: String,
+    actors: usize,
+}
+
+impl From<Playbook> for WidePlaybookRow {
+    fn from(playbook: Playbook) -> Self {
+        Self {
+            id: playbook.id,
+            title: playbook.title,
+            status: playbook.status,
+            created_at: playbook.created_at,
+            updated_at: playbook.updated_at,
+            owner: playbook.owner,
+            labels: playbook.labels,
+            actors: playbook.actors,
+        }
+    }
+}
+
+/// A case-insensitive pattern matching the search text literally.
+fn search_pattern(text: &str) -> Regex {
+    Regex::new(&format!("(?i){}", regex::escape(text))).unwrap()
+}
+
+/// Keep the items having a field matching the pattern. Returns true if some
+/// were filtered out, meaning the server didn't apply the search itself.
+fn search<T, F>(items: &mut Vec<T>, pattern: &Regex, fields: F) -> bool
+where
+    F: Fn(&T) -> [String; 2],
+{
+    let len = items.len();
+    items.retain(|item| fields(item).iter().any(|f| pattern.is_match(f)));
+    items.len() != len
+}
+
+impl From<&PlaybookSpec> for PlaybookTable {
+    fn from(value: &PlaybookSpec) -> Self {
+        Self {
+            id: value.id.clone(),
+            title: value.title.clone(),
+            description: value.description.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(title: &str, description: &str) -> PlaybookTable {
+        PlaybookTable { id: title.to_lowercase(), title: title.into(), description: description.into() }
+    }
+
+    fn fields(row: &PlaybookTable) -> [String; 2] {
+        [row.title.clone(), row.description.clone()]
+    }
+
+    #[test]
+    fn server_side_search() {
+        let mut rows = vec![row("Checkout", "the cart service"), row("Payments", "talks to checkout")];
+        assert!(!search(&mut rows, &search_pattern("CHECKOUT"), fields));
+        assert_eq!(rows.len(), 2);
+    }
+
+    #[test]
+    fn client_side_search() {
+        let mut rows = vec![row("Checkout", ""), row("Search", "full-text (beta)"), row("Web", "frontend")];
+        assert!(search(&mut rows, &search_pattern("(beta)"), fields));
+        assert_eq!(rows.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), vec!["search"]);
+    }
+
+    #[test]
+    fn highlight_matching_terms() {
+        let playbook = Playbook { title: "Checkout check".into(), ..Playbook::default() };
+        let highlighted = playbook.highlight(&search_pattern("check"));
+        assert_eq!(highlighted.title, format!("{}out {}", "Check".bold(), "check".bold()));
+    }
+
+    fn playbook() -> Value {
+        serde_json::json!({
+            "id": "42",
+            "title": "web",
+            "status": {"state": "Running"},
+            "created_at": "2024-01-15T10:30:00Z",
+            "updated_at": "2024-01-16T08:00:00+01:00",
+            "created_by": "jane@example.com",
+            "labels": {"team": "shop", "env": "staging"},
+            "characters": [{"name": "web"}, {"name": "api"}]
+        })
+    }
+
+    #[test]
+    fn pick_the_sparse_fields() {
+        let fields = vec![String::from("id"), String::from("status")];
+        let (playbooks, ignored) = project(vec![playbook()], &fields);
+        assert!(ignored);
+        assert_eq!(playbooks, vec![serde_json::json!({"id": "42", "status": {"state": "Running"}})]);
+
+        let (_, ignored) = project(playbooks, &fields);
+        assert!(!ignored, "the server applied the sparse fieldset");
+    }
+
+    #[test]
+    fn read_the_wide_fields() {
+        let playbook = Playbook::from(&playbook());
+        assert_eq!(playbook.status, "Running");
+        assert_eq!(playbook.created_at, "2024-01-15 10:30");
+        assert_eq!(playbook.updated_at, "2024-01-16 08:00");
+        assert_eq!(playbook.owner, "jane@example.com");
+        assert_eq!(playbook.labels, "env=staging,team=shop");
+        assert_eq!(playbook.actors, 2);
+
+        let empty = Playbook::from(&serde_json::json!({"id": "1", "title": "api"}));
+        assert_eq!((empty.created_at.as_str(), empty.labels.as_str(), empty.actors), ("", "", 0));
+    }
+
+    #[test]
+    fn pick_a_single_column() {
+        let columns = output::Columns { columns: vec![String::from("title")], ..Default::default() };
+        let table = columns.table([Playbook::from(&playbook())], Layout::default()).unwrap();
+        assert_eq!(table.lines().nth(1).unwrap().matches('│').count(), 2, "{}", table);
+        assert!(table.contains("web") && !table.contains("42"), "{}", table);
+
+        let columns = output::Columns { columns: vec![String::from("created_on")], ..Default::default() };
+        let err = columns.table([Playbook::from(&playbook())], Layout::default()).unwrap_err();
+        assert!(
+            matches!(err, Errors::UnknownColumn(name, available) if name == "created_on" && available.contains("created_at"))
+        );
+    }
+
+    #[test]
+    fn wide_mode_adds_columns() {
+        let columns = |table: String| table.lines().nth(1).unwrap().matches('│').count() - 1;
+
+        let normal = output::table_with_header([PlaybookRow::from(Playbook::from(&playbook()))], true);
+        let wide = output::wrapped_table_with_header(
+            [WidePlaybookRow::from(Playbook::from(&playbook()))],
+            true,
+            Some(WRAP_WIDTH),
+        );
+        assert_eq!(columns(normal), 3);
+        assert_eq!(columns(wide), 8);
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use clap::Args;
+
+use crate::context::Context;
+use crate::errors::Result;
+use crate::ops::logger::{self, Filter, Options, Timestamps};
+use crate::utils::time::parse_time_flag;
+
+/// Stream the logs of every actor of a playbook, a shorthand for `amp actor logs --all`
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// The ID of the playbook
+    id: String,
+
+    /// Only show lines containing the pattern, may be given multiple times
+    #[arg(long, value_name = "PATTERN", action = clap::ArgAction::Append)]
+    grep: Vec<String>,
+
+    /// Only show the lines logged since an RFC 3339 timestamp, a duration ago such as 5m, 2h or 1d, or now
+    #[arg(long, value_name = "TIME")]
+    since: Option<String>,
+
+    /// Only show the last N lines of each actor logged so far
+    #[arg(long, value_name = "N")]
+    tail: Option<u64>,
+
+    /// Keep streaming the new lines, or exit once the lines logged so far are shown
+    #[arg(long, action = clap::ArgAction::Set, default_value = "true")]
+    follow: bool,
+
+    /// Don't prefix the lines with the names of their actors
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    no_prefix: bool,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let options = Options {
+            filter: Filter::new(&self.grep, false, false, false)?,
+            timestamps: match ctx.timestamps {
+                true => Timestamps::Utc,
+                false => Timestamps::Off,
+            },
+            since: self.since.as_deref().map(parse_time_flag).transpose()?,
+            tail: self.tail,
+            follow: self.follow,
+            ..Options::default()
+        };
+        logger::tail_playbook(&ctx, &self.id, &options, !self.no_prefix).await
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use amp_common::resource::PlaybookSpec;
+use clap::Args;
+use tracing::{info, warn};
+
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::playbooks;
+
+/// Rename a playbook, changing nothing but its title
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// The ID of the playbook
+    id: String,
+
+    /// The new title of the playbook
+    title: String,
+
+    /// Rename right away, without showing the change, even if another playbook has the title
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    confirm: bool,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let path = format!("/playbooks/{}", self.id);
+        let api = ctx.api().await;
+        let request = playbooks::get(&api, &self.id);
+        let (value, _) = ctx.recorder.call_async("GET", &path, request).await?;
+        let playbook: PlaybookSpec =
+            serde_json::from_value(value).map_err(|e| Errors::FailedFetchPlaybook(e.to_string()))?;
+        if playbook.title == self.title {
+            info!("The playbook {} is already titled '{}'", self.id, self.title);
+            return Ok(());
+        }
+
+        let playbooks = ctx.recorder.call_async("GET", "/playbooks", ctx.playbooks().list_async(None)).await?;
+        if let Some(other) = duplicate(&playbooks, &self.id, &self.title) {
+            warn!("The playbook {} is already titled '{}'", other.id, self.title);
+            if !self.confirm && !ctx.prompter.confirm("Rename anyway?", false)? {
+                return Err(Errors::RenameConflict(self.title.clone()));
+            }
+        }
+        if !self.confirm {
+            ctx.stdout.println(format!("Renaming '{}' to '{}'", playbook.title, self.title))?;
+        }
+
+        let request = playbooks::rename(&api, &self.id, &self.title);
+        let body = serde_json::json!({ "title": self.title });
+        ctx.recorder.mutate_async("PATCH", &path, Some(body), request).await?;
+        info!("Renamed the playbook {}", self.id);
+        Ok(())
+    }
+}
+
+/// Another playbook with the same title, if any.
+fn duplicate<'a>(playbooks: &'a [PlaybookSpec], id: &str, title: &str) -> Option<&'a PlaybookSpec> {
+    playbooks.iter().find(|p| p.id != id && p.title == title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playbook(id: &str, title: &str) -> PlaybookSpec {
+        serde_json::from_value(serde_json::json!({"id": id, "title": title, "description": ""})).unwrap()
+    }
+
+    #[test]
+    fn detect_duplicate_titles() {
+        let playbooks = vec![playbook("1", "web"), playbook("2", "api")];
+        assert_eq!(duplicate(&playbooks, "1", "api").map(|p| p.id.as_str()), Some("2"));
+        assert!(duplicate(&playbooks, "2", "api").is_none(), "the renamed playbook itself is no duplicate");
+        assert!(duplicate(&playbooks, "1", "db").is_none());
+    }
+}
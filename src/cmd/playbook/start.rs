@@ -0,0 +1,92 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::Args;
+use serde_json::Value;
+use tabled::Tabled;
+use tracing::info;
+
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::actors::{self, State, Status};
+use crate::ops::readiness;
+use crate::output;
+use crate::progress::Progress;
+
+/// Start a stopped playbook
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// The ID of the playbook to start
+    id: String,
+
+    /// Wait until the actors are running, failing as soon as one of them failed
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    wait: bool,
+
+    /// Only wait for the actor with the given name
+    #[arg(long, value_name = "NAME", requires = "wait")]
+    actor: Option<String>,
+
+    /// The seconds to wait for the actors to be running before giving up
+    #[arg(long, default_value = "120", requires = "wait", value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: u64,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let path = format!("/playbooks/{}/actions/start", self.id);
+        let status = ctx.recorder.mutate_async("POST", &path, None, ctx.playbooks().start_async(&self.id)).await?;
+        if status != 204 {
+            return Err(Errors::FailedStartPlaybook(self.id.clone()));
+        }
+        info!("Started playbook {}", self.id);
+        if !self.wait {
+            return Ok(());
+        }
+
+        let api = ctx.api().await;
+        let progress = Progress::spinner("Waiting for the actors to be running...");
+        let list = || actors::list(&api, &self.id, Status::All);
+        let tick = |actors: &[Value]| {
+            progress.set_message(format!("Waiting for the actors to be running...\n{}", table(actors)))
+        };
+        let timeout = Duration::from_secs(self.timeout);
+        let every = readiness::POLL_INTERVAL;
+        let result =
+            readiness::until_running(&ctx.shutdown, &self.id, self.actor.as_deref(), every, timeout, list, tick).await;
+        progress.finish();
+
+        let actors = result?;
+        ctx.stdout.println(table(&actors))
+    }
+}
+
+#[derive(Tabled)]
+struct ActorTable {
+    name: String,
+    state: String,
+}
+
+/// The table of the actors along with their current states.
+fn table(actors: &[Value]) -> String {
+    let rows = actors.iter().map(|a| ActorTable {
+        name: a["name"].as_str().unwrap_or("unknown").to_string(),
+        state: State::from(a).status,
+    });
+    output::table_with_header(rows, true)
+}
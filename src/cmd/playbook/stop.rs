@@ -0,0 +1,200 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::Args;
+use tokio::task::JoinSet;
+use tracing::{error, info, warn};
+
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::actors::{self, Status};
+use crate::ops::readiness;
+use crate::progress::Progress;
+
+/// Stop a running playbook
+#[derive(Args, Debug)]
+#[command(after_help = crate::cmd::cli::AFTER_HELP_STRING)]
+pub struct Cli {
+    /// The ID of the playbook to stop
+    #[arg(required_unless_present = "all")]
+    id: Option<String>,
+
+    /// Stop every running playbook
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "id")]
+    all: bool,
+
+    /// The number of playbooks to stop at a time when using `--all`
+    #[arg(long, default_value = "1", requires = "all", value_parser = clap::value_parser!(u16).range(1..))]
+    parallel: u16,
+
+    /// Wait until every actor of the playbook is stopped
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "all")]
+    wait: bool,
+
+    /// The seconds to wait for the actors to stop before giving up
+    #[arg(long, default_value = "60", requires = "wait", value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: u64,
+
+    /// Kill the actors still running once the timeout elapsed, with SIGKILL
+    #[arg(long, action = clap::ArgAction::SetTrue, requires = "wait")]
+    force: bool,
+}
+
+impl Cli {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        if let Some(id) = &self.id {
+            stop(&ctx, id)?;
+            return match self.wait {
+                true => self.wait(&ctx, id).await,
+                false => Ok(()),
+            };
+        }
+
+        let options = HashMap::from([(String::from("status"), String::from("running"))]);
+        let playbooks = ctx.recorder.call_async("GET", "/playbooks", ctx.playbooks().list_async(Some(options))).await?;
+        if playbooks.is_empty() {
+            warn!("No running playbooks found");
+            return Ok(());
+        }
+
+        let ids = playbooks.into_iter().map(|p| p.id).collect();
+        let ctx1 = ctx.clone();
+        let mut errors = stop_all(ids, self.parallel as usize, move |id| stop(&ctx1, &id)).await;
+        // Every stop is planned in a dry run, none of them failed.
+        errors.retain(|err| !matches!(err, Errors::DryRun(_)));
+        if !errors.is_empty() {
+            return Err(Errors::BulkStopFailed(errors.iter().map(|e| e.to_string()).collect()));
+        }
+
+        Ok(())
+    }
+
+    /// Wait until the actors of the playbook are stopped, killing the ones
+    /// still running after the timeout if forced.
+    async fn wait(&self, ctx: &Context, id: &str) -> Result<()> {
+        let api = ctx.api().await;
+        let progress = Progress::spinner("Waiting for the actors to stop...");
+        let mut running = vec![];
+        let list = || actors::list(&api, id, Status::All);
+        let tick = |names: &[String]| {
+            progress.set_message(format!("Waiting for {} actor(s) to stop: {}", names.len(), names.join(", ")));
+            running = names.to_vec();
+        };
+        let timeout = Duration::from_secs(self.timeout);
+        let result = readiness::until_stopped(&ctx.shutdown, id, readiness::POLL_INTERVAL, timeout, list, tick).await;
+        progress.finish();
+
+        match result {
+            Err(Errors::StopTimeout(reason)) if self.force => {
+                warn!("Timed out waiting for playbook {}, killing {} actor(s)", reason, running.len());
+                for name in &running {
+                    let path = format!("/playbooks/{}/actors/{}/actions/kill", id, name);
+                    let request = actors::kill(&api, id, name);
+                    ctx.recorder.mutate_async("POST", &path, None, request).await?;
+                }
+                Ok(())
+            }
+            Ok(()) => {
+                info!("The actors of playbook {} are stopped", id);
+                Ok(())
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Stop the playbook with the given ID.
+fn stop(ctx: &Context, id: &str) -> Result<()> {
+    let path = format!("/playbooks/{}/actions/stop", id);
+    let status = ctx.recorder.mutate("POST", &path, None, || ctx.client.playbooks().stop(id))?;
+    if status != 204 {
+        return Err(Errors::FailedStopPlaybook(id.to_string()));
+    }
+
+    info!("Stopped playbook {}", id);
+
+    Ok(())
+}
+
+/// Stop the given playbooks, `parallel` at a time, and collect the errors
+/// instead of bailing out on the first failure.
+async fn stop_all<F>(ids: Vec<String>, parallel: usize, stop: F) -> Vec<Errors>
+where
+    F: Fn(String) -> Result<()> + Send + Sync + 'static,
+{
+    let stop = Arc::new(stop);
+    let total = ids.len();
+    let mut done = 0;
+    let mut ids = ids.into_iter();
+    let mut set = JoinSet::new();
+    let mut errors = vec![];
+
+    loop {
+        while set.len() < parallel {
+            let Some(id) = ids.next() else { break };
+            let stop = stop.clone();
+            set.spawn_blocking(move || stop(id));
+        }
+
+        let Some(joined) = set.join_next().await else { break };
+        done += 1;
+        info!("Stopping playbooks: {}/{} done", done, total);
+        match joined {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => {
+                error!("{}", err);
+                errors.push(err);
+            }
+            Err(err) => errors.push(Errors::FailedJoinTask(err)),
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn stop_in_parallel_and_collect_errors() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let (running1, peak1) = (running.clone(), peak.clone());
+        let ids = (0..6).map(|i| i.to_string()).collect();
+        let errors = stop_all(ids, 3, move |id| {
+            let now = running1.fetch_add(1, Ordering::SeqCst) + 1;
+            peak1.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(50));
+            running1.fetch_sub(1, Ordering::SeqCst);
+
+            match id.as_str() {
+                "2" | "4" => Err(Errors::FailedStopPlaybook(id)),
+                _ => Ok(()),
+            }
+        })
+        .await;
+
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+        assert_eq!(errors.len(), 2);
+    }
+}
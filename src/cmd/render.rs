@@ -12,21 +12,20 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::path::Path;
 use std::sync::Arc;
 
 use clap::Args;
 
 use crate::context::Context;
-use crate::errors::Result;
+use crate::errors::{Errors, Result};
+use crate::ops::pipeline;
+use crate::utils;
 
 /// Perform all image builds, and output rendered Kubernetes manifests
 #[derive(Args, Debug)]
 #[command(after_help = super::cli::AFTER_HELP_STRING)]
 pub struct Cli {
-    /// If true, amp will skip yes/no confirmation from the user
-    #[arg(long, action = clap::ArgAction::Set, default_value = "true", env = "AMP_ASSUME_YES")]
-    assume_yes: bool,
-
     /// Path or URL to the Amphitheatre config file
     #[arg(short, long, env = "AMP_FILENAME")]
     filename: Option<String>,
@@ -40,12 +39,20 @@ pub struct Cli {
     output: Option<String>,
 
     /// Activate profiles by name (prefixed with `-` to disable a profile)
-    #[arg(short, long, default_value = "[]", env = "AMP_PROFILE")]
-    profile: Option<Vec<String>>,
+    #[arg(short, long, env = "AMP_PROFILE", value_delimiter = ',', allow_hyphen_values = true)]
+    profile: Vec<String>,
 }
 
 impl Cli {
-    pub async fn exec(&self, _ctx: Arc<Context>) -> Result<()> {
-        Ok(())
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let (manifest, environment) = pipeline::effective(&ctx, &self.filename, &self.profile).await?;
+        let content = match environment {
+            Some(name) => format!("# environment: {}\n{}", name, manifest.content),
+            None => manifest.content,
+        };
+        match &self.output {
+            Some(path) => utils::atomic_write(Path::new(path), content.as_bytes()).map_err(Errors::FailedSaveManifest),
+            None => ctx.stdout.print(&content),
+        }
     }
 }
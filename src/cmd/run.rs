@@ -12,72 +12,117 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use amp_common::resource::PlaybookSpec;
 use clap::Args;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::context::Context;
 use crate::errors::Result;
-use crate::ops::pipeline::Options;
-use crate::ops::{cleaner, pipeline};
+use crate::ops::images::{self, ImageOverride};
+use crate::ops::metadata::GitSource;
+use crate::ops::pipeline::{self, Options};
+use crate::ops::playbook::{self, CreateOptions};
+use crate::utils;
+use crate::utils::git::{self, Source};
 
 /// Run a pipeline, build & deploy once
 #[derive(Args, Debug)]
 #[command(after_help = super::cli::AFTER_HELP_STRING)]
 pub struct Cli {
-    /// If true, amp will skip yes/no confirmation from the user
-    #[arg(long, action = clap::ArgAction::Set, default_value = "true", env = "AMP_ASSUME_YES")]
-    pub assume_yes: bool,
-
     /// Delete deployments after dev or debug mode is interrupted
     #[arg(long, action = clap::ArgAction::Set, default_value = "true", env = "AMP_CLEANUP")]
     cleanup: bool,
 
     /// Path or URL to the Amphitheatre config file
-    #[arg(short, long, env = "AMP_FILENAME")]
-    filename: Option<PathBuf>,
+    #[arg(short, long, env = "AMP_FILENAME", group = "source")]
+    filename: Option<String>,
 
     /// The URL of the remote git repository for your character where you want to run
-    #[arg(long, env = "AMP_GIT")]
+    #[arg(long, env = "AMP_GIT", group = "source")]
     git: Option<String>,
 
     /// The name of the character on the cluster you want to run on
-    #[arg(long, env = "AMP_NAME")]
+    #[arg(long, env = "AMP_NAME", group = "source")]
     name: Option<String>,
 
     /// Activate profiles by name (prefixed with `-` to disable a profile)
-    #[arg(short, long, env = "AMP_PROFILE")]
-    profile: Option<Vec<String>>,
+    #[arg(short, long, env = "AMP_PROFILE", value_delimiter = ',', allow_hyphen_values = true)]
+    profile: Vec<String>,
 
     /// Stream logs from deployed objects
     #[arg(long, action = clap::ArgAction::Set, default_value = "true", env = "AMP_TAIL")]
     tail: bool,
+
+    /// Deploy the lead character alone, ignoring the partners of the manifest
+    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_NO_PARTNERS")]
+    no_partners: bool,
+
+    /// Skip the lint rules with the given IDs, see `amp lint --rules`
+    #[arg(long, value_name = "RULE", value_delimiter = ',')]
+    allow: Vec<String>,
+
+    /// Suffix the character name with the git branch, so previews of parallel branches don't clash
+    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_PER_BRANCH")]
+    per_branch: bool,
+
+    /// Skip the warnings about the uncommitted and unpushed changes of the workspace
+    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_NO_GIT_CHECKS")]
+    no_git_checks: bool,
+
+    /// Don't run the hooks of the manifest
+    #[arg(long, action = clap::ArgAction::SetTrue, env = "AMP_NO_HOOKS")]
+    no_hooks: bool,
+
+    /// Keep streaming the events of the playbook once its actors are ready
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    follow: bool,
+
+    /// The seconds to wait for the actors to be ready before giving up
+    #[arg(long, default_value = "600", value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: u64,
+
+    /// Deploy the image instead of building the character from source, as NAME=REFERENCE
+    #[arg(short, long, value_name = "NAME=REFERENCE", value_parser = images::parse_override, value_delimiter = ',', conflicts_with_all = ["git", "name"])]
+    images: Vec<ImageOverride>,
+
+    /// Deploy the images of the artifacts file written by a build with `--file-output`
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["git", "name"])]
+    file_input: Option<PathBuf>,
 }
 
 impl Cli {
     pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
-        // Setup handler for for handling Ctrl-C signals.
-        cleaner::setup_signal_handler(ctx.clone(), self.cleanup);
-
         // Define the options for the pipeline.
         let mut opt = Options {
             cleanup: self.cleanup,
             tail: self.tail, // toggle log streaming
             live: false,     // sync the sources from local to server
             once: true,      // build & deploy once, then exit
+            partners: !self.no_partners,
+            allow: self.allow.clone(),
+            per_branch: self.per_branch,
+            hooks: !self.no_hooks,
+            timeout: Some(Duration::from_secs(self.timeout)),
+            follow: self.follow,
+            images: images::collect(&self.images, self.file_input.as_deref())?,
+            preview: false,
         };
 
         // Create the playbook based on the options
-        let playbook: PlaybookSpec;
-        if let Some(repository) = &self.git {
-            playbook = pipeline::pull(&ctx, repository)?;
+        let source = if let Some(repository) = &self.git {
+            if !self.no_git_checks {
+                let workspace = utils::manifest_path(".", ctx.workspace.as_deref());
+                git::check(ctx.prompter.as_ref(), &workspace, Source::Remote)?;
+            }
+            playbook::Source::Repository(GitSource { url: repository.clone(), ..GitSource::default() })
         } else if let Some(name) = &self.name {
-            playbook = pipeline::fetch(&ctx, name)?;
+            playbook::Source::Registry(name.clone())
         } else {
             opt.live = true;
-            playbook = pipeline::load(&ctx, &self.filename, opt.once).await?;
-        }
+            playbook::Source::Manifest { filename: self.filename.clone(), profiles: self.profile.clone() }
+        };
+        let playbook = playbook::create(&ctx, &CreateOptions::new(source, &opt)).await?.playbook;
 
         // Run the pipeline, build & deploy once.
         pipeline::run(&ctx, playbook, opt).await
@@ -12,7 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{path::PathBuf, sync::Arc};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use amp_client::client::Client;
 use amp_common::{
@@ -20,27 +21,41 @@ use amp_common::{
     resource::{ActorSpec, PlaybookSpec},
     schema::Character,
 };
+use serde::Serialize;
 use tokio::sync::RwLock;
+use tracing::warn;
 
+use crate::client::{http_client, Api, AsyncPlaybooks};
 use crate::errors::{Errors, Result};
+use crate::ops::hooks::Hooks;
+use crate::output::{self, Stdout};
+use crate::prompt::{InquirePrompter, Prompter};
+use crate::recorder::Recorder;
+use crate::shutdown::Shutdown;
+use crate::utils::{self, CachePolicy, Manifest, Origin, Variables};
 
 /// Session holds the current session state
 #[derive(Default, Debug)]
 #[allow(dead_code)]
 pub struct Session {
     pub workspace: RwLock<Option<PathBuf>>,
+    pub origin: RwLock<Option<Origin>>,
     pub character: RwLock<Option<Character>>,
     pub playbook: RwLock<Option<PlaybookSpec>>,
     pub actor: RwLock<Option<ActorSpec>>,
+    /// The hooks of the manifest, empty with `--no-hooks`
+    pub hooks: RwLock<Hooks>,
 }
 
 impl Session {
-    /// Load the character from the specified file.
-    pub async fn load(&self, path: &PathBuf) -> Result<()> {
-        let workspace = path.parent().unwrap().to_path_buf();
-        let character = Character::load(path).map_err(Errors::FailedLoadManifest)?;
+    /// Load the character from the given manifest, the workspace is only set
+    /// when the manifest is available locally.
+    pub async fn load(&self, manifest: Manifest) -> Result<()> {
+        let character = toml::from_str::<Character>(&manifest.content)
+            .map_err(|e| Errors::FailedParseManifest(manifest.origin.to_string(), e))?;
 
-        self.workspace.write().await.replace(workspace);
+        *self.workspace.write().await = manifest.workspace();
+        self.origin.write().await.replace(manifest.origin);
         self.character.write().await.replace(character);
 
         Ok(())
@@ -54,25 +69,218 @@ pub struct Context {
     pub cluster: RwLock<Cluster>,
     pub session: Session,
     pub client: Arc<Client>,
+    pub http: reqwest::Client,
+    pub recorder: Recorder,
+    pub shutdown: Shutdown,
+    pub prompter: Box<dyn Prompter>,
+    pub stdout: Stdout,
+    pub remote_cache: CachePolicy,
+    pub variables: Variables,
+    pub workspace: Option<PathBuf>,
+    pub timestamps: bool,
+    pub environment: Option<String>,
+    /// Plan the API mutations rather than sending them
+    pub dry_run: bool,
 }
 
 impl Context {
     /// Initialize a new context
     pub fn init() -> Result<Context> {
         let path = Configuration::path().map_err(Errors::InvalidConfigPath)?;
-        let configuration = Configuration::load(path).map_err(Errors::FailedLoadConfiguration)?;
+
+        // Without a configuration file, fall back to the environment variables
+        // when they name a server, else to the default configuration.
+        if !path.exists() && std::env::var_os("AMP_SERVER").is_some() {
+            return Context::new_from_env();
+        }
+
+        let configuration = load_configuration(&path)?;
         let cluster = get_context(&configuration)?;
+
+        Ok(Context::new(configuration, cluster))
+    }
+
+    /// Initialize a new context purely from the environment variables,
+    /// without consulting any configuration file.
+    pub fn new_from_env() -> Result<Context> {
+        let cluster = cluster_from_env(|key| std::env::var(key).ok())?;
+        Ok(Context::new(Configuration::default(), cluster))
+    }
+
+    fn new(configuration: Configuration, cluster: Cluster) -> Context {
         let client = Client::new(&format!("{}/v1", &cluster.server), cluster.token.clone());
 
-        Ok(Context {
+        Context {
             configuration: RwLock::new(configuration),
             cluster: RwLock::new(cluster),
             session: Session::default(),
             client: Arc::new(client),
-        })
+            http: http_client(),
+            recorder: Recorder::default(),
+            shutdown: Shutdown::default(),
+            prompter: Box::new(InquirePrompter::default()),
+            stdout: Stdout::default(),
+            remote_cache: CachePolicy::default(),
+            variables: Variables::default(),
+            workspace: None,
+            timestamps: false,
+            environment: None,
+            dry_run: false,
+        }
+    }
+
+    /// Check the consistency of the configuration and the current context,
+    /// all the issues found are returned at once. When `network` is true, the
+    /// server is also checked for reachability.
+    pub async fn validate(&self, network: bool) -> Vec<ContextError> {
+        let mut errors = vec![];
+
+        match Configuration::path() {
+            Ok(path) if path.exists() => {
+                if let Err(err) = std::fs::read_to_string(&path) {
+                    errors.push(ContextError::new("config", format!("{} is not readable: {}", path.display(), err)));
+                }
+            }
+            Ok(_) => {}
+            Err(err) => errors.push(ContextError::new("config", err.to_string())),
+        }
+
+        {
+            let configuration = self.configuration.read().await;
+            match &configuration.context {
+                Some(context) if context.iter().next().is_some() => {
+                    if context.current().is_none() {
+                        errors.push(ContextError::new("context", "the current context is not defined"));
+                    }
+                }
+                _ => errors.push(ContextError::new("context", "no context is defined")),
+            }
+        }
+
+        let cluster = self.cluster.read().await;
+        errors.extend(validate_cluster(&cluster));
+
+        if network && errors.is_empty() {
+            if let Err(err) = self.playbooks().list_async(None).await {
+                errors.push(ContextError::new("server", format!("{} is not reachable: {}", cluster.server, err)));
+            }
+        }
+
+        errors
+    }
+
+    /// The name of the current context, if any.
+    pub async fn context_name(&self) -> Option<String> {
+        let configuration = self.configuration.read().await;
+        configuration.context.as_ref().and_then(|c| c.current()).map(|(name, _)| name.to_string())
+    }
+
+    /// The API of the current context, for the requests made outside the API client.
+    pub async fn api(&self) -> Api {
+        let cluster = self.cluster.read().await;
+        Api::new(self.http.clone(), &cluster.server, cluster.token.as_deref())
+    }
+
+    /// The async playbooks client, which does not block the executor.
+    pub fn playbooks(&self) -> AsyncPlaybooks {
+        AsyncPlaybooks::new(self.client.clone())
+    }
+
+    /// Render the primary output as pretty JSON, with the timings of the command
+    /// when the summary is enabled, see [`output::json_with_timings`].
+    pub fn json<T: Serialize + ?Sized>(&self, value: &T) -> Result<String> {
+        output::json_with_timings(value, self.recorder.summary().as_ref())
     }
 }
 
+/// Load the configuration, a corrupted file is backed up and reset to the default
+/// configuration instead of failing every command. The other errors, such as an
+/// unreadable file, fail as they are.
+fn load_configuration(path: &Path) -> Result<Configuration> {
+    match Configuration::load(path.to_path_buf()) {
+        Ok(configuration) => Ok(configuration),
+        Err(err) if is_corrupted(path) => {
+            warn!("Failed to load configuration: {:#}", err);
+            utils::backup_corrupted(path).map_err(|e| Errors::FailedLoadConfiguration(e.into()))?;
+            let configuration = Configuration::default();
+            utils::save_configuration(&configuration)?;
+            Ok(configuration)
+        }
+        Err(err) => Err(Errors::FailedLoadConfiguration(err)),
+    }
+}
+
+/// Whether the configuration file reads fine but doesn't deserialize.
+fn is_corrupted(path: &Path) -> bool {
+    match std::fs::read(path) {
+        Ok(bytes) => !std::str::from_utf8(&bytes).is_ok_and(|s| toml::from_str::<Configuration>(s).is_ok()),
+        Err(_) => false,
+    }
+}
+
+/// Build the cluster from the `AMP_SERVER`, `AMP_TOKEN` and `AMP_CONTEXT_TITLE` variables
+/// returned by the given lookup, all of which are required.
+fn cluster_from_env<F: Fn(&str) -> Option<String>>(lookup: F) -> Result<Cluster> {
+    let mut missing = vec![];
+    let mut var = |key: &str| {
+        let value = lookup(key).filter(|v| !v.is_empty());
+        if value.is_none() {
+            missing.push(key.to_string());
+        }
+        value.unwrap_or_default()
+    };
+
+    let mut cluster = Cluster::default();
+    cluster.server = var("AMP_SERVER");
+    cluster.token = Some(var("AMP_TOKEN"));
+    cluster.title = var("AMP_CONTEXT_TITLE");
+
+    if !missing.is_empty() {
+        return Err(Errors::MissingEnvVars(missing));
+    }
+
+    Ok(cluster)
+}
+
+/// An issue found when validating the context.
+#[derive(Debug, PartialEq)]
+pub struct ContextError {
+    pub field: String,
+    pub message: String,
+}
+
+impl ContextError {
+    fn new(field: &str, message: impl Into<String>) -> Self {
+        Self { field: field.to_string(), message: message.into() }
+    }
+}
+
+impl std::fmt::Display for ContextError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Check the server URL and the token of the cluster.
+fn validate_cluster(cluster: &Cluster) -> Vec<ContextError> {
+    let mut errors = vec![];
+
+    match cluster.server.strip_prefix("https://") {
+        Some(host) if !host.is_empty() && !host.starts_with('/') && !host.contains(char::is_whitespace) => {}
+        _ => errors.push(ContextError::new("server", format!("{:?} is not a valid HTTPS URL", cluster.server))),
+    }
+
+    match cluster.token.as_deref() {
+        None | Some("") => errors.push(ContextError::new("token", "the token is empty")),
+        Some(token) if token.len() < 8 || token.contains(char::is_whitespace) => {
+            errors.push(ContextError::new("token", "the token does not look like a valid token"))
+        }
+        Some(_) => {}
+    }
+
+    errors
+}
+
 /// Get the current context from the configuration
 fn get_context(configuration: &Configuration) -> Result<Cluster> {
     if let Some(context) = &configuration.context {
@@ -83,3 +291,83 @@ fn get_context(configuration: &Configuration) -> Result<Cluster> {
 
     Err(Errors::NotFoundCurrentContext)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    fn missing(vars: &[(&str, &str)]) -> Vec<String> {
+        match cluster_from_env(lookup(vars)) {
+            Err(Errors::MissingEnvVars(missing)) => missing,
+            other => panic!("expected missing variables, got {:?}", other.map(|c| c.server)),
+        }
+    }
+
+    #[test]
+    fn validate_cluster_fields() {
+        let mut cluster = Cluster::default();
+        cluster.server = "https://cloud.amphitheatre.app".into();
+        cluster.token = Some("7c0e2bb1d4a54a1f".into());
+        assert!(validate_cluster(&cluster).is_empty());
+
+        cluster.server = "http://cloud.amphitheatre.app".into();
+        cluster.token = Some("short".into());
+        let fields: Vec<String> = validate_cluster(&cluster).into_iter().map(|e| e.field).collect();
+        assert_eq!(fields, vec!["server", "token"]);
+
+        cluster.server = "https://".into();
+        cluster.token = None;
+        assert_eq!(validate_cluster(&cluster).len(), 2);
+    }
+
+    #[test]
+    fn cluster_from_complete_env() {
+        let vars = [("AMP_SERVER", "https://cloud.amphitheatre.app"), ("AMP_TOKEN", "t"), ("AMP_CONTEXT_TITLE", "CI")];
+        let cluster = cluster_from_env(lookup(&vars)).unwrap();
+        assert_eq!(cluster.server, "https://cloud.amphitheatre.app");
+        assert_eq!(cluster.token.as_deref(), Some("t"));
+        assert_eq!(cluster.title, "CI");
+    }
+
+    #[test]
+    fn cluster_from_env_missing_server() {
+        assert_eq!(missing(&[("AMP_TOKEN", "t"), ("AMP_CONTEXT_TITLE", "CI")]), vec!["AMP_SERVER"]);
+    }
+
+    #[test]
+    fn cluster_from_env_missing_token() {
+        assert_eq!(missing(&[("AMP_SERVER", "https://a"), ("AMP_CONTEXT_TITLE", "CI")]), vec!["AMP_TOKEN"]);
+    }
+
+    #[test]
+    fn cluster_from_env_missing_title() {
+        assert_eq!(missing(&[("AMP_SERVER", "https://a"), ("AMP_TOKEN", "t")]), vec!["AMP_CONTEXT_TITLE"]);
+    }
+
+    #[test]
+    fn cluster_from_env_missing_all() {
+        assert_eq!(missing(&[]), vec!["AMP_SERVER", "AMP_TOKEN", "AMP_CONTEXT_TITLE"]);
+    }
+
+    #[test]
+    fn corrupted_only_when_unparsable() {
+        let dir = std::env::temp_dir().join(format!("amp-corrupted-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        assert!(!is_corrupted(&path), "a missing file is not corrupted");
+
+        std::fs::write(&path, "[context\ncurrent = ").unwrap();
+        assert!(is_corrupted(&path));
+
+        std::fs::write(&path, toml::to_string(&Configuration::default()).unwrap()).unwrap();
+        assert!(!is_corrupted(&path));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use super::{read, BuildMethod, Candidate, Detector};
+
+/// Detects a Dockerfile, with its exposed port and command.
+pub struct Docker;
+
+impl Detector for Docker {
+    fn detect(&self, dir: &Path) -> Vec<Candidate> {
+        let path = dir.join("Dockerfile");
+        let Some(content) = read(&path) else { return vec![] };
+
+        let mut candidate = Candidate::new("docker", path, 90);
+        candidate.build = BuildMethod::Dockerfile;
+        for line in content.lines().map(str::trim) {
+            let (instruction, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            match instruction.to_uppercase().as_str() {
+                "EXPOSE" => {
+                    let port = args.split_whitespace().next().and_then(|p| p.split('/').next());
+                    candidate.port = port.and_then(|p| p.parse().ok()).or(candidate.port);
+                }
+                "CMD" | "ENTRYPOINT" => candidate.command = Some(command(args.trim())),
+                _ => {}
+            }
+        }
+
+        vec![candidate]
+    }
+}
+
+/// The command of a `CMD` instruction, in exec or shell form.
+fn command(args: &str) -> String {
+    match serde_json::from_str::<Vec<String>>(args) {
+        Ok(parts) => parts.join(" "),
+        Err(_) => args.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::tests::fixture;
+
+    #[test]
+    fn detect_dockerfile() {
+        let candidates = Docker.detect(&fixture("docker"));
+        assert_eq!(candidates[0].port, Some(8080));
+        assert_eq!(candidates[0].command.as_deref(), Some("gunicorn -b 0.0.0.0:8080 app:app"));
+    }
+}
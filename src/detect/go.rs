@@ -0,0 +1,100 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use super::{read, BuildMethod, Candidate, Detector};
+
+/// The frameworks recognized from the required modules, with their usual port.
+const FRAMEWORKS: [(&str, &str, u16); 3] = [
+    ("github.com/gin-gonic/gin", "gin", 8080),
+    ("github.com/labstack/echo", "echo", 1323),
+    ("github.com/gofiber/fiber", "fiber", 3000),
+];
+
+/// Detects a Go module from its go.mod, built with its Dockerfile if any.
+pub struct Go;
+
+impl Detector for Go {
+    fn detect(&self, dir: &Path) -> Vec<Candidate> {
+        let path = dir.join("go.mod");
+        let Some(content) = read(&path) else { return vec![] };
+
+        let mut candidate = Candidate::new("go", path, 70);
+        candidate.command = Some(String::from("go run ."));
+        candidate.description = Some(String::from("A Go application"));
+        for line in content.lines().map(str::trim) {
+            match line.split_once(char::is_whitespace) {
+                Some(("module", module)) => candidate.name = name(module),
+                Some(("go", version)) => candidate.toolchain = Some(version.trim().to_string()),
+                _ => {}
+            }
+        }
+        if dir.join("Dockerfile").is_file() {
+            candidate.build = BuildMethod::Dockerfile;
+        }
+        if let Some((_, framework, port)) = FRAMEWORKS.iter().find(|(module, _, _)| content.contains(module)) {
+            candidate.framework = Some(framework);
+            candidate.port = Some(*port);
+            candidate.confidence = 80;
+        }
+
+        vec![candidate]
+    }
+}
+
+/// The last path component of the module, `v2` and later major versions
+/// suffixes are skipped.
+fn name(module: &str) -> Option<String> {
+    let mut components = module.trim().trim_matches('"').rsplit('/');
+    let last = components.next().filter(|c| !c.is_empty())?;
+    let major = last.strip_prefix('v').is_some_and(|v| v.parse::<u32>().is_ok_and(|n| n >= 2));
+    match major {
+        true => components.next().map(String::from),
+        false => Some(last.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::tests::fixture;
+
+    #[test]
+    fn read_the_module() {
+        let candidate = Go.detect(&fixture("go")).remove(0);
+        assert_eq!(candidate.name.as_deref(), Some("greeter"));
+        assert_eq!(candidate.toolchain.as_deref(), Some("1.22"));
+        assert_eq!(candidate.build, BuildMethod::Buildpacks);
+
+        let candidate = Go.detect(&fixture("go-docker")).remove(0);
+        assert_eq!(candidate.name.as_deref(), Some("api"));
+        assert_eq!(candidate.build, BuildMethod::Dockerfile);
+    }
+
+    #[test]
+    fn name_after_the_last_component() {
+        assert_eq!(name("github.com/example/greeter").as_deref(), Some("greeter"));
+        assert_eq!(name("github.com/example/api/v2").as_deref(), Some("api"));
+        assert_eq!(name("tool").as_deref(), Some("tool"));
+        assert_eq!(name(""), None);
+    }
+
+    #[test]
+    fn detect_gin() {
+        let candidate = Go.detect(&fixture("monorepo")).remove(0);
+        assert_eq!(candidate.framework, Some("gin"));
+        assert_eq!(candidate.port, Some(8080));
+    }
+}
@@ -0,0 +1,59 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use super::{read, Candidate, Detector};
+
+/// Detects a Maven or Gradle project, and Spring Boot applications.
+pub struct Java;
+
+impl Detector for Java {
+    fn detect(&self, dir: &Path) -> Vec<Candidate> {
+        let builds = [
+            ("pom.xml", "mvn spring-boot:run"),
+            ("build.gradle", "./gradlew bootRun"),
+            ("build.gradle.kts", "./gradlew bootRun"),
+        ];
+        let Some((path, content, command)) = builds
+            .iter()
+            .find_map(|(name, command)| read(&dir.join(name)).map(|content| (dir.join(name), content, command)))
+        else {
+            return vec![];
+        };
+
+        let mut candidate = Candidate::new("java", path, 60);
+        if content.contains("spring-boot") {
+            candidate.framework = Some("spring-boot");
+            candidate.port = Some(8080);
+            candidate.command = Some(command.to_string());
+            candidate.confidence = 80;
+        }
+
+        vec![candidate]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::tests::fixture;
+
+    #[test]
+    fn detect_spring_boot() {
+        let candidate = Java.detect(&fixture("java-maven")).remove(0);
+        assert_eq!(candidate.framework, Some("spring-boot"));
+        assert_eq!(candidate.command.as_deref(), Some("mvn spring-boot:run"));
+    }
+}
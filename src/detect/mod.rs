@@ -0,0 +1,168 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod docker;
+mod go;
+mod java;
+mod node;
+mod python;
+mod rust;
+
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How the character is built.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildMethod {
+    /// Built by Cloud Native Buildpacks from the sources
+    Buildpacks,
+    /// Built from the Dockerfile of the workspace
+    Dockerfile,
+}
+
+/// A candidate configuration for the workspace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Candidate {
+    pub language: &'static str,
+    /// The version of the language toolchain the project requires
+    pub toolchain: Option<String>,
+    pub framework: Option<&'static str>,
+    /// The name, version and description of the character, from the project manifest
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub build: BuildMethod,
+    /// The buildpacks builder, instead of the default one
+    pub builder: Option<&'static str>,
+    pub build_command: Option<String>,
+    pub port: Option<u16>,
+    pub command: Option<String>,
+    /// The command running the tests of the project
+    pub test_command: Option<String>,
+    /// How likely this candidate is the right one, from 0 to 100
+    pub confidence: u8,
+    /// The manifest file the candidate was detected from
+    pub manifest: PathBuf,
+    /// The members of a workspace, each of them a character of the monorepo
+    pub members: Vec<String>,
+}
+
+impl Candidate {
+    fn new(language: &'static str, manifest: PathBuf, confidence: u8) -> Self {
+        Self {
+            language,
+            toolchain: None,
+            framework: None,
+            name: None,
+            version: None,
+            description: None,
+            build: BuildMethod::Buildpacks,
+            builder: None,
+            build_command: None,
+            port: None,
+            command: None,
+            test_command: None,
+            confidence,
+            manifest,
+            members: vec![],
+        }
+    }
+}
+
+impl Display for Candidate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.language)?;
+        if let Some(toolchain) = &self.toolchain {
+            write!(f, " {}", toolchain)?;
+        }
+        if let Some(framework) = self.framework {
+            write!(f, " ({})", framework)?;
+        }
+        write!(f, " with {:?}", self.build)?;
+        if let Some(port) = self.port {
+            write!(f, ", port {}", port)?;
+        }
+        if let Some(command) = &self.command {
+            write!(f, ", `{}`", command)?;
+        }
+        Ok(())
+    }
+}
+
+/// A detector for one ecosystem, new ecosystems are supported by implementing
+/// it and listing the detector in [`detectors`].
+pub trait Detector {
+    /// Inspect the directory, returning the candidates found in it.
+    fn detect(&self, dir: &Path) -> Vec<Candidate>;
+}
+
+/// All the known detectors.
+pub fn detectors() -> Vec<Box<dyn Detector>> {
+    vec![
+        Box::new(docker::Docker),
+        Box::new(rust::Rust),
+        Box::new(node::Node),
+        Box::new(go::Go),
+        Box::new(python::Python),
+        Box::new(java::Java),
+    ]
+}
+
+/// Detect the candidates of the workspace, ranked by confidence. The directory
+/// and its ancestors are inspected in turn, the nearest one with a manifest wins.
+pub fn detect(dir: &Path) -> Vec<Candidate> {
+    let detectors = detectors();
+    for dir in dir.ancestors() {
+        let mut candidates: Vec<Candidate> = detectors.iter().flat_map(|d| d.detect(dir)).collect();
+        if !candidates.is_empty() {
+            candidates.sort_by(|a, b| b.confidence.cmp(&a.confidence));
+            return candidates;
+        }
+    }
+    vec![]
+}
+
+/// Read the file if it exists.
+fn read(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(super) fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/detect").join(name)
+    }
+
+    #[test]
+    fn nearest_manifest_wins() {
+        let candidates = detect(&fixture("monorepo/services/web/src"));
+        assert_eq!(candidates[0].language, "node");
+        assert_eq!(candidates[0].framework, Some("next"));
+        assert!(candidates.iter().all(|c| c.language != "go"));
+
+        let candidates = detect(&fixture("monorepo"));
+        assert_eq!(candidates[0].language, "go");
+    }
+
+    #[test]
+    fn rank_dockerfile_first() {
+        let candidates = detect(&fixture("docker"));
+        assert_eq!(candidates[0].build, BuildMethod::Dockerfile);
+        assert_eq!(candidates[0].port, Some(8080));
+        assert_eq!(candidates[1].language, "python");
+    }
+}
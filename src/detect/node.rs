@@ -0,0 +1,108 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use serde_json::Value;
+
+use super::{read, Candidate, Detector};
+
+/// The frameworks recognized from the dependencies, with their port and command.
+const FRAMEWORKS: [(&str, u16, &str); 5] = [
+    ("next", 3000, "npm run start"),
+    ("nuxt", 3000, "npm run start"),
+    ("vite", 5173, "npm run dev"),
+    ("@nestjs/core", 3000, "npm run start"),
+    ("express", 3000, "npm start"),
+];
+
+/// The command of the packages without a start script.
+const COMMAND: &str = "node index.js";
+
+/// The test script `npm init` writes, which always fails.
+const NO_TEST: &str = "no test specified";
+
+/// Detects a Node.js package from its package.json.
+pub struct Node;
+
+impl Detector for Node {
+    fn detect(&self, dir: &Path) -> Vec<Candidate> {
+        let path = dir.join("package.json");
+        let Some(package) = read(&path).and_then(|c| serde_json::from_str::<Value>(&c).ok()) else { return vec![] };
+
+        let has = |name: &str| ["dependencies", "devDependencies"].iter().any(|k| package[k].get(name).is_some());
+        let script = |name: &str| package["scripts"][name].as_str();
+        let field = |key: &str| package[key].as_str().filter(|v| !v.is_empty()).map(String::from);
+
+        let mut candidate = Candidate::new("node", path, 60);
+        // The scope of the package isn't part of the character name.
+        candidate.name = field("name").map(|n| n.rsplit('/').next().unwrap_or_default().to_string());
+        candidate.version = field("version");
+        candidate.description = field("description");
+        candidate.test_command = script("test").filter(|s| !s.contains(NO_TEST)).map(|_| String::from("npm test"));
+        if let Some((framework, port, command)) = FRAMEWORKS.iter().find(|(name, _, _)| has(name)) {
+            candidate.framework = Some(framework.trim_start_matches('@').split('/').next().unwrap_or(framework));
+            candidate.port = Some(*port);
+            candidate.command = Some(command.to_string());
+            candidate.confidence = 80;
+        } else if script("start").is_some() {
+            candidate.command = Some(String::from("npm start"));
+            candidate.confidence = 70;
+        } else {
+            candidate.command = Some(String::from(COMMAND));
+        }
+
+        vec![candidate]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::tests::fixture;
+
+    #[test]
+    fn detect_next() {
+        let candidate = Node.detect(&fixture("monorepo/services/web")).remove(0);
+        assert_eq!(candidate.framework, Some("next"));
+        assert_eq!(candidate.port, Some(3000));
+    }
+
+    #[test]
+    fn read_a_minimal_package() {
+        let candidate = Node.detect(&fixture("node-minimal")).remove(0);
+        assert_eq!(candidate.name.as_deref(), Some("greeter"));
+        assert_eq!((candidate.version, candidate.description), (None, None));
+        assert_eq!(candidate.command.as_deref(), Some("node index.js"));
+        assert_eq!(candidate.test_command, None, "the placeholder script of npm init isn't a test");
+        assert_eq!(candidate.confidence, 60);
+    }
+
+    #[test]
+    fn read_a_full_package() {
+        let candidate = Node.detect(&fixture("node-full")).remove(0);
+        assert_eq!(candidate.name.as_deref(), Some("storefront"));
+        assert_eq!(candidate.version.as_deref(), Some("2.3.1"));
+        assert_eq!(candidate.description.as_deref(), Some("The storefront of the shop"));
+        assert_eq!(candidate.command.as_deref(), Some("npm start"));
+        assert_eq!(candidate.test_command.as_deref(), Some("npm test"));
+    }
+
+    #[test]
+    fn detect_vite() {
+        let candidate = Node.detect(&fixture("node-vite")).remove(0);
+        assert_eq!(candidate.framework, Some("vite"));
+        assert_eq!(candidate.command.as_deref(), Some("npm run dev"));
+    }
+}
@@ -0,0 +1,70 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use super::{read, Candidate, Detector};
+
+/// The frameworks recognized from the dependencies, with their port and command.
+const FRAMEWORKS: [(&str, u16, &str); 3] = [
+    ("django", 8000, "python manage.py runserver 0.0.0.0:8000"),
+    ("fastapi", 8000, "uvicorn main:app --host 0.0.0.0 --port 8000"),
+    ("flask", 5000, "flask run --host 0.0.0.0"),
+];
+
+/// Detects a Python project from its pyproject.toml or requirements.txt.
+pub struct Python;
+
+impl Detector for Python {
+    fn detect(&self, dir: &Path) -> Vec<Candidate> {
+        let Some((path, content)) = ["pyproject.toml", "requirements.txt"]
+            .iter()
+            .map(|name| dir.join(name))
+            .find_map(|path| read(&path).map(|content| (path, content)))
+        else {
+            return vec![];
+        };
+
+        let content = content.to_lowercase();
+        let mut candidate = Candidate::new("python", path, 60);
+        if let Some((framework, port, command)) = FRAMEWORKS.iter().find(|(name, _, _)| content.contains(name)) {
+            candidate.framework = Some(framework);
+            candidate.port = Some(*port);
+            candidate.command = Some(command.to_string());
+            candidate.confidence = 75;
+        }
+
+        vec![candidate]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::tests::fixture;
+
+    #[test]
+    fn detect_flask() {
+        let candidate = Python.detect(&fixture("docker")).remove(0);
+        assert_eq!(candidate.framework, Some("flask"));
+        assert_eq!(candidate.port, Some(5000));
+    }
+
+    #[test]
+    fn detect_django_from_pyproject() {
+        let candidate = Python.detect(&fixture("python")).remove(0);
+        assert_eq!(candidate.framework, Some("django"));
+        assert!(candidate.manifest.ends_with("pyproject.toml"));
+    }
+}
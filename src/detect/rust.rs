@@ -0,0 +1,122 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use toml::{Table, Value};
+
+use super::{read, Candidate, Detector};
+
+/// The web frameworks recognized from the dependencies, with their usual port.
+const FRAMEWORKS: [(&str, &str, u16); 4] =
+    [("axum", "axum", 3000), ("actix-web", "actix", 8080), ("rocket", "rocket", 8000), ("warp", "warp", 3030)];
+
+/// The buildpacks builder with the Rust buildpack.
+const BUILDER: &str = "paketobuildpacks/builder:base";
+
+/// Detects a Cargo package or workspace, the members of a workspace being the
+/// characters of a monorepo.
+pub struct Rust;
+
+impl Detector for Rust {
+    fn detect(&self, dir: &Path) -> Vec<Candidate> {
+        let path = dir.join("Cargo.toml");
+        let Some(manifest) = read(&path).and_then(|c| c.parse::<Table>().ok()) else { return vec![] };
+
+        let binary = manifest.contains_key("bin") || dir.join("src/main.rs").exists();
+        let members = manifest.get("workspace").and_then(|w| w.get("members")).and_then(Value::as_array);
+
+        let package = manifest.get("package");
+        let field = |key: &str| package.and_then(|p| p.get(key)).and_then(Value::as_str).map(String::from);
+
+        let mut candidate = Candidate::new("rust", path, 30);
+        candidate.name = field("name");
+        candidate.version = field("version");
+        candidate.toolchain = field("rust-version");
+        candidate.builder = Some(BUILDER);
+        if let Some(members) = members {
+            candidate.confidence = 60;
+            candidate.members = members.iter().filter_map(Value::as_str).map(|m| member_name(dir, m)).collect();
+            if let Some(first) = candidate.members.first() {
+                candidate.build_command = Some(format!("cargo build --release -p {}", first));
+                candidate.command = Some(format!("./target/release/{}", first));
+            }
+        } else if binary {
+            candidate.confidence = 70;
+            candidate.build_command = Some(String::from("cargo build --release"));
+            candidate.command = match &candidate.name {
+                Some(name) => Some(format!("./target/release/{}", name)),
+                None => Some(String::from("cargo run --release")),
+            };
+        }
+
+        if let Some(deps) = manifest.get("dependencies").and_then(Value::as_table) {
+            if let Some((_, framework, port)) = FRAMEWORKS.iter().find(|(dep, _, _)| deps.contains_key(*dep)) {
+                candidate.framework = Some(framework);
+                candidate.port = Some(*port);
+                candidate.confidence += 10;
+            }
+        }
+
+        vec![candidate]
+    }
+}
+
+/// The package name of a workspace member, falling back to its directory name.
+fn member_name(dir: &Path, member: &str) -> String {
+    let manifest = read(&dir.join(member).join("Cargo.toml")).and_then(|c| c.parse::<Table>().ok());
+    let name = manifest.as_ref().and_then(|m| m.get("package")).and_then(|p| p.get("name")).and_then(Value::as_str);
+    name.map(String::from).unwrap_or_else(|| member.rsplit('/').next().unwrap_or(member).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::tests::fixture;
+
+    #[test]
+    fn detect_binary() {
+        let candidate = Rust.detect(&fixture("rust-bin")).remove(0);
+        assert_eq!(candidate.framework, Some("axum"));
+        assert_eq!(candidate.port, Some(3000));
+        assert_eq!(candidate.build_command.as_deref(), Some("cargo build --release"));
+        assert_eq!(candidate.command.as_deref(), Some("./target/release/hello"));
+    }
+
+    #[test]
+    fn read_the_package() {
+        let candidate = Rust.detect(&fixture("rust-bin")).remove(0);
+        assert_eq!(candidate.name.as_deref(), Some("hello"));
+        assert_eq!(candidate.version.as_deref(), Some("0.1.0"));
+        assert_eq!(candidate.builder, Some(BUILDER));
+        assert!(candidate.members.is_empty());
+    }
+
+    #[test]
+    fn detect_library() {
+        let candidate = Rust.detect(&fixture("rust-lib")).remove(0);
+        assert_eq!(candidate.confidence, 30);
+        assert_eq!(candidate.name.as_deref(), Some("utils"));
+        assert_eq!(candidate.command, None);
+    }
+
+    #[test]
+    fn detect_workspace() {
+        let candidate = Rust.detect(&fixture("rust-workspace")).remove(0);
+        assert_eq!(candidate.members, vec!["api-server"]);
+        assert_eq!(candidate.name, None);
+        assert_eq!(candidate.build_command.as_deref(), Some("cargo build --release -p api-server"));
+        assert_eq!(candidate.command.as_deref(), Some("./target/release/api-server"));
+    }
+}
@@ -12,9 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::path::StripPrefixError;
-
-use amp_common::{filesystem, http};
+use amp_common::http;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, Errors>;
@@ -31,7 +29,7 @@ pub enum Errors {
     NotFoundCurrentContext,
 
     #[error("Client error: {0}")]
-    ClientError(http::HTTPError),
+    ClientError(#[from] http::HTTPError),
 
     #[error("Failed to load manifest: {0}")]
     FailedLoadManifest(anyhow::Error),
@@ -56,7 +54,31 @@ pub enum Errors {
     FailedSaveManifest(std::io::Error),
 
     #[error("Failed to create playbook: {0}")]
-    FailedCreatePlaybook(http::HTTPError),
+    FailedCreatePlaybook(String),
+
+    #[error("Failed to fetch playbook: {0}")]
+    FailedFetchPlaybook(String),
+
+    #[error("Failed to list the playbooks: {0}")]
+    FailedListPlaybooks(String),
+
+    #[error("Failed to update playbook: {0}")]
+    FailedUpdatePlaybook(String),
+
+    #[error("Failed to read playbook {0}: {1}")]
+    FailedReadPlaybook(String, String),
+
+    #[error("Failed to resolve the conflict: {0}")]
+    ConflictResolutionFailed(String),
+
+    #[error("Aborted, {0}")]
+    Aborted(String),
+
+    #[error("Invalid [hooks] section: {0}")]
+    InvalidHooks(String),
+
+    #[error("The {0} hook failed: {1}")]
+    HookFailed(String, String),
 
     #[error("Failed to finish tar: {0}")]
     FailedFinishTar(std::io::Error),
@@ -64,8 +86,11 @@ pub enum Errors {
     #[error("Walk directory error: {0}")]
     WalkError(ignore::Error),
 
-    #[error("Failed to strip prefix: {0}")]
-    FailedStripPrefix(StripPrefixError),
+    #[error("{} is not within {}", .0.display(), .1.display())]
+    FailedStripPrefix(std::path::PathBuf, std::path::PathBuf),
+
+    #[error("{} is not valid UTF-8, and can't be synced", .0.display())]
+    NonUtf8Path(std::path::PathBuf),
 
     #[error("Failed to append path: {0}")]
     FailedAppendPath(std::io::Error),
@@ -88,9 +113,288 @@ pub enum Errors {
     #[error("Failed to add context: {0}")]
     FailedAddContext(anyhow::Error),
 
-    #[error("Not found character in current or parent directories: {0}")]
-    NotFoundManifest(filesystem::Error),
+    #[error("Failed to parse the kubeconfig: {0}")]
+    KubeconfigParseFailed(String),
+
+    #[error("Not found character in current or parent directories: {}", .0.display())]
+    NotFoundManifest(std::path::PathBuf),
 
     #[error("Invalid character")]
     InvalidCharacter,
+
+    #[error("Failed to fetch actors: {0}")]
+    ActorFetchFailed(http::HTTPError),
+
+    #[error("Failed to list actors: {0}")]
+    FailedListActors(String),
+
+    #[error("Failed to list the actor events: {0}")]
+    FailedListActorEvents(String),
+
+    #[error("Failed to render the template: {0}")]
+    TemplateRenderFailed(handlebars::RenderError),
+
+    #[error("Failed to serialize json: {0}")]
+    JsonSerializeError(serde_json::Error),
+
+    #[error("Failed to write csv: {0}")]
+    CsvError(csv::Error),
+
+    #[error("Failed to write output: {0}")]
+    FailedWriteOutput(std::io::Error),
+
+    #[error("Failed to resolve the user filter: {0}")]
+    UserFilterFailed(String),
+
+    #[error("Failed to search playbooks: {0}")]
+    SearchFailed(http::HTTPError),
+
+    #[error("Failed to stop playbook: {0}")]
+    FailedStopPlaybook(String),
+
+    #[error("Failed to stop playbooks:\n{}", .0.join("\n"))]
+    BulkStopFailed(Vec<String>),
+
+    #[error("Failed to join task: {0}")]
+    FailedJoinTask(tokio::task::JoinError),
+
+    #[error("Input required but not running interactively: {0}")]
+    PromptRequired(String),
+
+    #[error("Missing environment variables: {}", .0.join(", "))]
+    MissingEnvVars(Vec<String>),
+
+    #[error("Found {0} issue(s) in the context")]
+    InvalidContext(usize),
+
+    #[error("Failed to read manifest {0}: {1}")]
+    FailedReadManifest(String, std::io::Error),
+
+    #[error("Failed to fetch manifest {0}: {1}")]
+    FailedFetchManifest(String, reqwest::Error),
+
+    #[error("Manifest {0} is larger than {1} bytes")]
+    ManifestTooLarge(String, u64),
+
+    #[error("Failed to clone repository {0}: {1}")]
+    FailedCloneRepository(String, String),
+
+    #[error("Repository {0} is not cached, and syncing the remote cache is disabled")]
+    RemoteCacheMissing(String),
+
+    #[error("Invalid index of the remote cache: {0}")]
+    InvalidCacheIndex(String),
+
+    #[error("Failed to update the remote cache: {0}")]
+    FailedUpdateCache(std::io::Error),
+
+    #[error("Failed to parse manifest {0}: {1}")]
+    FailedParseManifest(String, toml::de::Error),
+
+    #[error("Failed to parse manifest {0}: {1}")]
+    FailedParseYamlManifest(String, String),
+
+    #[error("A local workspace is required to sync sources, but the manifest was loaded from {0}")]
+    NoLocalWorkspace(String),
+
+    #[error("Invalid grep pattern: {0}")]
+    InvalidGrepPattern(regex::Error),
+
+    #[error("Undefined variables in manifest: {}", .0.join(", "))]
+    UndefinedVariables(Vec<String>),
+
+    #[error("Found {1} issue(s) in manifest {0}")]
+    InvalidManifest(String, usize),
+
+    #[error("No manifest found at {}", .0.display())]
+    ManifestNotFoundAt(std::path::PathBuf),
+
+    #[error("Profile {0} is not defined in the manifest")]
+    NotFoundProfile(String),
+
+    #[error("Environment {0} is not defined in the manifest, available: {1}")]
+    NotFoundEnvironment(String, String),
+
+    #[error("Failed to read snapshot: {0}")]
+    SnapshotReadFailed(std::io::Error),
+
+    #[error("Failed to save snapshot: {0}")]
+    FailedSaveSnapshot(std::io::Error),
+
+    #[error("Partners form a cycle: {}", .0.join(" -> "))]
+    PartnerCycle(Vec<String>),
+
+    #[error("Partners {0} and {1} both reference {2}")]
+    DuplicatePartner(String, String, String),
+
+    #[error("No running playbook found for {0}")]
+    NoMatchingPlaybook(String),
+
+    #[error("Only local manifests can be fixed, got {0}")]
+    ManifestNotLocal(String),
+
+    #[error("Key {0} of the manifest is not a table")]
+    ManifestKeyConflict(String),
+
+    #[error("Failed to edit manifest {0}: {1}")]
+    FailedEditManifest(String, toml_edit::TomlError),
+
+    #[error("Failed to receive events: {0}")]
+    FailedReceiveEvents(String),
+
+    #[error("Failed to format the events as TAP: {0}")]
+    TapFormatFailed(String),
+
+    #[error("Failed to save the last event ID: {0}")]
+    FailedSaveEventState(std::io::Error),
+
+    #[error("The server rejected the manifest: {}", .0.join("; "))]
+    RemoteValidationFailed(Vec<String>),
+
+    #[error("Failed to validate the manifest on the server: {0}")]
+    FailedValidateRemote(String),
+
+    #[error("Character {0} is not deployed in playbook {1}")]
+    CharacterNotDeployed(String, String),
+
+    #[error("Failed to render the diff: {0}")]
+    DiffRenderFailed(String),
+
+    #[error("Failed to parse the log timestamp: {0}")]
+    TimestampParseFailed(chrono::ParseError),
+
+    #[error("Invalid time {0}, expected an RFC 3339 timestamp, a duration such as 5m, 2h or 1d, or now")]
+    InvalidTimestamp(String),
+
+    #[error("Failed to open the log stream: {0}")]
+    FailedOpenLogStream(String),
+
+    #[error("Failed to copy the files: {0}")]
+    FailedCopy(String),
+
+    #[error("Unknown lint rule {0}, expected one of: {1}")]
+    UnknownLintRule(String, String),
+
+    #[error("Failed to run the command in actor {0}: {1}")]
+    FailedExecActor(String, String),
+
+    #[error("Timed out running the command in actor {0}, killed it")]
+    ExecTimeout(String),
+
+    #[error("Characters {0} are not found, available: {1}")]
+    UnknownCharacters(String, String),
+
+    #[error("Character {0} is defined by both {} and {}", .1.display(), .2.display())]
+    DuplicateCharacter(String, std::path::PathBuf, std::path::PathBuf),
+
+    #[error("The manifest {} has no meta.name", .0.display())]
+    MissingCharacterName(std::path::PathBuf),
+
+    #[error("No characters found under {}", .0.display())]
+    NoCharactersFound(std::path::PathBuf),
+
+    #[error("The playbook is not ready after {after_secs} seconds")]
+    PlaybookNotReady { after_secs: u64 },
+
+    #[error("The playbook {0} failed: {1}")]
+    PlaybookFailed(String, String),
+
+    #[error("The actors are not ready after {after_secs} seconds, the last phase was {phase}")]
+    ActorsNotReady { after_secs: u64, phase: String },
+
+    #[error("Invalid artifacts file {0}: {1}")]
+    InvalidArtifacts(String, String),
+
+    #[error("Failed to validate the connection to {0}")]
+    ConnectionValidationFailed(String),
+
+    #[error("Failed to list the templates: {0}")]
+    FailedListTemplates(String),
+
+    #[error("Unknown template {0}, available: {1}")]
+    UnknownTemplate(String, String),
+
+    #[error("The template {1} failed at {0}: {2}")]
+    TemplateFailed(crate::ops::templates::Stage, String, String),
+
+    #[error("Failed to start playbook: {0}")]
+    FailedStartPlaybook(String),
+
+    #[error("Timed out waiting for the actors of playbook {0}")]
+    StartTimeout(String),
+
+    #[error("Failed to restart actor {0}: {1}")]
+    FailedRestartActor(String, String),
+
+    #[error("Failed to fetch the dependencies of the actors: {0}")]
+    FailedFetchDependencies(String),
+
+    #[error("Failed to restart {} actor(s): {}", .0.len(), .0.join("; "))]
+    RollingRestartFailed(Vec<String>),
+
+    #[error("Timed out waiting for playbook {0}")]
+    StopTimeout(String),
+
+    #[error("Failed to kill actor {0}: {1}")]
+    FailedKillActor(String, String),
+
+    #[error("Unknown column {0}, available columns: {1}")]
+    UnknownColumn(String, String),
+
+    #[error("Unsupported manifest format of {0}, expected a .toml, .yaml or .yml file")]
+    UnsupportedManifestFormat(String),
+
+    #[error("Invalid level pattern: {0}")]
+    InvalidLevelPattern(regex::Error),
+
+    #[error("Dry run, {0} was not sent")]
+    DryRun(String),
+
+    #[error("The template variable {0} has no value, set it with --var {0}=VALUE")]
+    TemplateMissingVariable(String),
+
+    #[error("Invalid git URL {0}, expected a https://, ssh:// or user@host:path repository URL")]
+    InvalidGitUrl(String),
+
+    #[error("Failed to start the terminal UI: {0}")]
+    TuiInitFailed(String),
+
+    #[error("Invalid sort field {0}, expected a column optionally followed by :asc or :desc")]
+    InvalidSortField(String),
+
+    #[error("Failed to serialize to YAML: {0}")]
+    YamlSerializeError(serde_yaml::Error),
+
+    #[error("Invalid label selector {0}, expected requirements like app=web, tier!=db, env in (dev,qa) or !canary")]
+    InvalidSelector(String),
+
+    #[error("Failed to replay the events: {0}")]
+    ReplayFailed(String),
+
+    #[error("Another playbook is already titled '{0}'")]
+    RenameConflict(String),
+
+    #[error("The log buffer exceeded {0} bytes, raise --max-buffer or lower --tail")]
+    LogBufferOverflow(usize),
+
+    #[error("Invalid field {0}, expected one of id, title, description, status, created_at, updated_at, owner, created_by, labels or characters")]
+    InvalidFieldName(String),
+
+    #[error("Failed to export the Helm chart: {0}")]
+    HelmExportFailed(String),
+
+    #[error("Failed to export the Docker Compose file: {0}")]
+    ComposeExportFailed(String),
+
+    #[error("The language {0} wasn't detected in the workspace, detected: {1}")]
+    LanguageNotDetected(String, String),
+
+    #[error("The manifest path {0} has no parent directory")]
+    InvalidManifestPath(std::path::PathBuf),
+
+    #[error("Interrupted before the wait was over")]
+    Interrupted,
+
+    #[error("No resources matched")]
+    NoMatch,
 }
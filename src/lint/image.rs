@@ -0,0 +1,120 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{entries, Document, Entry, Finding};
+
+/// The images of the manifest, with their reference.
+fn images(document: &Document) -> Vec<(Entry<'_>, String)> {
+    let images = entries(document).into_iter().filter(|e| e.key == "image");
+    images.filter_map(|e| e.value.as_str().map(String::from).map(|image| (e, image))).collect()
+}
+
+/// Images without a tag or a digest, which silently float on `latest`.
+pub fn tag(document: &Document) -> Vec<Finding> {
+    images(document)
+        .into_iter()
+        .filter(|(_, image)| {
+            let name = image.rsplit('/').next().unwrap_or_default();
+            !image.contains('@') && !name.contains(':')
+        })
+        .map(|(e, image)| Finding::new(format!("{} `{}` has no tag, `latest` will be used", e.path, image), e.value))
+        .collect()
+}
+
+/// Image references with an invalid registry host or repository path.
+pub fn registry(document: &Document) -> Vec<Finding> {
+    let mut findings = vec![];
+    for (entry, image) in images(document) {
+        let name = image.split('@').next().unwrap_or_default();
+        let (registry, repository) = match name.split_once('/') {
+            Some((host, rest)) if host.contains(['.', ':']) || host == "localhost" => (Some(host), rest),
+            _ => (None, name),
+        };
+        let repository = strip_tag(repository);
+
+        let problem = match registry {
+            Some(host) if !valid_host(host) => Some(format!("registry `{}` is not a valid host", host)),
+            _ if !valid_repository(repository) => Some(format!("repository `{}` is not valid", repository)),
+            _ => None,
+        };
+        if let Some(problem) = problem {
+            findings.push(Finding::new(format!("{} `{}`: {}", entry.path, image, problem), entry.value));
+        }
+    }
+    findings
+}
+
+/// The repository without its `:tag`.
+fn strip_tag(repository: &str) -> &str {
+    let start = repository.rfind('/').map_or(0, |i| i + 1);
+    match repository[start..].find(':') {
+        Some(i) => &repository[..start + i],
+        None => repository,
+    }
+}
+
+/// A host name with an optional port, like `registry.example.com:5000`.
+pub(crate) fn valid_host(host: &str) -> bool {
+    let (name, port) = host.split_once(':').unwrap_or((host, "1"));
+    let label = |l: &str| {
+        !l.is_empty()
+            && !l.starts_with('-')
+            && !l.ends_with('-')
+            && l.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    };
+    name.split('.').all(label) && port.parse::<u16>().is_ok_and(|p| p > 0)
+}
+
+/// Lowercase path components separated by `/`, like `library/nginx`.
+pub(crate) fn valid_repository(repository: &str) -> bool {
+    let component = |c: &str| {
+        let separator = |ch: char| matches!(ch, '.' | '_' | '-');
+        !c.is_empty()
+            && !c.starts_with(separator)
+            && !c.ends_with(separator)
+            && c.chars().all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || separator(ch))
+    };
+    repository.split('/').all(component)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::tests::document;
+
+    fn check(rule: fn(&Document) -> Vec<Finding>, image: &str) -> bool {
+        rule(&document(&format!("[deploy]\nimage = \"{}\"\n", image))).is_empty()
+    }
+
+    #[test]
+    fn images_need_a_tag() {
+        assert!(check(tag, "nginx:1.27"));
+        assert!(check(tag, "registry.example.com:5000/web/api:v2"));
+        assert!(check(tag, "nginx@sha256:0123abcd"));
+        assert!(!check(tag, "nginx"));
+        assert!(!check(tag, "registry.example.com:5000/web/api"));
+    }
+
+    #[test]
+    fn images_need_a_valid_registry() {
+        assert!(check(registry, "nginx"));
+        assert!(check(registry, "library/nginx:latest"));
+        assert!(check(registry, "localhost:5000/web"));
+        assert!(check(registry, "ghcr.io/org/web-app:1.0"));
+        assert!(!check(registry, "registry_example.com/web"));
+        assert!(!check(registry, "registry.example.com:99999/web"));
+        assert!(!check(registry, "ghcr.io/Org/web"));
+        assert!(!check(registry, "web//api"));
+    }
+}
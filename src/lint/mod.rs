@@ -0,0 +1,269 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+pub(crate) mod image;
+mod name;
+mod ports;
+mod resources;
+
+use std::fmt::Display;
+use std::ops::Range;
+
+use colored::Colorize;
+use toml_edit::{ImDocument, Item, TableLike, Value};
+use tracing::error;
+
+use crate::errors::{Errors, Result};
+use crate::utils::Manifest;
+
+/// The parsed manifest the rules inspect, it keeps the spans of the values.
+pub type Document = ImDocument<String>;
+
+/// How serious a diagnostic is, errors fail `amp lint` and block deployments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "{}", "warning".yellow()),
+            Severity::Error => write!(f, "{}", "error".red()),
+        }
+    }
+}
+
+/// A problem found by a rule, with the span of the offending value.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub message: String,
+    pub span: Option<Range<usize>>,
+}
+
+impl Finding {
+    fn new(message: impl Into<String>, value: &Value) -> Self {
+        Self { message: message.into(), span: value.span() }
+    }
+}
+
+/// A semantic check of the manifest, beyond what parsing catches.
+pub struct Rule {
+    pub id: &'static str,
+    pub severity: Severity,
+    pub description: &'static str,
+    check: fn(&Document) -> Vec<Finding>,
+}
+
+/// All the known rules, new rules are added by listing them here.
+pub const RULES: [Rule; 6] = [
+    Rule {
+        id: "port-range",
+        severity: Severity::Error,
+        description: "ports are between 1 and 65535",
+        check: ports::range,
+    },
+    Rule {
+        id: "duplicate-port",
+        severity: Severity::Error,
+        description: "a port is declared once",
+        check: ports::duplicates,
+    },
+    Rule {
+        id: "image-tag",
+        severity: Severity::Warning,
+        description: "images are pinned to a tag or a digest",
+        check: image::tag,
+    },
+    Rule {
+        id: "image-registry",
+        severity: Severity::Error,
+        description: "image references have a valid registry and repository",
+        check: image::registry,
+    },
+    Rule {
+        id: "dns-name",
+        severity: Severity::Error,
+        description: "the character name is a valid DNS-1123 label",
+        check: name::dns_label,
+    },
+    Rule {
+        id: "resource-quantity",
+        severity: Severity::Error,
+        description: "cpu and memory are valid quantities, like `500m` or `256Mi`",
+        check: resources::quantity,
+    },
+];
+
+/// A finding reported by a rule, located in the manifest.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// The 1-based line and column of the offending value
+    pub location: Option<(usize, usize)>,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some((line, column)) = self.location {
+            write!(f, "{}:{}: ", line, column)?;
+        }
+        write!(f, "{}[{}]: {}", self.severity, self.rule, self.message)
+    }
+}
+
+/// Run the rules on the manifest content, skipping the allowed ones. Content
+/// which doesn't parse has no diagnostics, the parse error is reported when the
+/// manifest is loaded.
+pub fn run(content: &str, allow: &[String], severity: Option<Severity>) -> Result<Vec<Diagnostic>> {
+    if let Some(id) = allow.iter().find(|id| !RULES.iter().any(|r| r.id == id.as_str())) {
+        let known: Vec<&str> = RULES.iter().map(|r| r.id).collect();
+        return Err(Errors::UnknownLintRule(id.clone(), known.join(", ")));
+    }
+    let Ok(document) = Document::parse(content.to_string()) else {
+        return Ok(vec![]);
+    };
+
+    let rules = RULES
+        .iter()
+        .filter(|r| !allow.iter().any(|id| id == r.id))
+        .filter(|r| severity.is_none() || severity == Some(r.severity));
+    let diagnostics = rules.flat_map(|rule| {
+        (rule.check)(&document).into_iter().map(|finding| Diagnostic {
+            rule: rule.id,
+            severity: rule.severity,
+            message: finding.message,
+            location: finding.span.map(|span| locate(content, span.start)),
+        })
+    });
+
+    Ok(diagnostics.collect())
+}
+
+/// Run the error rules before deploying the manifest, failing if any reports.
+pub fn enforce(manifest: &Manifest, allow: &[String]) -> Result<()> {
+    let origin = manifest.origin.to_string();
+    let diagnostics = run(&manifest.content, allow, Some(Severity::Error))?;
+    for diagnostic in &diagnostics {
+        error!("{}:{}", origin, diagnostic);
+    }
+
+    match diagnostics.len() {
+        0 => Ok(()),
+        n => Err(Errors::InvalidManifest(origin, n)),
+    }
+}
+
+/// The 1-based line and column of the byte offset.
+fn locate(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+    (line, column)
+}
+
+/// A value of the manifest, with its path like `deploy.services[0].port` and
+/// the key it is found under, array elements having the key of their array.
+pub struct Entry<'a> {
+    pub path: String,
+    pub key: &'a str,
+    pub value: &'a Value,
+}
+
+/// All the scalar values of the document, in order.
+fn entries(document: &Document) -> Vec<Entry<'_>> {
+    let mut entries = vec![];
+    visit_table(document.as_table(), "", &mut entries);
+    entries
+}
+
+fn visit_table<'a>(table: &'a dyn TableLike, prefix: &str, entries: &mut Vec<Entry<'a>>) {
+    for (key, item) in table.iter() {
+        let path = if prefix.is_empty() { key.to_string() } else { format!("{}.{}", prefix, key) };
+        match item {
+            Item::Table(table) => visit_table(table, &path, entries),
+            Item::ArrayOfTables(array) => {
+                for (i, table) in array.iter().enumerate() {
+                    visit_table(table, &format!("{}[{}]", path, i), entries);
+                }
+            }
+            Item::Value(value) => visit_value(key, value, path, entries),
+            Item::None => {}
+        }
+    }
+}
+
+fn visit_value<'a>(key: &'a str, value: &'a Value, path: String, entries: &mut Vec<Entry<'a>>) {
+    match value {
+        Value::InlineTable(table) => visit_table(table, &path, entries),
+        Value::Array(array) => {
+            for (i, value) in array.iter().enumerate() {
+                visit_value(key, value, format!("{}[{}]", path, i), entries);
+            }
+        }
+        _ => entries.push(Entry { path, key, value }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse the content for the tests of the rules.
+    pub(super) fn document(content: &str) -> Document {
+        Document::parse(content.to_string()).unwrap()
+    }
+
+    const CONTENT: &str = r#"[meta]
+name = "Web_App"
+
+[deploy]
+image = "nginx"
+"#;
+
+    #[test]
+    fn rule_ids_are_unique() {
+        let mut ids: Vec<&str> = RULES.iter().map(|r| r.id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), RULES.len());
+    }
+
+    #[test]
+    fn locate_the_findings() {
+        let diagnostics = run(CONTENT, &[], None).unwrap();
+        let found: Vec<(&str, Option<(usize, usize)>)> = diagnostics.iter().map(|d| (d.rule, d.location)).collect();
+        assert_eq!(found, vec![("image-tag", Some((5, 9))), ("dns-name", Some((2, 8)))]);
+    }
+
+    #[test]
+    fn skip_allowed_and_other_severities() {
+        let diagnostics = run(CONTENT, &["dns-name".to_string()], None).unwrap();
+        assert_eq!(diagnostics.iter().map(|d| d.rule).collect::<Vec<_>>(), vec!["image-tag"]);
+
+        assert!(run(CONTENT, &["dns-name".to_string()], Some(Severity::Error)).unwrap().is_empty());
+        assert!(matches!(run(CONTENT, &["nope".to_string()], None), Err(Errors::UnknownLintRule(..))));
+        assert!(run("[meta", &[], None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn walk_nested_values() {
+        let document = document("a = { b = [1, { c = 2 }] }\n[[d]]\ne = 3\n");
+        let paths: Vec<(String, &str)> = entries(&document).into_iter().map(|e| (e.path, e.key)).collect();
+        assert_eq!(paths, vec![("a.b[0]".into(), "b"), ("a.b[1].c".into(), "c"), ("d[0].e".into(), "e")]);
+    }
+}
@@ -0,0 +1,72 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Document, Finding};
+
+/// The longest DNS-1123 label.
+const MAX_LEN: usize = 63;
+
+/// The character name is used for the Kubernetes objects, so it must be a
+/// DNS-1123 label: lowercase alphanumerics and `-`, starting and ending with
+/// an alphanumeric, at most 63 characters.
+pub fn dns_label(document: &Document) -> Vec<Finding> {
+    let Some(value) = document.as_table().get("meta").and_then(|m| m.get("name")).and_then(|n| n.as_value()) else {
+        return vec![];
+    };
+    let Some(name) = value.as_str() else {
+        return vec![];
+    };
+
+    let alphanumeric = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit();
+    let problem = if name.is_empty() || name.len() > MAX_LEN {
+        Some(format!("must be 1 to {} characters long", MAX_LEN))
+    } else if !name.chars().all(|c| alphanumeric(c) || c == '-') {
+        Some("must only contain lowercase letters, digits and `-`".to_string())
+    } else if !name.starts_with(alphanumeric) || !name.ends_with(alphanumeric) {
+        Some("must start and end with a lowercase letter or a digit".to_string())
+    } else {
+        None
+    };
+
+    problem.map(|p| Finding::new(format!("meta.name `{}` {}", name, p), value)).into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::tests::document;
+
+    fn check(name: &str) -> Vec<Finding> {
+        dns_label(&document(&format!("[meta]\nname = \"{}\"\n", name)))
+    }
+
+    #[test]
+    fn valid_names() {
+        assert!(check("web").is_empty());
+        assert!(check("api-v2").is_empty());
+        assert!(check(&"a".repeat(63)).is_empty());
+        assert!(dns_label(&document("[deploy]\nimage = \"nginx\"\n")).is_empty());
+    }
+
+    #[test]
+    fn invalid_names() {
+        assert_eq!(
+            check("Web_App")[0].message,
+            "meta.name `Web_App` must only contain lowercase letters, digits and `-`"
+        );
+        assert_eq!(check("-web")[0].message, "meta.name `-web` must start and end with a lowercase letter or a digit");
+        assert_eq!(check(&"a".repeat(64)).len(), 1);
+        assert_eq!(check("").len(), 1);
+    }
+}
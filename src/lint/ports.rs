@@ -0,0 +1,84 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use super::{entries, Document, Finding};
+
+/// The keys holding port numbers.
+const KEYS: [&str; 2] = ["port", "ports"];
+
+/// Ports outside 1-65535.
+pub fn range(document: &Document) -> Vec<Finding> {
+    let ports = entries(document).into_iter().filter(|e| KEYS.contains(&e.key));
+    ports
+        .filter_map(|e| {
+            let port = e.value.as_integer()?;
+            let message = format!("{} is {}, ports must be between 1 and 65535", e.path, port);
+            (!(1..=65535).contains(&port)).then(|| Finding::new(message, e.value))
+        })
+        .collect()
+}
+
+/// Ports declared more than once.
+pub fn duplicates(document: &Document) -> Vec<Finding> {
+    let mut seen: HashMap<i64, String> = HashMap::new();
+    let mut findings = vec![];
+    for entry in entries(document).into_iter().filter(|e| KEYS.contains(&e.key)) {
+        let Some(port) = entry.value.as_integer() else { continue };
+        match seen.get(&port) {
+            Some(first) => {
+                let message = format!("{} reuses port {}, already declared by {}", entry.path, port, first);
+                findings.push(Finding::new(message, entry.value));
+            }
+            None => {
+                seen.insert(port, entry.path);
+            }
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::tests::document;
+
+    #[test]
+    fn ports_out_of_range() {
+        let document = document("[deploy]\nports = [80, 0, 70000]\n[[deploy.services]]\nport = -1\n");
+        let messages: Vec<String> = range(&document).into_iter().map(|f| f.message).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "deploy.ports[1] is 0, ports must be between 1 and 65535",
+                "deploy.ports[2] is 70000, ports must be between 1 and 65535",
+                "deploy.services[0].port is -1, ports must be between 1 and 65535",
+            ]
+        );
+        assert!(range(&crate::lint::tests::document("[deploy]\nports = [1, 65535]\n")).is_empty());
+    }
+
+    #[test]
+    fn duplicate_ports() {
+        let document = document("[[deploy.services]]\nport = 8080\n[[deploy.services]]\nport = 8080\nports = [9090]\n");
+        let findings = duplicates(&document);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].message,
+            "deploy.services[1].port reuses port 8080, already declared by deploy.services[0].port"
+        );
+        assert!(findings[0].span.is_some());
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use toml_edit::Value;
+
+use super::{entries, Document, Finding};
+
+/// The keys holding resource quantities.
+const KEYS: [&str; 2] = ["cpu", "memory"];
+
+/// A Kubernetes quantity, a decimal number with an optional SI or binary suffix.
+static QUANTITY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[0-9]+(\.[0-9]+)?([munkMGTPE]|[KMGTPE]i|[eE][0-9]+)?$").unwrap());
+
+/// CPU and memory amounts the server can't parse.
+pub fn quantity(document: &Document) -> Vec<Finding> {
+    let amounts = entries(document).into_iter().filter(|e| KEYS.contains(&e.key));
+    amounts
+        .filter_map(|e| {
+            let valid = match e.value {
+                Value::String(s) => QUANTITY.is_match(s.value()),
+                Value::Integer(i) => *i.value() >= 0,
+                Value::Float(f) => *f.value() >= 0.0,
+                _ => false,
+            };
+            let message =
+                format!("{} `{}` is not a valid quantity, like `500m` or `256Mi`", e.path, e.value.to_string().trim());
+            (!valid).then(|| Finding::new(message, e.value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::tests::document;
+
+    fn check(value: &str) -> bool {
+        quantity(&document(&format!("[deploy.resources]\nmemory = {}\n", value))).is_empty()
+    }
+
+    #[test]
+    fn valid_quantities() {
+        for value in ["\"256Mi\"", "\"1.5\"", "\"500m\"", "\"1G\"", "\"128974848\"", "\"129e6\"", "2", "0.5"] {
+            assert!(check(value), "{} should be valid", value);
+        }
+    }
+
+    #[test]
+    fn invalid_quantities() {
+        for value in ["\"256MB\"", "\"lots\"", "\"-1\"", "\"1.\"", "-2", "true"] {
+            assert!(!check(value), "{} should be invalid", value);
+        }
+
+        let findings = quantity(&document("[deploy.resources]\ncpu = \"two\"\n"));
+        assert_eq!(
+            findings[0].message,
+            "deploy.resources.cpu `\"two\"` is not a valid quantity, like `500m` or `256Mi`"
+        );
+    }
+}
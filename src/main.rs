@@ -12,33 +12,98 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod client;
 mod cmd;
 mod context;
+mod detect;
 mod errors;
+mod lint;
+mod migrate;
 mod ops;
+mod output;
 mod platform;
+mod progress;
+mod prompt;
+mod recorder;
+mod refresh;
+mod schema;
+mod shutdown;
+mod template;
+mod trace;
+mod tui;
 mod utils;
 
 use std::sync::Arc;
 
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
+use clap_verbosity_flag::VerbosityFilter;
 use context::Context;
-use errors::Result;
-use tracing::error;
-use tracing::metadata::LevelFilter;
-use tracing_subscriber::EnvFilter;
+use errors::{Errors, Result};
+use tracing::{error, info_span, Instrument};
 
 use crate::cmd::cli::Cli;
+use crate::prompt::InquirePrompter;
+use crate::recorder::Recorder;
+use crate::utils::Variables;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let filter = EnvFilter::builder().with_default_directive(LevelFilter::INFO.into()).from_env_lossy();
-    tracing_subscriber::fmt().without_time().with_target(false).with_env_filter(filter).init();
+    trace::init();
 
-    let ctx = Arc::new(Context::init()?);
-    if let Err(err) = Cli::parse().exec(ctx).await {
-        error!("{:#}", err);
-        std::process::exit(1);
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    let mut ctx = Context::init()?;
+    let verbose = matches!(cli.verbose.filter(), VerbosityFilter::Debug | VerbosityFilter::Trace);
+    ctx.recorder = Recorder::new(cli.debug_http.unwrap_or_default(), verbose).with_dry_run(cli.dry_run);
+    ctx.remote_cache = cli.sync_remote_cache;
+    ctx.workspace = cli.workspace.clone();
+    ctx.timestamps = cli.timestamps;
+    ctx.environment = cli.env.clone();
+    ctx.dry_run = cli.dry_run;
+    ctx.variables = Variables::new(cli.set.clone(), cli.no_substitution);
+    ctx.prompter = Box::new(InquirePrompter::new(cli.assume_yes));
+
+    let ctx = Arc::new(ctx);
+    tokio::spawn(shutdown::listen(ctx.clone()));
+
+    if cli.validate_context {
+        let errors = ctx.validate(false).await;
+        for err in &errors {
+            error!("Invalid context, {}", err);
+        }
+        if !errors.is_empty() {
+            std::process::exit(1);
+        }
+    }
+
+    // The root span of this invocation, all the operations are correlated to it.
+    let span = info_span!(
+        "amp",
+        command = matches.subcommand_name().unwrap_or_default(),
+        context = ctx.context_name().await.unwrap_or_default(),
+        workspace = %std::env::current_dir().unwrap_or_default().display(),
+    );
+    let result = cli.exec(ctx.clone()).instrument(span).await;
+    if let Some(timings) = ctx.recorder.summary() {
+        eprintln!("{}", timings);
+    }
+    if let Some(signal) = ctx.shutdown.signal() {
+        std::process::exit(signal.exit_code());
+    }
+    if cli.dry_run {
+        for call in ctx.recorder.planned() {
+            ctx.stdout.println(call)?;
+        }
+    }
+    match result {
+        // The dry run stopped at a planned call, once everything else succeeded.
+        Ok(()) | Err(Errors::DryRun(_)) => {}
+        // `--output none` only reports through the exit code.
+        Err(Errors::NoMatch) => std::process::exit(1),
+        Err(err) => {
+            error!("{:#}", err);
+            std::process::exit(1);
+        }
     }
 
     Ok(())
@@ -0,0 +1,161 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use toml_edit::{DocumentMut, Item, Table};
+
+/// A known legacy construct, and how to rewrite it to the current schema.
+struct Migration {
+    name: &'static str,
+    apply: fn(&mut DocumentMut) -> bool,
+}
+
+/// The supported migrations, applied in order.
+const MIGRATIONS: [Migration; 3] = [
+    Migration { name: "rename [character] to [meta]", apply: character_to_meta },
+    Migration { name: "rename [dependencies] to [partners]", apply: dependencies_to_partners },
+    Migration { name: "move [environment] to [deploy.env]", apply: environment_to_deploy_env },
+];
+
+/// The keys handled by the CLI itself rather than the schema.
+const CLI_KEYS: [&str; 3] = ["profiles", "env", "hooks"];
+
+/// The manifest rewritten to the current schema.
+#[derive(Debug)]
+pub struct Migrated {
+    pub content: String,
+    /// The names of the migrations applied
+    pub applied: Vec<&'static str>,
+    /// The top-level keys unknown to the schema, to be migrated by hand
+    pub follow_ups: Vec<String>,
+}
+
+/// Migrate the manifest content, keeping its comments and formatting. The
+/// `known` keys are the top-level keys of the current schema.
+pub fn migrate(content: &str, known: &[String]) -> Result<Migrated, toml_edit::TomlError> {
+    let mut document: DocumentMut = content.parse()?;
+    let applied = MIGRATIONS.iter().filter(|m| (m.apply)(&mut document)).map(|m| m.name).collect();
+
+    let follow_ups = document
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .filter(|key| !known.is_empty() && !known.contains(key) && !CLI_KEYS.contains(&key.as_str()))
+        .collect();
+
+    Ok(Migrated { content: document.to_string(), applied, follow_ups })
+}
+
+fn character_to_meta(document: &mut DocumentMut) -> bool {
+    rename(document, "character", "meta")
+}
+
+fn dependencies_to_partners(document: &mut DocumentMut) -> bool {
+    rename(document, "dependencies", "partners")
+}
+
+fn environment_to_deploy_env(document: &mut DocumentMut) -> bool {
+    let movable = match document.get("deploy") {
+        Some(deploy) => deploy.as_table().is_some_and(|t| !t.contains_key("env")),
+        None => true,
+    };
+    if !movable {
+        return false;
+    }
+    let Some(environment) = document.remove("environment") else {
+        return false;
+    };
+
+    let deploy = document.entry("deploy").or_insert_with(|| {
+        let mut table = Table::new();
+        table.set_implicit(true);
+        Item::Table(table)
+    });
+    if let Some(deploy) = deploy.as_table_mut() {
+        if environment.is_value() {
+            deploy.set_implicit(false);
+        }
+        deploy.insert("env", environment);
+    }
+
+    true
+}
+
+/// Rename the top-level key, unless the new key is already taken.
+fn rename(document: &mut DocumentMut, from: &str, to: &str) -> bool {
+    if document.contains_key(to) {
+        return false;
+    }
+    match document.remove(from) {
+        Some(item) => {
+            document.insert(to, item);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn known() -> Vec<String> {
+        ["meta", "partners", "build", "deploy"].iter().map(|k| k.to_string()).collect()
+    }
+
+    /// Migrate the `before.toml` fixture, and check it against `after.toml`.
+    fn check(name: &str) -> Migrated {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/fix").join(name);
+        let before = std::fs::read_to_string(dir.join("before.toml")).unwrap();
+        let after = std::fs::read_to_string(dir.join("after.toml")).unwrap();
+
+        let migrated = migrate(&before, &known()).unwrap();
+        let actual: toml::Table = toml::from_str(&migrated.content).unwrap();
+        let expected: toml::Table = toml::from_str(&after).unwrap();
+        assert_eq!(actual, expected);
+
+        for comment in before.lines().filter(|l| l.trim_start().starts_with('#')) {
+            assert!(migrated.content.contains(comment.trim()), "lost comment {:?}", comment);
+        }
+        migrated
+    }
+
+    #[test]
+    fn migrate_character_to_meta() {
+        assert_eq!(check("character-to-meta").applied, vec!["rename [character] to [meta]"]);
+    }
+
+    #[test]
+    fn migrate_dependencies_to_partners() {
+        assert_eq!(check("dependencies-to-partners").applied, vec!["rename [dependencies] to [partners]"]);
+    }
+
+    #[test]
+    fn migrate_environment_to_deploy_env() {
+        assert_eq!(check("environment-to-deploy-env").applied, vec!["move [environment] to [deploy.env]"]);
+    }
+
+    #[test]
+    fn list_unknown_keys_as_follow_ups() {
+        let migrated = migrate("[meta]\nname = \"web\"\n\n[tasks]\npre = \"make\"\n", &known()).unwrap();
+        assert!(migrated.applied.is_empty());
+        assert_eq!(migrated.follow_ups, vec!["tasks"]);
+    }
+
+    #[test]
+    fn keep_current_manifests_untouched() {
+        let content = "# web\n[meta]\nname = \"web\" # inline\n";
+        assert_eq!(migrate(content, &known()).unwrap().content, content);
+    }
+}
@@ -0,0 +1,501 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use futures::StreamExt;
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use reqwest::StatusCode;
+use serde_json::Value;
+use tracing::warn;
+
+use crate::client::{self, Api};
+use crate::errors::{Errors, Result};
+use crate::ops::listing::Response;
+use crate::utils::selector::Selector;
+
+/// The states the actors can be filtered on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Status {
+    Running,
+    Pending,
+    Failed,
+    #[default]
+    All,
+}
+
+impl Status {
+    /// The name of the state, as sent in the `status` query parameter.
+    fn as_str(&self) -> &'static str {
+        match self {
+            Status::Running => "running",
+            Status::Pending => "pending",
+            Status::Failed => "failed",
+            Status::All => "all",
+        }
+    }
+
+    pub fn matches(&self, state: &State) -> bool {
+        *self == Status::All || state.status.eq_ignore_ascii_case(self.as_str())
+    }
+}
+
+/// The state of an actor, read leniently from the raw actor, as the fields
+/// depend on the version of the server. `status` is either a string or an
+/// object with a `state` or `phase`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct State {
+    pub status: String,
+    /// The last error message of a failed actor
+    pub error: Option<String>,
+    /// The estimated seconds a pending actor has yet to wait
+    pub wait: Option<u64>,
+}
+
+impl From<&Value> for State {
+    fn from(actor: &Value) -> Self {
+        let status = &actor["status"];
+        let field = |keys: &[&str]| keys.iter().find_map(|k| status.get(k).or_else(|| actor.get(k)));
+        let state = status.as_str().or_else(|| field(&["state", "phase"]).and_then(Value::as_str));
+
+        Self {
+            status: state.unwrap_or("unknown").to_lowercase(),
+            error: field(&["error", "message", "last_error"]).and_then(Value::as_str).map(String::from),
+            wait: field(&["estimated_wait"]).and_then(Value::as_u64),
+        }
+    }
+}
+
+/// List the raw actors of the playbook, asking the server to filter them by
+/// state. Older servers ignore the parameter, so the caller filters them
+/// again with [`Status::matches`].
+pub async fn list(api: &Api, pid: &str, status: Status) -> Result<Vec<Value>> {
+    let response = list_if_changed(api, pid, status, None, None).await?;
+    Ok(response.data.unwrap_or_default())
+}
+
+/// List the raw actors like [`list`], also asking the server to filter them
+/// by labels, conditionally on the ETag of the last response. There is no
+/// data when the server reports them unchanged. Older servers ignore the
+/// selector as well, so the caller filters them again with [`Selector::matches`].
+pub async fn list_if_changed(
+    api: &Api,
+    pid: &str,
+    status: Status,
+    selector: Option<&Selector>,
+    if_none_match: Option<&str>,
+) -> Result<Response<Vec<Value>>> {
+    let mut request = api.get(&format!("/actors/{}", pid));
+    if status != Status::All {
+        request = request.query(&[("status", status.as_str())]);
+    }
+    if let Some(selector) = selector {
+        request = request.query(&[("selector", selector.to_string())]);
+    }
+    if let Some(etag) = if_none_match {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+
+    let response = client::send(request).await.map_err(|e| Errors::FailedListActors(e.to_string()))?;
+    let etag = response.headers().get(ETAG).and_then(|v| v.to_str().ok()).map(String::from);
+    if response.status() == StatusCode::NOT_MODIFIED {
+        let etag = etag.or(if_none_match.map(String::from));
+        return Ok(Response { data: None, etag, bytes: 0, total: None, next: None });
+    }
+
+    let response = response.error_for_status().map_err(|e| Errors::FailedListActors(e.to_string()))?;
+    let body = response.bytes().await.map_err(|e| Errors::FailedListActors(e.to_string()))?;
+    let data = serde_json::from_slice(&body).map_err(|e| Errors::FailedListActors(e.to_string()))?;
+    Ok(Response { data: Some(data), etag, bytes: body.len(), total: None, next: None })
+}
+
+/// The labels of the raw actor, skipping the values other than strings.
+pub fn labels(actor: &Value) -> HashMap<String, String> {
+    let labels = actor["labels"].as_object().into_iter().flatten();
+    labels.filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string()))).collect()
+}
+
+/// The names of the raw actors, skipping the ones without.
+pub fn names(actors: &[Value]) -> Vec<String> {
+    actors.iter().filter_map(|a| a["name"].as_str()).map(String::from).collect()
+}
+
+/// An event of an actor, read leniently from the raw event.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ActorEvent {
+    pub timestamp: String,
+    pub severity: String,
+    pub message: String,
+}
+
+impl From<&Value> for ActorEvent {
+    fn from(event: &Value) -> Self {
+        let field = |keys: &[&str]| keys.iter().find_map(|k| event.get(*k).and_then(Value::as_str)).map(String::from);
+        Self {
+            timestamp: field(&["timestamp", "time", "created_at"]).unwrap_or_default(),
+            severity: field(&["severity", "level", "type"]).unwrap_or_else(|| "Normal".into()),
+            message: field(&["message", "reason"]).unwrap_or_default(),
+        }
+    }
+}
+
+/// The most recent events of the actor, newest first, at most `count` of them.
+pub fn recent_events(events: &[Value], count: usize) -> Vec<ActorEvent> {
+    let mut events: Vec<(Option<DateTime<Utc>>, ActorEvent)> = events
+        .iter()
+        .map(ActorEvent::from)
+        .map(|e| (DateTime::parse_from_rfc3339(&e.timestamp).ok().map(|t| t.with_timezone(&Utc)), e))
+        .collect();
+    events.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| b.1.timestamp.cmp(&a.1.timestamp)));
+    events.into_iter().take(count).map(|(_, e)| e).collect()
+}
+
+/// List the raw events of the actor.
+pub async fn events(api: &Api, pid: &str, name: &str) -> Result<Vec<Value>> {
+    let request = api.get(&format!("/playbooks/{}/actors/{}/events", pid, name));
+    let response = client::send(request).await.and_then(|r| r.error_for_status());
+    let response = response.map_err(|e| Errors::FailedListActorEvents(e.to_string()))?;
+    response.json().await.map_err(|e| Errors::FailedListActorEvents(e.to_string()))
+}
+
+/// Restart the actor.
+pub async fn restart(api: &Api, pid: &str, name: &str) -> Result<()> {
+    let request = api.post(&format!("/playbooks/{}/actors/{}/actions/restart", pid, name));
+    let response = client::send(request).await.and_then(|r| r.error_for_status());
+    response.map(|_| ()).map_err(|e| Errors::FailedRestartActor(name.to_string(), e.to_string()))
+}
+
+/// Kill the actor with SIGKILL, rather than waiting for it to stop.
+pub async fn kill(api: &Api, pid: &str, name: &str) -> Result<()> {
+    let request = api.post(&format!("/playbooks/{}/actors/{}/actions/kill", pid, name));
+    let response =
+        client::send(request.json(&serde_json::json!({"signal": "SIGKILL"}))).await.and_then(|r| r.error_for_status());
+    response.map(|_| ()).map_err(|e| Errors::FailedKillActor(name.to_string(), e.to_string()))
+}
+
+/// Run the command in the actor and write its output as it streams, until the
+/// command exits. Past the limit, the command is killed with `SIGKILL`, the
+/// actor itself keeps running.
+pub async fn exec<F>(
+    api: &Api,
+    pid: &str,
+    name: &str,
+    command: &[String],
+    limit: Option<Duration>,
+    mut write: F,
+) -> Result<()>
+where
+    F: FnMut(&[u8]) -> Result<()>,
+{
+    // Named by the client, the command can be killed before any response.
+    let id = format!("{}-{}", std::process::id(), Utc::now().timestamp_micros());
+    let run = async {
+        let request = api.post(&format!("/playbooks/{}/actors/{}/exec", pid, name));
+        let body = serde_json::json!({"id": id, "command": command});
+        let response = client::send(request.json(&body)).await.and_then(|r| r.error_for_status());
+        let mut stream = response.map_err(|e| Errors::FailedExecActor(name.to_string(), e.to_string()))?.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            write(&chunk.map_err(|e| Errors::FailedExecActor(name.to_string(), e.to_string()))?)?;
+        }
+        Ok(())
+    };
+
+    let Some(limit) = limit else {
+        return run.await;
+    };
+    match tokio::time::timeout(limit, run).await {
+        Ok(result) => result,
+        Err(_) => {
+            kill_exec(api, pid, name, &id).await?;
+            Err(Errors::ExecTimeout(name.to_string()))
+        }
+    }
+}
+
+/// Kill the command run in the actor by `exec`.
+async fn kill_exec(api: &Api, pid: &str, name: &str, id: &str) -> Result<()> {
+    let request = api.post(&format!("/playbooks/{}/actors/{}/actions/kill", pid, name));
+    let body = serde_json::json!({"signal": "SIGKILL", "exec": id});
+    let response = client::send(request.json(&body)).await.and_then(|r| r.error_for_status());
+    response.map(|_| ()).map_err(|e| Errors::FailedKillActor(name.to_string(), e.to_string()))
+}
+
+/// The dependency graph of the actors of the playbook, the names of the
+/// actors each of them depends on by name.
+pub async fn dependency_graph(api: &Api, pid: &str) -> Result<BTreeMap<String, Vec<String>>> {
+    let response =
+        client::send(api.get(&format!("/playbooks/{}/dependencies", pid))).await.and_then(|r| r.error_for_status());
+    let response = response.map_err(|e| Errors::FailedFetchDependencies(e.to_string()))?;
+    response.json().await.map_err(|e| Errors::FailedFetchDependencies(e.to_string()))
+}
+
+/// The names in dependency order, the dependencies before the actors
+/// depending on them, and by name otherwise. The actors of a cycle are
+/// ordered by name after the others.
+pub fn dependency_order(names: &[String], graph: &BTreeMap<String, Vec<String>>) -> Vec<String> {
+    let mut pending: BTreeSet<&String> = names.iter().collect();
+    let mut order: Vec<String> = vec![];
+
+    loop {
+        let ready: Vec<&String> = pending
+            .iter()
+            .filter(|name| graph.get(name.as_str()).into_iter().flatten().all(|d| !pending.contains(d)))
+            .copied()
+            .collect();
+        if ready.is_empty() {
+            break;
+        }
+        for name in ready {
+            pending.remove(name);
+            order.push(name.clone());
+        }
+    }
+
+    if !pending.is_empty() {
+        let cycle: Vec<&str> = pending.iter().map(|n| n.as_str()).collect();
+        warn!("The actors {} depend on each other, restarting them by name", cycle.join(", "));
+        order.extend(pending.into_iter().cloned());
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Client;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, body_partial_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn api(server: &MockServer) -> Api {
+        Api::new(Client::new(), &server.uri(), None)
+    }
+
+    fn actors() -> Vec<Value> {
+        vec![
+            json!({"name": "web", "status": "Running"}),
+            json!({"name": "api", "status": {"state": "Failed", "message": "image pull backoff"}}),
+            json!({"name": "db", "status": {"phase": "Pending", "estimated_wait": 30}}),
+            json!({"name": "cache"}),
+        ]
+    }
+
+    fn filter(status: Status) -> Vec<String> {
+        let actors = actors();
+        let matching = actors.iter().filter(|a| status.matches(&State::from(*a)));
+        matching.map(|a| a["name"].as_str().unwrap().to_string()).collect()
+    }
+
+    #[test]
+    fn read_states() {
+        let states: Vec<State> = actors().iter().map(State::from).collect();
+        assert_eq!(states[0], State { status: "running".into(), ..Default::default() });
+        assert_eq!(states[1].error.as_deref(), Some("image pull backoff"));
+        assert_eq!(states[2], State { status: "pending".into(), error: None, wait: Some(30) });
+        assert_eq!(states[3].status, "unknown");
+    }
+
+    #[test]
+    fn exclude_other_states() {
+        assert_eq!(filter(Status::Running), vec!["web"]);
+        assert_eq!(filter(Status::Failed), vec!["api"]);
+        assert_eq!(filter(Status::Pending), vec!["db"]);
+        assert_eq!(filter(Status::All), vec!["web", "api", "db", "cache"]);
+    }
+
+    #[tokio::test]
+    async fn send_status_filter() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/actors/42"))
+            .and(query_param("status", "failed"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(actors()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let actors = list(&api(&server), "42", Status::Failed).await.unwrap();
+        assert_eq!(actors.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn report_failed_requests() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(500)).mount(&server).await;
+
+        let result = list(&api(&server), "42", Status::All).await;
+        assert!(matches!(result, Err(Errors::FailedListActors(_))));
+    }
+
+    #[tokio::test]
+    async fn list_unchanged_actors_without_data() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/actors/42"))
+            .and(header("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/actors/42"))
+            .respond_with(ResponseTemplate::new(200).insert_header("ETag", "\"v1\"").set_body_json(actors()))
+            .mount(&server)
+            .await;
+
+        let api = api(&server);
+        let first = list_if_changed(&api, "42", Status::All, None, None).await.unwrap();
+        assert_eq!(first.data.unwrap().len(), 4);
+        let second = list_if_changed(&api, "42", Status::All, None, first.etag.as_deref()).await.unwrap();
+        assert_eq!((second.data, second.etag.as_deref(), second.bytes), (None, Some("\"v1\""), 0));
+    }
+
+    #[test]
+    fn keep_the_most_recent_events() {
+        let events = vec![
+            json!({"timestamp": "2025-01-02T03:04:05Z", "severity": "Normal", "message": "Pulled"}),
+            json!({"timestamp": "2025-01-02T03:06:00+01:00", "severity": "Warning", "message": "Back-off"}),
+            json!({"timestamp": "2025-01-02T03:05:00Z", "level": "Normal", "reason": "Started"}),
+            json!({"message": "undated"}),
+        ];
+
+        let recent = recent_events(&events, 2);
+        let messages: Vec<&str> = recent.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec!["Started", "Pulled"]);
+        assert_eq!(recent_events(&events, 10).last().unwrap().severity, "Normal");
+    }
+
+    #[tokio::test]
+    async fn fetch_actor_events() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/playbooks/42/actors/web/events"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{"message": "Pulled"}])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let events = events(&api(&server), "42", "web").await.unwrap();
+        assert_eq!(events.len(), 1);
+    }
+
+    #[test]
+    fn order_the_dependencies_first() {
+        let names: Vec<String> = ["web", "api", "db", "cache", "worker"].iter().map(|n| n.to_string()).collect();
+        let graph = BTreeMap::from([
+            ("web".to_string(), vec!["api".to_string()]),
+            ("api".to_string(), vec!["db".to_string(), "cache".to_string()]),
+            ("worker".to_string(), vec!["db".to_string(), "queue".to_string()]),
+        ]);
+        assert_eq!(dependency_order(&names, &graph), vec!["cache", "db", "api", "worker", "web"]);
+
+        let cyclic = BTreeMap::from([
+            ("web".to_string(), vec!["api".to_string()]),
+            ("api".to_string(), vec!["web".to_string()]),
+        ]);
+        assert_eq!(dependency_order(&names, &cyclic), vec!["cache", "db", "worker", "api", "web"]);
+    }
+
+    #[tokio::test]
+    async fn restart_and_read_the_dependencies() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/playbooks/42/actors/web/actions/restart"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/playbooks/42/dependencies"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"web": ["api"], "api": []})))
+            .mount(&server)
+            .await;
+
+        restart(&api(&server), "42", "web").await.unwrap();
+        let err = restart(&api(&server), "42", "api").await.unwrap_err();
+        assert!(matches!(err, Errors::FailedRestartActor(name, _) if name == "api"));
+
+        let graph = dependency_graph(&api(&server), "42").await.unwrap();
+        assert_eq!(graph["web"], vec!["api"]);
+    }
+
+    #[tokio::test]
+    async fn kill_with_sigkill() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/playbooks/42/actors/web/actions/kill"))
+            .and(body_json(json!({"signal": "SIGKILL"})))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        kill(&api(&server), "42", "web").await.unwrap();
+        let err = kill(&api(&server), "42", "api").await.unwrap_err();
+        assert!(matches!(err, Errors::FailedKillActor(name, _) if name == "api"));
+    }
+
+    #[tokio::test]
+    async fn exec_streams_the_output() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/playbooks/42/actors/web/exec"))
+            .and(body_partial_json(json!({"command": ["echo", "hello"]})))
+            .respond_with(ResponseTemplate::new(200).set_body_string("hello\n"))
+            .mount(&server)
+            .await;
+
+        let mut output = vec![];
+        let command = vec!["echo".to_string(), "hello".to_string()];
+        let limit = Some(Duration::from_secs(5));
+        exec(&api(&server), "42", "web", &command, limit, |chunk| {
+            output.extend_from_slice(chunk);
+            Ok(())
+        })
+        .await
+        .unwrap();
+        assert_eq!(output, b"hello\n");
+    }
+
+    #[tokio::test]
+    async fn exec_kills_the_command_on_timeout() {
+        let server = MockServer::start().await;
+        // A command which never exits within the test.
+        Mock::given(method("POST"))
+            .and(path("/v1/playbooks/42/actors/web/exec"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(60)))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/playbooks/42/actors/web/actions/kill"))
+            .and(body_partial_json(json!({"signal": "SIGKILL"})))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let command = vec!["sleep".to_string(), "infinity".to_string()];
+        let limit = Some(Duration::from_millis(100));
+        let err = exec(&api(&server), "42", "web", &command, limit, |_| Ok(())).await.unwrap_err();
+        assert!(matches!(err, Errors::ExecTimeout(name) if name == "web"));
+
+        let requests = server.received_requests().await.unwrap();
+        let body = |i: usize| serde_json::from_slice::<Value>(&requests[i].body).unwrap();
+        assert_eq!(body(0)["id"], body(1)["exec"], "the kill names the execution");
+    }
+}
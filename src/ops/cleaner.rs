@@ -14,34 +14,11 @@
 
 use std::sync::Arc;
 
-use tokio::runtime::Runtime;
 use tracing::{info, warn};
 
 use crate::context::Context;
 use crate::errors::{Errors, Result};
 
-/// Setup handler for for handling Ctrl-C signals.
-pub fn setup_signal_handler(ctx: Arc<Context>, cleanup: bool) {
-    ctrlc::set_handler(move || {
-        warn!("Received Ctrl-C, will exit now");
-
-        if cleanup {
-            // Try to delete playbook if it is available in the session.
-            let context: Arc<Context> = ctx.clone();
-            // need a tokio runtime to spawn a future, so we create one here.
-            let rt = Runtime::new().expect("Failed to create tokio runtime");
-            rt.block_on(async move {
-                if let Err(err) = try_cleanup_playbook(&context).await {
-                    warn!("Failed to cleanup playbook: {:?}", err);
-                }
-            });
-        }
-
-        std::process::exit(1);
-    })
-    .expect("Error setting Ctrl-C handler");
-}
-
 /// Try to delete playbook if it is available in the session.
 pub async fn try_cleanup_playbook(ctx: &Arc<Context>) -> Result<()> {
     let playbook = ctx.session.playbook.read().await;
@@ -54,7 +31,8 @@ pub async fn try_cleanup_playbook(ctx: &Arc<Context>) -> Result<()> {
 
     // Delete playbook from the server.
     let pid = &playbook.as_ref().unwrap().id;
-    let status = ctx.client.playbooks().delete(pid).map_err(Errors::ClientError)?;
+    let path = format!("/playbooks/{}", pid);
+    let status = ctx.recorder.mutate_async("DELETE", &path, None, ctx.playbooks().delete_async(pid)).await?;
     if status != 204 {
         return Err(Errors::FailedDeletePlaybook(pid.to_string()));
     }
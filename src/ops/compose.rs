@@ -0,0 +1,215 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Translate a playbook into a Docker Compose file, for running it locally
+//! with `docker compose up` when Amphitheatre is unavailable. Each character
+//! becomes a service of its image, restarted unless stopped, depending on the
+//! partners that are characters of the playbook as well.
+
+use std::path::Path;
+
+use serde_json::{json, Map, Value};
+
+use crate::errors::{Errors, Result};
+use crate::ops::helm::Character;
+use crate::output;
+
+/// The name of the file written.
+pub const FILE: &str = "docker-compose.yml";
+
+/// The translated Compose file, with the features of the playbook it leaves out.
+#[derive(Debug)]
+pub struct Compose {
+    pub content: String,
+    pub unsupported: Vec<String>,
+}
+
+impl Compose {
+    /// Write the file into the directory, without overwriting one.
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(FILE);
+        if path.exists() {
+            return Err(Errors::ComposeExportFailed(format!("{} already exists", path.display())));
+        }
+        let failed = |e: std::io::Error| Errors::ComposeExportFailed(format!("{}: {}", path.display(), e));
+        std::fs::create_dir_all(dir).map_err(failed)?;
+        std::fs::write(&path, &self.content).map_err(failed)
+    }
+}
+
+/// The CPUs of a Kubernetes quantity, `500m` being `0.5`.
+fn cpus(quantity: &Value) -> Option<Value> {
+    let quantity = quantity.as_str().map(String::from).unwrap_or_else(|| quantity.to_string());
+    let cpus = match quantity.strip_suffix('m') {
+        Some(millis) => millis.parse::<f64>().ok()? / 1000.0,
+        None => quantity.parse::<f64>().ok()?,
+    };
+    Some(json!(cpus.to_string()))
+}
+
+/// The memory of a Kubernetes quantity in the byte units of Compose, `256Mi` being `256m`.
+fn memory(quantity: &Value) -> Option<Value> {
+    if let Some(bytes) = quantity.as_u64() {
+        return Some(json!(bytes.to_string()));
+    }
+    let quantity = quantity.as_str()?;
+    let split = quantity.find(|c: char| !c.is_ascii_digit()).unwrap_or(quantity.len());
+    let (amount, unit) = quantity.split_at(split);
+    let unit = match unit {
+        "" => "",
+        "K" | "Ki" | "k" => "k",
+        "M" | "Mi" => "m",
+        "G" | "Gi" => "g",
+        _ => return None,
+    };
+    (!amount.is_empty()).then(|| json!(format!("{}{}", amount, unit)))
+}
+
+fn service(character: &Character, names: &[&str], unsupported: &mut Vec<String>) -> Value {
+    let mut service = json!({"image": character.image, "restart": "unless-stopped"});
+    if !character.env.is_empty() {
+        let string = |v: &Value| Value::String(v.as_str().map(String::from).unwrap_or_else(|| v.to_string()));
+        let environment: Map<String, Value> = character.env.iter().map(|(k, v)| (k.clone(), string(v))).collect();
+        service["environment"] = Value::Object(environment);
+    }
+    if !character.ports.is_empty() {
+        service["ports"] = character.ports.iter().map(|p| json!(format!("{}:{}", p, p))).collect();
+    }
+    if let Some(resources) = &character.resources {
+        let mut limits = Map::new();
+        limits.extend(resources.get("cpu").and_then(cpus).map(|c| (String::from("cpus"), c)));
+        limits.extend(resources.get("memory").and_then(memory).map(|m| (String::from("memory"), m)));
+        if !limits.is_empty() {
+            service["deploy"] = json!({"resources": {"limits": limits}});
+        }
+    }
+
+    let (known, unknown): (Vec<&String>, Vec<&String>) =
+        character.partners.iter().partition(|p| names.contains(&p.as_str()));
+    if !known.is_empty() {
+        service["depends_on"] = json!(known);
+    }
+    for partner in unknown {
+        unsupported.push(format!("the partner {} of {} isn't a character of the playbook", partner, character.name));
+    }
+    service
+}
+
+/// Translate the playbook, as returned by the server, into a Compose file.
+/// The characters must all have an image, as Compose doesn't build them.
+pub fn compose(playbook: &Value) -> Result<Compose> {
+    let mut characters = vec![];
+    let mut unsupported = vec![];
+    for value in playbook["characters"].as_array().into_iter().flatten() {
+        let character = Character::read(value, &playbook["images"]);
+        if character.image.is_none() {
+            return Err(Errors::ComposeExportFailed(format!(
+                "the character {} has no image, build and push one first",
+                character.name
+            )));
+        }
+        if value.get("build").is_some_and(|b| !b.is_null()) {
+            unsupported.push(format!("{} is built from source, the service runs its image only", character.name));
+        }
+        if value["live"].as_bool() == Some(true) {
+            unsupported.push(format!("{} is synced live, mount the workspace as a volume instead", character.name));
+        }
+        characters.push(character);
+    }
+    if characters.is_empty() {
+        return Err(Errors::ComposeExportFailed(String::from("the playbook has no characters")));
+    }
+
+    let names: Vec<&str> = characters.iter().map(|c| c.name.as_str()).collect();
+    let services: Map<String, Value> =
+        characters.iter().map(|c| (c.name.clone(), service(c, &names, &mut unsupported))).collect();
+    let content = output::yaml(&json!({ "services": services }))?;
+    Ok(Compose { content, unsupported })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playbook() -> Value {
+        json!({
+            "title": "Shop",
+            "images": {"api": "ghcr.io/shop/api:1.2"},
+            "characters": [
+                {
+                    "meta": {"name": "web"},
+                    "partners": {"api": {"repo": "https://github.com/shop/api"}, "cdn": {"registry": "shop"}},
+                    "deploy": {
+                        "image": "nginx:1.27",
+                        "ports": [80, 443],
+                        "env": {"API_URL": "http://api:8080", "WORKERS": 4},
+                        "resources": {"cpu": "500m", "memory": "256Mi"},
+                    },
+                },
+                {"meta": {"name": "api"}, "deploy": {"services": [{"port": 8080}]}, "build": {}},
+                {"meta": {"name": "db"}, "deploy": {"image": "postgres:16", "resources": {"cpu": 2, "memory": "1Gi"}}},
+            ],
+        })
+    }
+
+    #[test]
+    fn translate_every_character() {
+        let compose = compose(&playbook()).unwrap();
+        let file: Value = serde_yaml::from_str(&compose.content).unwrap();
+        let services = file["services"].as_object().unwrap();
+        assert_eq!(services.keys().collect::<Vec<_>>(), vec!["api", "db", "web"]);
+        assert!(services.values().all(|s| s["restart"] == "unless-stopped"));
+
+        assert_eq!(
+            services["web"],
+            json!({
+                "image": "nginx:1.27",
+                "restart": "unless-stopped",
+                "environment": {"API_URL": "http://api:8080", "WORKERS": "4"},
+                "ports": ["80:80", "443:443"],
+                "deploy": {"resources": {"limits": {"cpus": "0.5", "memory": "256m"}}},
+                "depends_on": ["api"],
+            })
+        );
+        assert_eq!(services["api"]["image"], "ghcr.io/shop/api:1.2");
+        assert_eq!(services["api"]["ports"], json!(["8080:8080"]));
+        assert_eq!(services["db"]["deploy"]["resources"]["limits"], json!({"cpus": "2", "memory": "1g"}));
+
+        assert_eq!(
+            compose.unsupported,
+            vec![
+                "api is built from source, the service runs its image only",
+                "the partner cdn of web isn't a character of the playbook",
+            ]
+        );
+    }
+
+    #[test]
+    fn characters_need_an_image() {
+        let mut playbook = playbook();
+        playbook["images"] = json!({});
+        let result = compose(&playbook);
+        assert!(matches!(result, Err(Errors::ComposeExportFailed(message)) if message.contains("api has no image")));
+    }
+
+    #[test]
+    fn convert_the_quantities() {
+        assert_eq!(cpus(&json!("250m")), Some(json!("0.25")));
+        assert_eq!(cpus(&json!(1.5)), Some(json!("1.5")));
+        assert_eq!(cpus(&json!("lots")), None);
+        assert_eq!(memory(&json!("512Ki")), Some(json!("512k")));
+        assert_eq!(memory(&json!(1048576)), Some(json!("1048576")));
+        assert_eq!(memory(&json!("1Ti")), None);
+    }
+}
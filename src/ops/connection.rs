@@ -0,0 +1,93 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use amp_common::config::Cluster;
+use reqwest::{Client, StatusCode};
+
+use crate::client::{self, Api};
+use crate::errors::{Errors, Result};
+
+/// Check the server of the cluster is reachable with `GET /v1/health`, and
+/// that it accepts the token with `GET /v1/me`.
+pub async fn validate(http: &Client, cluster: &Cluster) -> Result<()> {
+    let server = cluster.server.trim_end_matches('/');
+    let failed = |reason: String| Errors::ConnectionValidationFailed(format!("{}: {}", server, reason));
+
+    let api = Api::new(http.clone(), server, cluster.token.as_deref());
+
+    let response = client::send(api.get("/health")).await.map_err(|e| failed(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(failed(format!("the health check returned {}", response.status())));
+    }
+
+    let response = client::send(api.get("/me")).await.map_err(|e| failed(e.to_string()))?;
+    match response.status() {
+        status if status.is_success() => Ok(()),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(failed(String::from("the token was rejected"))),
+        status => Err(failed(format!("the token check returned {}", status))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn cluster(server: &str) -> Cluster {
+        Cluster { title: "test".into(), server: format!("{}/", server), token: Some("secret".into()) }
+    }
+
+    async fn mount(server: &MockServer, endpoint: &str, status: u16) {
+        Mock::given(method("GET")).and(path(endpoint)).respond_with(ResponseTemplate::new(status)).mount(server).await;
+    }
+
+    #[tokio::test]
+    async fn accept_reachable_servers_and_valid_tokens() {
+        let server = MockServer::start().await;
+        mount(&server, "/v1/health", 200).await;
+        Mock::given(method("GET"))
+            .and(path("/v1/me"))
+            .and(header("authorization", "Bearer secret"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        validate(&Client::new(), &cluster(&server.uri())).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn report_unhealthy_servers_and_rejected_tokens() {
+        let server = MockServer::start().await;
+        mount(&server, "/v1/health", 503).await;
+        let err = validate(&Client::new(), &cluster(&server.uri())).await.unwrap_err();
+        assert!(err.to_string().contains(&server.uri()), "{}", err);
+        assert!(err.to_string().contains("503"), "{}", err);
+
+        let server = MockServer::start().await;
+        mount(&server, "/v1/health", 200).await;
+        mount(&server, "/v1/me", 401).await;
+        let err = validate(&Client::new(), &cluster(&server.uri())).await.unwrap_err();
+        assert!(matches!(&err, Errors::ConnectionValidationFailed(m) if m.ends_with("the token was rejected")));
+    }
+
+    #[tokio::test]
+    async fn report_unreachable_servers() {
+        let cluster = Cluster { title: "test".into(), server: "http://127.0.0.1:1".into(), token: None };
+        let err = validate(&Client::new(), &cluster).await.unwrap_err();
+        assert!(matches!(&err, Errors::ConnectionValidationFailed(m) if m.starts_with("http://127.0.0.1:1: ")));
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use futures::stream;
+use ignore::WalkBuilder;
+use reqwest::{Body, RequestBuilder};
+use serde::Serialize;
+
+use crate::client::{self, Api};
+use crate::errors::{Errors, Result};
+use crate::progress::{self, Progress};
+
+/// The size of the chunks the uploads are sent in.
+const CHUNK: usize = 64 * 1024;
+
+/// One side of a copy, `<actor>:<path>` or a local path.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Location {
+    Local(PathBuf),
+    Actor { name: String, path: String },
+}
+
+impl Location {
+    /// Parse a location, local paths are the ones starting with `.` or `/`,
+    /// a Windows drive, or without a colon.
+    pub fn parse(s: &str) -> Self {
+        let drive = s.len() >= 2 && s.as_bytes()[0].is_ascii_alphabetic() && s.as_bytes()[1] == b':';
+        match s.split_once(':') {
+            Some((name, path)) if !name.is_empty() && !drive && !s.starts_with(['.', '/']) => {
+                Location::Actor { name: name.to_string(), path: path.to_string() }
+            }
+            _ => Location::Local(PathBuf::from(s)),
+        }
+    }
+}
+
+/// The bytes transferred, and how long it took.
+#[derive(Debug, Serialize)]
+pub struct Transferred {
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl Transferred {
+    /// The transfer rate, in MB/s.
+    pub fn rate(&self) -> f64 {
+        progress::rate(self.bytes, self.elapsed)
+    }
+}
+
+/// The files endpoint of the actor, for the given path inside of it.
+pub fn request(api: &Api, method: reqwest::Method, pid: &str, name: &str, path: &str) -> RequestBuilder {
+    api.request(method, &format!("/actors/{}/{}/files", pid, name)).query(&[("path", path)])
+}
+
+/// Download the tarball of the request, passing each chunk to the sink as it
+/// is received.
+pub async fn download<F>(request: RequestBuilder, mut sink: F, progress: bool) -> Result<Transferred>
+where
+    F: FnMut(&[u8]) -> Result<()>,
+{
+    let start = Instant::now();
+    let mut response = client::send(request).await.map_err(|e| Errors::FailedCopy(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(Errors::FailedCopy(format!("the server responded with {}", response.status())));
+    }
+
+    let bar = Progress::bytes(response.content_length(), progress);
+    let mut bytes = 0;
+    while let Some(chunk) = response.chunk().await.map_err(|e| Errors::FailedCopy(e.to_string()))? {
+        sink(&chunk)?;
+        bytes += chunk.len() as u64;
+        bar.inc(chunk.len() as u64);
+    }
+    bar.finish();
+
+    Ok(Transferred { bytes, elapsed: start.elapsed() })
+}
+
+/// Upload the tarball with the request, in chunks so the progress can be shown.
+pub async fn upload(request: RequestBuilder, payload: Vec<u8>, progress: bool) -> Result<Transferred> {
+    let start = Instant::now();
+    let bytes = payload.len() as u64;
+    let bar = Progress::bytes(Some(bytes), progress);
+
+    let chunks: Vec<Vec<u8>> = payload.chunks(CHUNK).map(<[u8]>::to_vec).collect();
+    let ticker = bar.clone();
+    let body = stream::iter(chunks.into_iter().map(move |chunk| {
+        ticker.inc(chunk.len() as u64);
+        Ok::<_, std::io::Error>(chunk)
+    }));
+    let request = request.header(reqwest::header::CONTENT_LENGTH, bytes).body(Body::wrap_stream(body));
+    let response = client::send(request).await.map_err(|e| Errors::FailedCopy(e.to_string()))?;
+    bar.finish();
+    if !response.status().is_success() {
+        return Err(Errors::FailedCopy(format!("the server responded with {}", response.status())));
+    }
+
+    Ok(Transferred { bytes, elapsed: start.elapsed() })
+}
+
+/// Pack the file, or the files of the directory minus the ignored ones, into
+/// a tarball, showing the file count progress.
+pub fn pack(path: &Path, progress: bool) -> Result<Vec<u8>> {
+    let mut files = vec![];
+    if path.is_file() {
+        let name = path.file_name().map(PathBuf::from).unwrap_or_default();
+        files.push((path.to_path_buf(), name));
+    } else {
+        for entry in WalkBuilder::new(path).build() {
+            let entry = entry.map_err(Errors::WalkError)?;
+            if entry.path().is_file() {
+                let name = entry.path().strip_prefix(path).map(Path::to_path_buf).unwrap_or_default();
+                files.push((entry.path().to_path_buf(), name));
+            }
+        }
+    }
+
+    let bar = Progress::files(files.len() as u64, progress);
+    let mut tar = tar::Builder::new(Vec::new());
+    for (path, name) in &files {
+        tar.append_path_with_name(path, name).map_err(Errors::FailedAppendPath)?;
+        bar.inc(1);
+    }
+    bar.finish();
+
+    tar.into_inner().map_err(Errors::FailedFinishTar)
+}
+
+/// Unpack the tarball into the directory.
+pub fn unpack(payload: &[u8], dir: &Path) -> Result<()> {
+    tar::Archive::new(Cursor::new(payload)).unpack(dir).map_err(|e| Errors::FailedCopy(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Client;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    #[test]
+    fn parse_locations() {
+        let actor = |name: &str, path: &str| Location::Actor { name: name.into(), path: path.into() };
+        assert_eq!(Location::parse("web:/app/logs"), actor("web", "/app/logs"));
+        assert_eq!(Location::parse("web:"), actor("web", ""));
+        assert_eq!(Location::parse("./web:1"), Location::Local(PathBuf::from("./web:1")));
+        assert_eq!(Location::parse("/tmp/out"), Location::Local(PathBuf::from("/tmp/out")));
+        assert_eq!(Location::parse("C:\\out"), Location::Local(PathBuf::from("C:\\out")));
+        assert_eq!(Location::parse("out"), Location::Local(PathBuf::from("out")));
+    }
+
+    #[tokio::test]
+    async fn download_chunked_responses() {
+        let server = MockServer::start().await;
+        let body = vec![7u8; 3 * 1024 * 1024];
+        Mock::given(method("GET"))
+            .and(path("/v1/actors/42/web/files"))
+            .and(query_param("path", "/app"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(body.clone()))
+            .mount(&server)
+            .await;
+
+        let api = Api::new(Client::new(), &server.uri(), Some("t"));
+        let request = request(&api, reqwest::Method::GET, "42", "web", "/app");
+        let mut received = vec![];
+        let mut chunks = 0;
+        let transferred = download(
+            request,
+            |chunk| {
+                chunks += 1;
+                received.extend_from_slice(chunk);
+                Ok(())
+            },
+            false,
+        )
+        .await
+        .unwrap();
+
+        assert!(chunks > 1, "the body should arrive in several chunks");
+        assert_eq!(received, body);
+        assert_eq!(transferred.bytes, body.len() as u64);
+        let expected = body.len() as f64 / 1_000_000.0 / transferred.elapsed.as_secs_f64();
+        assert!((transferred.rate() - expected).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn upload_in_chunks() {
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(path("/v1/actors/42/web/files"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let api = Api::new(Client::new(), &server.uri(), None);
+        let request = request(&api, reqwest::Method::PUT, "42", "web", "/app");
+        let payload = vec![1u8; 2 * CHUNK + 10];
+        let transferred = upload(request, payload.clone(), false).await.unwrap();
+        assert_eq!(transferred.bytes, payload.len() as u64);
+
+        let received = &server.received_requests().await.unwrap()[0];
+        assert_eq!(received.body, payload);
+    }
+
+    #[tokio::test]
+    async fn fail_on_error_status() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(404)).mount(&server).await;
+
+        let api = Api::new(Client::new(), &server.uri(), None);
+        let request = request(&api, reqwest::Method::GET, "42", "web", "/missing");
+        let err = download(request, |_| Ok(()), false).await.unwrap_err();
+        assert!(matches!(err, Errors::FailedCopy(_)));
+    }
+
+    #[test]
+    fn pack_and_unpack_directories() {
+        let dir = std::env::temp_dir().join(format!("amp-copy-{}", std::process::id()));
+        let source = dir.join("source");
+        std::fs::create_dir_all(source.join("nested")).unwrap();
+        std::fs::write(source.join("a.txt"), "a").unwrap();
+        std::fs::write(source.join("nested/b.txt"), "b").unwrap();
+
+        let payload = pack(&source, false).unwrap();
+        unpack(&payload, &dir.join("target")).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("target/a.txt")).unwrap(), "a");
+        assert_eq!(std::fs::read_to_string(dir.join("target/nested/b.txt")).unwrap(), "b");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
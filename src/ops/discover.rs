@@ -0,0 +1,85 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use amp_common::schema::Character;
+
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::pipeline;
+use crate::prompt::Prompter;
+
+/// The given playbook ID, or else the ID of the running playbook whose title
+/// matches the name of the manifest, prompting if several match.
+pub async fn playbook_id(ctx: &Context, pid: Option<&str>) -> Result<String> {
+    if let Some(pid) = pid {
+        return Ok(pid.to_string());
+    }
+
+    let manifest = pipeline::read(ctx, &None, &[]).await?;
+    let origin = manifest.origin.to_string();
+    let character: Character = toml::from_str(&manifest.content).map_err(|e| Errors::FailedParseManifest(origin, e))?;
+    let name = character.meta.name;
+
+    let options =
+        HashMap::from([(String::from("title"), name.clone()), (String::from("status"), String::from("running"))]);
+    let playbooks = ctx.recorder.call_async("GET", "/playbooks", ctx.playbooks().list_async(Some(options))).await?;
+    let candidates: Vec<(String, String)> = playbooks.into_iter().map(|p| (p.id, p.title)).collect();
+
+    choose(&candidates, &name, ctx.prompter.as_ref())
+}
+
+/// Choose the playbook titled as the given name among the `(id, title)` candidates.
+fn choose(candidates: &[(String, String)], name: &str, prompter: &dyn Prompter) -> Result<String> {
+    let matches: Vec<&(String, String)> = candidates.iter().filter(|(_, title)| title == name).collect();
+    match matches.as_slice() {
+        [] => Err(Errors::NoMatchingPlaybook(name.to_string())),
+        [(id, _)] => Ok(id.clone()),
+        _ => {
+            let options: Vec<String> = matches.iter().map(|(id, title)| format!("{} ({})", id, title)).collect();
+            let index = prompter.select("Select the playbook:", &options, None)?;
+            Ok(matches[index].0.clone())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt::{Answer, ScriptedPrompter};
+
+    fn candidates() -> Vec<(String, String)> {
+        vec![("p1".into(), "web".into()), ("p2".into(), "api".into()), ("p3".into(), "web".into())]
+    }
+
+    #[test]
+    fn choose_the_single_match() {
+        let prompter = ScriptedPrompter::new(vec![]);
+        assert_eq!(choose(&candidates(), "api", &prompter).unwrap(), "p2");
+    }
+
+    #[test]
+    fn prompt_among_several_matches() {
+        let prompter = ScriptedPrompter::new(vec![Answer::Select(1)]);
+        assert_eq!(choose(&candidates(), "web", &prompter).unwrap(), "p3");
+    }
+
+    #[test]
+    fn report_no_match() {
+        let prompter = ScriptedPrompter::new(vec![]);
+        let err = choose(&candidates(), "worker", &prompter).unwrap_err();
+        assert!(matches!(err, Errors::NoMatchingPlaybook(name) if name == "worker"));
+    }
+}
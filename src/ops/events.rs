@@ -0,0 +1,353 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use futures::StreamExt;
+use reqwest::RequestBuilder;
+use reqwest_eventsource::retry::Never;
+use reqwest_eventsource::{Error, Event, EventSource};
+use serde_json::Value;
+use tokio::time::{sleep, Duration};
+use tracing::{info, warn};
+
+use crate::client::Api;
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::tap::Tap;
+use crate::utils;
+
+/// The directory holding the last received event IDs, relative to the working directory.
+const STATE_DIR: &str = ".amp";
+
+/// The output formats of the events.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// The event data as received
+    #[default]
+    Raw,
+    /// The test results as TAP, for the test harnesses
+    Tap,
+}
+
+/// Stream the events of the playbook, starting after the given event ID. When
+/// following, the stream is reconnected from the last received event until shutdown.
+pub async fn stream(ctx: &Context, pid: &str, after: Option<String>, follow: bool, format: Format) -> Result<()> {
+    let mut tap = (format == Format::Tap).then(Tap::default);
+    let state = state_path(Path::new(STATE_DIR), pid);
+    let print = |data: &str| match tap.as_mut() {
+        Some(tap) => tap.format(data)?.iter().try_for_each(|l| ctx.stdout.println(l)),
+        None => ctx.stdout.println(data),
+    };
+    let result = receive(ctx, pid, after, follow, Some(&state), print).await;
+    if let Some(plan) = tap.and_then(|t| t.finish()) {
+        ctx.stdout.println(&plan)?;
+    }
+    result
+}
+
+/// Replay the events of the playbook from the start, spaced as they originally
+/// were divided by the speed, or at once with a speed of 0. The replay stops
+/// after the event with the given ID, failing if the stream has none.
+pub async fn replay(ctx: &Context, pid: &str, speed: f64, stop_at: Option<&str>, format: Format) -> Result<()> {
+    let path = format!("/playbooks/{}/events", pid);
+    let mut es = connect(ctx, pid, None).await?;
+    es.set_retry_policy(Box::new(Never));
+
+    let mut tap = (format == Format::Tap).then(Tap::default);
+    let mut pacer = Pacer::new(speed);
+    let start = Instant::now();
+    let (mut frames, mut stopped) = (0, false);
+    let mut result = Ok(());
+    ctx.recorder.stream_opened(&path);
+
+    loop {
+        let event = tokio::select! {
+            event = es.next() => event,
+            _ = ctx.shutdown.triggered() => break,
+        };
+        let Some(event) = event else { break };
+
+        match event {
+            Ok(Event::Open) => {}
+            Ok(Event::Message(message)) => {
+                frames += 1;
+                if let Some(delay) = pacer.delay(&message.data) {
+                    tokio::select! {
+                        _ = sleep(delay) => {}
+                        _ = ctx.shutdown.triggered() => break,
+                    }
+                }
+                match tap.as_mut() {
+                    Some(tap) => tap.format(&message.data)?.iter().try_for_each(|l| ctx.stdout.println(l))?,
+                    None => ctx.stdout.println(&message.data)?,
+                }
+                if stop_at == Some(message.id.as_str()) {
+                    stopped = true;
+                    break;
+                }
+            }
+            Err(Error::StreamEnded) => break,
+            Err(err) => {
+                result = Err(Errors::ReplayFailed(err.to_string()));
+                break;
+            }
+        }
+    }
+
+    es.close();
+    ctx.recorder.stream_closed(&path, frames, start.elapsed(), None);
+    if let Some(plan) = tap.and_then(|t| t.finish()) {
+        ctx.stdout.println(&plan)?;
+    }
+    match stop_at {
+        Some(id) if result.is_ok() && !stopped && !ctx.shutdown.is_triggered() => {
+            Err(Errors::ReplayFailed(format!("the stream ended before the event {}", id)))
+        }
+        _ => result,
+    }
+}
+
+/// Spaces the replayed events by the time between their timestamps divided by
+/// the speed. The events without timestamps are replayed right away.
+struct Pacer {
+    speed: f64,
+    last: Option<DateTime<Utc>>,
+}
+
+impl Pacer {
+    fn new(speed: f64) -> Self {
+        Self { speed, last: None }
+    }
+
+    /// The time to wait before replaying the event.
+    fn delay(&mut self, data: &str) -> Option<Duration> {
+        let time = timestamp(data)?;
+        let elapsed = (time - self.last.replace(time)?).to_std().ok()?;
+        (self.speed > 0.0).then(|| elapsed.div_f64(self.speed))
+    }
+}
+
+/// The time of the event, from its `timestamp`, `time` or `created_at` field.
+fn timestamp(data: &str) -> Option<DateTime<Utc>> {
+    let event: Value = serde_json::from_str(data).ok()?;
+    let time = ["timestamp", "time", "created_at"].iter().find_map(|k| event.get(*k).and_then(Value::as_str))?;
+    DateTime::parse_from_rfc3339(time).ok().map(|t| t.with_timezone(&Utc))
+}
+
+/// Follow the events of the playbook from the start until shutdown, handing
+/// the data of each one to the sink. The last event ID isn't stored.
+pub async fn follow<F>(ctx: &Context, pid: &str, sink: F) -> Result<()>
+where
+    F: FnMut(&str) -> Result<()>,
+{
+    receive(ctx, pid, None, true, None, sink).await
+}
+
+/// Receive the events, storing the ID of the last one at the state path if any.
+async fn receive<F>(
+    ctx: &Context,
+    pid: &str,
+    after: Option<String>,
+    follow: bool,
+    state: Option<&Path>,
+    mut sink: F,
+) -> Result<()>
+where
+    F: FnMut(&str) -> Result<()>,
+{
+    let path = format!("/playbooks/{}/events", pid);
+    let mut last_id = after;
+
+    loop {
+        let mut es = connect(ctx, pid, last_id.as_deref()).await?;
+        if !follow {
+            es.set_retry_policy(Box::new(Never));
+        }
+
+        let start = Instant::now();
+        let mut frames = 0;
+        let mut result = Ok(());
+        ctx.recorder.stream_opened(&path);
+
+        loop {
+            let event = tokio::select! {
+                event = es.next() => event,
+                _ = ctx.shutdown.triggered() => break,
+            };
+            let Some(event) = event else { break };
+
+            match event {
+                Ok(Event::Open) => {}
+                Ok(Event::Message(message)) => {
+                    frames += 1;
+                    if !message.id.is_empty() {
+                        if let Some(state) = state {
+                            save_last_id(state, &message.id)?;
+                        }
+                        last_id = Some(message.id);
+                    }
+                    sink(&message.data)?;
+                }
+                Err(Error::StreamEnded) => break,
+                Err(err) if follow => warn!("The event stream is interrupted, reconnecting: {}", err),
+                Err(err) => {
+                    result = Err(Errors::FailedReceiveEvents(err.to_string()));
+                    break;
+                }
+            }
+        }
+
+        es.close();
+        ctx.recorder.stream_closed(&path, frames, start.elapsed(), None);
+        if !follow || ctx.shutdown.is_triggered() || result.is_err() {
+            return result;
+        }
+
+        info!("The event stream was closed, reconnecting after event {}", last_id.as_deref().unwrap_or("-"));
+        tokio::select! {
+            _ = sleep(Duration::from_secs(1)) => {}
+            _ = ctx.shutdown.triggered() => return Ok(()),
+        }
+    }
+}
+
+/// The last event ID stored for the playbook, if any.
+pub fn last_id(pid: &str) -> Option<String> {
+    load_last_id(&state_path(Path::new(STATE_DIR), pid))
+}
+
+/// The message of the last error among the events received so far, without
+/// following the stream.
+pub async fn last_error(ctx: &Context, pid: &str) -> Option<String> {
+    let mut es = connect(ctx, pid, None).await.ok()?;
+    es.set_retry_policy(Box::new(Never));
+
+    let mut messages = vec![];
+    let collect = async {
+        while let Some(Ok(event)) = es.next().await {
+            if let Event::Message(message) = event {
+                messages.push(message.data);
+            }
+        }
+    };
+    let _ = tokio::time::timeout(Duration::from_secs(5), collect).await;
+    es.close();
+
+    error_message(&messages)
+}
+
+/// The message of the last event whose `level` or `type` is an error.
+fn error_message(messages: &[String]) -> Option<String> {
+    messages.iter().rev().find_map(|data| {
+        let event: Value = serde_json::from_str(data).ok()?;
+        let kind = event.get("level").or_else(|| event.get("type")).and_then(Value::as_str)?;
+        if !kind.eq_ignore_ascii_case("error") && !kind.eq_ignore_ascii_case("failed") {
+            return None;
+        }
+        ["message", "reason", "error"].iter().find_map(|k| event.get(*k).and_then(Value::as_str)).map(String::from)
+    })
+}
+
+async fn connect(ctx: &Context, pid: &str, after: Option<&str>) -> Result<EventSource> {
+    let request = request(&ctx.api().await, pid, after);
+    EventSource::new(request).map_err(|e| Errors::FailedReceiveEvents(e.to_string()))
+}
+
+/// Build the initial SSE request, resuming after the given event ID.
+fn request(api: &Api, pid: &str, after: Option<&str>) -> RequestBuilder {
+    let mut request = api.get(&format!("/playbooks/{}/events", pid));
+    if let Some(id) = after {
+        request = request.header("Last-Event-ID", id);
+    }
+    request
+}
+
+fn state_path(dir: &Path, pid: &str) -> PathBuf {
+    dir.join(format!("last-event-{}", pid))
+}
+
+fn load_last_id(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+fn save_last_id(path: &Path, id: &str) -> Result<()> {
+    utils::atomic_write(path, id.as_bytes()).map_err(Errors::FailedSaveEventState)
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Client;
+
+    use super::*;
+
+    #[test]
+    fn resume_with_last_event_id() {
+        let resumed = request(&Api::new(Client::new(), "https://a", Some("t")), "p1", Some("42")).build().unwrap();
+        assert_eq!(resumed.url().as_str(), "https://a/v1/playbooks/p1/events");
+        assert_eq!(resumed.headers().get("Last-Event-ID").unwrap(), "42");
+
+        let initial = request(&Api::new(Client::new(), "https://a", None), "p1", None).build().unwrap();
+        assert!(initial.headers().get("Last-Event-ID").is_none());
+    }
+
+    #[test]
+    fn find_the_last_error_message() {
+        let messages: Vec<String> = [
+            r#"{"level": "error", "message": "image not found"}"#,
+            r#"{"type": "Failed", "reason": "crash loop back-off"}"#,
+            r#"{"level": "info", "message": "retrying"}"#,
+            "plain text",
+        ]
+        .iter()
+        .map(|m| m.to_string())
+        .collect();
+
+        assert_eq!(error_message(&messages).as_deref(), Some("crash loop back-off"));
+        assert_eq!(error_message(&messages[2..]), None);
+    }
+
+    #[test]
+    fn space_the_replayed_events() {
+        let event = |time: &str| format!(r#"{{"timestamp": "{}", "message": "built"}}"#, time);
+
+        let mut pacer = Pacer::new(2.0);
+        assert_eq!(pacer.delay(&event("2024-01-15T10:30:00Z")), None);
+        assert_eq!(pacer.delay(&event("2024-01-15T10:30:04Z")), Some(Duration::from_secs(2)));
+        assert_eq!(pacer.delay("plain text"), None);
+        assert_eq!(pacer.delay(&event("2024-01-15T10:30:05Z")), Some(Duration::from_millis(500)));
+        assert_eq!(pacer.delay(&event("2024-01-15T10:29:00Z")), None, "out of order events are not delayed");
+
+        let mut instant = Pacer::new(0.0);
+        instant.delay(&event("2024-01-15T10:30:00Z"));
+        assert_eq!(instant.delay(&event("2024-01-15T10:31:00Z")), None);
+    }
+
+    #[test]
+    fn store_last_event_id() {
+        let dir = std::env::temp_dir().join(format!("amp-events-{}", std::process::id()));
+        let path = state_path(&dir, "p1");
+        assert_eq!(load_last_id(&path), None);
+
+        save_last_id(&path, "41").unwrap();
+        save_last_id(&path, "42").unwrap();
+        assert_eq!(load_last_id(&path).as_deref(), Some("42"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
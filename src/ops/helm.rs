@@ -0,0 +1,404 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Translate a playbook into a minimal Helm chart, for moving off Amphitheatre
+//! onto plain Kubernetes. Each character becomes a `Deployment` of its image,
+//! its ports a `Service`, and its environment a `ConfigMap`, or a `Secret` for
+//! the variables named like credentials. The images and replicas are values.
+//!
+//! Some features have no Helm equivalent and are left out of the chart:
+//!
+//! - building the characters from source, the chart deploys their images only
+//! - the live sync of the workspace into the running actors
+//! - the partners of a character, which aren't exported along with it
+//! - the playbook lifecycle, such as starting, stopping and the events
+
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Map, Value};
+
+use crate::errors::{Errors, Result};
+use crate::output;
+
+/// The variables stored in a `Secret` rather than a `ConfigMap`.
+const SECRET_NAMES: [&str; 6] = ["SECRET", "PASSWORD", "TOKEN", "KEY", "CREDENTIAL", "PRIVATE"];
+
+/// A file of the chart, relative to its directory.
+#[derive(Debug, PartialEq, Eq)]
+pub struct File {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+/// The translated chart, with the features of the playbook it leaves out.
+#[derive(Debug)]
+pub struct Chart {
+    pub name: String,
+    pub files: Vec<File>,
+    pub unsupported: Vec<String>,
+}
+
+impl Chart {
+    /// Write the files of the chart under the directory, which must not exist yet.
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        if dir.exists() {
+            return Err(Errors::HelmExportFailed(format!("{} already exists", dir.display())));
+        }
+        let failed = |e: std::io::Error| Errors::HelmExportFailed(format!("{}: {}", dir.display(), e));
+        for file in &self.files {
+            let path = dir.join(&file.path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(failed)?;
+            }
+            std::fs::write(&path, &file.content).map_err(failed)?;
+        }
+        Ok(())
+    }
+}
+
+/// A character as read leniently from the playbook, also exported by [`crate::ops::compose`].
+#[derive(Debug, Default)]
+pub(crate) struct Character {
+    pub name: String,
+    pub image: Option<String>,
+    pub ports: Vec<u64>,
+    pub env: Map<String, Value>,
+    /// The CPU and memory, like `{"cpu": "500m", "memory": "256Mi"}`
+    pub resources: Option<Value>,
+    /// The names of the partners the character depends on
+    pub partners: Vec<String>,
+}
+
+impl Character {
+    pub fn read(character: &Value, images: &Value) -> Self {
+        let deploy = character.get("deploy").unwrap_or(character);
+        let name = character["meta"]["name"].as_str().or(character["name"].as_str()).unwrap_or_default();
+        let image = images[name].as_str().or(deploy["image"].as_str()).map(String::from);
+
+        let port = |v: &Value| v.as_u64().or(v["port"].as_u64());
+        let mut ports: Vec<u64> = deploy["ports"].as_array().into_iter().flatten().filter_map(port).collect();
+        for service in deploy["services"].as_array().into_iter().flatten() {
+            ports.extend(port(service));
+            ports.extend(service["ports"].as_array().into_iter().flatten().filter_map(port));
+        }
+        ports.sort_unstable();
+        ports.dedup();
+
+        Self {
+            name: name.to_string(),
+            image,
+            ports,
+            env: deploy["env"].as_object().cloned().unwrap_or_default(),
+            resources: deploy.get("resources").filter(|r| r.is_object()).cloned(),
+            partners: character["partners"].as_object().map(|p| p.keys().cloned().collect()).unwrap_or_default(),
+        }
+    }
+
+    /// The templated name of its resources, unique to the release.
+    fn resource(&self) -> String {
+        format!("{{{{ .Release.Name }}}}-{}", dns_label(&self.name))
+    }
+
+    fn labels(&self) -> Value {
+        json!({
+            "app.kubernetes.io/name": dns_label(&self.name),
+            "app.kubernetes.io/instance": "{{ .Release.Name }}",
+        })
+    }
+
+    /// The value of the character in `values.yaml`.
+    fn value(&self, key: &str) -> String {
+        format!("{{{{ (index .Values {:?}).{} }}}}", self.name, key)
+    }
+
+    /// The environment split into the plain variables and the credentials.
+    fn env(&self) -> (Map<String, Value>, Map<String, Value>) {
+        let string = |v: &Value| Value::String(v.as_str().map(String::from).unwrap_or_else(|| v.to_string()));
+        self.env.iter().map(|(k, v)| (k.clone(), string(v))).partition(|(k, _)| !secret(k))
+    }
+
+    fn deployment(&self) -> Value {
+        let (config, secrets) = self.env();
+        let mut container = json!({"name": dns_label(&self.name), "image": self.value("image")});
+        if !self.ports.is_empty() {
+            container["ports"] = self.ports.iter().map(|p| json!({"containerPort": p})).collect();
+        }
+        let mut sources = vec![];
+        if !config.is_empty() {
+            sources.push(json!({"configMapRef": {"name": self.resource()}}));
+        }
+        if !secrets.is_empty() {
+            sources.push(json!({"secretRef": {"name": self.resource()}}));
+        }
+        if !sources.is_empty() {
+            container["envFrom"] = Value::Array(sources);
+        }
+        if let Some(resources) = &self.resources {
+            container["resources"] = json!({ "requests": resources });
+        }
+
+        json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": {"name": self.resource(), "labels": self.labels()},
+            "spec": {
+                "replicas": self.value("replicas"),
+                "selector": {"matchLabels": self.labels()},
+                "template": {
+                    "metadata": {"labels": self.labels()},
+                    "spec": {"containers": [container]},
+                },
+            },
+        })
+    }
+
+    fn service(&self) -> Option<Value> {
+        let ports: Vec<Value> =
+            self.ports.iter().map(|p| json!({"name": format!("port-{}", p), "port": p, "targetPort": p})).collect();
+        (!ports.is_empty()).then(|| {
+            json!({
+                "apiVersion": "v1",
+                "kind": "Service",
+                "metadata": {"name": self.resource(), "labels": self.labels()},
+                "spec": {"selector": self.labels(), "ports": ports},
+            })
+        })
+    }
+
+    fn config_map(&self) -> Option<Value> {
+        let (config, _) = self.env();
+        (!config.is_empty()).then(|| {
+            json!({
+                "apiVersion": "v1",
+                "kind": "ConfigMap",
+                "metadata": {"name": self.resource(), "labels": self.labels()},
+                "data": config,
+            })
+        })
+    }
+
+    fn secret(&self) -> Option<Value> {
+        let (_, secrets) = self.env();
+        (!secrets.is_empty()).then(|| {
+            json!({
+                "apiVersion": "v1",
+                "kind": "Secret",
+                "metadata": {"name": self.resource(), "labels": self.labels()},
+                "type": "Opaque",
+                "stringData": secrets,
+            })
+        })
+    }
+}
+
+/// Whether the variable looks like a credential.
+fn secret(name: &str) -> bool {
+    let name = name.to_uppercase();
+    SECRET_NAMES.iter().any(|s| name.contains(s))
+}
+
+/// The name as a DNS label, lowercase alphanumerics separated by dashes.
+fn dns_label(name: &str) -> String {
+    let mut label = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            label.push(c.to_ascii_lowercase());
+        } else if !label.is_empty() && !label.ends_with('-') {
+            label.push('-');
+        }
+    }
+    label.truncate(63);
+    label.trim_end_matches('-').to_string()
+}
+
+/// Join the objects into a multi-document YAML file.
+fn documents(objects: Vec<Value>) -> Result<String> {
+    let documents = objects.iter().map(output::yaml).collect::<Result<Vec<String>>>()?;
+    Ok(documents.join("---\n"))
+}
+
+/// Translate the playbook, as returned by the server, into a chart. The
+/// characters must all have an image, as the chart doesn't build them.
+pub fn chart(playbook: &Value) -> Result<Chart> {
+    let title = playbook["title"].as_str().unwrap_or_default();
+    let name = dns_label(title);
+    if name.is_empty() {
+        return Err(Errors::HelmExportFailed(format!("the title '{}' isn't usable as a chart name", title)));
+    }
+
+    let mut characters = vec![];
+    let mut unsupported = vec![];
+    for value in playbook["characters"].as_array().into_iter().flatten() {
+        let character = Character::read(value, &playbook["images"]);
+        if character.image.is_none() {
+            return Err(Errors::HelmExportFailed(format!(
+                "the character {} has no image, build and push one first",
+                character.name
+            )));
+        }
+        if value.get("build").is_some_and(|b| !b.is_null()) {
+            unsupported.push(format!("{} is built from source, the chart deploys its image only", character.name));
+        }
+        if value["live"].as_bool() == Some(true) {
+            unsupported.push(format!("{} is synced live, the chart has no equivalent", character.name));
+        }
+        if !character.partners.is_empty() {
+            unsupported.push(format!("the partners of {} aren't exported", character.name));
+        }
+        characters.push(character);
+    }
+    if characters.is_empty() {
+        return Err(Errors::HelmExportFailed(String::from("the playbook has no characters")));
+    }
+
+    let metadata = json!({
+        "apiVersion": "v2",
+        "name": name,
+        "description": playbook["description"].as_str().filter(|d| !d.is_empty()).unwrap_or(title),
+        "type": "application",
+        "version": "0.1.0",
+    });
+    let values: Map<String, Value> =
+        characters.iter().map(|c| (c.name.clone(), json!({"image": c.image, "replicas": 1}))).collect();
+
+    let mut files = vec![
+        File { path: PathBuf::from("Chart.yaml"), content: output::yaml(&metadata)? },
+        File { path: PathBuf::from("values.yaml"), content: output::yaml(&values)? },
+        File {
+            path: PathBuf::from("templates/deployment.yaml"),
+            content: documents(characters.iter().map(Character::deployment).collect())?,
+        },
+    ];
+    let templates: [(&str, fn(&Character) -> Option<Value>); 3] = [
+        ("templates/service.yaml", Character::service),
+        ("templates/configmap.yaml", Character::config_map),
+        ("templates/secret.yaml", Character::secret),
+    ];
+    for (path, template) in templates {
+        let objects: Vec<Value> = characters.iter().filter_map(template).collect();
+        if !objects.is_empty() {
+            files.push(File { path: PathBuf::from(path), content: documents(objects)? });
+        }
+    }
+
+    Ok(Chart { name, files, unsupported })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playbook() -> Value {
+        json!({
+            "id": "42",
+            "title": "Shop (main)",
+            "description": "",
+            "images": {"api": "ghcr.io/shop/api:1.2"},
+            "characters": [
+                {
+                    "meta": {"name": "web"},
+                    "deploy": {
+                        "image": "nginx:1.27",
+                        "ports": [80],
+                        "services": [{"port": 443}, {"port": 80}],
+                        "env": {"LOG_LEVEL": "info", "API_TOKEN": "s3cr3t", "WORKERS": 4},
+                        "resources": {"cpu": "500m", "memory": "256Mi"},
+                    },
+                },
+                {"meta": {"name": "api"}, "build": {"dockerfile": "Dockerfile"}, "live": true},
+            ],
+        })
+    }
+
+    fn file<'a>(chart: &'a Chart, path: &str) -> Option<&'a str> {
+        chart.files.iter().find(|f| f.path == Path::new(path)).map(|f| f.content.as_str())
+    }
+
+    #[test]
+    fn translate_the_characters() {
+        let chart = chart(&playbook()).unwrap();
+        assert_eq!(chart.name, "shop-main");
+        let paths: Vec<&Path> = chart.files.iter().map(|f| f.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            [
+                "Chart.yaml",
+                "values.yaml",
+                "templates/deployment.yaml",
+                "templates/service.yaml",
+                "templates/configmap.yaml",
+                "templates/secret.yaml"
+            ]
+            .map(Path::new)
+        );
+
+        let metadata: Value = serde_yaml::from_str(file(&chart, "Chart.yaml").unwrap()).unwrap();
+        assert_eq!(metadata["apiVersion"], "v2");
+        assert_eq!(metadata["description"], "Shop (main)");
+        let values: Value = serde_yaml::from_str(file(&chart, "values.yaml").unwrap()).unwrap();
+        assert_eq!(
+            values,
+            json!({
+                "web": {"image": "nginx:1.27", "replicas": 1},
+                "api": {"image": "ghcr.io/shop/api:1.2", "replicas": 1},
+            })
+        );
+
+        let deployments = file(&chart, "templates/deployment.yaml").unwrap();
+        let web: Value = serde_yaml::from_str(deployments.split("---\n").next().unwrap()).unwrap();
+        let container = &web["spec"]["template"]["spec"]["containers"][0];
+        assert_eq!(web["metadata"]["name"], "{{ .Release.Name }}-web");
+        assert_eq!(container["image"], "{{ (index .Values \"web\").image }}");
+        assert_eq!(container["ports"], json!([{"containerPort": 80}, {"containerPort": 443}]));
+        assert_eq!(container["resources"], json!({"requests": {"cpu": "500m", "memory": "256Mi"}}));
+        assert_eq!(container["envFrom"].as_array().unwrap().len(), 2);
+        assert_eq!(deployments.matches("kind: Deployment").count(), 2);
+
+        let services = file(&chart, "templates/service.yaml").unwrap();
+        assert_eq!(services.matches("kind: Service").count(), 1, "the api has no ports");
+        let config: Value = serde_yaml::from_str(file(&chart, "templates/configmap.yaml").unwrap()).unwrap();
+        assert_eq!(config["data"], json!({"LOG_LEVEL": "info", "WORKERS": "4"}));
+        let secret: Value = serde_yaml::from_str(file(&chart, "templates/secret.yaml").unwrap()).unwrap();
+        assert_eq!(secret["stringData"], json!({"API_TOKEN": "s3cr3t"}));
+
+        assert_eq!(
+            chart.unsupported,
+            vec![
+                "api is built from source, the chart deploys its image only",
+                "api is synced live, the chart has no equivalent"
+            ]
+        );
+    }
+
+    #[test]
+    fn characters_need_an_image() {
+        let mut playbook = playbook();
+        playbook["images"] = json!({});
+        let result = chart(&playbook);
+        assert!(matches!(result, Err(Errors::HelmExportFailed(message)) if message.contains("api has no image")));
+
+        let result = chart(&json!({"title": "Empty", "characters": []}));
+        assert!(matches!(result, Err(Errors::HelmExportFailed(message)) if message.contains("no characters")));
+    }
+
+    #[test]
+    fn write_the_chart_once() {
+        let path = std::env::temp_dir().join(format!("amp-helm-{}", std::process::id()));
+        let chart = chart(&playbook()).unwrap();
+        chart.write(&path).unwrap();
+        assert!(path.join("templates/deployment.yaml").is_file());
+        assert!(matches!(chart.write(&path), Err(Errors::HelmExportFailed(_))));
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
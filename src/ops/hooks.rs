@@ -0,0 +1,367 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::time::timeout;
+use toml::Table;
+use tracing::{info, warn};
+
+use crate::errors::{Errors, Result};
+use crate::utils::manifest_edit::ManifestEdit;
+use crate::utils::Manifest;
+
+/// The key of the hooks table in the manifest.
+const HOOKS: &str = "hooks";
+
+/// The seconds a hook may run when the manifest doesn't say.
+const DEFAULT_TIMEOUT: u64 = 300;
+
+/// The events the hooks run around.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// Before the initial upload of the sources, its output files are synced
+    PreSync,
+    /// After the initial upload of the sources
+    PostSync,
+    /// Before the playbook is created
+    PreDeploy,
+    /// Once the playbook is running
+    PostReady,
+    /// When the dev mode is interrupted, before the cleanup
+    OnDevExit,
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::PreSync => "pre-sync",
+            Event::PostSync => "post-sync",
+            Event::PreDeploy => "pre-deploy",
+            Event::PostReady => "post-ready",
+            Event::OnDevExit => "on-dev-exit",
+        }
+    }
+}
+
+/// What to do when a hook fails or times out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Policy {
+    #[default]
+    Abort,
+    Warn,
+}
+
+/// The `[hooks]` section of the manifest. Each event takes a command, a
+/// table with a `command` and its own `timeout` and `on-failure`, or a list
+/// of those, run in order.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Hooks {
+    /// The default seconds a hook may run
+    timeout: Option<u64>,
+    /// The default failure policy
+    on_failure: Option<Policy>,
+    #[serde(default)]
+    pre_sync: Entries,
+    #[serde(default)]
+    post_sync: Entries,
+    #[serde(default)]
+    pre_deploy: Entries,
+    #[serde(default)]
+    post_ready: Entries,
+    #[serde(default)]
+    on_dev_exit: Entries,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Entries {
+    One(Entry),
+    Many(Vec<Entry>),
+}
+
+impl Default for Entries {
+    fn default() -> Self {
+        Entries::Many(vec![])
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    Command(String),
+    Table {
+        command: String,
+        timeout: Option<u64>,
+        #[serde(rename = "on-failure")]
+        on_failure: Option<Policy>,
+    },
+}
+
+/// A command to run, with its timeout and failure policy resolved.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Hook {
+    pub command: String,
+    pub timeout: Duration,
+    pub policy: Policy,
+}
+
+/// The environment the hooks run in, on top of the inherited one.
+#[derive(Debug, Default)]
+pub struct Env {
+    /// The working directory, the workspace of the manifest
+    pub dir: Option<PathBuf>,
+    pub playbook_id: Option<String>,
+    pub context: Option<String>,
+}
+
+impl Hooks {
+    /// The hooks of the event, in order.
+    pub fn get(&self, event: Event) -> Vec<Hook> {
+        let entries = match event {
+            Event::PreSync => &self.pre_sync,
+            Event::PostSync => &self.post_sync,
+            Event::PreDeploy => &self.pre_deploy,
+            Event::PostReady => &self.post_ready,
+            Event::OnDevExit => &self.on_dev_exit,
+        };
+        let entries = match entries {
+            Entries::One(entry) => std::slice::from_ref(entry),
+            Entries::Many(entries) => entries.as_slice(),
+        };
+
+        let hook = |command: &String, timeout: Option<u64>, policy: Option<Policy>| Hook {
+            command: command.clone(),
+            timeout: Duration::from_secs(timeout.or(self.timeout).unwrap_or(DEFAULT_TIMEOUT)),
+            policy: policy.or(self.on_failure).unwrap_or_default(),
+        };
+        entries
+            .iter()
+            .map(|entry| match entry {
+                Entry::Command(command) => hook(command, None, None),
+                Entry::Table { command, timeout, on_failure } => hook(command, *timeout, *on_failure),
+            })
+            .collect()
+    }
+}
+
+/// Read the hooks from the manifest, and remove them from its content as
+/// they are run by the CLI rather than deployed.
+pub fn extract(manifest: &Manifest) -> Result<(Hooks, String)> {
+    if !manifest.content.contains(HOOKS) {
+        return Ok((Hooks::default(), manifest.content.clone()));
+    }
+
+    let origin = manifest.origin.to_string();
+    let table: Table = toml::from_str(&manifest.content).map_err(|e| Errors::FailedParseManifest(origin.clone(), e))?;
+    let Some(value) = table.get(HOOKS).cloned() else {
+        return Ok((Hooks::default(), manifest.content.clone()));
+    };
+    let hooks = value.try_into::<Hooks>().map_err(|e| Errors::InvalidHooks(e.message().to_string()))?;
+
+    let mut edit = ManifestEdit::parse(&origin, &manifest.content)?;
+    edit.remove(&[HOOKS]);
+    Ok((hooks, edit.to_string()))
+}
+
+/// Run the hooks of the event in order, printing their output to stderr.
+pub async fn run(hooks: &Hooks, event: Event, env: &Env) -> Result<()> {
+    run_with(hooks, event, env, &|line| eprintln!("{}", line)).await
+}
+
+async fn run_with(hooks: &Hooks, event: Event, env: &Env, sink: &(dyn Fn(&str) + Sync)) -> Result<()> {
+    for hook in hooks.get(event) {
+        info!("Running the {} hook: {}", event.name(), hook.command);
+        let Err(reason) = execute(&hook, event, env, sink).await else {
+            continue;
+        };
+        match hook.policy {
+            Policy::Abort => return Err(Errors::HookFailed(event.name().to_string(), reason)),
+            Policy::Warn => warn!("The {} hook failed: {}", event.name(), reason),
+        }
+    }
+    Ok(())
+}
+
+/// Run the command with the shell, forwarding its output lines prefixed with
+/// the event, and kill it once the timeout expires.
+async fn execute(
+    hook: &Hook,
+    event: Event,
+    env: &Env,
+    sink: &(dyn Fn(&str) + Sync),
+) -> std::result::Result<(), String> {
+    let mut command = shell(&hook.command);
+    command.env("AMP_EVENT", event.name()).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    command.kill_on_drop(true);
+    if let Some(dir) = &env.dir {
+        command.current_dir(dir);
+    }
+    if let Some(id) = &env.playbook_id {
+        command.env("AMP_PLAYBOOK_ID", id);
+    }
+    if let Some(context) = &env.context {
+        command.env("AMP_CONTEXT", context);
+    }
+
+    let mut child = command.spawn().map_err(|e| e.to_string())?;
+    let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+    let output = async {
+        tokio::join!(forward(stdout, event, sink), forward(stderr, event, sink));
+        child.wait().await
+    };
+
+    match timeout(hook.timeout, output).await {
+        Ok(Ok(status)) if status.success() => Ok(()),
+        Ok(Ok(status)) => Err(format!("`{}` exited with {}", hook.command, status)),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => {
+            let _ = child.kill().await;
+            Err(format!("`{}` timed out after {}s", hook.command, hook.timeout.as_secs_f32()))
+        }
+    }
+}
+
+async fn forward<R: AsyncRead + Unpin>(reader: Option<R>, event: Event, sink: &(dyn Fn(&str) + Sync)) {
+    let Some(reader) = reader else { return };
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        sink(&format!("[{}] {}", event.name(), line));
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::utils::Origin;
+
+    fn hooks(content: &str) -> Hooks {
+        let manifest = Manifest { origin: Origin::Url("https://a/.amp.toml".into()), content: content.into() };
+        extract(&manifest).unwrap().0
+    }
+
+    /// Run the hooks of the event, returning the result and the output lines.
+    async fn run(hooks: &Hooks, event: Event, env: &Env) -> (Result<()>, Vec<String>) {
+        let lines = Mutex::new(vec![]);
+        let result = run_with(hooks, event, env, &|line| lines.lock().unwrap().push(line.to_string())).await;
+        (result, lines.into_inner().unwrap())
+    }
+
+    #[test]
+    fn extract_and_strip_hooks() {
+        let content = "[meta]\nname = \"web\"\n\n[hooks]\ntimeout = 10\npre-sync = \"make\"\n\
+                       post-ready = [\"./notify.sh\", { command = \"true\", timeout = 1, on-failure = \"warn\" }]\n";
+        let manifest = Manifest { origin: Origin::Url("https://a/.amp.toml".into()), content: content.into() };
+        let (hooks, content) = extract(&manifest).unwrap();
+
+        let table: Table = toml::from_str(&content).unwrap();
+        assert!(table.contains_key("meta") && !table.contains_key(HOOKS));
+        assert_eq!(
+            hooks.get(Event::PreSync),
+            vec![Hook { command: "make".into(), timeout: Duration::from_secs(10), policy: Policy::Abort }]
+        );
+        let post_ready = hooks.get(Event::PostReady);
+        assert_eq!(post_ready.len(), 2);
+        assert_eq!((post_ready[1].timeout, post_ready[1].policy), (Duration::from_secs(1), Policy::Warn));
+        assert!(hooks.get(Event::OnDevExit).is_empty());
+    }
+
+    #[test]
+    fn reject_unknown_events() {
+        let manifest = Manifest {
+            origin: Origin::Url("https://a/.amp.toml".into()),
+            content: "[hooks]\npre-build = \"make\"\n".into(),
+        };
+        assert!(matches!(extract(&manifest), Err(Errors::InvalidHooks(_))));
+    }
+
+    #[tokio::test]
+    async fn stream_prefixed_output() {
+        let hooks = hooks("[hooks]\npost-sync = \"echo synced; echo failed >&2\"\n");
+        let (result, mut lines) = run(&hooks, Event::PostSync, &Env::default()).await;
+        assert!(result.is_ok());
+        lines.sort();
+        assert_eq!(lines, vec!["[post-sync] failed", "[post-sync] synced"]);
+    }
+
+    #[tokio::test]
+    async fn export_the_environment() {
+        let hooks = hooks("[hooks]\npost-ready = 'echo \"$AMP_EVENT $AMP_PLAYBOOK_ID $AMP_CONTEXT\"'\n");
+        let env = Env { dir: None, playbook_id: Some("42".into()), context: Some("staging".into()) };
+        let (_, lines) = run(&hooks, Event::PostReady, &env).await;
+        assert_eq!(lines, vec!["[post-ready] post-ready 42 staging"]);
+    }
+
+    #[tokio::test]
+    async fn abort_or_warn_on_failure() {
+        let hooks = hooks("[hooks]\npre-deploy = [\"exit 3\", \"echo never\"]\n");
+        let (result, lines) = run(&hooks, Event::PreDeploy, &Env::default()).await;
+        assert!(matches!(result, Err(Errors::HookFailed(event, _)) if event == "pre-deploy"));
+        assert!(lines.is_empty());
+
+        let hooks = hooks_with_policy("warn");
+        let (result, lines) = run(&hooks, Event::PreDeploy, &Env::default()).await;
+        assert!(result.is_ok());
+        assert_eq!(lines, vec!["[pre-deploy] after"]);
+    }
+
+    fn hooks_with_policy(policy: &str) -> Hooks {
+        hooks(&format!("[hooks]\non-failure = \"{}\"\npre-deploy = [\"exit 3\", \"echo after\"]\n", policy))
+    }
+
+    #[tokio::test]
+    async fn kill_on_timeout() {
+        let hooks = hooks("[hooks]\non-dev-exit = { command = \"sleep 5\", timeout = 0 }\n");
+        let started = std::time::Instant::now();
+        let (result, _) = run(&hooks, Event::OnDevExit, &Env::default()).await;
+        assert!(matches!(result, Err(Errors::HookFailed(_, reason)) if reason.contains("timed out")));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn run_in_the_workspace() {
+        let dir = std::env::temp_dir().join(format!("amp-hooks-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let hooks = hooks("[hooks]\npre-sync = \"echo generated > codegen.rs\"\n");
+        let env = Env { dir: Some(dir.clone()), ..Env::default() };
+
+        let (result, _) = run(&hooks, Event::PreSync, &env).await;
+        assert!(result.is_ok());
+        assert_eq!(std::fs::read_to_string(dir.join("codegen.rs")).unwrap(), "generated\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
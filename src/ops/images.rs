@@ -0,0 +1,295 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::errors::{Errors, Result};
+use crate::lint::image::{valid_host, valid_repository};
+
+/// The longest tag, as accepted by the registries.
+const MAX_TAG: usize = 128;
+
+/// An image reference, `[registry/]repository[:tag][@digest]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reference {
+    pub registry: Option<String>,
+    pub repository: String,
+    pub tag: Option<String>,
+    pub digest: Option<String>,
+}
+
+impl FromStr for Reference {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (name, digest) = match s.split_once('@') {
+            Some((name, digest)) => (name, Some(digest)),
+            None => (s, None),
+        };
+        let (registry, path) = match name.split_once('/') {
+            Some((host, rest)) if host.contains(['.', ':']) || host == "localhost" => (Some(host), rest),
+            _ => (None, name),
+        };
+        let start = path.rfind('/').map_or(0, |i| i + 1);
+        let (repository, tag) = match path[start..].find(':') {
+            Some(i) => (&path[..start + i], Some(&path[start + i + 1..])),
+            None => (path, None),
+        };
+
+        if let Some(host) = registry.filter(|h| !valid_host(h)) {
+            return Err(format!("registry `{}` is not a valid host", host));
+        }
+        if !valid_repository(repository) {
+            return Err(format!("repository `{}` is not valid", repository));
+        }
+        if let Some(tag) = tag.filter(|t| !valid_tag(t)) {
+            return Err(format!("tag `{}` is not valid", tag));
+        }
+        if let Some(digest) = digest.filter(|d| !valid_digest(d)) {
+            return Err(format!("digest `{}` is not valid, expected such as sha256:<64 hex digits>", digest));
+        }
+
+        Ok(Self {
+            registry: registry.map(String::from),
+            repository: repository.to_string(),
+            tag: tag.map(String::from),
+            digest: digest.map(String::from),
+        })
+    }
+}
+
+impl Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(registry) = &self.registry {
+            write!(f, "{}/", registry)?;
+        }
+        write!(f, "{}", self.repository)?;
+        if let Some(tag) = &self.tag {
+            write!(f, ":{}", tag)?;
+        }
+        if let Some(digest) = &self.digest {
+            write!(f, "@{}", digest)?;
+        }
+        Ok(())
+    }
+}
+
+/// A word character followed by up to 127 word characters, dots and dashes.
+fn valid_tag(tag: &str) -> bool {
+    let word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    tag.len() <= MAX_TAG && tag.starts_with(word) && tag.chars().all(|c| word(c) || c == '.' || c == '-')
+}
+
+/// `algorithm:hex`, a sha256 digest has exactly 64 lowercase hex digits,
+/// the other algorithms at least 32.
+fn valid_digest(digest: &str) -> bool {
+    let Some((algorithm, hex)) = digest.split_once(':') else {
+        return false;
+    };
+    let hex_digits = hex.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c));
+    let algorithm_ok = !algorithm.is_empty()
+        && algorithm
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '+' | '.' | '_' | '-'));
+    match algorithm {
+        "sha256" => hex_digits && hex.len() == 64,
+        _ => algorithm_ok && hex_digits && hex.len() >= 32,
+    }
+}
+
+/// The image to deploy for a character instead of building it from source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageOverride {
+    pub name: String,
+    pub reference: Reference,
+}
+
+/// Parse a `NAME=REFERENCE` override, for the `--images` flags.
+pub fn parse_override(s: &str) -> std::result::Result<ImageOverride, String> {
+    let (name, reference) = match s.split_once('=') {
+        Some((name, reference)) if !name.is_empty() => (name, reference),
+        _ => return Err(format!("invalid NAME=REFERENCE: no `=` found in `{}`", s)),
+    };
+    let reference = reference.parse().map_err(|e| format!("invalid image `{}`: {}", reference, e))?;
+
+    Ok(ImageOverride { name: name.to_string(), reference })
+}
+
+/// The artifacts file written by a build with `--file-output`.
+#[derive(Deserialize)]
+struct Artifacts {
+    builds: Vec<Artifact>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Artifact {
+    image_name: String,
+    tag: String,
+}
+
+/// Read the overrides from the artifacts file, `{"builds": [{"imageName": ..., "tag": ...}]}`.
+pub fn read_artifacts(path: &Path) -> Result<Vec<ImageOverride>> {
+    let origin = path.display().to_string();
+    let content = fs::read_to_string(path).map_err(|e| Errors::InvalidArtifacts(origin.clone(), e.to_string()))?;
+    let artifacts: Artifacts =
+        serde_json::from_str(&content).map_err(|e| Errors::InvalidArtifacts(origin.clone(), e.to_string()))?;
+
+    artifacts
+        .builds
+        .into_iter()
+        .map(|a| parse_override(&format!("{}={}", a.image_name, a.tag)))
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|e| Errors::InvalidArtifacts(origin, e))
+}
+
+/// The overrides of the flags, and those of the artifacts file for the other
+/// characters.
+pub fn collect(flags: &[ImageOverride], file: Option<&Path>) -> Result<Vec<ImageOverride>> {
+    let mut overrides = flags.to_vec();
+    if let Some(path) = file {
+        let artifacts = read_artifacts(path)?;
+        overrides.extend(artifacts.into_iter().filter(|a| !flags.iter().any(|f| f.name == a.name)));
+    }
+    Ok(overrides)
+}
+
+/// Match the overrides against the names of the characters, the last override
+/// of a name wins. Returns the image of each overridden character.
+pub fn resolve(overrides: &[ImageOverride], names: &[String]) -> Result<BTreeMap<String, String>> {
+    let unknown: Vec<&str> =
+        overrides.iter().map(|o| o.name.as_str()).filter(|n| !names.iter().any(|name| name == n)).collect();
+    if !unknown.is_empty() {
+        return Err(Errors::UnknownCharacters(unknown.join(", "), names.join(", ")));
+    }
+
+    Ok(overrides.iter().map(|o| (o.name.clone(), o.reference.to_string())).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIGEST: &str = "sha256:9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    fn reference(s: &str) -> Reference {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_tag_and_digest_forms() {
+        let r = reference("nginx");
+        assert_eq!((r.registry, r.repository.as_str(), r.tag, r.digest), (None, "nginx", None, None));
+
+        let r = reference("registry.example.com:5000/web/api:v2.1");
+        assert_eq!(r.registry.as_deref(), Some("registry.example.com:5000"));
+        assert_eq!(r.repository, "web/api");
+        assert_eq!(r.tag.as_deref(), Some("v2.1"));
+
+        let r = reference(&format!("ghcr.io/org/web@{}", DIGEST));
+        assert_eq!(r.repository, "org/web");
+        assert_eq!((r.tag, r.digest.as_deref()), (None, Some(DIGEST)));
+
+        let r = reference(&format!("localhost:5000/web:ci-42@{}", DIGEST));
+        assert_eq!((r.tag.as_deref(), r.digest.as_deref()), (Some("ci-42"), Some(DIGEST)));
+    }
+
+    #[test]
+    fn display_the_parsed_reference() {
+        for s in ["nginx", "library/nginx:1.27", "localhost:5000/web:ci-42", &format!("ghcr.io/org/web:v1@{}", DIGEST)]
+        {
+            assert_eq!(reference(s).to_string(), s);
+        }
+    }
+
+    #[test]
+    fn reject_invalid_references() {
+        let invalid = [
+            "ghcr.io/Org/web",
+            "registry_example.com/web",
+            "web:",
+            "web:-beta",
+            "web:v1/oops",
+            "web@sha256:abc",
+            "web@sha256:9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08",
+            "web@md5",
+            "web@:0123456789abcdef0123456789abcdef",
+            "",
+        ];
+        for s in invalid {
+            assert!(s.parse::<Reference>().is_err(), "expected {:?} to be rejected", s);
+        }
+        assert!(format!("web:{}", "a".repeat(MAX_TAG + 1)).parse::<Reference>().is_err());
+        assert!("web@sha512:0123456789abcdef0123456789abcdef".parse::<Reference>().is_ok());
+    }
+
+    #[test]
+    fn parse_name_reference_pairs() {
+        let o = parse_override(&format!("web=ghcr.io/org/web@{}", DIGEST)).unwrap();
+        assert_eq!(o.name, "web");
+        assert_eq!(o.reference.digest.as_deref(), Some(DIGEST));
+
+        assert!(parse_override("ghcr.io/org/web:v1").unwrap_err().contains("no `=`"));
+        assert!(parse_override("=web:v1").is_err());
+        assert!(parse_override("web=Web:v1").unwrap_err().contains("invalid image `Web:v1`"));
+    }
+
+    #[test]
+    fn match_overrides_against_characters() {
+        let names = vec!["web".to_string(), "api".to_string()];
+        let overrides = vec![
+            parse_override("web=ghcr.io/org/web:v1").unwrap(),
+            parse_override("web=ghcr.io/org/web:v2").unwrap(),
+            parse_override("api=api:ci").unwrap(),
+        ];
+
+        let images = resolve(&overrides, &names).unwrap();
+        assert_eq!(images["web"], "ghcr.io/org/web:v2");
+        assert_eq!(images["api"], "api:ci");
+
+        let overrides = vec![parse_override("worker=worker:v1").unwrap()];
+        let err = resolve(&overrides, &names).unwrap_err();
+        assert!(
+            matches!(err, Errors::UnknownCharacters(unknown, available) if unknown == "worker" && available == "web, api")
+        );
+    }
+
+    #[test]
+    fn read_the_build_artifacts() {
+        let dir = std::env::temp_dir().join(format!("amp-images-artifacts-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("build.json");
+        let content = format!(
+            r#"{{"builds": [{{"imageName": "web", "tag": "ghcr.io/org/web@{}"}}, {{"imageName": "api", "tag": "api:ci"}}]}}"#,
+            DIGEST
+        );
+        fs::write(&path, content).unwrap();
+
+        let flags = vec![parse_override("api=api:local").unwrap()];
+        let overrides = collect(&flags, Some(&path)).unwrap();
+        let images = resolve(&overrides, &["web".to_string(), "api".to_string()]).unwrap();
+        assert_eq!(images["api"], "api:local");
+        assert_eq!(images["web"], format!("ghcr.io/org/web@{}", DIGEST));
+
+        fs::write(&path, r#"{"builds": [{"imageName": "web", "tag": "Web"}]}"#).unwrap();
+        assert!(matches!(read_artifacts(&path), Err(Errors::InvalidArtifacts(..))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
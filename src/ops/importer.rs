@@ -0,0 +1,391 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Display;
+
+use amp_client::playbooks::PlaybookPayload;
+use amp_common::resource::{PlaybookSpec, Preface};
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{error, info};
+
+use crate::client::{self, Api};
+use crate::errors::{Errors, Result};
+use crate::lint::{self, Severity};
+use crate::ops::metadata::{Metadata, Payload};
+use crate::ops::playbooks::{self, send};
+use crate::prompt::Prompter;
+use crate::recorder::Recorder;
+use crate::utils::manifest_edit;
+
+/// What to do when a playbook with the same title already exists.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Strategy {
+    /// Stop without importing
+    #[default]
+    Fail,
+    /// Import under the first free title suffixed with `-copy` or `-copy-N`
+    Rename,
+    /// Delete the existing playbook and create it again
+    Replace,
+    /// Update the existing playbook in place
+    Update,
+}
+
+/// A playbook as exported by `amp playbook get --output json`, the other
+/// fields are ignored.
+#[derive(Deserialize)]
+pub struct Exported {
+    title: String,
+    #[serde(default)]
+    description: Option<String>,
+    preface: Preface,
+    #[serde(flatten)]
+    metadata: Metadata,
+}
+
+impl From<Exported> for Payload {
+    fn from(exported: Exported) -> Self {
+        let playbook = PlaybookPayload {
+            title: exported.title,
+            description: exported.description.unwrap_or_default(),
+            preface: exported.preface,
+        };
+        Self { metadata: exported.metadata, ..Self::from(playbook) }
+    }
+}
+
+/// Where the manifest of the imported playbook comes from.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Source {
+    /// The manifest of the named character, inlined in the preface
+    Inline(String),
+    /// The URL of the repository holding the manifest
+    Repository(String),
+    /// The name of the character published to the registry
+    Registry(String),
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Inline(name) => write!(f, "the inline manifest of {}", name),
+            Source::Repository(url) => write!(f, "the manifest of the repository {}", url),
+            Source::Registry(name) => write!(f, "the character {} of the registry", name),
+        }
+    }
+}
+
+/// Validate the payload without the server, as in a dry run: the title, the
+/// source of the manifest, and the inline manifest against the error rules of
+/// `amp lint`. The conflicts with the existing titles aren't checked.
+pub fn validate(origin: &str, payload: &Payload) -> Result<Source> {
+    let preface = serde_json::to_value(&payload.playbook.preface).map_err(Errors::JsonSerializeError)?;
+    inspect(origin, &payload.playbook.title, &preface)
+}
+
+fn inspect(origin: &str, title: &str, preface: &Value) -> Result<Source> {
+    let invalid = |message: &str| Errors::FailedReadPlaybook(origin.to_string(), message.to_string());
+    if title.trim().is_empty() {
+        return Err(invalid("the title is empty"));
+    }
+
+    if let Some(manifest) = preface.get("manifest").filter(|m| m.is_object()) {
+        let content = manifest_edit::serialize(&without_nulls(manifest.clone()))?;
+        let diagnostics = lint::run(&content, &[], Some(Severity::Error))?;
+        for diagnostic in &diagnostics {
+            error!("{}: {}", origin, diagnostic);
+        }
+        if !diagnostics.is_empty() {
+            return Err(Errors::InvalidManifest(origin.to_string(), diagnostics.len()));
+        }
+        return Ok(Source::Inline(manifest["meta"]["name"].as_str().unwrap_or_default().to_string()));
+    }
+    if let Some(repository) = preface.get("repository").filter(|r| !r.is_null()) {
+        let url = repository.as_str().or(repository["repo"].as_str()).unwrap_or_default();
+        return Ok(Source::Repository(url.to_string()));
+    }
+    if preface.get("registry").is_some_and(|r| !r.is_null()) {
+        return Ok(Source::Registry(preface["name"].as_str().unwrap_or_default().to_string()));
+    }
+    Err(invalid("the preface has no manifest, repository or registry"))
+}
+
+/// The payload as pretty TOML, for previewing it in a dry run.
+pub fn preview(payload: &Payload) -> Result<String> {
+    let value = serde_json::to_value(payload).map_err(Errors::JsonSerializeError)?;
+    toml::to_string_pretty(&without_nulls(value)).map_err(Errors::TomlSerializeError)
+}
+
+/// The value without its null fields, which TOML can't represent.
+fn without_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            Value::Object(map.into_iter().filter(|(_, v)| !v.is_null()).map(|(k, v)| (k, without_nulls(v))).collect())
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(without_nulls).collect()),
+        value => value,
+    }
+}
+
+/// Import the playbook, resolving a conflict on its title with the strategy.
+/// Replacing asks for a confirmation first. Every call goes through the
+/// recorder, so a dry run plans the very mutations the strategy makes.
+pub async fn import(
+    api: &Api,
+    recorder: &Recorder,
+    mut payload: Payload,
+    strategy: Strategy,
+    prompter: &dyn Prompter,
+) -> Result<PlaybookSpec> {
+    let titles = recorder.call_async("GET", "/playbooks", titles(api)).await?;
+    let title = payload.playbook.title.clone();
+    let Some((id, _)) = titles.iter().find(|(_, t)| *t == title) else {
+        return create(api, recorder, &payload).await;
+    };
+
+    match strategy {
+        Strategy::Fail => Err(Errors::ConflictResolutionFailed(format!(
+            "playbook {} is already titled {}, see --on-conflict",
+            id, title
+        ))),
+        Strategy::Rename => {
+            payload.playbook.title = unique(&title, &titles);
+            info!("Importing the playbook as {}", payload.playbook.title);
+            create(api, recorder, &payload).await
+        }
+        Strategy::Replace => {
+            if !prompter.confirm(&format!("Replace the playbook {} ({})?", title, id), false)? {
+                return Err(Errors::ConflictResolutionFailed(format!("playbook {} was not replaced", id)));
+            }
+            let path = format!("/playbooks/{}", id);
+            match recorder.mutate_async("DELETE", &path, None, delete(api, id)).await {
+                // Plan the creation after the deletion in a dry run.
+                Err(Errors::DryRun(_)) => {}
+                result => result?,
+            }
+            info!("Replaced the playbook {}", title);
+            create(api, recorder, &payload).await
+        }
+        Strategy::Update => {
+            info!("Updating the playbook {} ({})", title, id);
+            let path = format!("/playbooks/{}", id);
+            let request = playbooks::update(api, id, &payload);
+            recorder.mutate_async("PATCH", &path, serde_json::to_value(&payload).ok(), request).await
+        }
+    }
+}
+
+async fn create(api: &Api, recorder: &Recorder, payload: &Payload) -> Result<PlaybookSpec> {
+    let request = playbooks::create(api, payload);
+    recorder.mutate_async("POST", "/playbooks", serde_json::to_value(payload).ok(), request).await
+}
+
+/// The IDs and titles of the existing playbooks.
+async fn titles(api: &Api) -> Result<Vec<(String, String)>> {
+    let value = send(api.get("/playbooks")).await.map_err(Errors::ConflictResolutionFailed)?;
+    let playbooks = value.as_array().map(Vec::as_slice).unwrap_or_default();
+
+    let string = |p: &Value, key: &str| p[key].as_str().unwrap_or_default().to_string();
+    Ok(playbooks.iter().map(|p| (string(p, "id"), string(p, "title"))).collect())
+}
+
+async fn delete(api: &Api, id: &str) -> Result<()> {
+    let response = client::send(api.delete(&format!("/playbooks/{}", id))).await.and_then(|r| r.error_for_status());
+    response.map(|_| ()).map_err(|e| Errors::FailedDeletePlaybook(format!("{}: {}", id, e)))
+}
+
+/// The first of `<title>-copy`, `<title>-copy-2`, `<title>-copy-3`... not taken.
+fn unique(title: &str, titles: &[(String, String)]) -> String {
+    let taken = |candidate: &str| titles.iter().any(|(_, t)| t == candidate);
+    let mut candidate = format!("{}-copy", title);
+    let mut n = 2;
+    while taken(&candidate) {
+        candidate = format!("{}-copy-{}", title, n);
+        n += 1;
+    }
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Client;
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::prompt::{Answer, ScriptedPrompter};
+
+    fn payload() -> Payload {
+        let exported = json!({"id": "1", "title": "web", "preface": Preface::registry("web", "hub", "latest")});
+        Payload::from(serde_json::from_value::<Exported>(exported).unwrap())
+    }
+
+    /// The playbook the server answers with, built from the request payload.
+    fn created(id: &str, title: &str) -> Value {
+        let mut value = serde_json::to_value(payload()).unwrap();
+        value["id"] = json!(id);
+        value["title"] = json!(title);
+        value
+    }
+
+    /// A server with an existing `web` playbook, and `web-copy` if `copies`.
+    async fn server(copies: bool) -> MockServer {
+        let server = MockServer::start().await;
+        let mut existing = vec![json!({"id": "1", "title": "web"}), json!({"id": "2", "title": "api"})];
+        if copies {
+            existing.push(json!({"id": "3", "title": "web-copy"}));
+        }
+        Mock::given(method("GET"))
+            .and(path("/v1/playbooks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(existing))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    async fn run(server: &MockServer, strategy: Strategy, answers: Vec<Answer>) -> Result<PlaybookSpec> {
+        let api = Api::new(Client::new(), &server.uri(), None);
+        import(&api, &Recorder::default(), payload(), strategy, &ScriptedPrompter::new(answers)).await
+    }
+
+    #[test]
+    fn validate_without_the_server() {
+        let payload = payload();
+        assert_eq!(validate("web.json", &payload).unwrap(), Source::Registry("web".into()));
+        assert!(preview(&payload).unwrap().contains("title = \"web\""));
+
+        let result = inspect("web.json", " ", &json!({"registry": "hub"}));
+        assert!(matches!(result, Err(Errors::FailedReadPlaybook(_, message)) if message == "the title is empty"));
+        let result = inspect("web.json", "web", &json!({"name": "web"}));
+        assert!(matches!(result, Err(Errors::FailedReadPlaybook(..))));
+
+        let manifest = json!({"manifest": {"meta": {"name": "web", "version": null}, "deploy": {"ports": [80]}}});
+        assert_eq!(inspect("web.json", "web", &manifest).unwrap(), Source::Inline("web".into()));
+        let manifest = json!({"manifest": {"meta": {"name": "web"}, "deploy": {"ports": [70000]}}});
+        let result = inspect("web.json", "web", &manifest);
+        assert!(matches!(result, Err(Errors::InvalidManifest(origin, 1)) if origin == "web.json"));
+    }
+
+    #[tokio::test]
+    async fn fail_on_conflict() {
+        let server = server(false).await;
+        let result = run(&server, Strategy::Fail, vec![]).await;
+        assert!(matches!(result, Err(Errors::ConflictResolutionFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn rename_until_unique() {
+        let server = server(true).await;
+        Mock::given(method("POST"))
+            .and(path("/v1/playbooks"))
+            .and(body_partial_json(json!({"title": "web-copy-2"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(created("4", "web-copy-2")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let playbook = run(&server, Strategy::Rename, vec![]).await.unwrap();
+        assert_eq!(playbook.title, "web-copy-2");
+    }
+
+    #[tokio::test]
+    async fn replace_after_confirmation() {
+        let server = server(false).await;
+        Mock::given(method("DELETE"))
+            .and(path("/v1/playbooks/1"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/playbooks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(created("4", "web")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let playbook = run(&server, Strategy::Replace, vec![Answer::Confirm(true)]).await.unwrap();
+        assert_eq!(playbook.id, "4");
+    }
+
+    #[tokio::test]
+    async fn keep_when_replacement_declined() {
+        let server = server(false).await;
+        Mock::given(method("DELETE")).respond_with(ResponseTemplate::new(204)).expect(0).mount(&server).await;
+
+        let result = run(&server, Strategy::Replace, vec![Answer::Confirm(false)]).await;
+        assert!(matches!(result, Err(Errors::ConflictResolutionFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn update_in_place() {
+        let server = server(false).await;
+        Mock::given(method("PATCH"))
+            .and(path("/v1/playbooks/1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(created("1", "web")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let playbook = run(&server, Strategy::Update, vec![]).await.unwrap();
+        assert_eq!(playbook.id, "1");
+    }
+
+    #[tokio::test]
+    async fn plan_the_mutations_of_the_strategy() {
+        let server = server(false).await;
+        for verb in ["POST", "PATCH", "DELETE"] {
+            Mock::given(method(verb)).respond_with(ResponseTemplate::new(204)).expect(0).mount(&server).await;
+        }
+        let api = Api::new(Client::new(), &server.uri(), None);
+        let prompter = ScriptedPrompter::new(vec![Answer::Confirm(true)]);
+
+        let mut planned = vec![];
+        for strategy in [Strategy::Rename, Strategy::Replace, Strategy::Update] {
+            let recorder = Recorder::default().with_dry_run(true);
+            let result = import(&api, &recorder, payload(), strategy, &prompter).await;
+            assert!(matches!(result, Err(Errors::DryRun(_))));
+            planned.push(recorder.planned().iter().map(|p| format!("{} {}", p.method, p.path)).collect::<Vec<_>>());
+        }
+        assert_eq!(planned[0], vec!["POST /playbooks"]);
+        assert_eq!(planned[1], vec!["DELETE /playbooks/1", "POST /playbooks"]);
+        assert_eq!(planned[2], vec!["PATCH /playbooks/1"]);
+    }
+
+    #[tokio::test]
+    async fn create_without_conflict() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(created("4", "web")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert_eq!(run(&server, Strategy::Fail, vec![]).await.unwrap().title, "web");
+    }
+
+    #[test]
+    fn suffix_copies() {
+        let titles = |names: &[&str]| names.iter().map(|n| (String::new(), n.to_string())).collect::<Vec<_>>();
+        assert_eq!(unique("web", &titles(&["web"])), "web-copy");
+        assert_eq!(unique("web", &titles(&["web", "web-copy", "web-copy-2"])), "web-copy-3");
+    }
+}
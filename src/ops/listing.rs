@@ -0,0 +1,100 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use reqwest::header::{ETAG, IF_NONE_MATCH, LINK};
+use reqwest::StatusCode;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+
+use crate::client::{self, Api};
+use crate::errors::{Errors, Result};
+
+/// The header some servers report the number of all the matching resources in.
+const TOTAL_COUNT: &str = "x-total-count";
+
+/// The options of a conditional request.
+#[derive(Clone, Debug, Default)]
+pub struct RequestOptions {
+    pub query: HashMap<String, String>,
+    /// The ETag of the last response, the server answers 304 if nothing changed since
+    pub if_none_match: Option<String>,
+}
+
+/// A response along with its ETag. There is no data when it is unchanged.
+#[derive(Debug)]
+pub struct Response<T> {
+    pub data: Option<T>,
+    pub etag: Option<String>,
+    /// The size of the body received
+    pub bytes: usize,
+    /// The number of all the matching resources, when the server reports it with `X-Total-Count`
+    pub total: Option<usize>,
+    /// The query of the next page, when the server paginates with a `Link` header
+    pub next: Option<HashMap<String, String>>,
+}
+
+/// List the playbooks, conditionally on the ETag of the options, as specs or
+/// as raw values for the sparse fieldsets.
+pub async fn list<T: DeserializeOwned>(api: &Api, options: &RequestOptions) -> Result<Response<Vec<T>>> {
+    let mut request = api.get("/playbooks").query(&options.query);
+    if let Some(etag) = &options.if_none_match {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+
+    let response = client::send(request).await.map_err(|e| Errors::FailedListPlaybooks(e.to_string()))?;
+    let etag = response.headers().get(ETAG).and_then(|v| v.to_str().ok()).map(String::from);
+    let total = response.headers().get(TOTAL_COUNT).and_then(|v| v.to_str().ok()).and_then(|v| v.parse().ok());
+    let next = response.headers().get(LINK).and_then(|v| v.to_str().ok()).and_then(next_page);
+    if response.status() == StatusCode::NOT_MODIFIED {
+        let etag = etag.or(options.if_none_match.clone());
+        return Ok(Response { data: None, etag, bytes: 0, total, next });
+    }
+
+    let response = response.error_for_status().map_err(|e| Errors::FailedListPlaybooks(e.to_string()))?;
+    let body = response.bytes().await.map_err(|e| Errors::FailedListPlaybooks(e.to_string()))?;
+    let data = serde_json::from_slice(&body).map_err(|e| Errors::FailedListPlaybooks(e.to_string()))?;
+    Ok(Response { data: Some(data), etag, bytes: body.len(), total, next })
+}
+
+/// The query of the `rel="next"` link of a `Link` header, such as
+/// `<https://cloud.amphitheatre.app/v1/playbooks?page=2>; rel="next"`.
+fn next_page(header: &str) -> Option<HashMap<String, String>> {
+    let is_next = |param: &str| {
+        let rel = param.trim().strip_prefix("rel=").map(|rel| rel.trim_matches('"'));
+        rel.is_some_and(|rel| rel.split_whitespace().any(|r| r.eq_ignore_ascii_case("next")))
+    };
+    let link = header.split(',').find(|link| link.split(';').skip(1).any(is_next))?;
+    let target = link.split(';').next()?.trim().strip_prefix('<')?.strip_suffix('>')?;
+    // Relative to any base, only the query matters.
+    let url = Url::parse("http://localhost/").ok()?.join(target).ok()?;
+    Some(url.query_pairs().into_owned().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_the_next_page_of_the_link_header() {
+        let header = r#"<https://a/v1/playbooks?page=1>; rel="prev", </v1/playbooks?q=web&page=3>; rel="next""#;
+        let next = next_page(header).unwrap();
+        assert_eq!(next.get("page").map(String::as_str), Some("3"));
+        assert_eq!(next.get("q").map(String::as_str), Some("web"));
+
+        assert!(next_page(r#"<https://a/v1/playbooks?page=1>; rel="prev""#).is_none());
+        assert!(next_page("</v1/playbooks?page=2>; rel=next").is_some());
+    }
+}
@@ -12,21 +12,615 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::errors::Result;
-use amp_client::client::Client;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use clap::ValueEnum;
 use futures::StreamExt;
-use tracing::info;
+use owo_colors::OwoColorize;
+use regex::Regex;
+use reqwest::RequestBuilder;
+use reqwest_eventsource::retry::Never;
+use reqwest_eventsource::{Error, Event, EventSource};
+use serde::{Deserialize, Serialize};
+use tracing::{info, trace, warn};
+
+use crate::client::{Api, REQUEST_ID};
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::actors::{self, Status};
+
+/// Filter applied to the log lines as they arrive, a line is shown if it
+/// matches any of the patterns (or none of them when inverted).
+#[derive(Debug, Default)]
+pub struct Filter {
+    patterns: Vec<Regex>,
+    invert: bool,
+    color: bool,
+}
+
+impl Filter {
+    /// Build the filter, patterns are plain substrings unless `regex` is set.
+    pub fn new(patterns: &[String], regex: bool, invert: bool, color: bool) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|p| if regex { Regex::new(p) } else { Regex::new(&regex::escape(p)) })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(Errors::InvalidGrepPattern)?;
+
+        Ok(Self { patterns, invert, color })
+    }
+
+    /// Return the line to print, or None if it is filtered out.
+    pub fn apply(&self, line: &str) -> Option<String> {
+        if self.patterns.is_empty() {
+            return Some(line.to_string());
+        }
+
+        let matched = self.patterns.iter().any(|p| p.is_match(line));
+        if matched == self.invert {
+            return None;
+        }
+        if !self.color || self.invert {
+            return Some(line.to_string());
+        }
+
+        Some(self.highlight(line))
+    }
+
+    /// Highlight the matching portions, overlapping matches are merged.
+    fn highlight(&self, line: &str) -> String {
+        let mut ranges: Vec<(usize, usize)> =
+            self.patterns.iter().flat_map(|p| p.find_iter(line).map(|m| (m.start(), m.end()))).collect();
+        ranges.sort();
+
+        let mut merged: Vec<(usize, usize)> = vec![];
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        let mut output = String::with_capacity(line.len());
+        let mut cursor = 0;
+        for (start, end) in merged {
+            output.push_str(&line[cursor..start]);
+            output.push_str(&line[start..end].red().bold().to_string());
+            cursor = end;
+        }
+        output.push_str(&line[cursor..]);
+
+        output
+    }
+}
+
+/// How the log lines are prefixed with their timestamps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Timestamps {
+    /// Print the lines as received
+    #[default]
+    Off,
+    /// RFC3339 in UTC
+    Utc,
+    /// RFC3339 in the local timezone, the one named by `TZ` if set
+    Local,
+    /// The seconds elapsed since the first line
+    Relative,
+}
+
+/// Prefix the log lines with their timestamps. A line starting with an RFC3339
+/// timestamp carries the time the server logged the entry, the other lines are
+/// stamped with the time they were received.
+#[derive(Debug, Default)]
+pub struct Stamper {
+    mode: Timestamps,
+    tz: Option<Tz>,
+    first: Option<DateTime<Utc>>,
+}
+
+impl Stamper {
+    pub fn new(mode: Timestamps) -> Self {
+        let tz = std::env::var("TZ").ok().and_then(|tz| tz.parse().ok());
+        Self { mode, tz, first: None }
+    }
+
+    /// Return the line prefixed with its timestamp, in the chosen mode.
+    pub fn stamp(&mut self, line: &str, received: DateTime<Utc>) -> Result<String> {
+        if self.mode == Timestamps::Off {
+            return Ok(line.to_string());
+        }
+
+        let (time, text) = split_timestamp(line)?;
+        let time = time.unwrap_or(received);
+        let first = *self.first.get_or_insert(time);
+        let prefix = match (self.mode, self.tz) {
+            (Timestamps::Relative, _) => format!("+{:.3}s", (time - first).num_milliseconds() as f64 / 1000.0),
+            (Timestamps::Local, Some(tz)) => time.with_timezone(&tz).to_rfc3339_opts(SecondsFormat::Millis, false),
+            (Timestamps::Local, None) => time.with_timezone(&Local).to_rfc3339_opts(SecondsFormat::Millis, false),
+            _ => time.to_rfc3339_opts(SecondsFormat::Millis, true),
+        };
+
+        Ok(format!("{} {}", prefix, text))
+    }
+}
+
+/// Split the leading RFC3339 timestamp off the line, if it starts with one.
+fn split_timestamp(line: &str) -> Result<(Option<DateTime<Utc>>, &str)> {
+    let (token, text) = line.split_once(' ').unwrap_or((line, ""));
+    let bytes = token.as_bytes();
+    let dated = bytes.len() > 10 && bytes[..4].iter().all(u8::is_ascii_digit) && bytes[4] == b'-' && bytes[10] == b'T';
+    if !dated {
+        return Ok((None, line));
+    }
+
+    let time = DateTime::parse_from_rfc3339(token).map_err(Errors::TimestampParseFailed)?;
+    Ok((Some(time.with_timezone(&Utc)), text))
+}
+
+/// The log level detected by default, a leading `ERROR`, `WARN`, `INFO` or
+/// `DEBUG` in any case, optionally bracketed.
+const LEVEL_PATTERN: &str = r"^\W*(?i)(error|warn|info|debug)\b";
+
+/// The output formats of the log lines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// The lines as received, prefixed with their timestamps if enabled
+    #[default]
+    Text,
+    /// One JSON object per line, for the log aggregators
+    Ndjson,
+}
+
+/// Infer the level of the log lines from their content. The level is the
+/// first capture group of the pattern, or the whole match if it has none.
+#[derive(Debug)]
+pub struct Levels {
+    pattern: Regex,
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Self { pattern: Regex::new(LEVEL_PATTERN).unwrap() }
+    }
+}
+
+impl Levels {
+    /// Build the detection from a custom pattern, or the default one.
+    pub fn new(pattern: Option<&str>) -> Result<Self> {
+        match pattern {
+            Some(pattern) => Ok(Self { pattern: Regex::new(pattern).map_err(Errors::InvalidLevelPattern)? }),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Return the level of the line in lowercase, or None if undetected.
+    pub fn detect(&self, line: &str) -> Option<String> {
+        let captures = self.pattern.captures(line)?;
+        let level = captures.get(1).or_else(|| captures.get(0))?;
+        Some(level.as_str().to_lowercase())
+    }
+}
+
+/// A log line as written by `--output ndjson`.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct Record {
+    pub timestamp: String,
+    pub actor: String,
+    pub message: String,
+    pub level: Option<String>,
+}
+
+impl Record {
+    /// Wrap the line of the actor, stamped with the time the server logged
+    /// it if it starts with one, or else the time it was received.
+    pub fn new(actor: &str, line: &str, received: DateTime<Utc>, levels: &Levels) -> Result<Self> {
+        let (time, message) = split_timestamp(line)?;
+        Ok(Self {
+            timestamp: time.unwrap_or(received).to_rfc3339_opts(SecondsFormat::Millis, true),
+            actor: actor.to_string(),
+            message: message.to_string(),
+            level: levels.detect(message),
+        })
+    }
+
+    /// Serialize the record as a single line of JSON.
+    pub fn to_line(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(Errors::JsonSerializeError)
+    }
+}
+
+/// What to receive from the log stream, and how to print it.
+#[derive(Debug)]
+pub struct Options {
+    pub filter: Filter,
+    pub timestamps: Timestamps,
+    pub format: Format,
+    /// How to infer the level of the lines, in the NDJSON format
+    pub levels: Levels,
+    /// Skip the lines logged before, on the server
+    pub since: Option<DateTime<Utc>>,
+    /// Only receive the last lines logged so far
+    pub tail: Option<u64>,
+    /// Keep receiving the new lines until shutdown
+    pub follow: bool,
+    /// The bytes of the lines kept for the last ones of `tail` without following
+    pub max_buffer: usize,
+    /// Fail rather than drop the oldest lines once the buffer is full
+    pub fail_on_overflow: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            filter: Filter::default(),
+            timestamps: Timestamps::Off,
+            format: Format::Text,
+            levels: Levels::default(),
+            since: None,
+            tail: None,
+            follow: true,
+            max_buffer: MAX_BUFFER,
+            fail_on_overflow: false,
+        }
+    }
+}
+
+/// The default bytes of the lines buffered for `tail`, 10 MiB.
+pub const MAX_BUFFER: usize = 10 * 1024 * 1024;
+
+/// The last lines of a stream, bounded both in number and in bytes.
+#[derive(Debug)]
+pub struct Tail {
+    lines: VecDeque<String>,
+    limit: usize,
+    max_bytes: usize,
+    bytes: usize,
+}
+
+impl Tail {
+    pub fn new(limit: usize, max_bytes: usize) -> Self {
+        Self { lines: VecDeque::new(), limit, max_bytes, bytes: 0 }
+    }
+
+    /// Keep the line, dropping the oldest ones past the limits. Returns
+    /// whether some were dropped because the buffer is full.
+    pub fn push(&mut self, line: String) -> bool {
+        self.bytes += line.len();
+        self.lines.push_back(line);
+        if self.lines.len() > self.limit {
+            self.pop();
+        }
+
+        let mut overflowed = false;
+        while self.bytes > self.max_bytes && !self.lines.is_empty() {
+            self.pop();
+            overflowed = true;
+        }
+        overflowed
+    }
 
-/// Receive the log stream from the server.
-pub async fn tail(client: &Client, pid: &str, name: &str) -> Result<()> {
-    info!("Receiving the log stream from the server...");
-    let mut es = client.actors().logs(pid, name);
+    fn pop(&mut self) {
+        if let Some(line) = self.lines.pop_front() {
+            self.bytes -= line.len();
+        }
+    }
+
+    /// The bytes of the lines kept.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn into_lines(self) -> VecDeque<String> {
+        self.lines
+    }
+}
+
+/// The line prefixed with the name of its actor, padded so that the lines of
+/// every actor align.
+#[derive(Debug, Default)]
+pub struct Prefix {
+    width: usize,
+}
+
+impl Prefix {
+    pub fn new(names: &[String]) -> Self {
+        Self { width: names.iter().map(|n| n.chars().count()).max().unwrap_or_default() }
+    }
+
+    pub fn apply(&self, name: &str, line: &str) -> String {
+        format!("[{}]{:pad$} {}", name, "", line, pad = self.width.saturating_sub(name.chars().count()))
+    }
+}
+
+/// Receive the log stream from the server, printing the lines kept by the filter.
+pub async fn tail(ctx: &Context, pid: &str, name: &str, options: &Options) -> Result<()> {
+    tail_into(ctx, pid, name, options, |line| ctx.stdout.println(line)).await
+}
+
+/// Receive the log streams of every actor of the playbook at once, see [`tail_all`].
+pub async fn tail_playbook(ctx: &Context, pid: &str, options: &Options, prefixed: bool) -> Result<()> {
+    let list = actors::list(&ctx.api().await, pid, Status::All).await?;
+    let names = actors::names(&list);
+    if names.is_empty() {
+        warn!("The playbook {} has no actors yet", pid);
+        return Ok(());
+    }
+
+    tail_all(ctx, pid, &names, options, prefixed).await
+}
+
+/// Receive the log streams of the actors at once, interleaving their lines,
+/// prefixed with the names of the actors unless disabled. The NDJSON records
+/// carry the names of the actors, so they are never prefixed.
+pub async fn tail_all(ctx: &Context, pid: &str, names: &[String], options: &Options, prefixed: bool) -> Result<()> {
+    let prefix = Prefix::new(names);
+    let streams = names.iter().map(|name| {
+        let prefix = &prefix;
+        tail_into(ctx, pid, name, options, move |line| match prefixed && options.format == Format::Text {
+            true => ctx.stdout.println(prefix.apply(name, &line)),
+            false => ctx.stdout.println(line),
+        })
+    });
+
+    futures::future::join_all(streams).await.into_iter().collect()
+}
+
+/// Receive the log stream like [`tail`], handing each line to the sink
+/// rather than printing it.
+pub async fn tail_into<F>(ctx: &Context, pid: &str, name: &str, options: &Options, mut sink: F) -> Result<()>
+where
+    F: FnMut(String) -> Result<()>,
+{
+    info!("Receiving the log stream of {} from the server...", name);
+    let path = format!("/actors/{}/{}/logs", pid, name);
+    let mut es = match (options.since, options.tail, options.follow) {
+        (None, None, true) => ctx.client.actors().logs(pid, name),
+        _ => open(ctx, &path, options).await?,
+    };
+    if !options.follow {
+        es.set_retry_policy(Box::new(Never));
+    }
+
+    let start = Instant::now();
+    let mut frames = 0;
+    let mut request_id = None;
+    let mut stamper = Stamper::new(options.timestamps);
+    // Without following, keep the last lines in case the server ignores `tail`.
+    let mut tail = match (options.tail, options.follow) {
+        (Some(limit), false) => Some(Tail::new(limit as usize, options.max_buffer)),
+        _ => None,
+    };
+    ctx.recorder.stream_opened(&path);
+
+    loop {
+        let event = tokio::select! {
+            event = es.next() => event,
+            _ = ctx.shutdown.triggered() => break,
+        };
+        let Some(event) = event else { break };
 
-    while let Some(event) = es.next().await {
-        if let Ok(reqwest_eventsource::Event::Message(message)) = event {
-            println!("{}", message.data);
+        match event {
+            Ok(Event::Message(message)) => {
+                frames += 1;
+                let received = Utc::now();
+                for line in message.data.lines().filter_map(|l| options.filter.apply(l)) {
+                    let line = match options.format {
+                        Format::Text => stamper.stamp(&line, received)?,
+                        Format::Ndjson => Record::new(name, &line, received, &options.levels)?.to_line()?,
+                    };
+                    match tail.as_mut() {
+                        Some(tail) if tail.push(line) => {
+                            if options.fail_on_overflow {
+                                return Err(Errors::LogBufferOverflow(options.max_buffer));
+                            }
+                            trace!("The log buffer of {} is full, dropped the oldest lines", name);
+                        }
+                        Some(_) => {}
+                        None => sink(line)?,
+                    }
+                }
+            }
+            Err(Error::InvalidStatusCode(_, response)) => {
+                request_id = response.headers().get(REQUEST_ID).and_then(|v| v.to_str().ok()).map(String::from);
+            }
+            Err(Error::StreamEnded) if !options.follow => break,
+            _ => {}
         }
     }
 
-    Ok(())
+    es.close();
+    ctx.recorder.stream_closed(&path, frames, start.elapsed(), request_id.as_deref());
+
+    tail.map_or(Ok(()), |tail| tail.into_lines().into_iter().try_for_each(sink))
+}
+
+/// Open the log stream with the `since`, `tail` and `follow` query
+/// parameters, which the client doesn't support.
+async fn open(ctx: &Context, path: &str, options: &Options) -> Result<EventSource> {
+    let request = request(&ctx.api().await, path, options);
+    EventSource::new(request).map_err(|e| Errors::FailedOpenLogStream(e.to_string()))
+}
+
+fn request(api: &Api, path: &str, options: &Options) -> RequestBuilder {
+    let mut request = api.get(path);
+    if let Some(since) = options.since {
+        request = request.query(&[("since", since.to_rfc3339_opts(SecondsFormat::Secs, true))]);
+    }
+    if let Some(tail) = options.tail {
+        request = request.query(&[("tail", tail)]);
+    }
+    if !options.follow {
+        request = request.query(&[("follow", false)]);
+    }
+    request
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Client;
+
+    use super::*;
+
+    fn filter(patterns: &[&str], regex: bool, invert: bool) -> Filter {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        Filter::new(&patterns, regex, invert, false).unwrap()
+    }
+
+    #[test]
+    fn suppress_non_matching_lines() {
+        let filter = filter(&["ERROR", "WARN"], false, false);
+        assert_eq!(filter.apply("  ERROR\tdisk is full "), Some("  ERROR\tdisk is full ".into()));
+        assert_eq!(filter.apply("WARN retrying"), Some("WARN retrying".into()));
+        assert_eq!(filter.apply("INFO started"), None);
+    }
+
+    #[test]
+    fn invert_and_regex_matching() {
+        let filter = filter(&[r"^GET /health"], true, true);
+        assert_eq!(filter.apply("GET /healthz 200"), None);
+        assert_eq!(filter.apply("POST /users 201"), Some("POST /users 201".into()));
+
+        assert!(Filter::new(&["(".into()], true, false, false).is_err());
+        assert!(Filter::new(&["(".into()], false, false, false).is_ok());
+    }
+
+    #[test]
+    fn highlight_merges_overlapping_matches() {
+        let patterns = vec!["err".to_string(), "error".to_string()];
+        let filter = Filter::new(&patterns, false, false, true).unwrap();
+        let line = filter.apply("an error here").unwrap();
+        assert_eq!(line, format!("an {} here", "error".red().bold()));
+    }
+
+    fn stamper(mode: Timestamps, tz: Option<Tz>) -> Stamper {
+        Stamper { mode, tz, first: None }
+    }
+
+    fn time(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn stamp_with_server_or_received_time() {
+        let received = time("2025-01-02T03:04:05Z");
+        let mut stamper = stamper(Timestamps::Utc, None);
+
+        let line = stamper.stamp("2025-01-02T03:04:00.5+01:00 listening on :8080", received).unwrap();
+        assert_eq!(line, "2025-01-02T02:04:00.500Z listening on :8080");
+        let line = stamper.stamp("ready", received).unwrap();
+        assert_eq!(line, "2025-01-02T03:04:05.000Z ready");
+
+        assert!(matches!(stamper.stamp("2025-13-02T03:04:00Z oops", received), Err(Errors::TimestampParseFailed(_))));
+        assert_eq!(Stamper::new(Timestamps::Off).stamp("as is", received).unwrap(), "as is");
+    }
+
+    #[test]
+    fn stamp_in_timezone() {
+        let mut stamper = stamper(Timestamps::Local, Some(chrono_tz::Asia::Tokyo));
+        let line = stamper.stamp("2025-01-02T03:04:05Z started", Utc::now()).unwrap();
+        assert_eq!(line, "2025-01-02T12:04:05.000+09:00 started");
+    }
+
+    #[test]
+    fn stamp_relative_to_first_line() {
+        let mut stamper = stamper(Timestamps::Relative, None);
+        let first = stamper.stamp("2025-01-02T03:04:05Z booting", Utc::now()).unwrap();
+        let later = stamper.stamp("ready", time("2025-01-02T03:04:07.250Z")).unwrap();
+        assert_eq!(first, "+0.000s booting");
+        assert_eq!(later, "+2.250s ready");
+    }
+
+    #[test]
+    fn request_the_window_of_lines() {
+        let since = time("2025-01-02T03:04:05Z");
+        let options = Options { since: Some(since), tail: Some(100), follow: false, ..Options::default() };
+        let api = Api::new(Client::new(), "https://a", Some("t"));
+        let request = request(&api, "/actors/p1/web/logs", &options).build().unwrap();
+        assert_eq!(
+            request.url().as_str(),
+            "https://a/v1/actors/p1/web/logs?since=2025-01-02T03%3A04%3A05Z&tail=100&follow=false"
+        );
+    }
+
+    #[test]
+    fn detect_the_levels() {
+        let levels = Levels::default();
+        assert_eq!(levels.detect("ERROR disk is full"), Some("error".into()));
+        assert_eq!(levels.detect("[Warn] retrying"), Some("warn".into()));
+        assert_eq!(levels.detect("debug: connected"), Some("debug".into()));
+        assert_eq!(levels.detect("listening on :8080, INFO"), None);
+
+        let levels = Levels::new(Some(r"level=(\w+)")).unwrap();
+        assert_eq!(levels.detect("ts=1 level=WARN msg=slow"), Some("warn".into()));
+        assert!(matches!(Levels::new(Some("(")), Err(Errors::InvalidLevelPattern(_))));
+    }
+
+    #[test]
+    fn ndjson_records_round_trip() {
+        let received = time("2025-01-02T03:04:05Z");
+        let levels = Levels::default();
+        for (line, timestamp, message, level) in [
+            ("2025-01-02T03:04:00+01:00 INFO listening", "2025-01-02T02:04:00.000Z", "INFO listening", Some("info")),
+            ("ERROR \"quoted\" \\ \t tab", "2025-01-02T03:04:05.000Z", "ERROR \"quoted\" \\ \t tab", Some("error")),
+            ("plain", "2025-01-02T03:04:05.000Z", "plain", None),
+        ] {
+            let json = Record::new("web", line, received, &levels).unwrap().to_line().unwrap();
+            assert!(!json.contains('\n'));
+            let record: Record = serde_json::from_str(&json).unwrap();
+            assert_eq!(
+                record,
+                Record {
+                    timestamp: timestamp.into(),
+                    actor: "web".into(),
+                    message: message.into(),
+                    level: level.map(String::from),
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn prefix_concurrent_streams() {
+        let names: Vec<String> = ["web", "api", "worker"].iter().map(|n| n.to_string()).collect();
+        let prefix = std::sync::Arc::new(Prefix::new(&names));
+        let lines = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+
+        let threads: Vec<_> = names
+            .iter()
+            .map(|name| {
+                let (prefix, lines, name) = (prefix.clone(), lines.clone(), name.clone());
+                std::thread::spawn(move || {
+                    for i in 0..50 {
+                        lines.lock().unwrap().push(prefix.apply(&name, &format!("line {}", i)));
+                    }
+                })
+            })
+            .collect();
+        threads.into_iter().for_each(|t| t.join().unwrap());
+
+        let lines = lines.lock().unwrap();
+        assert_eq!(lines.len(), 150);
+        for name in ["[web]    ", "[api]    ", "[worker] "] {
+            assert_eq!(lines.iter().filter(|l| l.starts_with(name)).count(), 50, "{}", name);
+        }
+        assert!(lines.contains(&"[worker] line 49".to_string()));
+    }
+
+    #[test]
+    fn bound_the_tail_buffer() {
+        let mut tail = Tail::new(3, 1024);
+        assert!((1..=5).all(|i| !tail.push(format!("line {}", i))), "the lines past the tail aren't an overflow");
+        assert_eq!(tail.into_lines(), ["line 3", "line 4", "line 5"]);
+
+        // 100 MiB of lines through a 1 MiB buffer.
+        let line = "x".repeat(1023) + "\n";
+        let mut tail = Tail::new(usize::MAX, 1024 * 1024);
+        let overflows = (0..100 * 1024).filter(|_| tail.push(line.clone())).count();
+        assert_eq!(tail.bytes(), 1024 * 1024);
+        assert_eq!(overflows, 99 * 1024);
+        assert_eq!(tail.into_lines().len(), 1024);
+    }
 }
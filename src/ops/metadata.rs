@@ -0,0 +1,159 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, HashMap};
+
+use amp_client::playbooks::PlaybookPayload;
+use amp_common::resource::CharacterSpec;
+use serde::{Deserialize, Serialize};
+use toml::Table;
+
+/// The optional metadata of the manifest shown on the playbook cards of the
+/// web UI. The description travels in the payload itself.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintainers: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// A git repository the server reads the manifest from, rather than the
+/// manifest being sent along.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitSource {
+    pub url: String,
+    /// The branch, tag or commit, the default branch if missing
+    #[serde(default, rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    /// The directory of the manifest in the repository, the root if missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subpath: Option<String>,
+}
+
+/// The playbook payload, enriched with the metadata the client doesn't know
+/// about. Missing fields are omitted, so older servers see the same payload.
+#[derive(Serialize)]
+pub struct Payload {
+    #[serde(flatten)]
+    pub playbook: PlaybookPayload,
+    #[serde(flatten)]
+    pub metadata: Metadata,
+    /// The git revision of the workspace, see [`crate::utils::git::Revision::attributes`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<HashMap<String, String>>,
+    /// Every character of the playbook, along with the lead of the preface
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub characters: Option<Vec<CharacterSpec>>,
+    /// The images deployed instead of building the characters, by name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub images: Option<BTreeMap<String, String>>,
+    /// The repository of the manifest, when it isn't inlined in the preface
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<GitSource>,
+}
+
+impl From<PlaybookPayload> for Payload {
+    fn from(playbook: PlaybookPayload) -> Self {
+        Self { playbook, metadata: Metadata::default(), attributes: None, characters: None, images: None, source: None }
+    }
+}
+
+/// Read the description and the metadata from the `[meta]` table of the
+/// manifest, ignoring the fields which are missing or of the wrong type.
+/// `authors` stands in for `maintainers`.
+pub fn from_manifest(content: &str) -> (Option<String>, Metadata) {
+    let table: Table = toml::from_str(content).unwrap_or_default();
+    let Some(meta) = table.get("meta").and_then(|m| m.as_table()) else {
+        return (None, Metadata::default());
+    };
+
+    let string = |key: &str| meta.get(key).and_then(|v| v.as_str()).map(String::from);
+    let strings = |key: &str| {
+        let array = meta.get(key)?.as_array()?;
+        Some(array.iter().filter_map(|v| v.as_str()).map(String::from).collect::<Vec<_>>())
+    };
+
+    let metadata = Metadata {
+        maintainers: strings("maintainers").or_else(|| strings("authors")).filter(|m| !m.is_empty()),
+        repository: string("repository"),
+        icon: string("icon"),
+    };
+    (string("description"), metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use amp_common::resource::Preface;
+    use serde_json::json;
+
+    use super::*;
+
+    fn playbook(description: &str) -> PlaybookPayload {
+        PlaybookPayload {
+            title: "Untitled".to_string(),
+            description: description.to_string(),
+            preface: Preface::registry("demo", "hub", "latest"),
+        }
+    }
+
+    #[test]
+    fn omit_missing_metadata() {
+        let (description, metadata) = from_manifest("[meta]\nname = \"web\"\nicon = 42\n");
+        assert_eq!(description, None);
+        assert!(metadata.is_empty());
+
+        let value = serde_json::to_value(Payload { metadata, ..Payload::from(playbook("")) }).unwrap();
+        let mut keys: Vec<&String> = value.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["description", "preface", "title"]);
+    }
+
+    #[test]
+    fn populate_all_metadata() {
+        let content = r#"
+[meta]
+name = "web"
+description = "The web frontend"
+authors = ["Jane <jane@example.com>"]
+repository = "https://github.com/org/web"
+icon = "https://example.com/web.png"
+"#;
+        let (description, metadata) = from_manifest(content);
+        let payload = Payload { metadata, ..Payload::from(playbook(&description.unwrap())) };
+
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(value["description"], "The web frontend");
+        assert_eq!(value["maintainers"], json!(["Jane <jane@example.com>"]));
+        assert_eq!(value["repository"], "https://github.com/org/web");
+        assert_eq!(value["icon"], "https://example.com/web.png");
+    }
+
+    #[test]
+    fn send_revision_attributes() {
+        let attributes = HashMap::from([(String::from("commit"), String::from("1a2b3c4"))]);
+        let payload = Payload { attributes: Some(attributes), ..Payload::from(playbook("")) };
+
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(value["attributes"], json!({"commit": "1a2b3c4"}));
+    }
+}
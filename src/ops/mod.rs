@@ -12,7 +12,28 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod actors;
 pub mod cleaner;
+pub mod compose;
+pub mod connection;
+pub mod copy;
+pub mod discover;
+pub mod events;
+pub mod helm;
+pub mod hooks;
+pub mod images;
+pub mod importer;
+pub mod listing;
 pub mod logger;
+pub mod metadata;
+pub mod monorepo;
+pub mod partners;
 pub mod pipeline;
+pub mod playbook;
+pub mod playbooks;
+pub mod poller;
+pub mod readiness;
+pub mod tap;
+pub mod templates;
+pub mod validate;
 pub mod watcher;
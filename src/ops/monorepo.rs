@@ -0,0 +1,463 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use amp_client::playbooks::PlaybookPayload;
+use amp_common::resource::{CharacterSpec, PlaybookSpec, Preface};
+use amp_common::schema::Character;
+use ignore::WalkBuilder;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+use tracing::{error, info, instrument};
+
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::lint;
+use crate::ops::logger::Prefix;
+use crate::ops::pipeline::{self, Options};
+use crate::ops::watcher::{NotifySource, ServerApi, SyncEngine};
+use crate::ops::{cleaner, hooks, logger, metadata::Payload, partners, playbook};
+use crate::output::Stdout;
+use crate::utils::{ManifestFormat, MANIFEST_NAMES};
+
+/// A character found under the root of the monorepo.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Member {
+    pub name: String,
+    pub manifest: PathBuf,
+}
+
+impl Member {
+    /// The directory of the manifest, synced into the actor of the character.
+    pub fn workspace(&self) -> PathBuf {
+        self.manifest.parent().map(Path::to_path_buf).unwrap_or_default()
+    }
+}
+
+/// Discover the characters under the root, sorted by name: the manifests of
+/// the directories not ignored by the ignore files, preferring `.amp.toml` when
+/// a directory holds several. Only the named characters are kept, unless
+/// `only` is empty.
+pub fn discover(root: &Path, only: &[String]) -> Result<Vec<Member>> {
+    let mut manifests: BTreeMap<PathBuf, PathBuf> = BTreeMap::new();
+    let walker = WalkBuilder::new(root).hidden(false).filter_entry(|e| e.file_name() != ".git").build();
+    for entry in walker {
+        let entry = entry.map_err(Errors::WalkError)?;
+        let name = entry.file_name().to_string_lossy();
+        let Some(rank) = MANIFEST_NAMES.iter().position(|m| *m == name) else {
+            continue;
+        };
+        let dir = entry.path().parent().map(Path::to_path_buf).unwrap_or_default();
+        let preferred = manifests.get(&dir).is_some_and(|p| rank_of(p) < rank);
+        if !preferred {
+            manifests.insert(dir, entry.path().to_path_buf());
+        }
+    }
+
+    let mut members: BTreeMap<String, Member> = BTreeMap::new();
+    for manifest in manifests.into_values() {
+        let name = character_name(&manifest)?;
+        if let Some(other) = members.get(&name) {
+            return Err(Errors::DuplicateCharacter(name, other.manifest.clone(), manifest));
+        }
+        members.insert(name.clone(), Member { name, manifest });
+    }
+
+    let unknown: Vec<&str> = only.iter().filter(|n| !members.contains_key(*n)).map(String::as_str).collect();
+    if !unknown.is_empty() {
+        let available: Vec<&str> = members.keys().map(String::as_str).collect();
+        return Err(Errors::UnknownCharacters(unknown.join(", "), available.join(", ")));
+    }
+    if !only.is_empty() {
+        members.retain(|name, _| only.contains(name));
+    }
+    if members.is_empty() {
+        return Err(Errors::NoCharactersFound(root.to_path_buf()));
+    }
+
+    Ok(members.into_values().collect())
+}
+
+fn rank_of(path: &Path) -> usize {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    MANIFEST_NAMES.iter().position(|m| *m == name).unwrap_or(MANIFEST_NAMES.len())
+}
+
+/// Read the `meta.name` of the manifest, without validating the rest of it.
+fn character_name(path: &Path) -> Result<String> {
+    let origin = path.display().to_string();
+    let content = fs::read_to_string(path).map_err(|e| Errors::FailedReadManifest(origin.clone(), e))?;
+    let content = ManifestFormat::detect(path)?.to_toml(&origin, content)?;
+    let table: toml::Table = toml::from_str(&content).map_err(|e| Errors::FailedParseManifest(origin, e))?;
+    let name = table.get("meta").and_then(|m| m.get("name")).and_then(|n| n.as_str());
+
+    name.filter(|n| !n.is_empty()).map(String::from).ok_or_else(|| Errors::MissingCharacterName(path.to_path_buf()))
+}
+
+/// Compose the payload of the playbook holding every character, led by the first one.
+pub fn compose(title: &str, characters: Vec<CharacterSpec>) -> Result<Payload> {
+    let lead = characters.first().ok_or(Errors::InvalidCharacter)?;
+    let names: Vec<&str> = characters.iter().map(|c| c.meta.name.as_str()).collect();
+    let playbook = PlaybookPayload {
+        title: title.to_string(),
+        description: format!("Characters: {}", names.join(", ")),
+        preface: Preface::manifest(lead),
+    };
+
+    Ok(Payload { characters: Some(characters), ..Payload::from(playbook) })
+}
+
+/// Load the character of each member. The partners are stripped, as they are
+/// members themselves or deployed by their own sessions, and the hooks aren't run.
+pub async fn load(
+    ctx: &Context,
+    members: &[Member],
+    profiles: &[String],
+    allow: &[String],
+) -> Result<Vec<CharacterSpec>> {
+    let mut characters = vec![];
+    for member in members {
+        let filename = Some(member.manifest.display().to_string());
+        let mut manifest = pipeline::read(ctx, &filename, profiles).await?;
+        lint::enforce(&manifest, allow)?;
+        manifest.content = partners::strip(&manifest)?;
+        let (_, content) = hooks::extract(&manifest)?;
+
+        let origin = manifest.origin.to_string();
+        let character: Character = toml::from_str(&content).map_err(|e| Errors::FailedParseManifest(origin, e))?;
+        characters.push(CharacterSpec { live: true, once: false, ..CharacterSpec::from(&character) });
+    }
+
+    Ok(characters)
+}
+
+/// The running playbook with the given title, or else a new playbook holding
+/// every character. True if the playbook was created.
+pub async fn create_or_reuse(
+    ctx: &Context,
+    title: &str,
+    characters: Vec<CharacterSpec>,
+) -> Result<(PlaybookSpec, bool)> {
+    let options =
+        HashMap::from([(String::from("title"), title.to_string()), (String::from("status"), String::from("running"))]);
+    let playbooks = ctx.recorder.call_async("GET", "/playbooks", ctx.playbooks().list_async(Some(options))).await?;
+    if let Some(playbook) = playbooks.into_iter().find(|p| p.title == title) {
+        info!("Reusing the running playbook {}", playbook.id);
+        return Ok((playbook, false));
+    }
+
+    let playbook = playbook::submit(ctx, &compose(title, characters)?, None).await?;
+    Ok((playbook, true))
+}
+
+/// The health of a character, as shown in the summary line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Health {
+    Starting,
+    Syncing,
+    Watching,
+    Stopped,
+    Failed,
+}
+
+impl std::fmt::Display for Health {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Health::Starting => write!(f, "starting"),
+            Health::Syncing => write!(f, "syncing"),
+            Health::Watching => write!(f, "watching"),
+            Health::Stopped => write!(f, "stopped"),
+            Health::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// Interleaves the output of the characters into a single stream, each line
+/// prefixed with the name of its character, and tracks their health.
+pub struct Multiplexer {
+    prefix: Prefix,
+    health: Mutex<Vec<(String, Health)>>,
+}
+
+impl Multiplexer {
+    pub fn new(names: &[String]) -> Self {
+        let health = names.iter().map(|n| (n.clone(), Health::Starting)).collect();
+        Self { prefix: Prefix::new(names), health: Mutex::new(health) }
+    }
+
+    /// The line prefixed with the name, padded so the lines of every character align.
+    pub fn prefix(&self, name: &str, line: &str) -> String {
+        self.prefix.apply(name, line)
+    }
+
+    /// Write the prefixed line, the lines are written whole even when
+    /// several characters write at once.
+    pub fn line(&self, out: &Stdout, name: &str, line: &str) -> Result<()> {
+        out.println(self.prefix(name, line))
+    }
+
+    /// Record the health of the character, returning the updated summary line.
+    pub fn set(&self, name: &str, health: Health) -> String {
+        let mut characters = self.health.lock().unwrap();
+        if let Some((_, current)) = characters.iter_mut().find(|(n, _)| n == name) {
+            *current = health;
+        }
+        summary(&characters)
+    }
+
+    /// The summary line, such as `2/3 watching: api watching, web watching, worker failed`.
+    pub fn summary(&self) -> String {
+        summary(&self.health.lock().unwrap())
+    }
+}
+
+fn summary(characters: &[(String, Health)]) -> String {
+    let watching = characters.iter().filter(|(_, h)| *h == Health::Watching).count();
+    let states: Vec<String> = characters.iter().map(|(n, h)| format!("{} {}", n, h)).collect();
+    format!("{}/{} watching: {}", watching, characters.len(), states.join(", "))
+}
+
+/// Run every member of the playbook: sync its sources, watch its changes and
+/// tail its logs, until the shutdown is triggered. A member failing is
+/// reported without stopping the others. The playbook is cleaned up if it was
+/// created by this session.
+#[instrument(name = "monorepo", skip_all, fields(playbook_id = %playbook.id, members = members.len()))]
+pub async fn run(
+    ctx: &Arc<Context>,
+    playbook: PlaybookSpec,
+    created: bool,
+    members: Vec<Member>,
+    options: Options,
+) -> Result<()> {
+    // wait playbook resolve finished.
+    if created {
+        sleep(Duration::from_secs(10)).await;
+    }
+
+    let path = format!("/playbooks/{}", playbook.id);
+    let playbook = ctx.recorder.call_async("GET", &path, ctx.playbooks().get_async(&playbook.id)).await?;
+    if created {
+        ctx.session.playbook.write().await.replace(playbook.clone());
+    }
+
+    let names: Vec<String> = members.iter().map(|m| m.name.clone()).collect();
+    let mux = Arc::new(Multiplexer::new(&names));
+    let mut tasks: Vec<JoinHandle<()>> = vec![];
+
+    for member in members {
+        let name = member.name.clone();
+        let (ctx1, mux1, pid) = (ctx.clone(), mux.clone(), playbook.id.clone());
+        tasks.push(tokio::task::spawn_blocking(move || {
+            let health = match sync(&ctx1, &mux1, &pid, &member) {
+                Ok(()) => Health::Stopped,
+                Err(err) => {
+                    let _ = mux1.line(&ctx1.stdout, &member.name, &format!("sync failed: {}", err));
+                    Health::Failed
+                }
+            };
+            report(&ctx1, &mux1, &member.name, health);
+        }));
+
+        if options.tail {
+            let (ctx1, mux1, pid) = (ctx.clone(), mux.clone(), playbook.id.clone());
+            tasks.push(tokio::spawn(async move {
+                let timestamps = match ctx1.timestamps {
+                    true => logger::Timestamps::Utc,
+                    false => logger::Timestamps::Off,
+                };
+                let options = logger::Options { timestamps, ..logger::Options::default() };
+                let sink = |line: String| mux1.line(&ctx1.stdout, &name, &line);
+                if let Err(err) = logger::tail_into(&ctx1, &pid, &name, &options, sink).await {
+                    error!("The log stream of {} is stopped: {:?}", name, err);
+                }
+            }));
+        }
+    }
+
+    info!("The playbook is running...");
+    ctx.shutdown.triggered().await;
+    for task in tasks {
+        let _ = task.await;
+    }
+    ctx.stdout.println(mux.summary())?;
+
+    // Cleanup the playbook if cleanup is enabled.
+    if options.cleanup && created {
+        if let Err(err) = cleaner::try_cleanup_playbook(ctx).await {
+            error!("Failed to cleanup playbook: {:?}", err);
+        }
+    }
+
+    Ok(())
+}
+
+/// Sync the full sources of the member, and then its changes until the shutdown.
+fn sync(ctx: &Context, mux: &Multiplexer, pid: &str, member: &Member) -> Result<()> {
+    let engine = SyncEngine::new(ServerApi::new(ctx), &member.workspace(), pid, &member.name);
+
+    report(ctx, mux, &member.name, Health::Syncing);
+    engine.initial_upload()?;
+    let mut source = NotifySource::new(&member.workspace())?;
+    report(ctx, mux, &member.name, Health::Watching);
+
+    engine.run(&mut source, &ctx.shutdown)
+}
+
+/// Record the health of the member, and print the updated summary line. The
+/// final summary is printed once every member is stopped.
+fn report(ctx: &Context, mux: &Multiplexer, name: &str, health: Health) {
+    let summary = mux.set(name, health);
+    if ctx.shutdown.is_triggered() {
+        return;
+    }
+    if let Err(err) = ctx.stdout.println(summary) {
+        error!("{}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("amp-monorepo-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn names(members: &[Member]) -> Vec<&str> {
+        members.iter().map(|m| m.name.as_str()).collect()
+    }
+
+    #[test]
+    fn discover_every_character_under_the_root() {
+        let root = temp_dir("discover");
+        write(&root, "services/web/.amp.toml", "[meta]\nname = \"web\"\n");
+        write(&root, "services/api/.amp.yaml", "meta:\n  name: api\n");
+        write(&root, "services/api/.amp.yml", "meta:\n  name: stale\n");
+        write(&root, "vendor/lib/.amp.toml", "[meta]\nname = \"lib\"\n");
+        write(&root, ".ignore", "vendor/\n");
+
+        let members = discover(&root, &[]).unwrap();
+        assert_eq!(names(&members), vec!["api", "web"]);
+        assert_eq!(members[1].workspace(), root.join("services/web"));
+
+        let members = discover(&root, &["web".to_string()]).unwrap();
+        assert_eq!(names(&members), vec!["web"]);
+    }
+
+    #[test]
+    fn reject_unknown_and_duplicate_characters() {
+        let root = temp_dir("reject");
+        write(&root, "a/.amp.toml", "[meta]\nname = \"web\"\n");
+
+        let err = discover(&root, &["worker".to_string()]).unwrap_err();
+        assert!(
+            matches!(err, Errors::UnknownCharacters(unknown, available) if unknown == "worker" && available == "web")
+        );
+
+        write(&root, "b/.amp.toml", "[meta]\nname = \"web\"\n");
+        assert!(matches!(discover(&root, &[]), Err(Errors::DuplicateCharacter(name, ..)) if name == "web"));
+
+        write(&root, "b/.amp.toml", "[meta]\nversion = \"0.1.0\"\n");
+        assert!(matches!(discover(&root, &[]), Err(Errors::MissingCharacterName(_))));
+
+        let empty = temp_dir("empty");
+        assert!(matches!(discover(&empty, &[]), Err(Errors::NoCharactersFound(_))));
+    }
+
+    fn spec(name: &str) -> CharacterSpec {
+        let character: Character =
+            toml::from_str(&format!("[meta]\nname = \"{}\"\nversion = \"0.1.0\"\n", name)).unwrap();
+        CharacterSpec { live: true, ..CharacterSpec::from(&character) }
+    }
+
+    #[test]
+    fn compose_a_playbook_of_every_character() {
+        let payload = compose("shop (main)", vec![spec("api"), spec("web")]).unwrap();
+        let value = serde_json::to_value(&payload).unwrap();
+
+        assert_eq!(value["title"], "shop (main)");
+        assert_eq!(value["description"], "Characters: api, web");
+        assert_eq!(value["preface"], serde_json::to_value(Preface::manifest(&spec("api"))).unwrap());
+        let names: Vec<&str> =
+            value["characters"].as_array().unwrap().iter().map(|c| c["meta"]["name"].as_str().unwrap()).collect();
+        assert_eq!(names, vec!["api", "web"]);
+
+        assert!(compose("empty", vec![]).is_err());
+    }
+
+    /// A writer shared with the test, to read back what was written.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn interleave_whole_prefixed_lines() {
+        let names = vec!["web".to_string(), "worker".to_string()];
+        let mux = Arc::new(Multiplexer::new(&names));
+        assert_eq!(mux.prefix("web", "ready"), "[web]    ready");
+        assert_eq!(mux.prefix("worker", "ready"), "[worker] ready");
+
+        let shared = Shared::default();
+        let out = Arc::new(Stdout::new(Box::new(shared.clone())));
+        let threads: Vec<_> = names
+            .iter()
+            .map(|name| {
+                let (mux, out, name) = (mux.clone(), out.clone(), name.clone());
+                std::thread::spawn(move || {
+                    (0..100).for_each(|i| mux.line(&out, &name, &format!("line {}", i)).unwrap())
+                })
+            })
+            .collect();
+        threads.into_iter().for_each(|t| t.join().unwrap());
+
+        let output = String::from_utf8(shared.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(output.lines().count(), 200);
+        assert!(output.lines().all(|l| l.starts_with("[web]    line ") || l.starts_with("[worker] line ")));
+    }
+
+    #[test]
+    fn track_the_health_of_each_character() {
+        let mux = Multiplexer::new(&["api".to_string(), "web".to_string(), "worker".to_string()]);
+        assert_eq!(mux.summary(), "0/3 watching: api starting, web starting, worker starting");
+
+        mux.set("api", Health::Watching);
+        mux.set("web", Health::Watching);
+        let summary = mux.set("worker", Health::Failed);
+        assert_eq!(summary, "2/3 watching: api watching, web watching, worker failed");
+    }
+}
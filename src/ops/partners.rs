@@ -0,0 +1,242 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+
+use toml::{Table, Value};
+use tracing::warn;
+
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::utils::manifest_edit::ManifestEdit;
+use crate::utils::{self, Manifest};
+
+/// The key of the partners table in the manifest.
+const PARTNERS: &str = "partners";
+
+/// Where a partner character comes from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// A git repository, with an optional `#<branch>` suffix
+    Git(String),
+    /// A character published to the registry
+    Registry(String),
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Git(repo) => write!(f, "{}", repo),
+            Source::Registry(name) => write!(f, "registry:{}", name),
+        }
+    }
+}
+
+/// A partner character the lead character depends on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Partner {
+    pub name: String,
+    pub source: Source,
+}
+
+/// Resolve the partners of the manifest, including the partners of the git
+/// partners, each one once. Cycles and duplicate references are rejected.
+pub async fn resolve(ctx: &Context, manifest: &Manifest) -> Result<Vec<Partner>> {
+    let lead = lead_key(&manifest.content);
+    let mut graph: HashMap<String, Vec<String>> = HashMap::new();
+    let mut partners: Vec<Partner> = vec![];
+    let mut queue = VecDeque::from([(lead.clone(), declared(&manifest.content, &manifest.origin.to_string())?)]);
+
+    while let Some((key, declared)) = queue.pop_front() {
+        let mut edges = vec![];
+        for partner in declared {
+            let Source::Git(location) = &partner.source else {
+                if !partners.contains(&partner) {
+                    partners.push(partner);
+                }
+                continue;
+            };
+
+            let child = normalize(location);
+            edges.push(child.clone());
+            if graph.contains_key(&child) || queue.iter().any(|(k, _)| k == &child) || child == lead {
+                continue;
+            }
+
+            let children = match fetch(ctx, location).await {
+                Ok(content) => declared(&content, location)?,
+                Err(err) => {
+                    warn!("Failed to read the manifest of partner {}, skipping its partners: {}", partner.name, err);
+                    vec![]
+                }
+            };
+            partners.push(partner);
+            queue.push_back((child, children));
+        }
+        graph.insert(key, edges);
+    }
+
+    if let Some(cycle) = find_cycle(&graph, &lead) {
+        return Err(Errors::PartnerCycle(cycle));
+    }
+
+    Ok(partners)
+}
+
+/// Remove the partners from the manifest, so only the lead character is deployed.
+pub fn strip(manifest: &Manifest) -> Result<String> {
+    if !manifest.content.contains(PARTNERS) {
+        return Ok(manifest.content.clone());
+    }
+
+    let mut edit = ManifestEdit::parse(&manifest.origin.to_string(), &manifest.content)?;
+    edit.remove(&[PARTNERS]);
+    Ok(edit.to_string())
+}
+
+/// The partners declared by the manifest content, either as a plain string
+/// (a git URL or a registry name) or a table with a `repo` and optional `branch`.
+fn declared(content: &str, origin: &str) -> Result<Vec<Partner>> {
+    let table: Table = toml::from_str(content).map_err(|e| Errors::FailedParseManifest(origin.into(), e))?;
+    let Some(Value::Table(declared)) = table.get(PARTNERS) else {
+        return Ok(vec![]);
+    };
+
+    let mut partners: Vec<Partner> = vec![];
+    for (name, value) in declared {
+        let source = match value {
+            Value::String(s) if s.contains("://") || s.ends_with(".git") => Source::Git(s.clone()),
+            Value::String(s) => Source::Registry(s.clone()),
+            Value::Table(t) => match (t.get("repo").and_then(Value::as_str), t.get("branch").and_then(Value::as_str)) {
+                (Some(repo), Some(branch)) => Source::Git(format!("{}#{}", repo, branch)),
+                (Some(repo), None) => Source::Git(repo.to_string()),
+                (None, _) => Source::Registry(name.clone()),
+            },
+            _ => Source::Registry(name.clone()),
+        };
+
+        if let Some(other) = partners.iter().find(|p| p.source == source) {
+            return Err(Errors::DuplicatePartner(other.name.clone(), name.clone(), source.to_string()));
+        }
+        partners.push(Partner { name: name.clone(), source });
+    }
+
+    Ok(partners)
+}
+
+async fn fetch(ctx: &Context, location: &str) -> Result<String> {
+    let location = if location.contains('#') { location.to_string() } else { format!("{}#", location) };
+    let manifest = utils::read_manifest(&ctx.http, &location, ctx.remote_cache).await?;
+    Ok(manifest.content)
+}
+
+/// The key of the lead character in the graph, its repository if known.
+fn lead_key(content: &str) -> String {
+    let table: Table = toml::from_str(content).unwrap_or_default();
+    let meta = table.get("meta");
+    match meta.and_then(|m| m.get("repository")).and_then(Value::as_str) {
+        Some(repository) => normalize(repository),
+        None => meta.and_then(|m| m.get("name")).and_then(Value::as_str).unwrap_or_default().to_string(),
+    }
+}
+
+/// Normalize a git location so the same repository is always the same key.
+fn normalize(location: &str) -> String {
+    let (repo, branch) = location.split_once('#').unwrap_or((location, ""));
+    let repo = repo.trim_end_matches('/').trim_end_matches(".git");
+    if branch.is_empty() {
+        return repo.to_string();
+    }
+    format!("{}#{}", repo, branch)
+}
+
+/// Find a cycle reachable from the start node, returned as the path closing it.
+fn find_cycle(graph: &HashMap<String, Vec<String>>, start: &str) -> Option<Vec<String>> {
+    fn visit(graph: &HashMap<String, Vec<String>>, node: &str, path: &mut Vec<String>) -> Option<Vec<String>> {
+        if let Some(pos) = path.iter().position(|n| n == node) {
+            let mut cycle = path[pos..].to_vec();
+            cycle.push(node.to_string());
+            return Some(cycle);
+        }
+
+        path.push(node.to_string());
+        for child in graph.get(node).into_iter().flatten() {
+            if let Some(cycle) = visit(graph, child, path) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        None
+    }
+
+    visit(graph, start, &mut vec![])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_declared_partners() {
+        let content = r#"
+[partners]
+api = { repo = "https://github.com/org/api.git", branch = "main" }
+cache = "redis"
+web = "https://github.com/org/web"
+"#;
+        let partners = declared(content, ".amp.toml").unwrap();
+        assert_eq!(partners[0].source, Source::Git("https://github.com/org/api.git#main".into()));
+        assert_eq!(partners[1].source, Source::Registry("redis".into()));
+        assert_eq!(partners[2].source, Source::Git("https://github.com/org/web".into()));
+    }
+
+    #[test]
+    fn reject_duplicate_references() {
+        let content = r#"
+[partners]
+api = "https://github.com/org/api"
+backend = "https://github.com/org/api"
+"#;
+        let err = declared(content, ".amp.toml").unwrap_err();
+        assert!(matches!(err, Errors::DuplicatePartner(a, b, _) if a == "api" && b == "backend"));
+    }
+
+    #[test]
+    fn strip_keeps_the_rest_of_the_manifest() {
+        let content = "# web\n[meta]\nname = \"web\" # lead\n\n[partners]\ncache = \"redis\"\n";
+        let manifest = Manifest { origin: utils::Origin::Url("https://a/.amp.toml".into()), content: content.into() };
+
+        assert_eq!(strip(&manifest).unwrap(), "# web\n[meta]\nname = \"web\" # lead\n");
+    }
+
+    #[test]
+    fn detect_cycles() {
+        let edges = |pairs: &[(&str, &[&str])]| -> HashMap<String, Vec<String>> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.iter().map(|s| s.to_string()).collect())).collect()
+        };
+
+        let graph = edges(&[("lead", &["a", "b"]), ("a", &["b"]), ("b", &[])]);
+        assert_eq!(find_cycle(&graph, "lead"), None);
+
+        let graph = edges(&[("lead", &["a"]), ("a", &["b"]), ("b", &["a"])]);
+        assert_eq!(find_cycle(&graph, "lead"), Some(vec!["a".into(), "b".into(), "a".into()]));
+    }
+
+    #[test]
+    fn normalize_locations() {
+        assert_eq!(normalize("https://github.com/org/api.git"), "https://github.com/org/api");
+        assert_eq!(normalize("https://github.com/org/api/#main"), "https://github.com/org/api#main");
+    }
+}
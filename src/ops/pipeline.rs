@@ -15,18 +15,20 @@
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use amp_client::playbooks::{PlaybookPayload, Playbooks};
-use amp_common::filesystem::Finder;
-use amp_common::resource::{CharacterSpec, PlaybookSpec, Preface};
+use amp_common::resource::{CharacterSpec, PlaybookSpec};
 use tokio::time::{sleep, Duration};
-use tracing::{debug, error, info};
+use tracing::{error, info, instrument, Span};
 
 use crate::context::Context;
 use crate::errors::{Errors, Result};
-use crate::ops::{cleaner, logger, watcher};
-use crate::utils;
+use crate::ops::hooks::{self, Env, Event};
+use crate::ops::images::ImageOverride;
+use crate::ops::watcher::{self, ServerApi, SyncEngine};
+use crate::ops::{cleaner, events, logger, readiness};
+use crate::utils::{self, environment, profile, Manifest, Origin};
 
 /// The options for the pipeline.
+#[derive(Default)]
 pub struct Options {
     /// Delete deployments after dev or debug mode is interrupted
     pub cleanup: bool,
@@ -36,112 +38,195 @@ pub struct Options {
     pub live: bool,
     /// Exit after one sync with live mode
     pub once: bool,
+    /// Deploy the partners of the character along with it
+    pub partners: bool,
+    /// The lint rules to skip before creating the playbook
+    pub allow: Vec<String>,
+    /// Suffix the character name with a slug of the git branch
+    pub per_branch: bool,
+    /// Run the hooks of the manifest
+    pub hooks: bool,
+    /// Wait for the actors to be ready for up to this long, and print their endpoints
+    pub timeout: Option<Duration>,
+    /// Keep streaming the events of the playbook once it is running
+    pub follow: bool,
+    /// The images to deploy instead of building the characters
+    pub images: Vec<ImageOverride>,
+    /// Upload the sources once for a static preview, which the server owns afterwards
+    pub preview: bool,
 }
 
-/// Create a playbook from the remote git repository.
-pub fn pull(ctx: &Context, repository: &str) -> Result<PlaybookSpec> {
-    create(
-        ctx.client.playbooks(),
-        PlaybookPayload {
-            title: "Untitled".to_string(),
-            description: "".to_string(),
-            preface: Preface::repository(repository),
-        },
-    )
+/// Read the manifest, which is a local file, an URL or a git reference, expand
+/// its placeholders and merge the active profiles and then the environment.
+/// Every command reading a manifest goes through here.
+pub async fn read(ctx: &Context, filename: &Option<String>, profiles: &[String]) -> Result<Manifest> {
+    effective(ctx, filename, profiles).await.map(|(manifest, _)| manifest)
 }
 
-/// Create a playbook from the remote registry.
-pub fn fetch(ctx: &Context, name: &str) -> Result<PlaybookSpec> {
-    create(
-        ctx.client.playbooks(),
-        PlaybookPayload {
-            title: "Untitled".to_string(),
-            description: "".to_string(),
-            preface: Preface::registry(name, "hub", "latest"),
-        },
-    )
-}
+/// Read the manifest like [`read`], along with the name of the environment applied.
+#[instrument(name = "manifest", skip_all, fields(path, environment))]
+pub async fn effective(
+    ctx: &Context,
+    filename: &Option<String>,
+    profiles: &[String],
+) -> Result<(Manifest, Option<String>)> {
+    let location = utils::resolve_manifest(filename.as_deref(), ctx.workspace.as_deref())?;
+    Span::current().record("path", &location);
+
+    let mut manifest = utils::read_manifest(&ctx.http, &location, ctx.remote_cache).await?;
+    manifest.content = ctx.variables.substitute(&manifest.content)?;
+    let context = ctx.context_name().await;
+    manifest.content = profile::apply(&manifest, profiles, context.as_deref(), |key| std::env::var(key).ok())?;
+
+    let (content, name) = environment::apply(&manifest, ctx.environment.as_deref(), context.as_deref())?;
+    manifest.content = content;
+    if let Some(name) = &name {
+        Span::current().record("environment", name);
+        info!("Using the {} environment", name);
+    }
 
-/// Create a playbook from the local manifest file.
-pub async fn load(ctx: &Context, filename: &Option<PathBuf>, once: bool) -> Result<PlaybookSpec> {
-    // load the character from the local character manifest.
-    let path = &filename.clone().unwrap_or(Finder::new().find().map_err(Errors::NotFoundManifest)?);
-    ctx.session.load(path).await?;
-
-    let manifest = ctx.session.character.read().await.clone().unwrap();
-    let character = CharacterSpec { live: true, once, ..CharacterSpec::from(&manifest) };
-
-    create(
-        ctx.client.playbooks(),
-        PlaybookPayload {
-            title: "Untitled".to_string(),
-            description: "".to_string(),
-            preface: Preface::manifest(&character),
-        },
-    )
+    Ok((manifest, name))
 }
 
-/// Create a playbook from the given payload.
-pub fn create(client: Playbooks, payload: PlaybookPayload) -> Result<PlaybookSpec> {
-    let playbook = client.create(payload).map_err(Errors::FailedCreatePlaybook)?;
+/// Run a pipeline.
+#[instrument(name = "pipeline", skip_all, fields(playbook_id = %playbook.id, live = options.live))]
+pub async fn run(ctx: &Arc<Context>, playbook: PlaybookSpec, options: Options) -> Result<()> {
+    let result = serve(ctx, playbook, &options).await;
 
-    info!("The playbook begins to create...");
-    debug!("The created playbook is:\n {:#?}", playbook);
+    // Cleanup the playbook if cleanup is enabled, also when interrupted before it was running.
+    if options.cleanup && matches!(result, Ok(()) | Err(Errors::Interrupted)) {
+        if let Err(err) = cleaner::try_cleanup_playbook(ctx).await {
+            error!("Failed to cleanup playbook: {:?}", err);
+        }
+    }
 
-    Ok(playbook)
+    result
 }
 
-/// Run a pipeline.
-pub async fn run(ctx: &Arc<Context>, playbook: PlaybookSpec, options: Options) -> Result<()> {
+/// Sync and run the playbook, until shutdown unless it is run once.
+async fn serve(ctx: &Arc<Context>, playbook: PlaybookSpec, options: &Options) -> Result<()> {
     // wait playbook resolve finished.
-    sleep(Duration::from_secs(10)).await;
+    tokio::select! {
+        _ = sleep(Duration::from_secs(10)) => {}
+        _ = ctx.shutdown.triggered() => return Err(Errors::Interrupted),
+    }
 
-    let playbook = ctx.client.playbooks().get(&playbook.id).map_err(Errors::ClientError)?;
+    let path = format!("/playbooks/{}", playbook.id);
+    let playbook = ctx.recorder.call_async("GET", &path, ctx.playbooks().get_async(&playbook.id)).await?;
     ctx.session.playbook.write().await.replace(playbook.clone());
 
     let pid = Arc::new(playbook.id.clone());
     let name = Arc::new(lead_name(&playbook).ok_or(Errors::InvalidCharacter)?);
 
-    // Initial sync the full sources into the server.
-    if options.live {
+    // Initial sync the full sources into the server, the server clones the
+    // sources of the previews from git itself.
+    if options.live || (options.preview && !git_sourced(ctx).await) {
+        hook(ctx, Event::PreSync, Some(pid.as_str())).await?;
         info!("Syncing the full sources into the server...");
-        let workspace = ctx.session.workspace.read().await.clone().unwrap();
-        utils::upload(&ctx.client.actors(), &pid, &name, &workspace)?;
+        let workspace = workspace(ctx).await?;
+        let (ctx1, pid1, name1) = (ctx.clone(), pid.clone(), name.clone());
+        // The upload blocks, so it runs on its own thread to be abandoned on shutdown.
+        let upload = tokio::task::spawn_blocking(move || {
+            SyncEngine::new(ServerApi::new(&ctx1), &workspace, &pid1, &name1).initial_upload()
+        });
+        tokio::select! {
+            joined = upload => joined.unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))?,
+            _ = ctx.shutdown.triggered() => return Err(Errors::Interrupted),
+        }
+        hook(ctx, Event::PostSync, Some(pid.as_str())).await?;
     }
 
     // Watch file changes and sync the changed files.
     if !options.once {
-        let client1 = ctx.client.clone();
+        let ctx1 = ctx.clone();
         let pid1 = pid.clone();
         let name1 = name.clone();
-        let workspace = ctx.session.workspace.read().await.clone().unwrap();
+        let workspace = workspace(ctx).await?;
 
         tokio::spawn(async move {
-            if let Err(err) = watcher::watch(&workspace, &client1, &pid1, &name1).await {
+            if let Err(err) = watcher::watch(&workspace, &ctx1, &pid1, &name1).await {
                 error!("The watcher is stopped: {:?}", err);
             }
         });
     }
 
+    // Wait for the actors to be ready, and print the endpoints the server exposes them on.
+    if let Some(timeout) = options.timeout {
+        let actors = readiness::until_ready(ctx, &pid, timeout).await?;
+        for endpoint in actors.iter().flat_map(readiness::endpoints) {
+            ctx.stdout.println(endpoint)?;
+        }
+    }
+
     info!("The playbook is running...");
+    hook(ctx, Event::PostReady, Some(pid.as_str())).await?;
 
-    // Receive the log stream from the server.
+    // Receive the log stream from the server, the events along with it when
+    // following, or keep watching until shutdown.
     if options.tail {
-        if let Err(err) = logger::tail(&ctx.client, &pid, &name).await {
+        if options.follow {
+            let (ctx1, pid1) = (ctx.clone(), pid.clone());
+            tokio::spawn(async move { follow(&ctx1, &pid1).await });
+        }
+        let options = logger::Options { timestamps: timestamps(ctx), ..logger::Options::default() };
+        if let Err(err) = logger::tail(ctx, &pid, &name, &options).await {
             error!("The log stream is stopped: {:?}", err);
         }
+    } else if options.follow {
+        follow(ctx, &pid).await;
+    } else if !options.once {
+        ctx.shutdown.triggered().await;
     }
 
-    // Cleanup the playbook if cleanup is enabled.
-    if options.cleanup {
-        if let Err(err) = cleaner::try_cleanup_playbook(ctx).await {
-            error!("Failed to cleanup playbook: {:?}", err);
+    if !options.once {
+        if let Err(err) = hook(ctx, Event::OnDevExit, Some(pid.as_str())).await {
+            error!("{}", err);
         }
     }
 
     Ok(())
 }
 
+/// Whether the manifest was read from a git reference rather than the local workspace.
+async fn git_sourced(ctx: &Context) -> bool {
+    matches!(ctx.session.origin.read().await.as_ref(), Some(Origin::Git { .. }))
+}
+
+/// Run the hooks of the event, in the workspace of the manifest.
+pub async fn hook(ctx: &Context, event: Event, playbook_id: Option<&str>) -> Result<()> {
+    let env = Env {
+        dir: ctx.session.workspace.read().await.clone(),
+        playbook_id: playbook_id.map(String::from),
+        context: ctx.context_name().await,
+    };
+    hooks::run(&*ctx.session.hooks.read().await, event, &env).await
+}
+
+/// Stream the events of the playbook until shutdown.
+async fn follow(ctx: &Context, pid: &str) {
+    if let Err(err) = events::stream(ctx, pid, None, true, events::Format::Raw).await {
+        error!("The event stream is stopped: {:?}", err);
+    }
+}
+
+/// The timestamps of the log lines, as set by the global `--timestamps` flag.
+fn timestamps(ctx: &Context) -> logger::Timestamps {
+    match ctx.timestamps {
+        true => logger::Timestamps::Utc,
+        false => logger::Timestamps::Off,
+    }
+}
+
+/// The local workspace of the loaded manifest, remote manifests have none.
+async fn workspace(ctx: &Context) -> Result<PathBuf> {
+    if let Some(workspace) = ctx.session.workspace.read().await.clone() {
+        return Ok(workspace);
+    }
+
+    let origin = ctx.session.origin.read().await.as_ref().map(|o| o.to_string()).unwrap_or_default();
+    Err(Errors::NoLocalWorkspace(origin))
+}
+
 /// get lead character name based on preface type.
 fn lead_name(playbook: &PlaybookSpec) -> Option<String> {
     if playbook.preface.registry.is_some() || playbook.preface.manifest.is_some() {
@@ -0,0 +1,421 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use amp_client::playbooks::PlaybookPayload;
+use amp_common::resource::{CharacterSpec, PlaybookSpec, Preface};
+use amp_common::schema::Character;
+use serde_json::Value;
+use tracing::{debug, info, instrument, warn, Span};
+
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::lint;
+use crate::ops::hooks::{self, Event};
+use crate::ops::images::{self, ImageOverride};
+use crate::ops::metadata::{self, GitSource, Payload};
+use crate::ops::{partners, pipeline, playbooks, readiness, templates};
+use crate::utils::git::{self, Revision};
+use crate::utils::naming;
+
+/// The attempts at creating a playbook with an idempotency key.
+const CREATE_ATTEMPTS: usize = 3;
+
+/// Where a playbook is created from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// The manifest, a local file, an URL or a git reference, with the profiles to activate
+    Manifest { filename: Option<String>, profiles: Vec<String> },
+    /// A remote git repository, whose manifest the server reads itself
+    Repository(GitSource),
+    /// A character published to the registry
+    Registry(String),
+    /// A template of the index, or a direct reference to its manifest, see [`templates::resolve`]
+    Template(String),
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Source::Manifest { filename: None, profiles: vec![] }
+    }
+}
+
+/// The options for creating a playbook, whichever the command.
+#[derive(Clone, Debug, Default)]
+pub struct CreateOptions {
+    pub source: Source,
+    /// The title of the playbook, instead of the one named after the character
+    pub title: Option<String>,
+    /// The description of the playbook, instead of the one of the manifest
+    pub description: Option<String>,
+    /// Whether this character is live or not
+    pub live: bool,
+    /// Exit after one sync with live mode
+    pub once: bool,
+    /// Deploy the partners of the character along with it
+    pub partners: bool,
+    /// The lint rules to skip before creating the playbook
+    pub allow: Vec<String>,
+    /// Suffix the character name with a slug of the git branch
+    pub per_branch: bool,
+    /// Run the hooks of the manifest
+    pub hooks: bool,
+    /// The images to deploy instead of building the characters
+    pub images: Vec<ImageOverride>,
+    /// The server creates the playbook once for all the requests with the same key
+    pub idempotency_key: Option<String>,
+    /// Wait for the actors to be ready for up to this long
+    pub wait: Option<Duration>,
+}
+
+impl CreateOptions {
+    /// The options to create the playbook of the pipeline from the source.
+    pub fn new(source: Source, opt: &pipeline::Options) -> Self {
+        Self {
+            source,
+            live: opt.live,
+            once: opt.once,
+            partners: opt.partners,
+            allow: opt.allow.clone(),
+            per_branch: opt.per_branch,
+            hooks: opt.hooks,
+            images: opt.images.clone(),
+            ..Self::default()
+        }
+    }
+}
+
+/// The created playbook, along with its actors once ready if waited for.
+#[derive(Debug)]
+pub struct PlaybookHandle {
+    pub playbook: PlaybookSpec,
+    pub actors: Vec<Value>,
+}
+
+impl PlaybookHandle {
+    /// The endpoints the server exposes the ready actors on.
+    pub fn endpoints(&self) -> Vec<String> {
+        self.actors.iter().flat_map(readiness::endpoints).collect()
+    }
+}
+
+/// The playbook API of the server.
+pub trait PlaybookApi {
+    fn create(&self, payload: &Payload, idempotency_key: Option<&str>) -> impl Future<Output = Result<PlaybookSpec>>;
+
+    /// Wait for the actors of the playbook to be ready, and return them.
+    fn until_ready(&self, pid: &str, timeout: Duration) -> impl Future<Output = Result<Vec<Value>>>;
+}
+
+/// The playbook API of the server of the current context.
+pub struct ServerPlaybooks<'a> {
+    ctx: &'a Arc<Context>,
+}
+
+impl<'a> ServerPlaybooks<'a> {
+    pub fn new(ctx: &'a Arc<Context>) -> Self {
+        Self { ctx }
+    }
+}
+
+impl PlaybookApi for ServerPlaybooks<'_> {
+    async fn create(&self, payload: &Payload, idempotency_key: Option<&str>) -> Result<PlaybookSpec> {
+        submit(self.ctx, payload, idempotency_key).await
+    }
+
+    async fn until_ready(&self, pid: &str, timeout: Duration) -> Result<Vec<Value>> {
+        readiness::until_ready(self.ctx, pid, timeout).await
+    }
+}
+
+/// Create a playbook from the source of the options: read, validate and turn
+/// it into the payload, create the playbook and wait for its actors if asked to.
+pub async fn create(ctx: &Arc<Context>, opt: &CreateOptions) -> Result<PlaybookHandle> {
+    let payload = match &opt.source {
+        Source::Manifest { filename, profiles } => from_manifest(ctx, filename, profiles, opt).await?,
+        Source::Template(reference) => from_template(ctx, reference, opt).await?,
+        Source::Repository(source) => from_repository(source)?,
+        Source::Registry(name) => untitled(Preface::registry(name, "hub", "latest")),
+    };
+    pipeline::hook(ctx, Event::PreDeploy, None).await?;
+
+    let handle = create_with(&ServerPlaybooks::new(ctx), payload, opt).await?;
+    ctx.session.playbook.write().await.replace(handle.playbook.clone());
+    Ok(handle)
+}
+
+/// Create the playbook of the payload with the API, once the overrides of the
+/// options applied, and wait for its actors if asked to.
+#[instrument(name = "create_playbook", skip_all, fields(playbook_id))]
+pub async fn create_with(api: &impl PlaybookApi, mut payload: Payload, opt: &CreateOptions) -> Result<PlaybookHandle> {
+    if let Some(title) = &opt.title {
+        payload.playbook.title.clone_from(title);
+    }
+    if let Some(description) = &opt.description {
+        payload.playbook.description.clone_from(description);
+    }
+
+    let playbook = api.create(&payload, opt.idempotency_key.as_deref()).await?;
+    Span::current().record("playbook_id", &playbook.id);
+    info!("The playbook begins to create...");
+    debug!("The created playbook is:\n {:#?}", playbook);
+
+    let actors = match opt.wait {
+        Some(timeout) => api.until_ready(&playbook.id, timeout).await?,
+        None => vec![],
+    };
+    Ok(PlaybookHandle { playbook, actors })
+}
+
+/// Create a playbook from the given payload as is.
+pub async fn submit(ctx: &Context, payload: &Payload, idempotency_key: Option<&str>) -> Result<PlaybookSpec> {
+    let api = ctx.api().await;
+    let body = serde_json::to_value(payload).ok();
+    // The server creates the playbook once per key, so the request is safe to retry.
+    let attempts = if idempotency_key.is_some() { CREATE_ATTEMPTS } else { 1 };
+    let request = || playbooks::create_idempotent(&api, payload, idempotency_key);
+    ctx.recorder.mutate_retry_async("POST", "/playbooks", body, attempts, request).await
+}
+
+/// Read the manifest, check it against the lint rules, resolve its partners
+/// and load its character into the session.
+async fn from_manifest(
+    ctx: &Context,
+    filename: &Option<String>,
+    profiles: &[String],
+    opt: &CreateOptions,
+) -> Result<Payload> {
+    let mut manifest = pipeline::read(ctx, filename, profiles).await?;
+    lint::enforce(&manifest, &opt.allow)?;
+    let mut names = vec![];
+    if opt.partners {
+        let partners = partners::resolve(ctx, &manifest).await?;
+        names.extend(partners.iter().map(|p| p.name.clone()));
+        if !partners.is_empty() {
+            let names: Vec<String> = partners.iter().map(|p| format!("{} ({})", p.name, p.source)).collect();
+            info!("Deploying with {} partner(s): {}", partners.len(), names.join(", "));
+        }
+    } else {
+        manifest.content = partners::strip(&manifest)?;
+    }
+    let (hooks, content) = hooks::extract(&manifest)?;
+    manifest.content = content;
+    if opt.hooks {
+        *ctx.session.hooks.write().await = hooks;
+    }
+    let (description, metadata) = metadata::from_manifest(&manifest.content);
+    ctx.session.load(manifest).await?;
+
+    let manifest = ctx.session.character.read().await.clone().ok_or(Errors::InvalidCharacter)?;
+    let mut character = spec(&manifest, opt);
+    names.insert(0, character.meta.name.clone());
+    let images = images::resolve(&opt.images, &names)?;
+
+    let workspace = ctx.session.workspace.read().await.clone();
+    let revision = workspace.map(|w| Revision::detect(&w)).unwrap_or_default();
+    let title = naming::title(&character.meta.name, &revision);
+    if opt.per_branch {
+        match revision.branch.as_deref().and_then(naming::slug) {
+            Some(slug) => character.meta.name = naming::suffixed(&character.meta.name, &slug),
+            None => warn!("No git branch found for --per-branch, the character name is kept as is"),
+        }
+    }
+
+    let playbook =
+        PlaybookPayload { title, description: description.unwrap_or_default(), preface: Preface::manifest(&character) };
+    let images = (!images.is_empty()).then_some(images);
+    Ok(Payload { metadata, attributes: revision.attributes(), images, ..Payload::from(playbook) })
+}
+
+/// Fetch and validate the manifest of the template, the errors naming the stage that failed.
+async fn from_template(ctx: &Context, reference: &str, opt: &CreateOptions) -> Result<Payload> {
+    let manifest = templates::load(ctx, reference, &opt.allow).await?;
+    let character = ctx.session.character.read().await.clone().ok_or(Errors::InvalidCharacter)?;
+    let character = spec(&character, opt);
+    let (description, metadata) = metadata::from_manifest(&manifest.content);
+
+    let playbook = PlaybookPayload {
+        title: character.meta.name.clone(),
+        description: description.unwrap_or_default(),
+        preface: Preface::manifest(&character),
+    };
+    Ok(Payload { metadata, ..Payload::from(playbook) })
+}
+
+/// The payload of the playbooks whose characters the server resolves itself.
+/// The payload of the repository, pointing the server at the manifest
+/// instead of inlining it.
+fn from_repository(source: &GitSource) -> Result<Payload> {
+    git::validate_url(&source.url)?;
+    Ok(Payload { source: Some(source.clone()), ..untitled(Preface::repository(&source.url)) })
+}
+
+fn untitled(preface: Preface) -> Payload {
+    Payload::from(PlaybookPayload { title: "Untitled".to_string(), description: "".to_string(), preface })
+}
+
+/// The character to create the playbook with, live or not as set by the options.
+fn spec(character: &Character, opt: &CreateOptions) -> CharacterSpec {
+    CharacterSpec { live: opt.live, once: opt.once, ..CharacterSpec::from(character) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::client::Api;
+
+    /// Answers with the created playbook, or fails the creation if `fail`.
+    #[derive(Default)]
+    struct FakeApi {
+        fail: bool,
+        created: Mutex<Vec<(Value, Option<String>)>>,
+        waited: Mutex<Vec<(String, Duration)>>,
+    }
+
+    impl PlaybookApi for FakeApi {
+        async fn create(&self, payload: &Payload, idempotency_key: Option<&str>) -> Result<PlaybookSpec> {
+            let mut value = serde_json::to_value(payload).unwrap();
+            self.created.lock().unwrap().push((value.clone(), idempotency_key.map(String::from)));
+            if self.fail {
+                return Err(Errors::FailedCreatePlaybook("500 Internal Server Error".into()));
+            }
+            value["id"] = json!("42");
+            Ok(serde_json::from_value(value).unwrap())
+        }
+
+        async fn until_ready(&self, pid: &str, timeout: Duration) -> Result<Vec<Value>> {
+            self.waited.lock().unwrap().push((pid.to_string(), timeout));
+            Ok(vec![json!({"name": "web", "endpoints": ["https://web.example.com"]})])
+        }
+    }
+
+    fn payload() -> Payload {
+        untitled(Preface::registry("web", "hub", "latest"))
+    }
+
+    #[tokio::test]
+    async fn create_without_waiting() {
+        let api = FakeApi::default();
+        let handle = create_with(&api, payload(), &CreateOptions::default()).await.unwrap();
+
+        assert_eq!(handle.playbook.id, "42");
+        assert!(handle.endpoints().is_empty());
+        assert!(api.waited.lock().unwrap().is_empty());
+        assert_eq!(api.created.lock().unwrap()[0].1, None);
+    }
+
+    #[tokio::test]
+    async fn override_the_payload() {
+        let api = FakeApi::default();
+        let opt = CreateOptions {
+            title: Some("checkout".into()),
+            description: Some("The checkout of the shop".into()),
+            idempotency_key: Some("7f9c2ba4".into()),
+            ..CreateOptions::default()
+        };
+        let handle = create_with(&api, payload(), &opt).await.unwrap();
+        assert_eq!(handle.playbook.title, "checkout");
+
+        let created = api.created.lock().unwrap();
+        let (value, key) = &created[0];
+        assert_eq!(
+            (value["title"].as_str(), value["description"].as_str()),
+            (Some("checkout"), Some("The checkout of the shop"))
+        );
+        assert_eq!(key.as_deref(), Some("7f9c2ba4"));
+    }
+
+    #[tokio::test]
+    async fn wait_for_the_actors() {
+        let api = FakeApi::default();
+        let opt = CreateOptions { wait: Some(Duration::from_secs(60)), ..CreateOptions::default() };
+        let handle = create_with(&api, payload(), &opt).await.unwrap();
+
+        assert_eq!(handle.endpoints(), vec!["https://web.example.com"]);
+        assert_eq!(*api.waited.lock().unwrap(), vec![(String::from("42"), Duration::from_secs(60))]);
+    }
+
+    #[tokio::test]
+    async fn failed_creations_are_not_waited_for() {
+        let api = FakeApi { fail: true, ..FakeApi::default() };
+        let opt = CreateOptions { wait: Some(Duration::from_secs(60)), ..CreateOptions::default() };
+        let err = create_with(&api, payload(), &opt).await.unwrap_err();
+
+        assert!(matches!(err, Errors::FailedCreatePlaybook(_)));
+        assert!(api.waited.lock().unwrap().is_empty());
+    }
+
+    /// The value of the first `key` found in the JSON, depth first.
+    fn find<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+        match value {
+            Value::Object(map) => map.get(key).or_else(|| map.values().find_map(|v| find(v, key))),
+            Value::Array(list) => list.iter().find_map(|v| find(v, key)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn point_the_server_at_the_repository() {
+        let source = GitSource {
+            url: "https://github.com/amphitheatre-app/amp-example-go".into(),
+            reference: Some("v1.2.0".into()),
+            subpath: Some("services/api".into()),
+        };
+        let value = serde_json::to_value(from_repository(&source).unwrap()).unwrap();
+        assert_eq!(
+            value["source"],
+            json!({"url": "https://github.com/amphitheatre-app/amp-example-go", "ref": "v1.2.0", "subpath": "services/api"})
+        );
+        assert_eq!(value["preface"], serde_json::to_value(Preface::repository(&source.url)).unwrap());
+
+        let source = GitSource { url: "github.com/org/repo".into(), ..GitSource::default() };
+        assert!(matches!(from_repository(&source), Err(Errors::InvalidGitUrl(_))));
+    }
+
+    #[tokio::test]
+    async fn create_previews_without_live_sync() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/playbooks"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+
+        let character: Character = toml::from_str("[meta]\nname = \"web\"\nversion = \"0.1.0\"\n").unwrap();
+        let dev = CreateOptions { live: true, ..CreateOptions::default() };
+        let preview = CreateOptions { once: true, ..CreateOptions::default() };
+        for opt in [&dev, &preview] {
+            let playbook = PlaybookPayload {
+                title: "web".into(),
+                description: "".into(),
+                preface: Preface::manifest(&spec(&character, opt)),
+            };
+            let api = Api::new(reqwest::Client::new(), &server.uri(), None);
+            let _ = playbooks::create(&api, &Payload::from(playbook)).await;
+        }
+
+        let requests = server.received_requests().await.unwrap();
+        let lives: Vec<Option<Value>> =
+            requests.iter().map(|r| find(&serde_json::from_slice(&r.body).unwrap(), "live").cloned()).collect();
+        assert_eq!(lives, vec![Some(Value::Bool(true)), Some(Value::Bool(false))]);
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use amp_common::resource::PlaybookSpec;
+use reqwest::RequestBuilder;
+use serde_json::Value;
+
+use crate::client::{self, Api};
+use crate::errors::{Errors, Result};
+use crate::ops::metadata::{Metadata, Payload};
+
+/// The header naming the creation requests the server deduplicates.
+const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+
+/// Create a playbook from the enriched payload.
+pub async fn create(api: &Api, payload: &Payload) -> Result<PlaybookSpec> {
+    create_idempotent(api, payload, None).await
+}
+
+/// Create a playbook like [`create`], the server creating it once for all the
+/// requests sent with the same idempotency key.
+pub async fn create_idempotent(api: &Api, payload: &Payload, idempotency_key: Option<&str>) -> Result<PlaybookSpec> {
+    let mut request = api.post("/playbooks").json(payload);
+    if let Some(key) = idempotency_key {
+        request = request.header(IDEMPOTENCY_KEY, key);
+    }
+    let value = send(request).await.map_err(Errors::FailedCreatePlaybook)?;
+    serde_json::from_value(value).map_err(|e| Errors::FailedCreatePlaybook(e.to_string()))
+}
+
+/// Get the playbook along with its metadata.
+pub async fn get(api: &Api, pid: &str) -> Result<(Value, Metadata)> {
+    let value = send(api.get(&format!("/playbooks/{}", pid))).await.map_err(Errors::FailedFetchPlaybook)?;
+    let metadata = serde_json::from_value(value.clone()).unwrap_or_default();
+    Ok((value, metadata))
+}
+
+/// Update the playbook in place with the enriched payload.
+pub async fn update(api: &Api, id: &str, payload: &Payload) -> Result<PlaybookSpec> {
+    let request = api.patch(&format!("/playbooks/{}", id)).json(payload);
+    let value = send(request).await.map_err(Errors::FailedUpdatePlaybook)?;
+    serde_json::from_value(value).map_err(|e| Errors::FailedUpdatePlaybook(e.to_string()))
+}
+
+/// Change the title of the playbook, sending nothing but the title so the
+/// other fields are left as they are.
+pub async fn rename(api: &Api, id: &str, title: &str) -> Result<PlaybookSpec> {
+    let body = serde_json::json!({ "title": title });
+    let value =
+        send(api.patch(&format!("/playbooks/{}", id)).json(&body)).await.map_err(Errors::FailedUpdatePlaybook)?;
+    serde_json::from_value(value).map_err(|e| Errors::FailedUpdatePlaybook(e.to_string()))
+}
+
+pub(super) async fn send(request: RequestBuilder) -> std::result::Result<Value, String> {
+    let response = client::send(request).await.map_err(|e| e.to_string())?;
+    let response = response.error_for_status().map_err(|e| e.to_string())?;
+    response.json().await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use amp_client::playbooks::PlaybookPayload;
+    use amp_common::resource::Preface;
+    use reqwest::Client;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn payload() -> Payload {
+        Payload::from(PlaybookPayload {
+            title: "Untitled".to_string(),
+            description: "".to_string(),
+            preface: Preface::registry("demo", "hub", "latest"),
+        })
+    }
+
+    #[tokio::test]
+    async fn create_returns_server_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/playbooks"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = create(&Api::new(Client::new(), &server.uri(), None), &payload()).await.unwrap_err();
+        assert!(matches!(err, Errors::FailedCreatePlaybook(_)));
+    }
+
+    #[tokio::test]
+    async fn create_with_the_idempotency_key() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/playbooks"))
+            .and(header("idempotency-key", "7f9c2ba4"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let api = Api::new(Client::new(), &server.uri(), None);
+        let _ = create_idempotent(&api, &payload(), Some("7f9c2ba4")).await;
+    }
+
+    #[tokio::test]
+    async fn get_reads_metadata() {
+        let server = MockServer::start().await;
+        let body = json!({"id": "42", "title": "web", "repository": "https://github.com/org/web"});
+        Mock::given(method("GET"))
+            .and(path("/v1/playbooks/42"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .mount(&server)
+            .await;
+
+        let (value, metadata) = get(&Api::new(Client::new(), &server.uri(), None), "42").await.unwrap();
+        assert_eq!(value, body);
+        assert_eq!(metadata.repository.as_deref(), Some("https://github.com/org/web"));
+        assert_eq!(metadata.maintainers, None);
+    }
+
+    #[tokio::test]
+    async fn rename_sends_only_the_title() {
+        let server = MockServer::start().await;
+        Mock::given(method("PATCH"))
+            .and(path("/v1/playbooks/42"))
+            .and(body_json(json!({"title": "storefront"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "42", "title": "storefront"})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let playbook = rename(&Api::new(Client::new(), &server.uri(), None), "42", "storefront").await.unwrap();
+        assert_eq!(playbook.title, "storefront");
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use amp_common::resource::PlaybookSpec;
+
+use crate::client::Api;
+use crate::errors::Result;
+use crate::ops::listing::{self, RequestOptions};
+
+/// Polls the playbooks, keeping the last list while the server reports it unchanged.
+pub struct Poller {
+    options: RequestOptions,
+    playbooks: Vec<PlaybookSpec>,
+    /// The size of the last list received
+    size: usize,
+    /// The bytes received, and the ones spared by the unchanged lists
+    pub received: usize,
+    pub spared: usize,
+}
+
+impl Poller {
+    pub fn new(query: HashMap<String, String>) -> Self {
+        let options = RequestOptions { query, if_none_match: None };
+        Self { options, playbooks: vec![], size: 0, received: 0, spared: 0 }
+    }
+
+    /// Poll the list, returns whether it changed since the last poll.
+    pub async fn poll(&mut self, api: &Api) -> Result<bool> {
+        let response = listing::list::<PlaybookSpec>(api, &self.options).await?;
+        self.options.if_none_match = response.etag;
+        self.received += response.bytes;
+
+        match response.data {
+            Some(playbooks) => {
+                // Servers without ETags send the list every time, tell whether it changed.
+                let value = |playbooks: &Vec<PlaybookSpec>| serde_json::to_value(playbooks).ok();
+                let changed = self.size == 0 || value(&self.playbooks) != value(&playbooks);
+                self.size = response.bytes;
+                self.playbooks = playbooks;
+                Ok(changed)
+            }
+            None => {
+                self.spared += self.size;
+                Ok(false)
+            }
+        }
+    }
+
+    pub fn playbooks(&self) -> &[PlaybookSpec] {
+        &self.playbooks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amp_client::playbooks::PlaybookPayload;
+    use amp_common::resource::Preface;
+    use reqwest::Client;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::ops::metadata::Payload;
+
+    #[tokio::test]
+    async fn poll_with_etags() {
+        let server = MockServer::start().await;
+        let playbook = Payload::from(PlaybookPayload {
+            title: "Untitled".to_string(),
+            description: "The web frontend".to_string(),
+            preface: Preface::registry("demo", "hub", "latest"),
+        });
+        let mut body = serde_json::to_value(&playbook).unwrap();
+        body["id"] = json!("42");
+        let body = json!([body]);
+        Mock::given(method("GET"))
+            .and(path("/v1/playbooks"))
+            .and(header("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304).insert_header("ETag", "\"v1\""))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/playbooks"))
+            .respond_with(ResponseTemplate::new(200).insert_header("ETag", "\"v1\"").set_body_json(&body))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // A second poll for a minute, on an unchanged list.
+        let mut poller = Poller::new(HashMap::new());
+        let api = Api::new(Client::new(), &server.uri(), None);
+        assert!(poller.poll(&api).await.unwrap());
+        for _ in 1..60 {
+            assert!(!poller.poll(&api).await.unwrap());
+        }
+
+        let size = serde_json::to_vec(&body).unwrap().len();
+        assert_eq!(poller.playbooks().len(), 1);
+        assert_eq!(poller.playbooks()[0].id, "42");
+        assert_eq!(poller.received, size);
+        assert_eq!(poller.spared, 59 * size);
+    }
+
+    #[tokio::test]
+    async fn list_without_etag_support() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/playbooks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let mut poller = Poller::new(HashMap::new());
+        let api = Api::new(Client::new(), &server.uri(), None);
+        assert!(poller.poll(&api).await.unwrap());
+        assert!(!poller.poll(&api).await.unwrap());
+        assert_eq!(poller.spared, 0);
+    }
+}
@@ -0,0 +1,527 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::{self, Display};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+use tokio::time::{interval, sleep, Duration, Instant, MissedTickBehavior};
+use tracing::{debug, info};
+
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::actors::{self, State, Status};
+use crate::ops::events;
+use crate::progress::Progress;
+use crate::shutdown::Shutdown;
+
+/// The statuses of a ready playbook or actor. This is the one definition of
+/// ready, shared by every command waiting for one.
+const READY: [&str; 2] = ["running", "ready"];
+
+/// The statuses of a playbook or actor which won't become ready.
+const FAILED: [&str; 2] = ["failed", "error"];
+
+/// The statuses of a stopped actor.
+const STOPPED: [&str; 3] = ["stopped", "terminated", "completed"];
+
+/// How often the actors are polled while waiting for them.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+pub fn is_ready(state: &State) -> bool {
+    READY.contains(&state.status.as_str())
+}
+
+pub fn is_failed(state: &State) -> bool {
+    FAILED.contains(&state.status.as_str())
+}
+
+pub fn is_stopped(state: &State) -> bool {
+    STOPPED.contains(&state.status.as_str())
+}
+
+/// What the playbook became while waiting for it.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Running,
+    /// Failed, with the error message of the playbook if any
+    Failed(Option<String>),
+}
+
+/// Poll the state of the playbook every `every` until it is running or failed,
+/// calling `tick` with the state after each poll. Fails with
+/// [`Errors::PlaybookNotReady`] once the timeout elapsed, and with
+/// [`Errors::Interrupted`] once the shutdown is triggered.
+pub async fn wait<F, Fut, T>(
+    shutdown: &Shutdown,
+    every: Duration,
+    timeout: Duration,
+    mut poll: F,
+    mut tick: T,
+) -> Result<Outcome>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<State>>,
+    T: FnMut(&State),
+{
+    let start = Instant::now();
+    let deadline = sleep(timeout);
+    tokio::pin!(deadline);
+
+    let mut ticker = interval(every);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            biased;
+            _ = shutdown.triggered() => return Err(Errors::Interrupted),
+            _ = &mut deadline => return Err(Errors::PlaybookNotReady { after_secs: start.elapsed().as_secs() }),
+            _ = ticker.tick() => {}
+        }
+
+        let state = poll().await?;
+        tick(&state);
+        if is_ready(&state) {
+            return Ok(Outcome::Running);
+        }
+        if is_failed(&state) {
+            return Ok(Outcome::Failed(state.error));
+        }
+    }
+}
+
+/// The state of the playbook from the raw states of its actors: failed as soon
+/// as one of them is, and running once all of them are ready.
+pub fn combine(actors: &[Value]) -> State {
+    let states: Vec<(&str, State)> =
+        actors.iter().map(|a| (a["name"].as_str().unwrap_or("unknown"), State::from(a))).collect();
+
+    if let Some((name, state)) = states.iter().find(|(_, s)| is_failed(s)) {
+        let error = format!("{}: {}", name, state.error.as_deref().unwrap_or("failed"));
+        return State { status: "failed".into(), error: Some(error), wait: None };
+    }
+    let ready = !states.is_empty() && states.iter().all(|(_, s)| is_ready(s));
+    let status = if ready { "running" } else { "pending" };
+
+    State { status: status.into(), ..State::default() }
+}
+
+/// Poll the actors of the playbook every `every` until all of them, or the
+/// one named, are running, calling `tick` with them after each poll. Fails as
+/// soon as one of them failed, and with [`Errors::StartTimeout`] once the
+/// timeout elapsed. Returns the actors.
+pub async fn until_running<F, Fut, T>(
+    shutdown: &Shutdown,
+    pid: &str,
+    actor: Option<&str>,
+    every: Duration,
+    timeout: Duration,
+    mut list: F,
+    mut tick: T,
+) -> Result<Vec<Value>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Vec<Value>>>,
+    T: FnMut(&[Value]),
+{
+    let latest: Mutex<Vec<Value>> = Mutex::default();
+    let poll = || {
+        let list = list();
+        let latest = &latest;
+        async move {
+            let list: Vec<Value> = list.await?.into_iter().filter(|a| actor.map_or(true, |n| a["name"] == n)).collect();
+            let state = combine(&list);
+            *latest.lock().unwrap() = list;
+            Ok(state)
+        }
+    };
+    let outcome = wait(shutdown, every, timeout, poll, |_| tick(&latest.lock().unwrap())).await;
+
+    match outcome {
+        Ok(Outcome::Running) => Ok(latest.into_inner().unwrap()),
+        Ok(Outcome::Failed(error)) => {
+            Err(Errors::PlaybookFailed(pid.to_string(), error.unwrap_or_else(|| "unknown error".into())))
+        }
+        Err(Errors::PlaybookNotReady { after_secs }) => {
+            let latest = latest.into_inner().unwrap();
+            let mut waiting: Vec<String> = latest
+                .iter()
+                .map(|a| (a["name"].as_str().unwrap_or("unknown"), State::from(a)))
+                .filter(|(_, state)| !is_ready(state))
+                .map(|(name, state)| format!("{} {}", name, state.status))
+                .collect();
+            if let Some(name) = actor.filter(|_| latest.is_empty()) {
+                waiting.push(format!("{} not found", name));
+            }
+            Err(Errors::StartTimeout(format!(
+                "{} after {} seconds, waiting for {}",
+                pid,
+                after_secs,
+                waiting.join(", ")
+            )))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Poll the actors of the playbook every `every` until all of them are
+/// stopped or gone, calling `tick` with the names of the ones still running
+/// after each poll. Fails with [`Errors::StopTimeout`] once the timeout elapsed.
+pub async fn until_stopped<F, Fut, T>(
+    shutdown: &Shutdown,
+    pid: &str,
+    every: Duration,
+    timeout: Duration,
+    mut list: F,
+    mut tick: T,
+) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Vec<Value>>>,
+    T: FnMut(&[String]),
+{
+    let start = Instant::now();
+    let deadline = sleep(timeout);
+    tokio::pin!(deadline);
+
+    let mut ticker = interval(every);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut running: Vec<String> = vec![];
+
+    loop {
+        tokio::select! {
+            biased;
+            _ = shutdown.triggered() => return Err(Errors::Interrupted),
+            _ = ticker.tick() => {}
+            _ = &mut deadline => {
+                let after_secs = start.elapsed().as_secs();
+                return Err(Errors::StopTimeout(format!("{} after {} seconds, still running: {}", pid, after_secs, running.join(", "))));
+            }
+        }
+
+        let actors = list().await?;
+        running = actors
+            .iter()
+            .filter(|a| !is_stopped(&State::from(*a)))
+            .map(|a| a["name"].as_str().unwrap_or("unknown").to_string())
+            .collect();
+        tick(&running);
+        if running.is_empty() {
+            return Ok(());
+        }
+    }
+}
+
+/// The URLs the server exposes the actor on, either strings or objects with
+/// a `url`, in `endpoints` or `status.endpoints`.
+pub fn endpoints(actor: &Value) -> Vec<String> {
+    let list = actor.get("endpoints").or_else(|| actor["status"].get("endpoints")).and_then(Value::as_array);
+    let url = |e: &Value| e.as_str().or_else(|| e.get("url").and_then(Value::as_str)).map(String::from);
+    list.map(|l| l.iter().filter_map(url).collect()).unwrap_or_default()
+}
+
+/// A step of the build or deploy of an actor, read leniently from the data of
+/// an event with a `phase` or a `stage`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Step {
+    pub actor: Option<String>,
+    pub phase: String,
+    pub message: Option<String>,
+}
+
+impl Step {
+    pub fn parse(data: &str) -> Option<Self> {
+        let event: Value = serde_json::from_str(data).ok()?;
+        let string = |keys: &[&str]| keys.iter().find_map(|k| event.get(*k).and_then(Value::as_str)).map(String::from);
+
+        Some(Self {
+            actor: string(&["actor", "character", "name"]),
+            phase: string(&["phase", "stage"])?.to_lowercase(),
+            message: string(&["message", "reason"]),
+        })
+    }
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(actor) = &self.actor {
+            write!(f, "{}: ", actor)?;
+        }
+        write!(f, "{}", self.phase)?;
+        if let Some(message) = &self.message {
+            write!(f, " ({})", message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Wait until every actor of the playbook is ready, showing the steps of the
+/// build and deploy as received from the event stream. Returns the actors.
+pub async fn until_ready(ctx: &Arc<Context>, pid: &str, timeout: Duration) -> Result<Vec<Value>> {
+    let api = ctx.api().await;
+    let spinner = Progress::spinner("Waiting for the actors to be ready...");
+    let phase: Arc<Mutex<Option<String>>> = Arc::default();
+
+    let follower = {
+        let (ctx, pid, spinner, phase) = (ctx.clone(), pid.to_string(), spinner.clone(), phase.clone());
+        tokio::spawn(async move {
+            let sink = |data: &str| {
+                if let Some(step) = Step::parse(data) {
+                    spinner.suspend(|| info!("{}", step));
+                    spinner.set_message(format!("Waiting for the actors to be ready, {}...", step.phase));
+                    phase.lock().unwrap().replace(step.phase);
+                }
+                Ok(())
+            };
+            if let Err(err) = events::follow(&ctx, &pid, sink).await {
+                debug!("The event stream is stopped: {}", err);
+            }
+        })
+    };
+
+    let latest: Mutex<Vec<Value>> = Mutex::default();
+    let poll = || async {
+        let list = actors::list(&api, pid, Status::All).await?;
+        let state = combine(&list);
+        *latest.lock().unwrap() = list;
+        Ok(state)
+    };
+    let outcome = wait(&ctx.shutdown, POLL_INTERVAL, timeout, poll, |_| {}).await;
+    follower.abort();
+    spinner.finish();
+
+    match outcome {
+        Ok(Outcome::Running) => Ok(latest.into_inner().unwrap()),
+        Ok(Outcome::Failed(error)) => {
+            let error = match error {
+                Some(error) => Some(error),
+                None => events::last_error(ctx, pid).await,
+            };
+            Err(Errors::PlaybookFailed(pid.to_string(), error.unwrap_or_else(|| "unknown error".into())))
+        }
+        Err(Errors::PlaybookNotReady { after_secs }) => {
+            let phase = phase.lock().unwrap().clone().unwrap_or_else(|| "pending".into());
+            Err(Errors::ActorsNotReady { after_secs, phase })
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::client::Api;
+
+    fn state(status: &str, error: Option<&str>) -> State {
+        State { status: status.into(), error: error.map(String::from), wait: None }
+    }
+
+    /// Wait on the scripted states, returning the outcome and the number of polls.
+    async fn script(states: Vec<State>, timeout: Duration) -> (Result<Outcome>, usize) {
+        let states = RefCell::new(states.into_iter());
+        let mut polls = 0;
+        let every = Duration::from_millis(5);
+        let poll = || {
+            let state = states.borrow_mut().next().unwrap_or_else(|| state("pending", None));
+            async move { Ok(state) }
+        };
+        let outcome = wait(&Shutdown::default(), every, timeout, poll, |_| polls += 1).await;
+        (outcome, polls)
+    }
+
+    #[tokio::test]
+    async fn wait_until_running() {
+        let states = vec![state("pending", None), state("pending", None), state("running", None)];
+        let (outcome, polls) = script(states, Duration::from_secs(5)).await;
+        assert_eq!(outcome.unwrap(), Outcome::Running);
+        assert_eq!(polls, 3);
+    }
+
+    #[tokio::test]
+    async fn report_the_failure() {
+        let states = vec![state("pending", None), state("failed", Some("image pull backoff"))];
+        let (outcome, _) = script(states, Duration::from_secs(5)).await;
+        assert_eq!(outcome.unwrap(), Outcome::Failed(Some("image pull backoff".into())));
+    }
+
+    #[tokio::test]
+    async fn stop_waiting_on_shutdown() {
+        let shutdown = Shutdown::default();
+        shutdown.trigger(crate::shutdown::Signal::Interrupt);
+        let poll = || async { Ok(state("pending", None)) };
+        let outcome = wait(&shutdown, Duration::from_millis(5), Duration::from_secs(5), poll, |_| {}).await;
+        assert!(matches!(outcome, Err(Errors::Interrupted)));
+    }
+
+    /// Serve the actors of the playbook 42 in the given states, one response per poll.
+    async fn transitions(server: &MockServer, states: &[serde_json::Value]) {
+        for (i, actors) in states.iter().enumerate() {
+            let mock = Mock::given(method("GET")).and(path("/v1/actors/42"));
+            let mock = mock.respond_with(ResponseTemplate::new(200).set_body_json(actors));
+            match i + 1 == states.len() {
+                true => mock.mount(server).await,
+                false => mock.up_to_n_times(1).mount(server).await,
+            }
+        }
+    }
+
+    async fn start(server: &MockServer, actor: Option<&str>, timeout: Duration) -> (Result<Vec<Value>>, Vec<String>) {
+        let api = Api::new(reqwest::Client::new(), &server.uri(), None);
+        let list = || actors::list(&api, "42", Status::All);
+        let mut ticks = vec![];
+        let tick =
+            |actors: &[Value]| ticks.push(actors.iter().map(|a| State::from(a).status).collect::<Vec<_>>().join(","));
+        let every = Duration::from_millis(5);
+        let result = until_running(&Shutdown::default(), "42", actor, every, timeout, list, tick).await;
+        (result, ticks)
+    }
+
+    #[tokio::test]
+    async fn wait_until_the_actors_are_running() {
+        let server = MockServer::start().await;
+        transitions(
+            &server,
+            &[
+                json!([{"name": "web", "status": "Pending"}, {"name": "db", "status": "Pending"}]),
+                json!([{"name": "web", "status": "Building"}, {"name": "db", "status": "Running"}]),
+                json!([{"name": "web", "status": "Running"}, {"name": "db", "status": "Running"}]),
+            ],
+        )
+        .await;
+
+        let (result, ticks) = start(&server, None, Duration::from_secs(5)).await;
+        assert_eq!(result.unwrap().len(), 2);
+        assert_eq!(ticks, vec!["pending,pending", "building,running", "running,running"]);
+    }
+
+    #[tokio::test]
+    async fn wait_for_a_single_actor() {
+        let server = MockServer::start().await;
+        transitions(
+            &server,
+            &[
+                json!([{"name": "web", "status": "Pending"}, {"name": "db", "status": "Running"}]),
+                json!([{"name": "web", "status": "Pending"}, {"name": "db", "status": "Failed"}]),
+            ],
+        )
+        .await;
+
+        let (result, ticks) = start(&server, Some("db"), Duration::from_secs(5)).await;
+        assert_eq!(result.unwrap()[0]["name"], "db");
+        assert_eq!(ticks, vec!["running"]);
+
+        let (result, _) = start(&server, Some("web"), Duration::from_millis(50)).await;
+        let err = result.unwrap_err();
+        assert!(matches!(&err, Errors::StartTimeout(m) if m.ends_with("waiting for web pending")), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn stop_waiting_once_an_actor_failed() {
+        let server = MockServer::start().await;
+        let failed = json!({"name": "db", "status": {"state": "Failed", "message": "image pull backoff"}});
+        transitions(&server, &[json!([{"name": "web", "status": "Pending"}, failed])]).await;
+
+        let (result, _) = start(&server, None, Duration::from_secs(5)).await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            Errors::PlaybookFailed("42".into(), "db: image pull backoff".into()).to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn wait_until_the_actors_are_stopped() {
+        let server = MockServer::start().await;
+        transitions(
+            &server,
+            &[
+                json!([{"name": "web", "status": "Running"}, {"name": "db", "status": "Running"}]),
+                json!([{"name": "web", "status": "Stopped"}, {"name": "db", "status": "Terminating"}]),
+                json!([{"name": "web", "status": "Stopped"}]),
+            ],
+        )
+        .await;
+
+        let api = Api::new(reqwest::Client::new(), &server.uri(), None);
+        let list = || actors::list(&api, "42", Status::All);
+        let mut counts = vec![];
+        let every = Duration::from_millis(5);
+        let tick = |running: &[String]| counts.push(running.len());
+        until_stopped(&Shutdown::default(), "42", every, Duration::from_secs(5), list, tick).await.unwrap();
+        assert_eq!(counts, vec![2, 1, 0]);
+    }
+
+    #[tokio::test]
+    async fn time_out_on_actors_never_stopping() {
+        let server = MockServer::start().await;
+        transitions(&server, &[json!([{"name": "web", "status": "Stopped"}, {"name": "db", "status": "Running"}])])
+            .await;
+
+        let api = Api::new(reqwest::Client::new(), &server.uri(), None);
+        let list = || actors::list(&api, "42", Status::All);
+        let every = Duration::from_millis(5);
+        let err = until_stopped(&Shutdown::default(), "42", every, Duration::from_millis(50), list, |_| {})
+            .await
+            .unwrap_err();
+        assert!(matches!(&err, Errors::StopTimeout(m) if m.ends_with("still running: db")), "{}", err);
+    }
+
+    #[test]
+    fn combine_the_actor_states() {
+        let ready = json!({"name": "web", "status": "Running"});
+        let pending = json!({"name": "db", "status": {"phase": "Pending"}});
+        let failed = json!({"name": "api", "status": {"state": "Failed", "message": "image pull backoff"}});
+
+        assert_eq!(combine(&[]).status, "pending");
+        assert_eq!(combine(&[ready.clone(), pending.clone()]).status, "pending");
+        assert_eq!(combine(&[ready.clone(), json!({"name": "db", "status": "Ready"})]).status, "running");
+
+        let state = combine(&[ready, pending, failed]);
+        assert_eq!(state.status, "failed");
+        assert_eq!(state.error.as_deref(), Some("api: image pull backoff"));
+    }
+
+    #[test]
+    fn read_the_endpoints() {
+        let actor = json!({"endpoints": ["https://web.example.com", {"url": "tcp://web:5432"}, 42]});
+        assert_eq!(endpoints(&actor), vec!["https://web.example.com", "tcp://web:5432"]);
+
+        let actor = json!({"status": {"state": "Running", "endpoints": [{"url": "https://api.example.com"}]}});
+        assert_eq!(endpoints(&actor), vec!["https://api.example.com"]);
+        assert!(endpoints(&json!({"name": "db"})).is_empty());
+    }
+
+    #[test]
+    fn parse_the_steps() {
+        let step =
+            Step::parse(r#"{"actor": "web", "phase": "Building", "message": "pulling the base image"}"#).unwrap();
+        assert_eq!(step.to_string(), "web: building (pulling the base image)");
+        assert_eq!(Step::parse(r#"{"stage": "deploy"}"#).unwrap().to_string(), "deploy");
+        assert_eq!(Step::parse(r#"{"message": "no phase"}"#), None);
+        assert_eq!(Step::parse("plain text"), None);
+    }
+
+    #[tokio::test]
+    async fn time_out_while_pending() {
+        let (outcome, polls) = script(vec![], Duration::from_millis(30)).await;
+        assert!(matches!(outcome, Err(Errors::PlaybookNotReady { after_secs: 0 })));
+        assert!(polls > 1);
+    }
+}
@@ -0,0 +1,150 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde_json::Value;
+
+use crate::errors::{Errors, Result};
+
+/// Formats the test results among the playbook events as TAP version 13.
+///
+/// The events are JSON objects, the TAP fields are read from their `metadata`:
+/// a result has a `test` description, an `ok` boolean and an optional
+/// `directive` (`TODO` or `SKIP`) with a `reason`, while the plan comes from
+/// the event with the `total` count of tests. Other events are left out.
+#[derive(Debug, Default)]
+pub struct Tap {
+    started: bool,
+    planned: bool,
+    count: u64,
+}
+
+impl Tap {
+    /// The TAP lines for the event data, if any.
+    pub fn format(&mut self, data: &str) -> Result<Vec<String>> {
+        let event: Value =
+            serde_json::from_str(data).map_err(|e| Errors::TapFormatFailed(format!("invalid event: {}", e)))?;
+        let Some(metadata) = event.get("metadata").and_then(Value::as_object) else {
+            return Ok(vec![]);
+        };
+
+        let mut lines = vec![];
+        if let Some(total) = metadata.get("total") {
+            let total = total.as_u64().ok_or_else(|| invalid("total", total))?;
+            if !self.planned {
+                self.planned = true;
+                lines.push(format!("1..{}", total));
+            }
+        }
+
+        if let Some(test) = metadata.get("test") {
+            let description = test.as_str().ok_or_else(|| invalid("test", test))?;
+            let ok = match metadata.get("ok") {
+                Some(ok) => ok.as_bool().ok_or_else(|| invalid("ok", ok))?,
+                None => return Err(Errors::TapFormatFailed(format!("missing ok for test {:?}", description))),
+            };
+
+            self.count += 1;
+            let mut line = format!("{} {} - {}", if ok { "ok" } else { "not ok" }, self.count, escape(description));
+            if let Some(directive) = metadata.get("directive") {
+                let directive = match directive.as_str().map(str::to_ascii_uppercase).as_deref() {
+                    Some("TODO") => "TODO",
+                    Some("SKIP") => "SKIP",
+                    _ => return Err(invalid("directive", directive)),
+                };
+                line.push_str(" # ");
+                line.push_str(directive);
+                if let Some(reason) = metadata.get("reason").and_then(Value::as_str) {
+                    line.push(' ');
+                    line.push_str(&escape(reason));
+                }
+            }
+            lines.push(line);
+        }
+
+        if !lines.is_empty() && !self.started {
+            self.started = true;
+            lines.insert(0, "TAP version 13".to_string());
+        }
+        Ok(lines)
+    }
+
+    /// The trailing plan, counted from the results when no event carried the
+    /// total. None if there were no test results at all.
+    pub fn finish(&self) -> Option<String> {
+        (self.count > 0 && !self.planned).then(|| format!("1..{}", self.count))
+    }
+}
+
+fn invalid(field: &str, value: &Value) -> Errors {
+    Errors::TapFormatFailed(format!("invalid {}: {}", field, value))
+}
+
+/// Escape the `#` and `\` of the descriptions, and keep them on one line.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('#', "\\#").replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/events").join(name);
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    fn format_all(stream: &str) -> Result<String> {
+        let mut tap = Tap::default();
+        let mut lines = vec![];
+        for data in stream.lines().filter(|l| !l.trim().is_empty()) {
+            lines.extend(tap.format(data)?);
+        }
+        lines.extend(tap.finish());
+        Ok(lines.iter().map(|l| format!("{}\n", l)).collect())
+    }
+
+    #[test]
+    fn format_test_results() {
+        assert_eq!(format_all(&fixture("tests.jsonl")).unwrap(), fixture("tests.tap"));
+    }
+
+    #[test]
+    fn plan_at_the_end_without_total() {
+        let stream = r#"{"message":"a","metadata":{"test":"a","ok":true}}
+{"message":"b","metadata":{"test":"b","ok":false,"directive":"skip","reason":"no # db"}}"#;
+        let expected = "TAP version 13\nok 1 - a\nnot ok 2 - b # SKIP no \\# db\n1..2\n";
+        assert_eq!(format_all(stream).unwrap(), expected);
+    }
+
+    #[test]
+    fn skip_other_events() {
+        assert_eq!(format_all(r#"{"message":"Resolving"}"#).unwrap(), "");
+    }
+
+    #[test]
+    fn reject_invalid_events() {
+        for data in [
+            "not json",
+            r#"{"metadata":{"test":"a"}}"#,
+            r#"{"metadata":{"test":"a","ok":"yes"}}"#,
+            r#"{"metadata":{"test":"a","ok":true,"directive":"LATER"}}"#,
+            r#"{"metadata":{"total":-1}}"#,
+        ] {
+            let err = Tap::default().format(data).unwrap_err();
+            assert!(matches!(err, Errors::TapFormatFailed(_)), "{:?}", data);
+        }
+    }
+}
@@ -0,0 +1,276 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::client::{self, Api};
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::lint;
+use crate::ops::hooks;
+use crate::ops::playbook::{self, CreateOptions, Source};
+use crate::prompt::Prompter;
+use crate::utils::{self, Manifest};
+
+/// A `{{NAME}}` placeholder of a template, filled in when the manifest is initialized.
+static PLACEHOLDER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").unwrap());
+
+/// A template of the index, the manifest of a curated example playbook.
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+pub struct Template {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// The git URL of the repository containing the manifest, or the URL of the manifest
+    #[serde(alias = "url", alias = "repository")]
+    pub location: String,
+}
+
+/// The stage a template failed at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Fetch,
+    Validation,
+    Deployment,
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stage::Fetch => write!(f, "fetch"),
+            Stage::Validation => write!(f, "validation"),
+            Stage::Deployment => write!(f, "deployment"),
+        }
+    }
+}
+
+/// List the templates of the index of the server.
+pub async fn index(api: &Api) -> Result<Vec<Template>> {
+    let response = client::send(api.get("/templates")).await.and_then(|r| r.error_for_status());
+    let response = response.map_err(|e| Errors::FailedListTemplates(e.to_string()))?;
+    response.json().await.map_err(|e| Errors::FailedListTemplates(e.to_string()))
+}
+
+/// The manifest location of the reference if it names one directly: git
+/// references and manifest URLs are kept, and git URLs read the `.amp.toml`
+/// of the default branch. Returns None for the names of the index.
+pub fn locate(reference: &str) -> Option<String> {
+    let url = reference.starts_with("https://") || reference.starts_with("http://");
+    if reference.contains('#') || (url && utils::yaml::is_yaml(reference)) || (url && reference.ends_with(".toml")) {
+        return Some(reference.to_string());
+    }
+    if url || reference.starts_with("git@") || reference.ends_with(".git") {
+        return Some(format!("{}#", reference));
+    }
+
+    None
+}
+
+/// Resolve the reference to the location of its manifest, looking the names up in the index.
+pub async fn resolve(api: &Api, reference: &str) -> Result<String> {
+    if let Some(location) = locate(reference) {
+        return Ok(location);
+    }
+
+    let templates = index(api).await?;
+    match templates.iter().find(|t| t.name == reference) {
+        Some(template) => Ok(locate(&template.location).unwrap_or_else(|| template.location.clone())),
+        None => {
+            let names: Vec<&str> = templates.iter().map(|t| t.name.as_str()).collect();
+            Err(Errors::UnknownTemplate(reference.to_string(), names.join(", ")))
+        }
+    }
+}
+
+/// Create a non-live playbook from the template, wait for its actors to be
+/// ready and return their endpoints. The errors name the stage that failed.
+pub async fn create(ctx: &Arc<Context>, reference: &str, allow: &[String], timeout: Duration) -> Result<Vec<String>> {
+    let opt = CreateOptions {
+        source: Source::Template(reference.to_string()),
+        once: true,
+        allow: allow.to_vec(),
+        wait: Some(timeout),
+        ..CreateOptions::default()
+    };
+    match playbook::create(ctx, &opt).await {
+        Ok(handle) => Ok(handle.endpoints()),
+        Err(err @ Errors::TemplateFailed(..)) => Err(err),
+        Err(err) => Err(Errors::TemplateFailed(Stage::Deployment, reference.to_string(), err.to_string())),
+    }
+}
+
+/// Fetch and validate the manifest of the template, and load its character
+/// into the session. The errors name the stage that failed.
+pub async fn load(ctx: &Context, reference: &str, allow: &[String]) -> Result<Manifest> {
+    let failed =
+        |stage: Stage| move |err: Errors| Errors::TemplateFailed(stage, reference.to_string(), err.to_string());
+
+    let manifest = fetch(ctx, reference).await.map_err(failed(Stage::Fetch))?;
+    validate(ctx, manifest, allow).await.map_err(failed(Stage::Validation))
+}
+
+async fn fetch(ctx: &Context, reference: &str) -> Result<Manifest> {
+    let location = resolve(&ctx.api().await, reference).await?;
+    info!("Fetching the template from {}", location);
+    utils::read_manifest(&ctx.http, &location, ctx.remote_cache).await
+}
+
+/// The unique names of the placeholders of the template, in order of appearance.
+pub fn placeholders(content: &str) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for captures in PLACEHOLDER.captures_iter(content) {
+        if !names.iter().any(|n| n == &captures[1]) {
+            names.push(captures[1].to_string());
+        }
+    }
+    names
+}
+
+/// Fill in the placeholders of the template, the given values take precedence
+/// over the prompted ones. Empty values are asked again, and a placeholder
+/// left unfilled without a terminal fails the whole template.
+pub fn fill(content: &str, given: &[(String, String)], prompter: &dyn Prompter) -> Result<String> {
+    let mut values: HashMap<String, String> = given.iter().filter(|(_, v)| !v.is_empty()).cloned().collect();
+    for name in placeholders(content) {
+        if values.contains_key(&name) {
+            continue;
+        }
+        let value = loop {
+            let value = match prompter.text(&format!("{}:", name), None) {
+                Err(Errors::PromptRequired(_)) => return Err(Errors::TemplateMissingVariable(name)),
+                result => result?,
+            };
+            if !value.trim().is_empty() {
+                break value;
+            }
+            warn!("{} can't be empty", name);
+        };
+        values.insert(name, value);
+    }
+
+    Ok(PLACEHOLDER.replace_all(content, |captures: &Captures| values[&captures[1]].clone()).into_owned())
+}
+
+/// Expand the `--set` variables of the manifest, and check it against the lint rules.
+async fn validate(ctx: &Context, mut manifest: Manifest, allow: &[String]) -> Result<Manifest> {
+    manifest.content = ctx.variables.substitute(&manifest.content)?;
+    lint::enforce(&manifest, allow)?;
+
+    let (_, content) = hooks::extract(&manifest)?;
+    if content != manifest.content {
+        warn!("The hooks of the template are not run");
+        manifest.content = content;
+    }
+    ctx.session.load(manifest.clone()).await?;
+
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Client;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::prompt::{Answer, ScriptedPrompter};
+
+    #[test]
+    fn locate_direct_references() {
+        let cases = [
+            ("https://github.com/org/demo", Some("https://github.com/org/demo#")),
+            ("git@github.com:org/demo.git", Some("git@github.com:org/demo.git#")),
+            ("https://github.com/org/demo#v1:web/.amp.toml", Some("https://github.com/org/demo#v1:web/.amp.toml")),
+            ("https://example.com/demo/.amp.yaml", Some("https://example.com/demo/.amp.yaml")),
+            ("https://example.com/demo.toml", Some("https://example.com/demo.toml")),
+            ("hello-world", None),
+        ];
+        for (reference, expected) in cases {
+            assert_eq!(locate(reference).as_deref(), expected, "{}", reference);
+        }
+    }
+
+    async fn server() -> MockServer {
+        let server = MockServer::start().await;
+        let body = serde_json::json!([
+            { "name": "hello-world", "description": "A static page", "url": "https://github.com/org/hello" },
+            { "name": "todo", "location": "https://github.com/org/todo#main:api/.amp.toml" },
+        ]);
+        Mock::given(method("GET"))
+            .and(path("/v1/templates"))
+            .and(header("authorization", "Bearer secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn resolve_names_from_the_index() {
+        let server = server().await;
+        let api = Api::new(Client::new(), &server.uri(), Some("secret"));
+        let resolve = |reference| resolve(&api, reference);
+
+        assert_eq!(resolve("hello-world").await.unwrap(), "https://github.com/org/hello#");
+        assert_eq!(resolve("todo").await.unwrap(), "https://github.com/org/todo#main:api/.amp.toml");
+
+        let err = resolve("blog").await.unwrap_err();
+        assert_eq!(err.to_string(), "Unknown template blog, available: hello-world, todo");
+    }
+
+    #[tokio::test]
+    async fn list_the_index() {
+        let server = server().await;
+        let templates = index(&Api::new(Client::new(), &server.uri(), Some("secret"))).await.unwrap();
+        assert_eq!(templates[0].description, "A static page");
+        assert_eq!(templates[1].description, "");
+    }
+
+    const TEMPLATE: &str = "[character]\nname = \"{{NAME}}\"\nrepository = \"{{ REPO }}/{{NAME}}\"\n";
+
+    #[test]
+    fn find_the_unique_placeholders() {
+        assert_eq!(placeholders(TEMPLATE), vec!["NAME", "REPO"]);
+        assert!(placeholders("image = \"${REGISTRY}/web\" {{}} {{1ST}}").is_empty());
+    }
+
+    #[test]
+    fn fill_with_the_given_and_prompted_values() {
+        let given = vec![("REPO".into(), "https://github.com/org".into())];
+        let prompter = ScriptedPrompter::new(vec![Answer::Text("  ".into()), Answer::Text("demo".into())]);
+        let content = fill(TEMPLATE, &given, &prompter).unwrap();
+        assert_eq!(content, "[character]\nname = \"demo\"\nrepository = \"https://github.com/org/demo\"\n");
+    }
+
+    #[test]
+    fn fail_on_unfilled_variables() {
+        let given = vec![("NAME".into(), "demo".into()), ("REPO".into(), String::new())];
+        let err = fill(TEMPLATE, &given, &ScriptedPrompter::new(vec![Answer::Default])).unwrap_err();
+        assert!(matches!(err, Errors::TemplateMissingVariable(name) if name == "REPO"));
+    }
+
+    #[test]
+    fn attribute_failures_to_their_stage() {
+        let err = Errors::TemplateFailed(Stage::Validation, "todo".into(), "1 error(s)".into());
+        assert_eq!(err.to_string(), "The template todo failed at validation: 1 error(s)");
+    }
+}
@@ -0,0 +1,106 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::warn;
+
+use crate::client::{self, Api};
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+
+/// The response of the validate endpoint, listing the problems found.
+#[derive(Debug, Default, Deserialize)]
+struct Report {
+    #[serde(default)]
+    errors: Vec<String>,
+}
+
+/// Validate the manifest on the server, which may check what the CLI can't,
+/// such as registry access or quotas. Returns None if the server doesn't
+/// support validation.
+pub async fn remote(ctx: &Context, content: &str) -> Result<Option<Vec<String>>> {
+    let errors = request(&ctx.api().await, content).await?;
+    if errors.is_none() {
+        warn!("The server does not support validation, only the local checks were run");
+    }
+    Ok(errors)
+}
+
+async fn request(api: &Api, content: &str) -> Result<Option<Vec<String>>> {
+    let request = api.post("/validate").json(&json!({ "manifest": content }));
+    let response = client::send(request).await.map_err(|e| Errors::FailedValidateRemote(e.to_string()))?;
+    match response.status() {
+        StatusCode::NOT_FOUND => Ok(None),
+        status if status.is_success() || status == StatusCode::UNPROCESSABLE_ENTITY => {
+            let report: Report = response.json().await.map_err(|e| Errors::FailedValidateRemote(e.to_string()))?;
+            Ok(Some(report.errors))
+        }
+        status => Err(Errors::FailedValidateRemote(format!("unexpected status {}", status))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Client;
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn returns_server_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/validate"))
+            .and(header("authorization", "Bearer secret"))
+            .and(body_json(json!({ "manifest": "[meta]\n" })))
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({ "errors": ["quota exceeded"] })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let errors = request(&Api::new(Client::new(), &server.uri(), Some("secret")), "[meta]\n").await.unwrap();
+        assert_eq!(errors, Some(vec!["quota exceeded".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn accepts_valid_manifest() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/validate"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .mount(&server)
+            .await;
+
+        assert_eq!(request(&Api::new(Client::new(), &server.uri(), None), "").await.unwrap(), Some(vec![]));
+    }
+
+    #[tokio::test]
+    async fn unsupported_endpoint_is_skipped() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(404)).mount(&server).await;
+
+        assert_eq!(request(&Api::new(Client::new(), &server.uri(), None), "").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn fails_on_server_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(500)).mount(&server).await;
+
+        assert!(request(&Api::new(Client::new(), &server.uri(), None), "").await.is_err());
+    }
+}
@@ -12,101 +12,817 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
+use std::net::{TcpStream, ToSocketAddrs};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use amp_client::client::Client;
 use amp_common::sync::{self, EventKinds, Synchronization};
 use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{Match, WalkBuilder};
 use notify::event::RemoveKind;
 use notify::EventKind::Remove;
 use notify::RecursiveMode::Recursive;
 use notify::{Event, RecommendedWatcher, Watcher};
-use tracing::{debug, error, trace, warn};
+use tracing::{debug, error, info, instrument, trace, warn, Span};
 
+use crate::context::Context;
 use crate::errors::{Errors, Result};
+use crate::progress::Progress;
+use crate::shutdown::Shutdown;
 use crate::utils;
+use crate::utils::git::{self, Revision};
+use crate::utils::paths;
 
-///  Watch file changes and sync the changed files.
-pub async fn watch(workspace: &Path, client: &Client, pid: &str, name: &str) -> Result<()> {
-    let (tx, rx) = std::sync::mpsc::channel();
+/// How long to wait for an event before checking for the shutdown.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How often the server is probed while it is unreachable.
+const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The most changed paths kept while offline, the full sources are uploaded
+/// again on reconnect once more have changed.
+const MAX_PENDING: usize = 1000;
+
+/// The sync API of the server.
+pub trait SyncApi {
+    fn sync(&self, pid: &str, name: &str, req: Synchronization) -> Result<()>;
+
+    /// Whether the server can be reached at all, to tell the transport errors
+    /// from the ones of the server.
+    fn reachable(&self) -> bool;
+}
 
-    // We listen to the file changes giving Notify
-    // a function that will get called when events happen.
-    let config = notify::Config::default();
-    let mut watcher = RecommendedWatcher::new(tx, config).map_err(Errors::FailedCreateWatcher)?;
-    watcher.watch(workspace, Recursive).map_err(Errors::FailedWatchDirectory)?;
+/// The sync API of the server of the current context.
+pub struct ServerApi<'a> {
+    ctx: &'a Context,
+}
+
+impl<'a> ServerApi<'a> {
+    pub fn new(ctx: &'a Context) -> Self {
+        Self { ctx }
+    }
+}
+
+impl SyncApi for ServerApi<'_> {
+    fn sync(&self, pid: &str, name: &str, req: Synchronization) -> Result<()> {
+        let path = format!("/actors/{}/{}/sync", pid, name);
+        self.ctx.recorder.mutate("POST", &path, None, || self.ctx.client.actors().sync(pid, name, req))?;
+        Ok(())
+    }
+
+    fn reachable(&self) -> bool {
+        let Ok(cluster) = self.ctx.cluster.try_read() else {
+            return true;
+        };
+        connectable(&cluster.server)
+    }
+}
+
+/// Whether a TCP connection to the host of the server can be opened.
+fn connectable(server: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(server) else {
+        return false;
+    };
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        return false;
+    };
+    let Ok(addrs) = (host, port).to_socket_addrs() else {
+        return false;
+    };
+    addrs.into_iter().any(|addr| TcpStream::connect_timeout(&addr, Duration::from_secs(2)).is_ok())
+}
+
+/// What waiting for the next file event gave.
+pub enum Next {
+    Event(notify::Result<Event>),
+    Timeout,
+    Closed,
+}
+
+/// Where the file events come from.
+pub trait EventSource {
+    /// Wait for the next event, for up to the given timeout.
+    fn next(&mut self, timeout: Duration) -> Next;
+}
+
+/// The file events of the workspace, as notified by the platform.
+pub struct NotifySource {
+    /// Kept alive for as long as the events are received
+    _watcher: RecommendedWatcher,
+    rx: Receiver<notify::Result<Event>>,
+}
 
-    let mut builder = GitignoreBuilder::new(workspace);
-    builder.add(".gitignore");
-    let matcher = builder.build().unwrap();
+impl NotifySource {
+    pub fn new(workspace: &Path) -> Result<Self> {
+        let (tx, rx) = std::sync::mpsc::channel();
 
-    for event in rx {
-        if let Err(err) = event {
-            error!("Got a notify error: {err:?}");
-            continue;
+        // We listen to the file changes giving Notify
+        // a function that will get called when events happen.
+        let config = notify::Config::default();
+        let mut watcher = RecommendedWatcher::new(tx, config).map_err(Errors::FailedCreateWatcher)?;
+        watcher.watch(workspace, Recursive).map_err(Errors::FailedWatchDirectory)?;
+
+        Ok(Self { _watcher: watcher, rx })
+    }
+}
+
+impl EventSource for NotifySource {
+    fn next(&mut self, timeout: Duration) -> Next {
+        match self.rx.recv_timeout(timeout) {
+            Ok(event) => Next::Event(event),
+            Err(RecvTimeoutError::Timeout) => Next::Timeout,
+            Err(RecvTimeoutError::Disconnected) => Next::Closed,
+        }
+    }
+}
+
+/// The changes recorded while the server is unreachable, coalesced by path.
+struct Offline {
+    /// The changed paths, and whether each one was a directory
+    pending: BTreeMap<PathBuf, bool>,
+    /// More than [`MAX_PENDING`] paths changed, they were dropped
+    overflowed: bool,
+    probed: Instant,
+    status: Progress,
+}
+
+impl Offline {
+    fn new() -> Self {
+        let status = Progress::spinner("");
+        let offline = Self { pending: BTreeMap::new(), overflowed: false, probed: Instant::now(), status };
+        offline.show();
+        offline
+    }
+
+    fn record(&mut self, event: &Event) {
+        let is_dir = event.kind == Remove(RemoveKind::Folder);
+        for path in &event.paths {
+            self.pending.insert(path.clone(), is_dir || path.is_dir());
         }
-        let event = event.unwrap();
-        if is_ignored(&matcher, workspace, &event.paths)? {
-            continue;
+        if self.pending.len() > MAX_PENDING {
+            self.pending.clear();
+            self.overflowed = true;
         }
+        self.show();
+    }
 
-        handle(client, pid, name, workspace, event)?;
+    fn show(&self) {
+        let message = match self.overflowed {
+            true => String::from("offline — too many changes, the sources will be uploaded again"),
+            false => format!("offline — {} changes pending", self.pending.len()),
+        };
+        self.status.set_message(message);
     }
+}
 
-    Ok(())
+/// Syncs the workspace into an actor: the full sources first, and then each
+/// change of the files that are not ignored. While the server is unreachable,
+/// the changes are queued and replayed once it is back.
+pub struct SyncEngine<A: SyncApi> {
+    api: A,
+    workspace: PathBuf,
+    pid: String,
+    name: String,
+    matcher: Ignore,
+    stopped: AtomicBool,
+    offline: Mutex<Option<Offline>>,
+    probe_interval: Duration,
 }
 
-fn handle(client: &Client, pid: &str, name: &str, base: &Path, event: Event) -> Result<()> {
-    trace!("Changed: {:?}", event);
+impl<A: SyncApi> SyncEngine<A> {
+    pub fn new(api: A, workspace: &Path, pid: &str, name: &str) -> Self {
+        // The events carry canonical paths on some platforms, such as /private/var on macOS.
+        let workspace = paths::canonical(workspace);
+        let matcher = Ignore::new(&workspace);
+        Self {
+            api,
+            workspace,
+            pid: pid.into(),
+            name: name.into(),
+            matcher,
+            stopped: AtomicBool::new(false),
+            offline: Mutex::new(None),
+            probe_interval: PROBE_INTERVAL,
+        }
+    }
+
+    /// Upload the full sources of the workspace.
+    #[instrument(name = "upload", skip_all, fields(playbook_id = self.pid, actor = self.name, files, bytes))]
+    pub fn initial_upload(&self) -> Result<()> {
+        let mut paths: Vec<(PathBuf, PathBuf)> = vec![];
+
+        let base = &self.workspace;
+        for entry in WalkBuilder::new(base).build() {
+            let entry = entry.map_err(Errors::WalkError)?;
+            let path = entry.path();
+
+            if path.is_dir() {
+                continue;
+            }
+
+            paths.push(utils::strip(base, path)?);
+        }
+
+        let payload = utils::archive(&paths)?;
+        Span::current().record("files", paths.len()).record("bytes", payload.len());
+        let attributes = Revision::detect(base).attributes();
+        let req = Synchronization { kind: EventKinds::Overwrite, paths: vec![], attributes, payload: Some(payload) };
+        self.api.sync(&self.pid, &self.name, req)
+    }
+
+    /// Handle the events of the source until it is closed, the engine is shut
+    /// down, or the shutdown is triggered.
+    pub fn run<S: EventSource>(&self, source: &mut S, shutdown: &Shutdown) -> Result<()> {
+        // Poll the source so that the watcher is closed once the shutdown is triggered.
+        while !self.stopped.load(Ordering::Relaxed) && !shutdown.is_triggered() {
+            let event = match source.next(POLL_INTERVAL) {
+                Next::Event(event) => event,
+                Next::Timeout => {
+                    self.reconnect(false)?;
+                    continue;
+                }
+                Next::Closed => break,
+            };
+            match event {
+                Ok(event) => self.handle_event(event)?,
+                Err(err) => error!("Got a notify error: {err:?}"),
+            }
+            self.reconnect(false)?;
+        }
 
-    let kind = EventKinds::from(event.kind);
-    if kind == EventKinds::Rename || kind == EventKinds::Other {
-        warn!("Not supported event: {:?}", event);
-        return Ok(());
+        Ok(())
     }
 
-    let mut paths: Vec<(PathBuf, PathBuf)> = vec![];
-    for path in event.paths {
-        paths.push(utils::strip(base, &path)?);
+    /// The number of changes queued while offline, None when online.
+    pub fn pending(&self) -> Option<usize> {
+        self.offline.lock().unwrap().as_ref().map(|o| o.pending.len())
     }
 
-    let mut req = Synchronization { kind: kind.clone(), paths: vec![], attributes: None, payload: None };
+    /// Probe the server while offline, once the probe interval elapsed unless
+    /// forced, and replay the queued changes once it is reachable again.
+    /// True once online.
+    pub fn reconnect(&self, force: bool) -> Result<bool> {
+        let mut offline = self.offline.lock().unwrap();
+        let Some(state) = offline.as_mut() else {
+            return Ok(true);
+        };
+        if !force && state.probed.elapsed() < self.probe_interval {
+            return Ok(false);
+        }
+        state.probed = Instant::now();
+        if !self.api.reachable() {
+            debug!("The server is still unreachable");
+            return Ok(false);
+        }
+
+        let count = state.pending.len();
+        match self.replay(state) {
+            Err(_) if !self.api.reachable() => return Ok(false),
+            result => result?,
+        }
+        state.status.finish();
+        *offline = None;
+        info!("The server is reachable again, synced {} pending change(s)", count);
 
-    // Because the file or directory was removed yet, we can't get the file type.
-    // so we determine the file type by original event kind.
-    if kind == EventKinds::Remove {
-        let is_dir = event.kind == Remove(RemoveKind::Folder);
-        req.paths = paths.iter().map(|(_, b)| format_path(b, is_dir)).collect();
-    } else {
-        req.paths = paths.iter().map(|(a, b)| format_path(b, a.is_dir())).collect();
+        Ok(true)
     }
 
-    if kind == EventKinds::Modify {
-        req.payload = Some(utils::archive(&paths)?);
+    /// Sync the queued changes as a minimal batch: the paths still existing are
+    /// modified, the others removed. The full sources are uploaded instead once
+    /// too many changed.
+    fn replay(&self, state: &Offline) -> Result<()> {
+        if state.overflowed {
+            return self.initial_upload();
+        }
+
+        let base = &self.workspace;
+        let attributes = Revision::detect(base).attributes();
+        let (existing, removed): (Vec<_>, Vec<_>) = state.pending.iter().partition(|(path, _)| path.exists());
+
+        // The directories still existing are implied by the files within them.
+        let files: Vec<&PathBuf> = existing.into_iter().map(|(path, _)| path).filter(|p| p.is_file()).collect();
+        if !files.is_empty() {
+            let paths = files.iter().map(|p| utils::strip(base, p)).collect::<Result<Vec<_>>>()?;
+            let mut req = Synchronization {
+                kind: EventKinds::Modify,
+                paths: vec![],
+                attributes: attributes.clone(),
+                payload: None,
+            };
+            req.paths = paths.iter().map(|(_, b)| format_path(b, false)).collect::<Result<_>>()?;
+            req.payload = Some(utils::archive(&paths)?);
+            self.api.sync(&self.pid, &self.name, req)?;
+        }
+
+        if !removed.is_empty() {
+            let mut req = Synchronization { kind: EventKinds::Remove, paths: vec![], attributes, payload: None };
+            for (path, is_dir) in removed {
+                req.paths.push(format_path(&utils::strip(base, path)?.1, *is_dir)?);
+            }
+            self.api.sync(&self.pid, &self.name, req)?;
+        }
+
+        Ok(())
     }
 
-    debug!("The sync request is: {:?}", req);
-    client.actors().sync(pid, name, req).map_err(Errors::ClientError)?;
+    /// Stop handling the events, the run returns before waiting for the next one.
+    pub fn shutdown(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
 
-    Ok(())
+    /// Sync the change, unless a path is ignored or outside of the workspace.
+    /// The change is queued while the server is unreachable.
+    pub fn handle_event(&self, mut event: Event) -> Result<()> {
+        event.paths = normalize(event.paths);
+        if is_ignored(&self.matcher, &self.workspace, &event.paths)? {
+            return Ok(());
+        }
+
+        let mut offline = self.offline.lock().unwrap();
+        if let Some(state) = offline.as_mut() {
+            state.record(&event);
+            return Ok(());
+        }
+
+        match self.handle(event.clone()) {
+            Err(err) if !self.api.reachable() => {
+                warn!("The server is unreachable, the changes are queued until it is back: {}", err);
+                let mut state = Offline::new();
+                state.record(&event);
+                *offline = Some(state);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    #[instrument(name = "sync", skip_all, fields(playbook_id = self.pid, actor = self.name, kind, files, bytes))]
+    fn handle(&self, event: Event) -> Result<()> {
+        trace!("Changed: {:?}", event);
+
+        let kind = EventKinds::from(event.kind);
+        Span::current().record("kind", format!("{:?}", kind)).record("files", event.paths.len());
+        if kind == EventKinds::Rename || kind == EventKinds::Other {
+            warn!("Not supported event: {:?}", event);
+            return Ok(());
+        }
+
+        let base = &self.workspace;
+        let mut paths: Vec<(PathBuf, PathBuf)> = vec![];
+        for path in &event.paths {
+            paths.push(utils::strip(base, path)?);
+        }
+
+        // Detect the revision on every sync, as the HEAD may have moved since the last one.
+        let attributes = Revision::detect(base).attributes();
+        let mut req = Synchronization { kind: kind.clone(), paths: vec![], attributes, payload: None };
+
+        // Because the file or directory was removed yet, we can't get the file type.
+        // so we determine the file type by original event kind.
+        if kind == EventKinds::Remove {
+            let is_dir = event.kind == Remove(RemoveKind::Folder);
+            req.paths = paths.iter().map(|(_, b)| format_path(b, is_dir)).collect::<Result<_>>()?;
+        } else {
+            req.paths = paths.iter().map(|(a, b)| format_path(b, a.is_dir())).collect::<Result<_>>()?;
+        }
+
+        if kind == EventKinds::Modify {
+            let payload = utils::archive(&paths)?;
+            Span::current().record("bytes", payload.len());
+            req.payload = Some(payload);
+        }
+
+        debug!("The sync request is: {:?}", req);
+        self.api.sync(&self.pid, &self.name, req)
+    }
+}
+
+///  Watch file changes and sync the changed files.
+pub async fn watch(workspace: &Path, ctx: &Context, pid: &str, name: &str) -> Result<()> {
+    let engine = SyncEngine::new(ServerApi::new(ctx), workspace, pid, name);
+    info!("Watching {}, with the ignore files up to {}", engine.workspace.display(), engine.matcher.root.display());
+    let mut source = NotifySource::new(&engine.workspace)?;
+    engine.run(&mut source, &ctx.shutdown)
 }
 
-fn format_path(path: &Path, is_dir: bool) -> sync::Path {
-    let path_string = path.to_str().unwrap().to_string();
-    match is_dir {
+fn format_path(path: &Path, is_dir: bool) -> Result<sync::Path> {
+    let path_string = paths::to_remote(path)?;
+    Ok(match is_dir {
         true => sync::Path::Directory(path_string),
         false => sync::Path::File(path_string),
+    })
+}
+
+/// The event paths without their verbatim prefixes, and without the duplicates
+/// that only differ by case on the platforms ignoring it.
+fn normalize(event_paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let simplified = event_paths.iter().map(|p| PathBuf::from(paths::simplify(&p.to_string_lossy()).as_ref()));
+    paths::dedup(simplified.collect())
+}
+
+/// The ignore files governing the workspace, from its own `.gitignore` up to
+/// the ones at the root of the repository, so that the root rules (target/,
+/// node_modules/...) apply to a manifest living in a subdirectory.
+struct Ignore {
+    /// The matchers of each directory, the closest to the workspace first
+    matchers: Vec<Gitignore>,
+    /// The directory of the farthest ignore file
+    root: PathBuf,
+}
+
+impl Ignore {
+    fn new(workspace: &Path) -> Self {
+        let root = git::root(workspace);
+
+        let dirs: Vec<&Path> = match &root {
+            Some(root) => workspace.ancestors().take_while(|d| d.starts_with(root)).collect(),
+            None => vec![workspace],
+        };
+        let matchers = dirs
+            .into_iter()
+            .map(|dir| {
+                let mut builder = GitignoreBuilder::new(dir);
+                builder.add(dir.join(".gitignore"));
+                if Some(dir) == root.as_deref() {
+                    builder.add(dir.join(".git/info/exclude"));
+                }
+                builder.build().unwrap_or_else(|_| Gitignore::empty())
+            })
+            .collect();
+
+        Self { matchers, root: root.unwrap_or_else(|| workspace.to_path_buf()) }
+    }
+
+    /// Whether the path is ignored, the closest ignore file deciding.
+    fn matched(&self, path: &Path, is_dir: bool) -> bool {
+        for matcher in &self.matchers {
+            match matcher.matched_path_or_any_parents(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
     }
 }
 
-fn is_ignored(matcher: &Gitignore, root: &Path, paths: &Vec<PathBuf>) -> Result<bool> {
+/// Whether the event is to be skipped, the paths outside of the workspace are
+/// never synced.
+fn is_ignored(matcher: &Ignore, root: &Path, paths: &Vec<PathBuf>) -> Result<bool> {
     for path in paths {
-        let name = path.strip_prefix(root).map_err(Errors::FailedStripPrefix)?;
-        if matcher.matched(name, false).is_ignore() {
-            debug!("The file is ignored: {:?}", name);
+        let Ok(relative) = paths::relative(root, path) else {
+            debug!("The file is outside of the workspace: {:?}", path);
+            return Ok(true);
+        };
+        // Rebase the path on the root, the matchers expect its exact prefix.
+        if matcher.matched(&root.join(relative), path.is_dir()) {
+            debug!("The file is ignored: {:?}", path);
             return Ok(true);
         }
     }
 
     Ok(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// A repository with the root rules, and the manifest in `apps/web`.
+    fn monorepo(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("amp-watcher-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join(".git/info")).unwrap();
+        fs::create_dir_all(root.join("apps/web/src")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\nnode_modules/\n*.log\n").unwrap();
+        fs::write(root.join(".git/info/exclude"), "scratch.txt\n").unwrap();
+        fs::write(root.join("apps/web/.gitignore"), "!keep.log\ndist/\n").unwrap();
+        root
+    }
+
+    fn ignored(root: &Path, path: &str) -> bool {
+        let workspace = root.join("apps/web");
+        is_ignored(&Ignore::new(&workspace), &workspace, &vec![root.join(path)]).unwrap()
+    }
+
+    #[test]
+    fn apply_root_rules_to_subdirectories() {
+        let root = monorepo("root-rules");
+        assert!(ignored(&root, "apps/web/target/debug/web"));
+        assert!(ignored(&root, "apps/web/node_modules/left-pad/index.js"));
+        assert!(ignored(&root, "apps/web/src/debug.log"));
+        assert!(ignored(&root, "apps/web/scratch.txt"));
+        assert!(!ignored(&root, "apps/web/src/main.rs"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn closest_rules_win() {
+        let root = monorepo("closest");
+        assert!(!ignored(&root, "apps/web/keep.log"));
+        assert!(ignored(&root, "apps/web/dist/app.js"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn never_sync_outside_of_workspace() {
+        let root = monorepo("outside");
+        assert!(ignored(&root, "apps/api/src/main.rs"));
+        assert!(ignored(&root, "README.md"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn normalize_event_paths() {
+        let event_paths = vec![PathBuf::from(r"\\?\C:\app\a.rs"), PathBuf::from(r"C:\app\a.rs")];
+        assert_eq!(normalize(event_paths), vec![PathBuf::from(r"C:\app\a.rs")]);
+    }
+
+    #[test]
+    fn ignore_files_without_repository() {
+        let dir = std::env::temp_dir().join(format!("amp-watcher-plain-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(".gitignore"), "*.tmp\n").unwrap();
+
+        let matcher = Ignore::new(&dir);
+        assert!(is_ignored(&matcher, &dir, &vec![dir.join("a.tmp")]).unwrap());
+        assert!(!is_ignored(&matcher, &dir, &vec![dir.join("a.rs")]).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// The sync API recording the requests, or failing them all. During an
+    /// outage, the requests fail until the server was probed `outage` times.
+    #[derive(Clone, Default)]
+    struct FakeApi {
+        requests: std::sync::Arc<std::sync::Mutex<Vec<Synchronization>>>,
+        fail: bool,
+        outage: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl SyncApi for FakeApi {
+        fn sync(&self, pid: &str, name: &str, req: Synchronization) -> Result<()> {
+            assert_eq!((pid, name), ("42", "web"));
+            if self.fail || self.outage.load(Ordering::SeqCst) > 0 {
+                return Err(Errors::InvalidCharacter);
+            }
+            self.requests.lock().unwrap().push(req);
+            Ok(())
+        }
+
+        fn reachable(&self) -> bool {
+            let probe = |n: usize| n.checked_sub(1);
+            self.outage.fetch_update(Ordering::SeqCst, Ordering::SeqCst, probe).is_err()
+        }
+    }
+
+    impl FakeApi {
+        fn take(&self) -> Vec<Synchronization> {
+            std::mem::take(&mut *self.requests.lock().unwrap())
+        }
+    }
+
+    /// The events given in advance, closed once they are all consumed.
+    struct ScriptedSource {
+        events: std::collections::VecDeque<Next>,
+    }
+
+    impl EventSource for ScriptedSource {
+        fn next(&mut self, _timeout: Duration) -> Next {
+            self.events.pop_front().unwrap_or(Next::Closed)
+        }
+    }
+
+    fn engine(name: &str, api: &FakeApi) -> (PathBuf, SyncEngine<FakeApi>) {
+        let root = monorepo(name);
+        fs::write(root.join("apps/web/src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("apps/web/src/lib.rs"), "").unwrap();
+        fs::create_dir_all(root.join("apps/web/target/debug")).unwrap();
+        fs::write(root.join("apps/web/target/debug/web"), "").unwrap();
+        let engine = SyncEngine::new(api.clone(), &root.join("apps/web"), "42", "web");
+        (root, engine)
+    }
+
+    fn outage(api: &FakeApi, probes: usize) {
+        api.outage.store(probes, Ordering::SeqCst);
+    }
+
+    fn event(kind: notify::EventKind, engine: &SyncEngine<FakeApi>, paths: &[&str]) -> Event {
+        paths.iter().fold(Event::new(kind), |event, path| event.add_path(engine.workspace.join(path)))
+    }
+
+    fn modify(engine: &SyncEngine<FakeApi>, paths: &[&str]) -> Event {
+        use notify::event::{DataChange, ModifyKind};
+        event(notify::EventKind::Modify(ModifyKind::Data(DataChange::Content)), engine, paths)
+    }
+
+    fn describe(path: &sync::Path) -> String {
+        if let sync::Path::File(path) = path {
+            return format!("file {}", path);
+        }
+        if let sync::Path::Directory(path) = path {
+            return format!("dir {}", path);
+        }
+        String::new()
+    }
+
+    fn archived(payload: &[u8]) -> Vec<String> {
+        let mut archive = tar::Archive::new(payload);
+        let entries = archive.entries().unwrap();
+        let mut names: Vec<String> = entries.map(|e| e.unwrap().path().unwrap().display().to_string()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn upload_the_sources_not_ignored() {
+        let api = FakeApi::default();
+        let (root, engine) = engine("upload", &api);
+        engine.initial_upload().unwrap();
+
+        let requests = api.take();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].kind, EventKinds::Overwrite);
+        let names = archived(requests[0].payload.as_deref().unwrap());
+        assert_eq!(names, vec!["src/lib.rs", "src/main.rs"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn skip_ignored_events() {
+        let api = FakeApi::default();
+        let (root, engine) = engine("skip", &api);
+        engine.handle_event(modify(&engine, &["target/debug/web"])).unwrap();
+        engine.handle_event(modify(&engine, &["src/debug.log"])).unwrap();
+        engine.handle_event(modify(&engine, &["src/main.rs", "target/debug/web"])).unwrap();
+
+        let outside = Event::new(notify::EventKind::Any).add_path(root.join("apps/api/src/main.rs"));
+        engine.handle_event(outside).unwrap();
+
+        assert!(api.take().is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn translate_events_into_sync_requests() {
+        let api = FakeApi::default();
+        let (root, engine) = engine("translate", &api);
+        engine.handle_event(modify(&engine, &["src/main.rs"])).unwrap();
+        fs::create_dir_all(engine.workspace.join("src/old")).unwrap();
+        fs::remove_dir_all(engine.workspace.join("src/old")).unwrap();
+        engine.handle_event(event(notify::EventKind::Remove(RemoveKind::Folder), &engine, &["src/old"])).unwrap();
+
+        let requests = api.take();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].kind, EventKinds::Modify);
+        assert_eq!(requests[0].paths.iter().map(describe).collect::<Vec<_>>(), vec!["file src/main.rs"]);
+        assert_eq!(archived(requests[0].payload.as_deref().unwrap()), vec!["src/main.rs"]);
+
+        assert_eq!(requests[1].kind, EventKinds::Remove);
+        assert_eq!(requests[1].paths.iter().map(describe).collect::<Vec<_>>(), vec!["dir src/old"]);
+        assert!(requests[1].payload.is_none());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn batch_the_paths_of_an_event() {
+        let api = FakeApi::default();
+        let (root, engine) = engine("batch", &api);
+        engine.handle_event(modify(&engine, &["src/main.rs", "src/lib.rs", "src/main.rs"])).unwrap();
+
+        let requests = api.take();
+        assert_eq!(requests.len(), 1);
+        let paths: Vec<String> = requests[0].paths.iter().map(describe).collect();
+        assert_eq!(paths, vec!["file src/main.rs", "file src/lib.rs"]);
+        assert_eq!(archived(requests[0].payload.as_deref().unwrap()), vec!["src/lib.rs", "src/main.rs"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn run_until_the_source_is_closed() {
+        let api = FakeApi::default();
+        let (root, engine) = engine("run", &api);
+        let events = vec![
+            Next::Event(Ok(modify(&engine, &["src/main.rs"]))),
+            Next::Timeout,
+            Next::Event(Err(notify::Error::generic("overflow"))),
+            Next::Event(Ok(modify(&engine, &["src/lib.rs"]))),
+        ];
+        let mut source = ScriptedSource { events: events.into() };
+        engine.run(&mut source, &Shutdown::default()).unwrap();
+
+        assert_eq!(api.take().len(), 2, "the notify errors are only logged");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn stop_on_shutdown() {
+        let api = FakeApi::default();
+        let (root, engine) = engine("shutdown", &api);
+        let mut source = ScriptedSource { events: vec![Next::Event(Ok(modify(&engine, &["src/main.rs"])))].into() };
+
+        engine.shutdown();
+        engine.run(&mut source, &Shutdown::default()).unwrap();
+        assert_eq!(source.events.len(), 1, "no event is consumed once shut down");
+        assert!(api.take().is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn propagate_sync_errors() {
+        let api = FakeApi { fail: true, ..FakeApi::default() };
+        let (root, engine) = engine("errors", &api);
+        let events =
+            vec![Next::Event(Ok(modify(&engine, &["src/main.rs"]))), Next::Event(Ok(modify(&engine, &["src/lib.rs"])))];
+        let mut source = ScriptedSource { events: events.into() };
+
+        let err = engine.run(&mut source, &Shutdown::default()).unwrap_err();
+        assert!(matches!(err, Errors::InvalidCharacter));
+        assert_eq!(source.events.len(), 1, "the run stops at the first error");
+        assert!(engine.initial_upload().is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn queue_the_changes_during_an_outage() {
+        let api = FakeApi::default();
+        let (root, engine) = engine("outage", &api);
+        outage(&api, usize::MAX);
+
+        engine.handle_event(modify(&engine, &["src/main.rs"])).unwrap();
+        engine.handle_event(modify(&engine, &["src/lib.rs", "src/main.rs"])).unwrap();
+        fs::write(engine.workspace.join("src/new.rs"), "").unwrap();
+        engine.handle_event(modify(&engine, &["src/new.rs"])).unwrap();
+        engine.handle_event(modify(&engine, &["src/main.rs"])).unwrap();
+        engine.handle_event(event(notify::EventKind::Remove(RemoveKind::Folder), &engine, &["src/old"])).unwrap();
+        assert_eq!(engine.pending(), Some(4), "the changes are coalesced by path");
+        assert!(!engine.reconnect(true).unwrap());
+        assert!(api.take().is_empty());
+
+        outage(&api, 0);
+        assert!(engine.reconnect(true).unwrap());
+        assert_eq!(engine.pending(), None);
+
+        let requests = api.take();
+        assert_eq!(requests.len(), 2, "the pending changes are replayed as a batch");
+        assert_eq!(requests[0].kind, EventKinds::Modify);
+        let paths: Vec<String> = requests[0].paths.iter().map(describe).collect();
+        assert_eq!(paths, vec!["file src/lib.rs", "file src/main.rs", "file src/new.rs"]);
+        assert_eq!(archived(requests[0].payload.as_deref().unwrap()), vec!["src/lib.rs", "src/main.rs", "src/new.rs"]);
+        assert_eq!(requests[1].kind, EventKinds::Remove);
+        assert_eq!(requests[1].paths.iter().map(describe).collect::<Vec<_>>(), vec!["dir src/old"]);
+
+        engine.handle_event(modify(&engine, &["src/lib.rs"])).unwrap();
+        assert_eq!(api.take().len(), 1, "the changes are synced again once online");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn upload_the_sources_again_after_too_many_changes() {
+        let api = FakeApi::default();
+        let (root, engine) = engine("overflow", &api);
+        outage(&api, usize::MAX);
+
+        let paths: Vec<String> = (0..=MAX_PENDING).map(|i| format!("src/gen/{}.rs", i)).collect();
+        let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+        engine.handle_event(modify(&engine, &paths)).unwrap();
+        assert_eq!(engine.pending(), Some(0), "the dropped paths don't hold memory");
+
+        outage(&api, 0);
+        assert!(engine.reconnect(true).unwrap());
+        let requests = api.take();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].kind, EventKinds::Overwrite);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn probe_until_the_server_is_back() {
+        let api = FakeApi::default();
+        let (root, mut engine) = engine("probe", &api);
+        engine.probe_interval = Duration::ZERO;
+        outage(&api, 4);
+
+        let events = vec![
+            Next::Event(Ok(modify(&engine, &["src/main.rs"]))),
+            Next::Timeout,
+            Next::Timeout,
+            Next::Event(Ok(modify(&engine, &["src/lib.rs"]))),
+        ];
+        let mut source = ScriptedSource { events: events.into() };
+        engine.run(&mut source, &Shutdown::default()).unwrap();
+
+        assert_eq!(engine.pending(), None);
+        let requests = api.take();
+        assert_eq!(requests.len(), 1);
+        let paths: Vec<String> = requests[0].paths.iter().map(describe).collect();
+        assert_eq!(paths, vec!["file src/lib.rs", "file src/main.rs"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,619 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+use clap::{Args, ValueEnum};
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::Value;
+use similar::{Algorithm, ChangeTag, TextDiff};
+use tabled::builder::Builder;
+use tabled::settings::object::Rows;
+use tabled::settings::peaker::Priority;
+use tabled::settings::{Remove, Style, Width};
+use tabled::Tabled;
+
+use crate::errors::{Errors, Result};
+use crate::recorder::Timings;
+use crate::utils;
+
+/// The output format for commands that print resources.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Print resources as a human readable table
+    #[default]
+    Table,
+    /// Print resources as pretty JSON
+    Json,
+    /// Print resources as comma-separated values, with a header row
+    Csv,
+    /// Print the identifier of each resource, one per line
+    Name,
+}
+
+/// Stdout is the only writer for the primary output of commands, so that
+/// it stays pipeable. Logs, progress and warnings all go to stderr.
+pub struct Stdout {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Default for Stdout {
+    fn default() -> Self {
+        Self::new(Box::new(io::stdout()))
+    }
+}
+
+impl Stdout {
+    pub fn new(writer: Box<dyn Write + Send>) -> Self {
+        Self { writer: Mutex::new(writer) }
+    }
+
+    /// Write the content as is.
+    pub fn print(&self, content: impl Display) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        write!(writer, "{}", content).and_then(|_| writer.flush()).map_err(Errors::FailedWriteOutput)
+    }
+
+    /// Write the raw bytes, for the binary output such as the tarballs.
+    pub fn write(&self, bytes: &[u8]) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.write_all(bytes).and_then(|_| writer.flush()).map_err(Errors::FailedWriteOutput)
+    }
+
+    /// Write the content followed by a newline.
+    pub fn println(&self, content: impl Display) -> Result<()> {
+        self.print(format_args!("{}\n", content))
+    }
+}
+
+/// A row which can be written as a CSV record.
+pub trait CsvRecord {
+    fn csv_headers() -> Vec<String>;
+    fn to_csv_record(&self) -> Vec<String>;
+}
+
+/// Every table row is a CSV record, with the same headers and fields.
+impl<T: Tabled> CsvRecord for T {
+    fn csv_headers() -> Vec<String> {
+        T::headers().into_iter().map(Cow::into_owned).collect()
+    }
+
+    fn to_csv_record(&self) -> Vec<String> {
+        self.fields().into_iter().map(Cow::into_owned).collect()
+    }
+}
+
+/// Render the rows as a table.
+pub fn table<R: Tabled>(rows: impl IntoIterator<Item = R>) -> String {
+    table_with_header(rows, true)
+}
+
+/// Render the rows as a table, optionally without the header row. Headerless
+/// tables are meant for scripts, so they are printed without borders too.
+pub fn table_with_header<R: Tabled>(rows: impl IntoIterator<Item = R>, header: bool) -> String {
+    wrapped_table_with_header(rows, header, None)
+}
+
+/// Render the rows as a table like [`table_with_header`], wrapping the cells
+/// wider than the given width onto several lines.
+pub fn wrapped_table_with_header<R: Tabled>(
+    rows: impl IntoIterator<Item = R>,
+    header: bool,
+    width: Option<usize>,
+) -> String {
+    table_with_layout(rows, Layout::new(header).wrap(width))
+}
+
+/// Render the rows as a table laid out as given.
+pub fn table_with_layout<R: Tabled>(rows: impl IntoIterator<Item = R>, layout: Layout) -> String {
+    styled(tabled::Table::new(rows), layout)
+}
+
+/// Render the records as a table like [`table_with_layout`], the first record
+/// being the header row.
+pub fn records_table(records: Vec<Vec<String>>, layout: Layout) -> String {
+    let mut builder = Builder::default();
+    for record in records {
+        builder.push_record(record);
+    }
+    styled(builder.build(), layout)
+}
+
+/// Render the first field of the rows, their identifiers, one per line.
+pub fn names<R: Tabled>(rows: impl IntoIterator<Item = R>) -> String {
+    rows.into_iter().filter_map(|row| row.fields().into_iter().next()).map(|name| format!("{}\n", name)).collect()
+}
+
+fn styled(mut table: tabled::Table, layout: Layout) -> String {
+    if let Some(width) = layout.wrap {
+        table.modify(Rows::new(1..), Width::wrap(width).keep_words(true));
+    }
+    match layout.header {
+        true => table.with(Style::modern()),
+        false => table.with(Style::blank()).with(Remove::row(Rows::first())),
+    };
+    if let Some(width) = layout.fit {
+        table.with(Width::truncate(width).suffix("…").priority(Priority::max(true)));
+    }
+    table.to_string()
+}
+
+/// How a table is laid out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    /// Print the header row, and the borders
+    pub header: bool,
+    /// Wrap the cells wider than this onto several lines
+    pub wrap: Option<usize>,
+    /// Ellipsize the widest cells so the table fits in this width
+    pub fit: Option<usize>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+impl Layout {
+    pub fn new(header: bool) -> Self {
+        Self { header, wrap: None, fit: None }
+    }
+
+    pub fn wrap(self, width: Option<usize>) -> Self {
+        Self { wrap: width, ..self }
+    }
+}
+
+/// The modifiers of the tables, shared by the commands listing resources.
+#[derive(Args, Clone, Debug, Default)]
+pub struct TableArgs {
+    /// Don't print the header row of the table and CSV output, for scripts
+    #[arg(long, visible_alias = "no-headers", action = clap::ArgAction::SetTrue)]
+    pub no_header: bool,
+
+    /// Show the long values in full rather than ellipsized to fit the terminal, and the extra columns if any
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "output")]
+    pub wide: bool,
+}
+
+impl TableArgs {
+    /// The layout of the tables. The long values are ellipsized to fit in
+    /// terminals unless wide, the redirected tables are never truncated.
+    pub fn layout(&self) -> Layout {
+        let fit = if self.wide { None } else { terminal_width() };
+        Layout { fit, ..Layout::new(!self.no_header) }
+    }
+}
+
+/// The width of the terminal, None if stdout is not one.
+fn terminal_width() -> Option<usize> {
+    console::Term::stdout().size_checked().map(|(_, columns)| columns as usize)
+}
+
+/// The columns of a table to show and the ones to sort its rows by, named
+/// after the headers, case-insensitively and with `_` or `-` for the spaces.
+#[derive(Args, Clone, Debug, Default)]
+pub struct Columns {
+    /// Show the given columns of the table, in this order
+    #[arg(long, value_name = "COLUMN", value_delimiter = ',')]
+    pub columns: Vec<String>,
+
+    /// Sort the rows of the table by the given columns, the next ones breaking
+    /// the ties, each ascending or followed by `:asc` or `:desc`, for example
+    /// `status,created_at:desc`
+    #[arg(long, visible_alias = "sort", value_name = "COLUMN[:asc|desc],...")]
+    pub sort_by: Option<String>,
+
+    /// Show none of the columns, the same as `--columns ""`
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "columns")]
+    pub no_columns: bool,
+}
+
+impl Columns {
+    /// Whether the table is shown as is.
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty() && self.sort_by.is_none() && !self.no_columns
+    }
+
+    /// Fall back to the columns saved as `output.columns.<kind>` in the configuration.
+    pub fn or_configured(&self, kind: &str) -> Self {
+        let mut columns = self.clone();
+        if columns.columns.is_empty() && !columns.no_columns {
+            let configured = utils::setting(&["output", "columns", kind]);
+            let names = configured.as_ref().and_then(|v| v.as_array()).into_iter().flatten();
+            columns.columns = names.filter_map(|name| name.as_str().map(String::from)).collect();
+        }
+        columns
+    }
+
+    /// Fall back to all the columns of the rows.
+    pub fn or_headers<R: Tabled>(self) -> Self {
+        match self.columns.is_empty() && !self.no_columns {
+            true => Self { columns: R::headers().into_iter().map(Cow::into_owned).collect(), ..self },
+            false => self,
+        }
+    }
+
+    /// Render the picked columns of the rows as a table like [`table_with_layout`].
+    pub fn table<R: Tabled>(&self, rows: impl IntoIterator<Item = R>, layout: Layout) -> Result<String> {
+        match self.is_empty() {
+            true => Ok(table_with_layout(rows, layout)),
+            false => Ok(records_table(self.select(rows)?, layout)),
+        }
+    }
+
+    /// Pick the columns of the rows and sort them, the header being the first record.
+    pub fn select<R: Tabled>(&self, rows: impl IntoIterator<Item = R>) -> Result<Vec<Vec<String>>> {
+        let (header, rows) = self.select_keyed(rows.into_iter().map(|row| ((), row)).collect())?;
+        Ok(std::iter::once(header).chain(rows.into_iter().map(|(_, fields)| fields)).collect())
+    }
+
+    /// Pick the columns of the keyed rows and sort them, returning the header apart.
+    pub fn select_keyed<K, R: Tabled>(&self, rows: Vec<(K, R)>) -> Result<(Vec<String>, Vec<(K, Vec<String>)>)> {
+        let headers: Vec<String> = R::headers().into_iter().map(Cow::into_owned).collect();
+        let index = |name: &str| {
+            headers.iter().position(|h| column_name(h) == column_name(name)).ok_or_else(|| {
+                let available: Vec<String> = headers.iter().map(|h| column_name(h)).collect();
+                Errors::UnknownColumn(name.to_string(), available.join(", "))
+            })
+        };
+        let picked = match self.columns.is_empty() && !self.no_columns {
+            true => (0..headers.len()).collect(),
+            false => self.columns.iter().filter(|c| !c.is_empty()).map(|c| index(c)).collect::<Result<Vec<usize>>>()?,
+        };
+
+        let mut rows: Vec<(K, Vec<String>)> =
+            rows.into_iter().map(|(key, row)| (key, row.fields().into_iter().map(Cow::into_owned).collect())).collect();
+        if let Some(spec) = &self.sort_by {
+            let keys = SortKey::parse(spec)?;
+            let keys = keys
+                .into_iter()
+                .map(|key| {
+                    index(&key.column).map(|i| (i, key.descending)).map_err(|_| Errors::InvalidSortField(key.column))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            rows.sort_by(|(_, a), (_, b)| {
+                keys.iter().fold(Ordering::Equal, |ordering, &(i, descending)| {
+                    ordering.then_with(|| match descending {
+                        true => compare(&a[i], &b[i]).reverse(),
+                        false => compare(&a[i], &b[i]),
+                    })
+                })
+            });
+        }
+
+        let header = picked.iter().map(|&i| headers[i].clone()).collect();
+        let rows = rows.into_iter().map(|(key, fields)| (key, picked.iter().map(|&i| fields[i].clone()).collect()));
+        Ok((header, rows.collect()))
+    }
+}
+
+/// A column to sort the rows by, and its direction.
+#[derive(Debug, PartialEq, Eq)]
+struct SortKey {
+    column: String,
+    descending: bool,
+}
+
+impl SortKey {
+    /// Parse the comma-separated `column[:asc|desc]` keys of `--sort-by`.
+    fn parse(spec: &str) -> Result<Vec<SortKey>> {
+        let key = |field: &str| {
+            let (column, direction) = field.trim().split_once(':').unwrap_or((field.trim(), "asc"));
+            match (column.is_empty(), direction.to_lowercase().as_str()) {
+                (false, "asc") => Ok(SortKey { column: column.to_string(), descending: false }),
+                (false, "desc") => Ok(SortKey { column: column.to_string(), descending: true }),
+                _ => Err(Errors::InvalidSortField(field.trim().to_string())),
+            }
+        };
+        spec.split(',').map(key).collect()
+    }
+}
+
+/// The name of the column of the header, for the command line.
+fn column_name(header: &str) -> String {
+    header.trim().to_lowercase().replace([' ', '-'], "_")
+}
+
+/// Compare the cells as numbers if both are, or as case-insensitive text.
+fn compare(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        _ => a.to_lowercase().cmp(&b.to_lowercase()),
+    }
+}
+
+/// Render the rows as CSV, with a header row.
+pub fn csv<R: CsvRecord>(rows: &[R]) -> Result<String> {
+    csv_with_header(rows, true)
+}
+
+/// Render the rows as CSV, optionally without the header row.
+pub fn csv_with_header<R: CsvRecord>(rows: &[R], header: bool) -> Result<String> {
+    let mut writer = ::csv::Writer::from_writer(vec![]);
+    if header {
+        writer.write_record(R::csv_headers()).map_err(Errors::CsvError)?;
+    }
+    for row in rows {
+        writer.write_record(row.to_csv_record()).map_err(Errors::CsvError)?;
+    }
+
+    let bytes = writer.into_inner().map_err(|e| Errors::CsvError(e.into_error().into()))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Render the value as pretty JSON.
+pub fn json<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    serde_json::to_string_pretty(value).map_err(Errors::JsonSerializeError)
+}
+
+/// Render the value as pretty JSON, with the timings of the command under a
+/// `timings` key if any. The other values than objects, such as the lists, are
+/// then wrapped as `{"items": ..., "timings": ...}`.
+pub fn json_with_timings<T: Serialize + ?Sized>(value: &T, timings: Option<&Timings>) -> Result<String> {
+    let value = serde_json::to_value(value).map_err(Errors::JsonSerializeError)?;
+    let Some(timings) = timings else {
+        return json(&value);
+    };
+
+    let timings = serde_json::to_value(timings).map_err(Errors::JsonSerializeError)?;
+    let mut map = match value {
+        Value::Object(map) => map,
+        items => serde_json::Map::from_iter([(String::from("items"), items)]),
+    };
+    map.insert(String::from("timings"), timings);
+    json(&map)
+}
+
+/// Render the value as YAML.
+pub fn yaml<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    serde_yaml::to_string(value).map_err(Errors::YamlSerializeError)
+}
+
+/// The lines of context around the changes of a diff.
+pub const DIFF_CONTEXT: usize = 3;
+
+/// Render the colored unified diff between the texts, None if they are equal.
+pub fn diff(old: &str, new: &str, old_label: &str, new_label: &str) -> Option<String> {
+    diff_with_context(old, new, old_label, new_label, DIFF_CONTEXT)
+}
+
+/// Render the colored unified diff with the given lines of context around the changes.
+pub fn diff_with_context(old: &str, new: &str, old_label: &str, new_label: &str, context: usize) -> Option<String> {
+    if old == new {
+        return None;
+    }
+
+    let diff = TextDiff::from_lines(old, new);
+    let unified = diff.unified_diff().context_radius(context).header(old_label, new_label).to_string();
+    let lines = unified.lines().map(|line| match line.chars().next() {
+        _ if line.starts_with("+++") || line.starts_with("---") => line.bold().to_string(),
+        Some('+') => line.green().to_string(),
+        Some('-') => line.red().to_string(),
+        Some('@') => line.cyan().to_string(),
+        _ => line.to_string(),
+    });
+
+    Some(lines.map(|line| line + "\n").collect())
+}
+
+/// Count the lines added and removed between the texts.
+pub fn diff_stat(old: &str, new: &str) -> (usize, usize) {
+    let changes = similar::utils::diff_lines(Algorithm::Myers, old, new);
+    let added = changes.iter().filter(|(tag, _)| *tag == ChangeTag::Insert).count();
+    let removed = changes.iter().filter(|(tag, _)| *tag == ChangeTag::Delete).count();
+    (added, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Tabled)]
+    struct Row {
+        name: String,
+        description: String,
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let rows = vec![
+            Row { name: "web".into(), description: "frontend, with \"quotes\"".into() },
+            Row { name: "db".into(), description: "".into() },
+        ];
+        let output = csv(&rows).unwrap();
+
+        let mut reader = ::csv::Reader::from_reader(output.as_bytes());
+        assert_eq!(reader.headers().unwrap(), vec!["name", "description"]);
+
+        let records: Vec<Vec<String>> =
+            reader.records().map(|r| r.unwrap().iter().map(String::from).collect()).collect();
+        assert_eq!(records, rows.iter().map(|r| r.to_csv_record()).collect::<Vec<_>>());
+        assert_eq!(records[0][1], "frontend, with \"quotes\"");
+    }
+
+    #[test]
+    fn omit_the_header() {
+        let rows = || vec![Row { name: "web".into(), description: "frontend".into() }];
+
+        let table = table_with_header(rows(), false);
+        let first = table.lines().next().unwrap();
+        assert!(first.contains("web") && first.contains("frontend"), "{}", table);
+        assert!(!table.contains("name"));
+        assert!(table_with_header(rows(), true).contains("name"));
+
+        assert_eq!(csv_with_header(&rows(), false).unwrap(), "web,frontend\n");
+    }
+
+    fn columns(columns: &[&str], sort_by: Option<&str>) -> Columns {
+        let columns = columns.iter().map(|c| c.to_string()).collect();
+        Columns { columns, sort_by: sort_by.map(String::from), no_columns: false }
+    }
+
+    #[derive(Tabled)]
+    struct Actor {
+        name: String,
+        #[tabled(rename = "last restart")]
+        restarted: String,
+        restarts: usize,
+    }
+
+    fn actors() -> Vec<Actor> {
+        [("web", "10:02", 10), ("api", "09:30", 2), ("db", "11:45", 0)]
+            .map(|(n, r, c)| Actor { name: n.into(), restarted: r.into(), restarts: c })
+            .into()
+    }
+
+    #[test]
+    fn pick_and_order_columns() {
+        let records = columns(&["RESTARTS", "name"], None).select(actors()).unwrap();
+        assert_eq!(records[0], vec!["restarts", "name"]);
+        assert_eq!(records[1], vec!["10", "web"]);
+
+        let records = columns(&["last-restart"], None).select(actors()).unwrap();
+        assert_eq!(records[0], vec!["last restart"]);
+        assert_eq!(columns(&[], None).select(actors()).unwrap()[0].len(), 3);
+
+        let none = Columns { no_columns: true, ..Columns::default() };
+        assert!(none.select(actors()).unwrap().iter().all(Vec::is_empty));
+        assert!(columns(&[""], None).select(actors()).unwrap().iter().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn sort_by_column() {
+        let names = |sort_by| {
+            let records = columns(&["name"], Some(sort_by)).select(actors()).unwrap();
+            records.into_iter().skip(1).map(|r| r[0].clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(names("name"), vec!["api", "db", "web"]);
+        assert_eq!(names("restarts"), vec!["db", "api", "web"], "numbers are sorted by value");
+        assert_eq!(names("last_restart"), vec!["api", "web", "db"]);
+    }
+
+    #[derive(Tabled)]
+    struct Playbook {
+        title: String,
+        status: String,
+        #[tabled(rename = "created at")]
+        created_at: String,
+    }
+
+    fn playbooks() -> Vec<Playbook> {
+        [("web", "running", "2024-01-02"), ("db", "failed", "2024-01-03"), ("api", "running", "2024-01-05")]
+            .map(|(t, s, c)| Playbook { title: t.into(), status: s.into(), created_at: c.into() })
+            .into()
+    }
+
+    #[test]
+    fn sort_by_several_columns() {
+        let titles = |sort_by| {
+            let records = columns(&["title"], Some(sort_by)).select(playbooks()).unwrap();
+            records.into_iter().skip(1).map(|r| r[0].clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(titles("status,created_at"), vec!["db", "web", "api"]);
+        assert_eq!(titles("status:asc,created_at:desc"), vec!["db", "api", "web"]);
+        assert_eq!(titles("status:desc, title"), vec!["api", "web", "db"]);
+        assert_eq!(titles("status"), vec!["db", "web", "api"], "the ties keep their order");
+
+        for invalid in ["status,owner", "status:up", ":desc", "status,"] {
+            let error = columns(&["title"], Some(invalid)).select(playbooks()).unwrap_err();
+            assert!(matches!(error, Errors::InvalidSortField(_)), "{}: {}", invalid, error);
+        }
+    }
+
+    #[test]
+    fn list_the_available_columns() {
+        let error = columns(&["name", "age"], None).select(actors()).unwrap_err();
+        assert_eq!(error.to_string(), "Unknown column age, available columns: name, last_restart, restarts");
+        assert!(columns(&[], Some("age")).select(actors()).is_err());
+    }
+
+    fn layout_rows() -> Vec<Row> {
+        vec![
+            Row { name: "web".into(), description: "the storefront, with a rather long description".into() },
+            Row { name: "db".into(), description: "".into() },
+        ]
+    }
+
+    #[test]
+    fn fit_the_table_in_the_width() {
+        let layout = Layout { fit: Some(30), ..Layout::default() };
+        let table = table_with_layout(layout_rows(), layout);
+        assert!(table.lines().all(|l| l.chars().count() <= 30), "{}", table);
+        assert!(table.contains("the storefront") && table.contains('…'), "{}", table);
+        assert!(table.contains("web"), "the shortest columns are kept: {}", table);
+
+        let wide = table_with_layout(layout_rows(), Layout::default());
+        assert!(wide.contains("a rather long description"), "{}", wide);
+    }
+
+    #[test]
+    fn snapshot_of_layouts() {
+        let table = table_with_layout(layout_rows(), Layout::new(false));
+        let expected = format!(" web   the storefront, with a rather long description \n db{}", " ".repeat(51));
+        assert_eq!(table, expected);
+        assert_eq!(names(layout_rows()), "web\ndb\n");
+        assert_eq!(names(Vec::<Row>::new()), "");
+    }
+
+    #[test]
+    fn wrap_long_cells() {
+        let rows = || vec![Row { name: "web".into(), description: "the frontend of the shop".into() }];
+
+        let table = wrapped_table_with_header(rows(), true, Some(12));
+        assert!(table.lines().all(|line| line.chars().count() <= 3 + 5 + 12 + 4), "{}", table);
+        assert!(table.lines().count() > table_with_header(rows(), true).lines().count());
+    }
+
+    #[test]
+    fn diff_context_and_stat() {
+        let old = "a\nb\nc\nd\ne\nf\n";
+        let new = "a\nb\nc\nD\ne\nf\ng\n";
+
+        let narrow = diff_with_context(old, new, "old", "new", 0).unwrap();
+        assert!(!narrow.contains(" c\n"));
+        let wide = diff_with_context(old, new, "old", "new", 1).unwrap();
+        assert!(wide.contains(" c\n"));
+        assert!(diff(old, old, "old", "new").is_none());
+
+        assert_eq!(diff_stat(old, new), (2, 1));
+        assert_eq!(diff_stat(old, old), (0, 0));
+    }
+
+    #[test]
+    fn json_with_timings_on_objects_and_lists() {
+        let timings = Timings { calls: 2, api: Duration::from_millis(120), local: Duration::ZERO, slowest: None };
+        let object: Value =
+            serde_json::from_str(&json_with_timings(&json!({"id": "1"}), Some(&timings)).unwrap()).unwrap();
+        assert_eq!(object["timings"], json!({"calls": 2, "api_ms": 120, "local_ms": 0, "slowest": null}));
+
+        let list: Value =
+            serde_json::from_str(&json_with_timings(&json!([{"id": "1"}]), Some(&timings)).unwrap()).unwrap();
+        assert_eq!(list["items"], json!([{"id": "1"}]));
+        assert_eq!(list["timings"]["calls"], 2);
+
+        assert!(!json_with_timings(&json!({"id": "1"}), None).unwrap().contains("timings"));
+        assert_eq!(json_with_timings(&json!([]), None).unwrap(), json(&json!([])).unwrap());
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+/// Transfers smaller than this are done before a progress bar is worth it.
+const THRESHOLD: u64 = 1024 * 1024;
+
+/// A progress bar on stderr, so that stdout can be redirected. It is hidden
+/// when disabled, and for the small transfers.
+#[derive(Clone)]
+pub struct Progress {
+    bar: ProgressBar,
+}
+
+impl Progress {
+    /// The progress of a transfer of the given length in bytes, a spinner if unknown.
+    pub fn bytes(total: Option<u64>, enabled: bool) -> Self {
+        let bar = match total {
+            _ if !enabled => ProgressBar::hidden(),
+            Some(total) if total < THRESHOLD => ProgressBar::hidden(),
+            Some(total) => with_style(
+                ProgressBar::with_draw_target(Some(total), ProgressDrawTarget::stderr()),
+                "{bar:30} {bytes}/{total_bytes} {bytes_per_sec} ETA {eta}",
+            ),
+            None => with_style(
+                ProgressBar::with_draw_target(None, ProgressDrawTarget::stderr()),
+                "{spinner} {bytes} {bytes_per_sec}",
+            ),
+        };
+        Self { bar }
+    }
+
+    /// The progress over the given number of files.
+    pub fn files(total: u64, enabled: bool) -> Self {
+        let bar = match enabled {
+            true => with_style(
+                ProgressBar::with_draw_target(Some(total), ProgressDrawTarget::stderr()),
+                "{bar:30} {pos}/{len} files",
+            ),
+            false => ProgressBar::hidden(),
+        };
+        Self { bar }
+    }
+
+    /// A spinner with the given message, for the waits of unknown length.
+    pub fn spinner(message: &str) -> Self {
+        let bar = with_style(ProgressBar::with_draw_target(None, ProgressDrawTarget::stderr()), "{spinner} {msg}");
+        bar.set_message(message.to_string());
+        bar.enable_steady_tick(Duration::from_millis(100));
+        Self { bar }
+    }
+
+    pub fn set_message(&self, message: String) {
+        self.bar.set_message(message);
+    }
+
+    /// Hide the progress while running the closure, so that it can log lines.
+    pub fn suspend<F: FnOnce() -> R, R>(&self, f: F) -> R {
+        self.bar.suspend(f)
+    }
+
+    pub fn inc(&self, delta: u64) {
+        self.bar.inc(delta);
+    }
+
+    pub fn finish(&self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+fn with_style(bar: ProgressBar, template: &str) -> ProgressBar {
+    if let Ok(style) = ProgressStyle::with_template(template) {
+        bar.set_style(style);
+    }
+    bar
+}
+
+/// The transfer rate, in MB/s.
+pub fn rate(bytes: u64, elapsed: Duration) -> f64 {
+    match elapsed.as_secs_f64() {
+        secs if secs > 0.0 => bytes as f64 / 1_000_000.0 / secs,
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compute_rates() {
+        assert_eq!(rate(5_000_000, Duration::from_secs(2)), 2.5);
+        assert_eq!(rate(1_500_000, Duration::from_millis(500)), 3.0);
+        assert_eq!(rate(42, Duration::ZERO), 0.0);
+    }
+
+    #[test]
+    fn hide_small_or_disabled_transfers() {
+        assert!(Progress::bytes(Some(1024), true).bar.is_hidden());
+        assert!(Progress::bytes(Some(THRESHOLD * 8), false).bar.is_hidden());
+        assert!(Progress::files(10, false).bar.is_hidden());
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::IsTerminal;
+
+use inquire::{Confirm, MultiSelect, Password, Select, Text};
+
+use crate::errors::{Errors, Result};
+
+/// Prompter asks the user for input, so that interactive flows can be driven
+/// by a scripted implementation in tests.
+pub trait Prompter: Send + Sync {
+    /// Select one of the options, returns the index of the selected option.
+    fn select(&self, message: &str, options: &[String], default: Option<usize>) -> Result<usize>;
+
+    /// Select any of the options, returns the indexes of the selected options.
+    fn multi_select(&self, message: &str, options: &[String], defaults: &[usize]) -> Result<Vec<usize>>;
+
+    /// Ask for a line of text, the default is used when the input is empty.
+    fn text(&self, message: &str, default: Option<&str>) -> Result<String>;
+
+    /// Ask for a secret without echoing it.
+    fn password(&self, message: &str) -> Result<String>;
+
+    /// Ask a yes/no question.
+    fn confirm(&self, message: &str, default: bool) -> Result<bool>;
+}
+
+/// The terminal prompter backed by inquire.
+///
+/// When stdin is not a terminal, prompts fall back to their defaults and fail
+/// if there is none. Confirmations are answered with yes when `assume_yes` is set.
+pub struct InquirePrompter {
+    assume_yes: bool,
+    interactive: bool,
+}
+
+impl InquirePrompter {
+    pub fn new(assume_yes: bool) -> Self {
+        Self { assume_yes, interactive: std::io::stdin().is_terminal() }
+    }
+}
+
+impl Default for InquirePrompter {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl Prompter for InquirePrompter {
+    fn select(&self, message: &str, options: &[String], default: Option<usize>) -> Result<usize> {
+        if !self.interactive {
+            return default.ok_or_else(|| Errors::PromptRequired(message.to_string()));
+        }
+
+        let mut prompt = Select::new(message, options.to_vec());
+        if let Some(default) = default {
+            prompt = prompt.with_starting_cursor(default);
+        }
+        prompt.raw_prompt().map(|answer| answer.index).map_err(Errors::InquireError)
+    }
+
+    fn multi_select(&self, message: &str, options: &[String], defaults: &[usize]) -> Result<Vec<usize>> {
+        if !self.interactive {
+            return Ok(defaults.to_vec());
+        }
+
+        let prompt = MultiSelect::new(message, options.to_vec()).with_default(defaults);
+        let answers = prompt.raw_prompt().map_err(Errors::InquireError)?;
+        Ok(answers.into_iter().map(|answer| answer.index).collect())
+    }
+
+    fn text(&self, message: &str, default: Option<&str>) -> Result<String> {
+        if !self.interactive {
+            return default.map(String::from).ok_or_else(|| Errors::PromptRequired(message.to_string()));
+        }
+
+        let mut prompt = Text::new(message);
+        if let Some(default) = default {
+            prompt = prompt.with_default(default);
+        }
+        prompt.prompt().map_err(Errors::InquireError)
+    }
+
+    fn password(&self, message: &str) -> Result<String> {
+        if !self.interactive {
+            return Err(Errors::PromptRequired(message.to_string()));
+        }
+
+        Password::new(message).prompt().map_err(Errors::InquireError)
+    }
+
+    fn confirm(&self, message: &str, default: bool) -> Result<bool> {
+        if let Some(answer) = self.preset(default) {
+            return Ok(answer);
+        }
+
+        Confirm::new(message).with_default(default).prompt().map_err(Errors::InquireError)
+    }
+}
+
+impl InquirePrompter {
+    /// The answer of a confirmation given without prompting, None if the user is asked.
+    fn preset(&self, default: bool) -> Option<bool> {
+        match (self.assume_yes, self.interactive) {
+            (true, _) => Some(true),
+            (false, false) => Some(default),
+            (false, true) => None,
+        }
+    }
+}
+
+/// A scripted answer for the [`ScriptedPrompter`].
+#[cfg(test)]
+#[derive(Debug)]
+pub enum Answer {
+    Select(usize),
+    MultiSelect(Vec<usize>),
+    Text(String),
+    Password(String),
+    Confirm(bool),
+    /// Accept the default of the prompt
+    Default,
+}
+
+/// A prompter replaying the given answers in order, for tests.
+#[cfg(test)]
+pub struct ScriptedPrompter {
+    answers: std::sync::Mutex<std::collections::VecDeque<Answer>>,
+}
+
+#[cfg(test)]
+impl ScriptedPrompter {
+    pub fn new(answers: Vec<Answer>) -> Self {
+        Self { answers: std::sync::Mutex::new(answers.into()) }
+    }
+
+    fn next(&self, message: &str) -> Answer {
+        let answer = self.answers.lock().unwrap().pop_front();
+        answer.unwrap_or_else(|| panic!("no scripted answer left for {:?}", message))
+    }
+}
+
+#[cfg(test)]
+impl Prompter for ScriptedPrompter {
+    fn select(&self, message: &str, _: &[String], default: Option<usize>) -> Result<usize> {
+        match self.next(message) {
+            Answer::Select(index) => Ok(index),
+            Answer::Default => default.ok_or_else(|| Errors::PromptRequired(message.to_string())),
+            other => panic!("unexpected answer {:?} for {:?}", other, message),
+        }
+    }
+
+    fn multi_select(&self, message: &str, _: &[String], defaults: &[usize]) -> Result<Vec<usize>> {
+        match self.next(message) {
+            Answer::MultiSelect(indexes) => Ok(indexes),
+            Answer::Default => Ok(defaults.to_vec()),
+            other => panic!("unexpected answer {:?} for {:?}", other, message),
+        }
+    }
+
+    fn text(&self, message: &str, default: Option<&str>) -> Result<String> {
+        match self.next(message) {
+            Answer::Text(text) => Ok(text),
+            Answer::Default => default.map(String::from).ok_or_else(|| Errors::PromptRequired(message.to_string())),
+            other => panic!("unexpected answer {:?} for {:?}", other, message),
+        }
+    }
+
+    fn password(&self, message: &str) -> Result<String> {
+        match self.next(message) {
+            Answer::Password(password) => Ok(password),
+            other => panic!("unexpected answer {:?} for {:?}", other, message),
+        }
+    }
+
+    fn confirm(&self, message: &str, default: bool) -> Result<bool> {
+        match self.next(message) {
+            Answer::Confirm(yes) => Ok(yes),
+            Answer::Default => Ok(default),
+            other => panic!("unexpected answer {:?} for {:?}", other, message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_interactive_falls_back_to_defaults() {
+        let prompter = InquirePrompter { assume_yes: false, interactive: false };
+        assert_eq!(prompter.select("Pick", &["a".into(), "b".into()], Some(1)).unwrap(), 1);
+        assert_eq!(prompter.multi_select("Pick", &["a".into(), "b".into()], &[0, 1]).unwrap(), vec![0, 1]);
+        assert_eq!(prompter.text("Name", Some("demo")).unwrap(), "demo");
+        assert!(!prompter.confirm("Sure?", false).unwrap());
+        assert!(matches!(prompter.text("Name", None), Err(Errors::PromptRequired(_))));
+        assert!(matches!(prompter.password("Token"), Err(Errors::PromptRequired(_))));
+
+        let prompter = InquirePrompter { assume_yes: true, interactive: false };
+        assert!(prompter.confirm("Sure?", false).unwrap());
+    }
+
+    #[test]
+    fn confirm_prompts_unless_assume_yes() {
+        use clap::Parser;
+
+        use crate::cmd::cli::Cli;
+
+        let cli = Cli::try_parse_from(["amp", "clean", "--all"]).unwrap();
+        let prompter = InquirePrompter { assume_yes: cli.assume_yes, interactive: true };
+        assert_eq!(prompter.preset(false), None, "the user is asked");
+
+        let cli = Cli::try_parse_from(["amp", "clean", "--all", "--assume-yes"]).unwrap();
+        let prompter = InquirePrompter { assume_yes: cli.assume_yes, interactive: true };
+        assert_eq!(prompter.preset(false), Some(true));
+    }
+}
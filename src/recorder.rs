@@ -0,0 +1,552 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::field::Empty;
+use tracing::{info_span, Instrument, Span};
+
+use crate::errors::{self, Errors};
+
+/// Keys whose values are never printed, matched case-insensitively.
+const SECRET_KEYS: [&str; 6] = ["token", "password", "secret", "authorization", "credential", "api_key"];
+
+/// Byte arrays and strings longer than this are elided in full dumps.
+const MAX_INLINE_BYTES: usize = 256;
+
+tokio::task_local! {
+    /// The response of the API call being recorded, noted by [`observe`].
+    static RESPONSE: RefCell<Response>;
+}
+
+/// How much detail to print for each API call.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Mode {
+    /// Print nothing
+    #[default]
+    Off,
+    /// Print one concise line per call
+    Summary,
+    /// Print the summary line followed by the redacted response body
+    Full,
+}
+
+/// Recorder observes the API calls made by the CLI, dumps them to stderr
+/// and keeps their durations for the timings summary. In a dry run, the
+/// mutating calls are planned rather than sent.
+#[derive(Debug)]
+pub struct Recorder {
+    mode: Mode,
+    summary: bool,
+    dry_run: bool,
+    started: Instant,
+    calls: Mutex<Vec<Call>>,
+    planned: Mutex<Vec<Planned>>,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self::new(Mode::Off, false)
+    }
+}
+
+/// A finished attempt of an API call, the retries of a call are recorded
+/// as attempts of their own.
+#[derive(Clone, Debug, Serialize)]
+pub struct Call {
+    pub method: String,
+    pub path: String,
+    pub attempt: usize,
+    #[serde(rename = "duration_ms", serialize_with = "as_millis")]
+    pub duration: Duration,
+    #[serde(flatten)]
+    pub response: Response,
+}
+
+impl Call {
+    /// The summary line of the call, `OK` with the size of the response or
+    /// `ERR` with the error, followed by the request ID if any.
+    fn line(&self, outcome: &str, detail: &str) -> String {
+        let mut line = format!("[http] {} {}", self.method, self.path);
+        if let Some(status) = self.response.status {
+            line.push_str(&format!(" {}", status));
+        }
+        line.push_str(&format!(" {} {} {}", outcome, format_duration(self.duration), detail));
+        if let Some(id) = &self.response.request_id {
+            line.push_str(&format!(" request-id={}", id));
+        }
+        line
+    }
+}
+
+/// The status and the request ID of the response to an API call, when the
+/// call noted them with [`observe`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Response {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// Note the status and the request ID of the response to the API call being
+/// recorded, outside of a recorded call it is a no-op.
+pub fn observe(status: u16, request_id: Option<&str>) {
+    let _ = RESPONSE.try_with(|response| {
+        *response.borrow_mut() = Response { status: Some(status), request_id: request_id.map(String::from) }
+    });
+}
+
+/// A mutating API call that a dry run didn't send.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Planned {
+    pub method: String,
+    pub path: String,
+    /// The redacted body of the request, if any
+    pub body: Option<Value>,
+}
+
+impl Display for Planned {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.method, self.path)?;
+        if let Some(body) = &self.body {
+            write!(f, "\n{}", serde_json::to_string_pretty(body).unwrap_or_default())?;
+        }
+        Ok(())
+    }
+}
+
+/// The aggregated timings of a command run.
+#[derive(Debug, Serialize)]
+pub struct Timings {
+    pub calls: usize,
+    #[serde(rename = "api_ms", serialize_with = "as_millis")]
+    pub api: Duration,
+    #[serde(rename = "local_ms", serialize_with = "as_millis")]
+    pub local: Duration,
+    pub slowest: Option<Call>,
+}
+
+impl Display for Timings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "API: {} calls, {:.1}s total", self.calls, self.api.as_secs_f64())?;
+        if let Some(call) = &self.slowest {
+            write!(f, " (slowest: {} {} {})", call.method, call.path, format_duration(call.duration))?;
+        }
+        write!(f, "; local: {:.1}s", self.local.as_secs_f64())
+    }
+}
+
+impl Recorder {
+    /// Create a recorder, `summary` enables the timings summary of the command.
+    pub fn new(mode: Mode, summary: bool) -> Self {
+        Self {
+            mode,
+            summary,
+            dry_run: false,
+            started: Instant::now(),
+            calls: Mutex::new(vec![]),
+            planned: Mutex::new(vec![]),
+        }
+    }
+
+    /// Plan the mutating calls instead of sending them, see [`Recorder::mutate`].
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// The mutating calls planned so far by the dry run.
+    pub fn planned(&self) -> Vec<Planned> {
+        self.planned.lock().unwrap().clone()
+    }
+
+    /// The timings since the recorder was created, if the summary is enabled.
+    pub fn summary(&self) -> Option<Timings> {
+        self.summary.then(|| self.timings(self.started.elapsed()))
+    }
+
+    /// Aggregate the recorded calls, the local time is what remains of the given
+    /// elapsed time of the whole command.
+    pub fn timings(&self, elapsed: Duration) -> Timings {
+        let calls = self.calls.lock().unwrap();
+        let api: Duration = calls.iter().map(|c| c.duration).sum();
+        let slowest = calls.iter().max_by_key(|c| c.duration).cloned();
+
+        Timings { calls: calls.len(), api, local: elapsed.saturating_sub(api), slowest }
+    }
+
+    /// Run the given API call, printing a summary of it according to the mode.
+    pub fn call<T, E, F>(&self, method: &str, path: &str, f: F) -> Result<T, E>
+    where
+        T: Serialize,
+        E: Display,
+        F: FnOnce() -> Result<T, E>,
+    {
+        let span = info_span!("api", method, path, attempt = 1, status = Empty, duration_ms = Empty, bytes = Empty);
+        let _entered = span.enter();
+
+        let start = Instant::now();
+        let (result, response) = RESPONSE.sync_scope(RefCell::default(), || (f(), RESPONSE.with(RefCell::take)));
+        self.report(method, path, 1, start.elapsed(), response, &result);
+
+        result
+    }
+
+    /// Await the given API call, printing a summary of it according to the mode.
+    pub async fn call_async<T, E, F>(&self, method: &str, path: &str, f: F) -> Result<T, E>
+    where
+        T: Serialize,
+        E: Display,
+        F: Future<Output = Result<T, E>>,
+    {
+        self.attempt(method, path, 1, f).await
+    }
+
+    /// Await the API call built by `f` until it succeeds, up to `attempts` times.
+    /// Each attempt is reported and timed on its own.
+    pub async fn retry_async<T, E, F, Fut>(&self, method: &str, path: &str, attempts: usize, mut f: F) -> Result<T, E>
+    where
+        T: Serialize,
+        E: Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match self.attempt(method, path, attempt, f()).await {
+                Err(_) if attempt < attempts => attempt += 1,
+                result => return result,
+            }
+        }
+    }
+
+    async fn attempt<T, E, F>(&self, method: &str, path: &str, attempt: usize, f: F) -> Result<T, E>
+    where
+        T: Serialize,
+        E: Display,
+        F: Future<Output = Result<T, E>>,
+    {
+        let span = info_span!("api", method, path, attempt, status = Empty, duration_ms = Empty, bytes = Empty);
+        async move {
+            let start = Instant::now();
+            let observed = async { (f.await, RESPONSE.with(RefCell::take)) };
+            let (result, response) = RESPONSE.scope(RefCell::default(), observed).await;
+            self.report(method, path, attempt, start.elapsed(), response, &result);
+
+            result
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Run the given mutating API call like [`Recorder::call`]. In a dry run,
+    /// the call is planned with its redacted body instead, and the command is
+    /// stopped with [`Errors::DryRun`] since what follows depends on its response.
+    pub fn mutate<T, E, F>(&self, method: &str, path: &str, body: Option<Value>, f: F) -> errors::Result<T>
+    where
+        T: Serialize,
+        E: Display + Into<Errors>,
+        F: FnOnce() -> Result<T, E>,
+    {
+        self.plan(method, path, body)?;
+        self.call(method, path, f).map_err(Into::into)
+    }
+
+    /// Await the given mutating API call, or plan it in a dry run, see [`Recorder::mutate`].
+    pub async fn mutate_async<T, E, F>(&self, method: &str, path: &str, body: Option<Value>, f: F) -> errors::Result<T>
+    where
+        T: Serialize,
+        E: Display + Into<Errors>,
+        F: Future<Output = Result<T, E>>,
+    {
+        self.plan(method, path, body)?;
+        self.call_async(method, path, f).await.map_err(Into::into)
+    }
+
+    /// Retry the given mutating API call like [`Recorder::retry_async`], or plan
+    /// it in a dry run. Only the calls the server deduplicates are safe to retry.
+    pub async fn mutate_retry_async<T, E, F, Fut>(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<Value>,
+        attempts: usize,
+        f: F,
+    ) -> errors::Result<T>
+    where
+        T: Serialize,
+        E: Display + Into<Errors>,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.plan(method, path, body)?;
+        self.retry_async(method, path, attempts, f).await.map_err(Into::into)
+    }
+
+    fn plan(&self, method: &str, path: &str, body: Option<Value>) -> errors::Result<()> {
+        if !self.dry_run {
+            return Ok(());
+        }
+
+        let call = Planned { method: method.to_string(), path: path.to_string(), body: body.map(redact) };
+        self.planned.lock().unwrap().push(call);
+        Err(Errors::DryRun(format!("{} {}", method, path)))
+    }
+
+    fn report<T, E>(
+        &self,
+        method: &str,
+        path: &str,
+        attempt: usize,
+        elapsed: Duration,
+        response: Response,
+        result: &Result<T, E>,
+    ) where
+        T: Serialize,
+        E: Display,
+    {
+        if let Some(status) = response.status {
+            Span::current().record("status", status);
+        }
+        Span::current().record("duration_ms", elapsed.as_millis() as u64);
+        let call = Call { method: method.to_string(), path: path.to_string(), attempt, duration: elapsed, response };
+        self.calls.lock().unwrap().push(call.clone());
+
+        if self.mode == Mode::Off {
+            return;
+        }
+        if attempt > 1 {
+            eprintln!("[http] {} {} retrying, attempt {}", method, path, attempt);
+        }
+
+        match result {
+            Ok(value) => {
+                let body = serde_json::to_value(value).unwrap_or(Value::Null);
+                let bytes = serde_json::to_vec(&body).map(|b| b.len()).unwrap_or_default();
+                Span::current().record("bytes", bytes);
+                eprintln!("{}", call.line("OK", &format_bytes(bytes)));
+                if self.mode == Mode::Full {
+                    let body = redact(body);
+                    eprintln!("{}", serde_json::to_string_pretty(&body).unwrap_or_default());
+                }
+            }
+            Err(err) => {
+                eprintln!("{}", call.line("ERR", &format!("({})", err)));
+            }
+        }
+    }
+
+    /// Print that a streaming connection was opened.
+    pub fn stream_opened(&self, path: &str) {
+        if self.mode != Mode::Off {
+            eprintln!("[http] GET {} STREAM opened", path);
+        }
+    }
+
+    /// Print that a streaming connection was closed, with the frames received.
+    pub fn stream_closed(&self, path: &str, frames: usize, elapsed: Duration, request_id: Option<&str>) {
+        if self.mode == Mode::Off {
+            return;
+        }
+
+        let mut line = format!("[http] GET {} STREAM closed {} {} frames", path, format_duration(elapsed), frames);
+        if let Some(id) = request_id {
+            line.push_str(&format!(" request-id={}", id));
+        }
+        eprintln!("{}", line);
+    }
+}
+
+/// Replace secret values and elide binary payloads in the given JSON value.
+fn redact(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| {
+                    let lower = key.to_lowercase();
+                    if SECRET_KEYS.iter().any(|s| lower.contains(s)) && !value.is_null() {
+                        (key, Value::String("<redacted>".into()))
+                    } else {
+                        (key, redact(value))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(items) if items.len() > MAX_INLINE_BYTES && items.iter().all(Value::is_u64) => {
+            Value::String(format!("<binary {} bytes>", items.len()))
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(redact).collect()),
+        Value::String(s) if s.len() > MAX_INLINE_BYTES => Value::String(format!("<elided {} bytes>", s.len())),
+        other => other,
+    }
+}
+
+fn as_millis<S: serde::Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{}ms", duration.as_millis())
+}
+
+fn format_bytes(bytes: usize) -> String {
+    if bytes < 1024 {
+        return format!("{}B", bytes);
+    }
+    format!("{:.1}KiB", bytes as f64 / 1024.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::client::{self, Api, REQUEST_ID};
+
+    #[test]
+    fn redact_secrets() {
+        let value = redact(json!({"title": "demo", "token": "abc", "nested": {"Password": "p"}}));
+        assert_eq!(value, json!({"title": "demo", "token": "<redacted>", "nested": {"Password": "<redacted>"}}));
+    }
+
+    #[test]
+    fn api_calls_are_spanned() {
+        let (subscriber, spans) = crate::trace::capture();
+        tracing::subscriber::with_default(subscriber, || {
+            let _ = Recorder::default().call("GET", "/playbooks", || {
+                observe(200, Some("abc"));
+                Ok::<_, String>(())
+            });
+        });
+
+        let spans = spans.lock().unwrap();
+        let api = spans.iter().find(|s| s.name == "api").expect("an api span");
+        assert_eq!(api.fields.get("method").map(String::as_str), Some("GET"));
+        assert_eq!(api.fields.get("path").map(String::as_str), Some("/playbooks"));
+        assert_eq!(api.fields.get("status").map(String::as_str), Some("200"));
+        assert!(api.fields.contains_key("duration_ms"));
+    }
+
+    #[tokio::test]
+    async fn record_the_status_and_the_request_id() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(201).insert_header(REQUEST_ID, "abc").set_body_json(json!({})))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .respond_with(ResponseTemplate::new(503).insert_header(REQUEST_ID, "def"))
+            .mount(&server)
+            .await;
+        let api = Api::new(reqwest::Client::new(), &server.uri(), None);
+
+        let recorder = Recorder::default();
+        let request = |request: reqwest::RequestBuilder| async move {
+            client::send(request).await.and_then(|r| r.error_for_status()).map(|_| ())
+        };
+        let created = recorder.call_async("POST", "/playbooks", request(api.post("/playbooks"))).await;
+        assert!(created.is_ok());
+        let deleted = recorder.call_async("DELETE", "/playbooks/42", request(api.delete("/playbooks/42"))).await;
+        assert!(deleted.is_err());
+        let _ = recorder.call("GET", "/playbooks", || Ok::<_, String>(()));
+        observe(200, Some("ignored"));
+
+        let calls = recorder.calls.lock().unwrap().clone();
+        let responses: Vec<_> = calls.iter().map(|c| (c.response.status, c.response.request_id.as_deref())).collect();
+        assert_eq!(responses, vec![(Some(201), Some("abc")), (Some(503), Some("def")), (None, None)]);
+
+        let line = |call: &Call, outcome: &str, detail: &str| {
+            Call { duration: Duration::from_millis(12), ..call.clone() }.line(outcome, detail)
+        };
+        assert_eq!(line(&calls[0], "OK", "2B"), "[http] POST /playbooks 201 OK 12ms 2B request-id=abc");
+        assert_eq!(line(&calls[1], "ERR", "(503)"), "[http] DELETE /playbooks/42 503 ERR 12ms (503) request-id=def");
+        assert_eq!(line(&calls[2], "OK", "4B"), "[http] GET /playbooks OK 12ms 4B");
+    }
+
+    #[test]
+    fn aggregate_timings() {
+        let recorder = Recorder::default();
+        let _ = recorder.call("GET", "/playbooks", || Ok::<_, String>(()));
+        let slow = Duration::from_millis(820);
+        recorder.report("POST", "/playbooks", 1, slow, Response::default(), &Ok::<_, String>(()));
+
+        let timings = recorder.timings(Duration::from_secs(2));
+        assert_eq!(timings.calls, 2);
+        assert_eq!(timings.slowest.as_ref().map(|c| c.path.as_str()), Some("/playbooks"));
+        assert_eq!(timings.slowest.as_ref().map(|c| c.method.as_str()), Some("POST"));
+        assert!(timings.to_string().starts_with("API: 2 calls"));
+    }
+
+    #[tokio::test]
+    async fn count_the_attempts_separately() {
+        let recorder = Recorder::default();
+        let mut failures = 2;
+        let result = recorder
+            .retry_async("POST", "/playbooks", 3, || {
+                let result = match failures {
+                    0 => Ok(()),
+                    _ => Err(String::from("503 Service Unavailable")),
+                };
+                failures = failures.saturating_sub(1);
+                async move { result }
+            })
+            .await;
+        assert!(result.is_ok());
+
+        let calls = recorder.calls.lock().unwrap().clone();
+        assert_eq!(calls.iter().map(|c| c.attempt).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(recorder.timings(Duration::from_secs(1)).calls, 3);
+
+        let result = recorder.retry_async("GET", "/playbooks", 2, || async { Err::<(), _>("timed out") }).await;
+        assert_eq!(result, Err("timed out"));
+        assert_eq!(recorder.timings(Duration::from_secs(1)).calls, 5);
+    }
+
+    #[test]
+    fn plan_the_mutations_in_a_dry_run() {
+        let recorder = Recorder::default().with_dry_run(true);
+        let sent = Mutex::new(vec![]);
+
+        let list = recorder.call("GET", "/playbooks", || Ok::<_, String>(sent.lock().unwrap().push("GET")));
+        assert!(list.is_ok());
+        let body = json!({"title": "demo", "token": "abc"});
+        let create =
+            recorder.mutate("POST", "/playbooks", Some(body), || Ok::<_, Errors>(sent.lock().unwrap().push("POST")));
+        assert!(matches!(create, Err(Errors::DryRun(call)) if call == "POST /playbooks"));
+
+        assert_eq!(*sent.lock().unwrap(), vec!["GET"]);
+        let planned = recorder.planned();
+        assert_eq!(planned.len(), 1);
+        assert_eq!(
+            planned[0].to_string(),
+            "POST /playbooks\n{\n  \"title\": \"demo\",\n  \"token\": \"<redacted>\"\n}"
+        );
+    }
+
+    #[test]
+    fn elide_binary_payloads() {
+        let value = redact(json!({ "payload": vec![1u8; 1024] }));
+        assert_eq!(value, json!({"payload": "<binary 1024 bytes>"}));
+    }
+}
@@ -0,0 +1,187 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use chrono::{SecondsFormat, Utc};
+use colored::Colorize;
+use tabled::Tabled;
+use tokio::time::{sleep, Duration};
+
+use crate::errors::Result;
+use crate::output::{self, Layout, Stdout};
+use crate::shutdown::Shutdown;
+
+/// Clear the screen and move the cursor to the top left corner.
+const CLEAR: &str = "\x1b[2J\x1b[H";
+
+/// Refreshes a table in place on terminals, or appends timestamped snapshots
+/// of it when redirected. The rows added since the previous snapshot are
+/// flagged with `+`, and the removed ones are shown once more flagged with `-`.
+pub struct Refresh {
+    interactive: bool,
+    /// The fields of the rows of the previous snapshot, by key
+    previous: Option<BTreeMap<String, Vec<String>>>,
+    /// Whether the previous snapshot flagged rows, to clear the flags on the next refresh
+    flagged: bool,
+}
+
+impl Refresh {
+    pub fn new(interactive: bool) -> Self {
+        Self { interactive, previous: None, flagged: false }
+    }
+
+    /// Whether the last snapshot flagged rows, so it is worth refreshing even
+    /// if nothing changed since.
+    pub fn flagged(&self) -> bool {
+        self.flagged
+    }
+
+    /// Render the keyed rows as a table, flagging the changes since the previous snapshot.
+    pub fn table<R: Tabled>(&mut self, rows: Vec<(String, R)>, layout: Layout) -> String {
+        let headers = R::headers().into_iter().map(|h| h.into_owned()).collect();
+        let rows = rows.into_iter().map(|(key, row)| (key, row.fields().into_iter().map(|f| f.into_owned()).collect()));
+        self.records(headers, rows.collect(), layout)
+    }
+
+    /// Render the keyed records as a table like [`Refresh::table`], such as the
+    /// columns picked by [`output::Columns`].
+    pub fn records(&mut self, headers: Vec<String>, rows: Vec<(String, Vec<String>)>, layout: Layout) -> String {
+        let current: BTreeMap<String, Vec<String>> = rows.iter().cloned().collect();
+        let previous = self.previous.replace(current.clone());
+
+        let mut records = vec![[String::new()].into_iter().chain(headers).collect()];
+        let mut flagged = false;
+        for (key, _) in &rows {
+            let added = previous.as_ref().is_some_and(|p| !p.contains_key(key));
+            flagged |= added;
+            records.push(flag(if added { "+".green().to_string() } else { String::new() }, &current[key]));
+        }
+        let removed = previous.iter().flatten().filter(|(key, _)| !current.contains_key(*key));
+        for (_, fields) in removed {
+            flagged = true;
+            let fields: Vec<String> = fields.iter().map(|f| f.dimmed().to_string()).collect();
+            records.push(flag("-".red().to_string(), &fields));
+        }
+        self.flagged = flagged;
+
+        output::records_table(records, layout)
+    }
+
+    /// Write the snapshot, in place of the previous one on terminals.
+    pub fn show(&self, stdout: &Stdout, snapshot: &str) -> Result<()> {
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        match self.interactive {
+            true => stdout.print(format!("{}{}\n\n{}\n", CLEAR, now, snapshot)),
+            false => stdout.print(format!("# {}\n{}\n", now, snapshot)),
+        }
+    }
+}
+
+fn flag(flag: String, fields: &[String]) -> Vec<String> {
+    std::iter::once(flag).chain(fields.iter().cloned()).collect()
+}
+
+/// Paces the refreshes: the first tick is right away, the next ones after
+/// every interval, until the shutdown is triggered.
+pub struct Ticker<'a> {
+    shutdown: &'a Shutdown,
+    interval: Duration,
+    started: bool,
+}
+
+impl<'a> Ticker<'a> {
+    pub fn new(shutdown: &'a Shutdown, interval: Duration) -> Self {
+        Self { shutdown, interval, started: false }
+    }
+
+    /// Wait for the next refresh, returns false once the shutdown is triggered.
+    pub async fn tick(&mut self) -> bool {
+        if !std::mem::replace(&mut self.started, true) {
+            return !self.shutdown.is_triggered();
+        }
+
+        tokio::select! {
+            _ = sleep(self.interval) => !self.shutdown.is_triggered(),
+            _ = self.shutdown.triggered() => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shutdown::Signal;
+
+    #[derive(Tabled)]
+    struct Row {
+        name: String,
+        status: String,
+    }
+
+    fn rows(rows: &[(&str, &str)]) -> Vec<(String, Row)> {
+        rows.iter().map(|(n, s)| (n.to_string(), Row { name: n.to_string(), status: s.to_string() })).collect()
+    }
+
+    /// The flags and names of the rows of the rendered table.
+    fn flags(table: &str) -> Vec<String> {
+        let table = String::from_utf8(strip_ansi(table.as_bytes())).unwrap();
+        table.lines().map(|l| l.split_whitespace().collect::<Vec<_>>().join(" ")).filter(|l| !l.is_empty()).collect()
+    }
+
+    fn strip_ansi(bytes: &[u8]) -> Vec<u8> {
+        let mut out = vec![];
+        let mut escape = false;
+        for &b in bytes {
+            match (escape, b) {
+                (false, 0x1b) => escape = true,
+                (true, b'm') => escape = false,
+                (true, _) => {}
+                (false, _) => out.push(b),
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn flag_the_rows_added_and_removed() {
+        let mut refresh = Refresh::new(false);
+
+        let first = refresh.table(rows(&[("web", "running"), ("api", "pending")]), Layout::new(false));
+        assert_eq!(flags(&first), vec!["web running", "api pending"]);
+        assert!(!refresh.flagged(), "the first snapshot has nothing to compare with");
+
+        let second = refresh.table(rows(&[("web", "running"), ("db", "pending")]), Layout::new(false));
+        assert_eq!(flags(&second), vec!["web running", "+ db pending", "- api pending"]);
+        assert!(refresh.flagged());
+
+        let third = refresh.table(rows(&[("web", "running"), ("db", "running")]), Layout::new(false));
+        assert_eq!(flags(&third), vec!["web running", "db running"]);
+        assert!(!refresh.flagged(), "the flags are cleared on the next refresh");
+    }
+
+    #[tokio::test]
+    async fn stop_refreshing_on_shutdown() {
+        let shutdown = Shutdown::default();
+        let mut ticker = Ticker::new(&shutdown, Duration::from_millis(5));
+        let mut ticks = 0;
+        while ticker.tick().await {
+            ticks += 1;
+            if ticks == 3 {
+                shutdown.trigger(Signal::Interrupt);
+            }
+        }
+        assert_eq!(ticks, 3);
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Display;
+
+use amp_common::schema::Character;
+use schemars::schema::{InstanceType, RootSchema, Schema, SchemaObject, SingleOrVec};
+use serde_json::Value;
+
+/// The JSON Schema of the character manifest, with the struct docs as descriptions.
+pub fn manifest() -> RootSchema {
+    schemars::schema_for!(Character)
+}
+
+/// An issue found when validating a manifest against the schema.
+#[derive(Debug, PartialEq)]
+pub struct Issue {
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = if self.path.is_empty() { "<root>" } else { &self.path };
+        write!(f, "{}: {}", path, self.message)
+    }
+}
+
+/// Validate the value against the schema, reporting the type mismatches, the
+/// missing required keys and the unknown keys with their paths.
+pub fn validate(schema: &RootSchema, value: &Value) -> Vec<Issue> {
+    let mut issues = vec![];
+    Validator { root: schema }.object(&schema.schema, value, "", &mut issues);
+    issues
+}
+
+struct Validator<'a> {
+    root: &'a RootSchema,
+}
+
+impl Validator<'_> {
+    fn schema(&self, schema: &Schema, value: &Value, path: &str, issues: &mut Vec<Issue>) {
+        match schema {
+            Schema::Bool(true) => {}
+            Schema::Bool(false) => issues.push(issue(path, "is not allowed")),
+            Schema::Object(object) => self.object(object, value, path, issues),
+        }
+    }
+
+    fn object(&self, schema: &SchemaObject, value: &Value, path: &str, issues: &mut Vec<Issue>) {
+        if let Some(reference) = &schema.reference {
+            let name = reference.trim_start_matches("#/definitions/");
+            if let Some(definition) = self.root.definitions.get(name) {
+                self.schema(definition, value, path, issues);
+            }
+            return;
+        }
+
+        if let Some(subschemas) = &schema.subschemas {
+            for subschema in subschemas.all_of.iter().flatten() {
+                self.schema(subschema, value, path, issues);
+            }
+            for alternatives in [&subschemas.any_of, &subschemas.one_of].into_iter().flatten() {
+                issues.extend(self.closest(alternatives, value, path));
+            }
+        }
+
+        if let Some(types) = &schema.instance_type {
+            let types: &[InstanceType] = match types {
+                SingleOrVec::Single(t) => std::slice::from_ref(t),
+                SingleOrVec::Vec(types) => types,
+            };
+            if !types.iter().any(|t| matches(t, value)) {
+                let expected: Vec<String> = types.iter().map(|t| format!("{:?}", t).to_lowercase()).collect();
+                issues.push(issue(path, format!("expected {}, found {}", expected.join(" or "), kind(value))));
+                return;
+            }
+        }
+
+        if let Some(values) = &schema.enum_values {
+            if !values.contains(value) {
+                let allowed: Vec<String> = values.iter().map(Value::to_string).collect();
+                issues.push(issue(path, format!("expected one of {}, found {}", allowed.join(", "), value)));
+            }
+        }
+
+        if let (Some(array), Value::Array(items)) = (&schema.array, value) {
+            if let Some(SingleOrVec::Single(item)) = &array.items {
+                for (i, value) in items.iter().enumerate() {
+                    self.schema(item, value, &format!("{}[{}]", path, i), issues);
+                }
+            }
+        }
+
+        if let (Some(object), Value::Object(map)) = (&schema.object, value) {
+            for key in object.required.iter().filter(|k| !map.contains_key(*k)) {
+                issues.push(issue(&join(path, key), "is required"));
+            }
+            for (key, value) in map {
+                let path = join(path, key);
+                match (object.properties.get(key), &object.additional_properties) {
+                    (Some(property), _) => self.schema(property, value, &path, issues),
+                    (None, Some(additional)) => self.schema(additional, value, &path, issues),
+                    (None, None) => issues.push(issue(&path, "is an unknown key")),
+                }
+            }
+        }
+    }
+
+    /// The issues of the alternative that fits the value best, none if any fits.
+    fn closest(&self, alternatives: &[Schema], value: &Value, path: &str) -> Vec<Issue> {
+        let mut best: Option<Vec<Issue>> = None;
+        for alternative in alternatives {
+            let mut issues = vec![];
+            self.schema(alternative, value, path, &mut issues);
+            let better = match &best {
+                Some(best) => issues.len() < best.len(),
+                None => true,
+            };
+            if better {
+                best = Some(issues);
+            }
+        }
+        best.unwrap_or_default()
+    }
+}
+
+fn issue(path: &str, message: impl Into<String>) -> Issue {
+    Issue { path: path.to_string(), message: message.into() }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        return key.to_string();
+    }
+    format!("{}.{}", path, key)
+}
+
+fn matches(expected: &InstanceType, value: &Value) -> bool {
+    match expected {
+        InstanceType::Null => value.is_null(),
+        InstanceType::Boolean => value.is_boolean(),
+        InstanceType::Object => value.is_object(),
+        InstanceType::Array => value.is_array(),
+        InstanceType::Number => value.is_number(),
+        InstanceType::String => value.is_string(),
+        InstanceType::Integer => value.is_i64() || value.is_u64(),
+    }
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use schemars::JsonSchema;
+    use serde_json::json;
+
+    use super::*;
+
+    /// A sample manifest
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Sample {
+        /// The name of the sample
+        name: String,
+        services: Vec<Service>,
+        labels: Option<std::collections::HashMap<String, String>>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Service {
+        port: u16,
+        image: Option<String>,
+    }
+
+    fn paths(value: Value) -> Vec<String> {
+        validate(&schemars::schema_for!(Sample), &value).into_iter().map(|i| i.path).collect()
+    }
+
+    #[test]
+    fn report_unknown_keys_and_type_mismatches() {
+        let value = json!({"name": "web", "services": [{"port": 80}, {"port": "80", "extra": true}]});
+        assert_eq!(paths(value), vec!["services[1].extra", "services[1].port"]);
+    }
+
+    #[test]
+    fn report_missing_required_keys() {
+        assert_eq!(paths(json!({"services": [], "labels": {"a": 1}})), vec!["name", "labels.a"]);
+        assert!(paths(json!({"name": "web", "services": [{"port": 80, "image": null}]})).is_empty());
+    }
+
+    #[test]
+    fn descriptions_from_docs() {
+        let schema = schemars::schema_for!(Sample);
+        let description = schema.schema.metadata.as_ref().and_then(|m| m.description.as_deref());
+        assert_eq!(description, Some("A sample manifest"));
+    }
+
+    #[test]
+    fn every_manifest_field_in_schema() {
+        // The strict validation reports any key of the manifest missing from the schema.
+        let value = serde_json::to_value(Character::new("demo")).unwrap();
+        let issues = validate(&manifest(), &value);
+        assert!(issues.is_empty(), "{:?}", issues);
+    }
+}
@@ -0,0 +1,150 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use tokio::sync::watch;
+use tracing::warn;
+
+use crate::context::Context;
+
+/// The signal which asked the CLI to shut down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Signal {
+    /// SIGINT, or Ctrl-C on Windows
+    Interrupt,
+    /// SIGTERM, or the console being closed on Windows
+    Terminate,
+}
+
+impl Signal {
+    /// The conventional exit code for a process terminated by this signal.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Signal::Interrupt => 130,
+            Signal::Terminate => 143,
+        }
+    }
+}
+
+/// Shutdown is a token shared by every long-running loop or stream,
+/// so they can clean up and return once a signal is received.
+#[derive(Debug)]
+pub struct Shutdown {
+    tx: watch::Sender<Option<Signal>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self { tx: watch::Sender::new(None) }
+    }
+}
+
+impl Shutdown {
+    /// Trigger the shutdown, returns false if it was already triggered.
+    pub fn trigger(&self, signal: Signal) -> bool {
+        self.tx.send_if_modified(|current| {
+            if current.is_some() {
+                return false;
+            }
+            current.replace(signal);
+            true
+        })
+    }
+
+    /// The signal that triggered the shutdown, if any.
+    pub fn signal(&self) -> Option<Signal> {
+        *self.tx.borrow()
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.signal().is_some()
+    }
+
+    /// Wait until the shutdown is triggered.
+    pub async fn triggered(&self) -> Signal {
+        let mut rx = self.tx.subscribe();
+        let signal = *rx.wait_for(Option::is_some).await.expect("the shutdown sender is alive");
+        signal.unwrap()
+    }
+}
+
+/// Listen for SIGINT/SIGTERM (and Windows console events) and trigger the
+/// shutdown of the given context. A second signal exits immediately.
+pub async fn listen(ctx: Arc<Context>) {
+    loop {
+        let signal = recv().await;
+        if !ctx.shutdown.trigger(signal) {
+            warn!("Received {:?} again, forcing exit", signal);
+            std::process::exit(signal.exit_code());
+        }
+        warn!("Received {:?}, shutting down (press Ctrl-C again to force)", signal);
+    }
+}
+
+#[cfg(unix)]
+async fn recv() -> Signal {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate()).expect("Error setting SIGTERM handler");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => Signal::Interrupt,
+        _ = terminate.recv() => Signal::Terminate,
+    }
+}
+
+#[cfg(windows)]
+async fn recv() -> Signal {
+    use tokio::signal::windows::{ctrl_break, ctrl_close};
+
+    let mut brk = ctrl_break().expect("Error setting Ctrl-Break handler");
+    let mut close = ctrl_close().expect("Error setting console close handler");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => Signal::Interrupt,
+        _ = brk.recv() => Signal::Interrupt,
+        _ = close.recv() => Signal::Terminate,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn trigger_only_once() {
+        let shutdown = Shutdown::default();
+        assert!(!shutdown.is_triggered());
+        assert!(shutdown.trigger(Signal::Terminate));
+        assert!(!shutdown.trigger(Signal::Interrupt));
+        assert_eq!(shutdown.signal(), Some(Signal::Terminate));
+        assert_eq!(shutdown.signal().map(|s| s.exit_code()), Some(143));
+    }
+
+    #[tokio::test]
+    async fn propagate_to_waiters() {
+        let shutdown = Arc::new(Shutdown::default());
+
+        let waiter = shutdown.clone();
+        let handle = tokio::spawn(async move { waiter.triggered().await });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        shutdown.trigger(Signal::Interrupt);
+
+        assert_eq!(handle.await.unwrap(), Signal::Interrupt);
+        // Waiting after the trigger returns immediately.
+        assert_eq!(shutdown.triggered().await, Signal::Interrupt);
+    }
+}
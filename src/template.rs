@@ -0,0 +1,114 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Write;
+
+use chrono::DateTime;
+use handlebars::{handlebars_helper, no_escape, Handlebars};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::errors::{Errors, Result};
+
+// The values are taken as JSON, so that missing fields render as empty.
+handlebars_helper!(upper: |s: Json| text(s).to_uppercase());
+handlebars_helper!(lower: |s: Json| text(s).to_lowercase());
+handlebars_helper!(truncate: |s: Json, len: u64| truncate_chars(text(s), len as usize));
+handlebars_helper!(format_date: |date: Json, format: str| format_rfc3339(text(date), format));
+
+/// Render the template once for each item, one per line, like
+/// `docker inspect --format`. The items are the template contexts, and the
+/// values are printed as is, without HTML escaping.
+pub fn render<T: Serialize>(template: &str, items: &[T]) -> Result<String> {
+    let mut registry = Handlebars::new();
+    registry.register_escape_fn(no_escape);
+    registry.register_helper("upper", Box::new(upper));
+    registry.register_helper("lower", Box::new(lower));
+    registry.register_helper("truncate", Box::new(truncate));
+    registry.register_helper("format_date", Box::new(format_date));
+    registry.register_template_string("item", template).map_err(|e| Errors::TemplateRenderFailed(e.into()))?;
+
+    let mut output = String::new();
+    for item in items {
+        output += &registry.render("item", item).map_err(Errors::TemplateRenderFailed)?;
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+fn text(value: &Value) -> &str {
+    value.as_str().unwrap_or_default()
+}
+
+/// The RFC 3339 date in the strftime format, as is if either is invalid.
+fn format_rfc3339(date: &str, format: &str) -> String {
+    let mut output = String::new();
+    match DateTime::parse_from_rfc3339(date) {
+        Ok(parsed) if write!(output, "{}", parsed.format(format)).is_ok() => output,
+        _ => date.to_string(),
+    }
+}
+
+/// The first characters of the text, with an ellipsis if it was cut.
+fn truncate_chars(text: &str, len: usize) -> String {
+    match text.char_indices().nth(len) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn items() -> Vec<serde_json::Value> {
+        vec![
+            json!({"id": "1", "title": "Web", "description": "The <web> frontend", "created_at": "2024-03-01T10:00:00Z"}),
+            json!({"id": "2", "title": "Api", "description": null}),
+        ]
+    }
+
+    #[test]
+    fn render_each_item() {
+        assert_eq!(render("{{id}} {{title}}", &items()).unwrap(), "1 Web\n2 Api\n");
+    }
+
+    #[test]
+    fn render_helpers() {
+        let template =
+            r#"{{upper title}} {{lower title}} {{truncate description 7}} {{format_date created_at "%Y-%m-%d"}}"#;
+        let output = render(template, &items()[..1]).unwrap();
+        assert_eq!(output, "WEB web The <we… 2024-03-01\n");
+    }
+
+    #[test]
+    fn render_missing_values_as_empty() {
+        assert_eq!(render("{{id}}:{{upper description}}", &items()[1..]).unwrap(), "2:\n");
+    }
+
+    #[test]
+    fn keep_invalid_dates() {
+        let items = vec![json!({"created_at": "yesterday"})];
+        assert_eq!(render(r#"{{format_date created_at "%Y"}}"#, &items).unwrap(), "yesterday\n");
+        assert_eq!(format_rfc3339("2024-03-01T10:00:00Z", "%Q"), "2024-03-01T10:00:00Z");
+    }
+
+    #[test]
+    fn reject_invalid_templates() {
+        assert!(matches!(render("{{#if id}}", &items()), Err(Errors::TemplateRenderFailed(_))));
+        assert!(matches!(render("{{upper}}", &items()), Err(Errors::TemplateRenderFailed(_))));
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tracing::metadata::LevelFilter;
+use tracing_subscriber::EnvFilter;
+
+/// Initialize the global tracing subscriber, span context of the
+/// current command and operation is printed along with each event.
+/// Logs are written to stderr, keeping stdout for the command output.
+pub fn init() {
+    let filter = EnvFilter::builder().with_default_directive(LevelFilter::INFO.into()).from_env_lossy();
+    tracing_subscriber::fmt()
+        .without_time()
+        .with_target(false)
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
+#[cfg(test)]
+pub use capture::capture;
+
+#[cfg(test)]
+mod capture {
+    use std::collections::HashMap;
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::Subscriber;
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::Layer;
+
+    /// A span captured in tests, with its fields formatted as strings.
+    #[derive(Debug, Default)]
+    pub struct CapturedSpan {
+        pub name: &'static str,
+        pub fields: HashMap<String, String>,
+    }
+
+    type Spans = Arc<Mutex<Vec<CapturedSpan>>>;
+
+    /// Create a subscriber which captures all the spans and their fields.
+    pub fn capture() -> (impl Subscriber + Send + Sync, Spans) {
+        let spans = Spans::default();
+        let subscriber = tracing_subscriber::registry().with(CaptureLayer { spans: spans.clone() });
+        (subscriber, spans)
+    }
+
+    struct CaptureLayer {
+        spans: Spans,
+    }
+
+    struct Index(usize);
+
+    struct Visitor<'a>(&'a mut HashMap<String, String>);
+
+    impl Visit for Visitor<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0.insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+
+    impl<S> Layer<S> for CaptureLayer
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            let mut span = CapturedSpan { name: attrs.metadata().name(), ..Default::default() };
+            attrs.record(&mut Visitor(&mut span.fields));
+
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(span);
+            if let Some(span) = ctx.span(id) {
+                span.extensions_mut().insert(Index(spans.len() - 1));
+            }
+        }
+
+        fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+            let Some(span) = ctx.span(id) else { return };
+            if let Some(Index(index)) = span.extensions().get::<Index>() {
+                values.record(&mut Visitor(&mut self.spans.lock().unwrap()[*index].fields));
+            }
+        }
+    }
+}
@@ -0,0 +1,813 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The full-screen dashboard of `amp status --tui` and `amp playbook watch`:
+//! the playbooks on the left, the actors of the selected one in the center,
+//! the details, logs or events below, and the activity of the dashboard at
+//! the bottom. The keys are configurable in the `[tui.keys]` table of the
+//! configuration, such as `restart = "R"` or `quit = ["q", "esc"]`.
+
+use std::collections::VecDeque;
+use std::io::Stdout;
+use std::sync::Arc;
+
+use amp_common::resource::PlaybookSpec;
+use chrono::Local;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Cell, Clear, List, ListState, Paragraph, Row, Table, TableState};
+use ratatui::{Frame, Terminal};
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Duration};
+use tracing::warn;
+
+use crate::cmd::actor::restart;
+use crate::context::Context;
+use crate::errors::{Errors, Result};
+use crate::ops::actors::{self, State, Status};
+use crate::ops::events;
+use crate::ops::logger::{self, Options};
+use crate::ops::poller::Poller;
+use crate::utils;
+
+/// The dashboard polls the playbooks and actors this often.
+const REFRESH: Duration = Duration::from_secs(2);
+
+/// The lines of the log or event stream kept for the bottom panel.
+const STREAM_LINES: usize = 500;
+
+/// The entries of the activity panel kept.
+const ACTIVITY_LINES: usize = 50;
+
+/// What a key does in the dashboard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    /// Move the focus to the playbooks
+    Left,
+    /// Move the focus to the actors
+    Right,
+    /// Show the actors of the playbook, or the details of the actor
+    Enter,
+    Logs,
+    Events,
+    Refresh,
+    Restart,
+    /// Stop the running playbook, or start the stopped one
+    StartStop,
+    Delete,
+    Help,
+    Quit,
+}
+
+impl Action {
+    const ALL: [Action; 13] = [
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Enter,
+        Action::Logs,
+        Action::Events,
+        Action::Refresh,
+        Action::Restart,
+        Action::StartStop,
+        Action::Delete,
+        Action::Help,
+        Action::Quit,
+    ];
+
+    /// The name of the action in the `[tui.keys]` table.
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Enter => "enter",
+            Action::Logs => "logs",
+            Action::Events => "events",
+            Action::Refresh => "refresh",
+            Action::Restart => "restart",
+            Action::StartStop => "start_stop",
+            Action::Delete => "delete",
+            Action::Help => "help",
+            Action::Quit => "quit",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Action::Up => "Move up",
+            Action::Down => "Move down",
+            Action::Left => "Focus the playbooks",
+            Action::Right => "Focus the actors",
+            Action::Enter => "Select the playbook, or show the actor",
+            Action::Logs => "Stream the logs of the actor",
+            Action::Events => "Stream the events of the playbook",
+            Action::Refresh => "Refresh now",
+            Action::Restart => "Restart the actor",
+            Action::StartStop => "Start or stop the playbook",
+            Action::Delete => "Delete the playbook",
+            Action::Help => "Show this help",
+            Action::Quit => "Quit",
+        }
+    }
+}
+
+/// The default key bindings of the dashboard.
+const BINDINGS: &[(KeyCode, Action)] = &[
+    (KeyCode::Up, Action::Up),
+    (KeyCode::Down, Action::Down),
+    (KeyCode::Left, Action::Left),
+    (KeyCode::Right, Action::Right),
+    (KeyCode::Enter, Action::Enter),
+    (KeyCode::Char('l'), Action::Logs),
+    (KeyCode::Char('e'), Action::Events),
+    (KeyCode::Char('r'), Action::Refresh),
+    (KeyCode::Char('R'), Action::Restart),
+    (KeyCode::Char('s'), Action::StartStop),
+    (KeyCode::Char('d'), Action::Delete),
+    (KeyCode::Char('?'), Action::Help),
+    (KeyCode::Char('q'), Action::Quit),
+    (KeyCode::Esc, Action::Quit),
+];
+
+/// The keys bound to the actions.
+#[derive(Debug)]
+pub struct Keymap {
+    bindings: Vec<(KeyCode, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self { bindings: BINDINGS.to_vec() }
+    }
+}
+
+impl Keymap {
+    /// The default bindings, the actions of `[tui.keys]` in the configuration bound to their keys instead.
+    pub fn configured() -> Self {
+        match utils::setting(&["tui", "keys"]) {
+            Some(toml::Value::Table(table)) => Self::from_table(&table),
+            _ => Self::default(),
+        }
+    }
+
+    fn from_table(table: &toml::Table) -> Self {
+        let mut keymap = Self::default();
+        for (name, keys) in table {
+            let Some(action) = Action::ALL.into_iter().find(|a| a.name() == name) else {
+                warn!("Unknown action {} in tui.keys, expected one of {}", name, names().join(", "));
+                continue;
+            };
+            let keys: Vec<&str> = match keys {
+                toml::Value::String(key) => vec![key.as_str()],
+                toml::Value::Array(keys) => keys.iter().filter_map(toml::Value::as_str).collect(),
+                _ => vec![],
+            };
+            let Some(keys) = keys.into_iter().map(parse_key).collect::<Option<Vec<KeyCode>>>() else {
+                warn!("Invalid keys of {} in tui.keys, expected characters or up, down, left, right, enter, esc", name);
+                continue;
+            };
+
+            keymap.bindings.retain(|(code, bound)| *bound != action && !keys.contains(code));
+            keymap.bindings.extend(keys.into_iter().map(|code| (code, action)));
+        }
+        keymap
+    }
+
+    /// The action bound to the key, Ctrl-C quitting as the raw mode doesn't raise SIGINT.
+    pub fn dispatch(&self, key: KeyEvent) -> Option<Action> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Some(Action::Quit);
+        }
+        self.bindings.iter().find(|(code, _)| *code == key.code).map(|(_, action)| *action)
+    }
+
+    /// The keys bound to the action, for the help.
+    fn keys(&self, action: Action) -> String {
+        let keys: Vec<String> = self.bindings.iter().filter(|(_, a)| *a == action).map(|(c, _)| key_name(c)).collect();
+        keys.join(", ")
+    }
+}
+
+fn names() -> Vec<&'static str> {
+    Action::ALL.iter().map(Action::name).collect()
+}
+
+fn parse_key(key: &str) -> Option<KeyCode> {
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(KeyCode::Char(c)),
+        _ => match key.to_lowercase().as_str() {
+            "up" => Some(KeyCode::Up),
+            "down" => Some(KeyCode::Down),
+            "left" => Some(KeyCode::Left),
+            "right" => Some(KeyCode::Right),
+            "enter" => Some(KeyCode::Enter),
+            "esc" => Some(KeyCode::Esc),
+            _ => None,
+        },
+    }
+}
+
+fn key_name(code: &KeyCode) -> String {
+    match code {
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Up => String::from("↑"),
+        KeyCode::Down => String::from("↓"),
+        KeyCode::Left => String::from("←"),
+        KeyCode::Right => String::from("→"),
+        KeyCode::Enter => String::from("enter"),
+        KeyCode::Esc => String::from("esc"),
+        code => code.to_string(),
+    }
+}
+
+/// The side effects of an action, run by the event loop.
+#[derive(Debug, PartialEq, Eq)]
+enum Effect {
+    None,
+    /// Poll the playbooks and the actors of the selected one
+    Refresh,
+    Logs(String, String),
+    Events(String),
+    Restart(String, String),
+    Start(String),
+    Stop(String),
+    Delete(String),
+    Quit,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Focus {
+    #[default]
+    Playbooks,
+    Actors,
+}
+
+/// What the bottom panel shows.
+#[derive(Debug, Default, PartialEq, Eq)]
+enum Bottom {
+    #[default]
+    Empty,
+    /// The details of the named actor
+    Details(String),
+    /// The log stream of the named actor
+    Logs(String),
+    /// The event stream of the playbook
+    Events(String),
+}
+
+/// The state of the dashboard, updated by the actions and the polls.
+#[derive(Debug, Default)]
+struct App {
+    playbooks: Vec<PlaybookSpec>,
+    /// The cursor in the playbooks
+    playbook: usize,
+    /// The playbook whose actors are shown
+    pid: Option<String>,
+    actors: Vec<Value>,
+    /// The cursor in the actors
+    actor: usize,
+    focus: Focus,
+    bottom: Bottom,
+    /// The last lines of the log or event stream
+    lines: VecDeque<String>,
+    /// What happened, the last entry at the end
+    activity: VecDeque<String>,
+    /// The playbook to delete once confirmed
+    confirm: Option<String>,
+    help: bool,
+}
+
+impl App {
+    fn new(pid: Option<String>) -> Self {
+        Self { pid, ..Self::default() }
+    }
+
+    /// Handle the key, answering the confirmation or closing the help first.
+    fn key(&mut self, key: KeyEvent, keymap: &Keymap) -> Effect {
+        if let Some(pid) = self.confirm.take() {
+            if key.code == KeyCode::Char('y') {
+                return Effect::Delete(pid);
+            }
+            self.note(format!("Kept the playbook {}", pid));
+            return Effect::None;
+        }
+        if std::mem::take(&mut self.help) {
+            return Effect::None;
+        }
+        keymap.dispatch(key).map_or(Effect::None, |action| self.apply(action))
+    }
+
+    fn apply(&mut self, action: Action) -> Effect {
+        match (action, self.focus) {
+            (Action::Quit, _) => return Effect::Quit,
+            (Action::Help, _) => self.help = true,
+            (Action::Left, _) => self.focus = Focus::Playbooks,
+            (Action::Right, _) => self.focus = Focus::Actors,
+            (Action::Up, Focus::Playbooks) => self.playbook = self.playbook.saturating_sub(1),
+            (Action::Down, Focus::Playbooks) => self.playbook = next(self.playbook, self.playbooks.len()),
+            (Action::Up, Focus::Actors) => self.actor = self.actor.saturating_sub(1),
+            (Action::Down, Focus::Actors) => self.actor = next(self.actor, self.actors.len()),
+            (Action::Enter, Focus::Playbooks) => {
+                let Some(playbook) = self.playbooks.get(self.playbook) else { return Effect::None };
+                if self.pid.as_deref() != Some(&playbook.id) {
+                    self.pid = Some(playbook.id.clone());
+                    (self.actors, self.actor, self.bottom) = (vec![], 0, Bottom::Empty);
+                }
+                self.focus = Focus::Actors;
+                return Effect::Refresh;
+            }
+            (Action::Enter, Focus::Actors) => {
+                if let Some(name) = self.actor_name() {
+                    self.bottom = Bottom::Details(name);
+                }
+            }
+            (Action::Refresh, _) => {
+                self.note("Refreshed");
+                return Effect::Refresh;
+            }
+            (Action::Logs, _) => {
+                let (Some(pid), Some(name)) = (self.pid.clone(), self.actor_name()) else { return Effect::None };
+                self.lines.clear();
+                self.bottom = Bottom::Logs(name.clone());
+                return Effect::Logs(pid, name);
+            }
+            (Action::Events, _) => {
+                let Some(pid) = self.pid.clone() else { return Effect::None };
+                self.lines.clear();
+                self.bottom = Bottom::Events(pid.clone());
+                return Effect::Events(pid);
+            }
+            (Action::Restart, _) => {
+                let (Some(pid), Some(name)) = (self.pid.clone(), self.actor_name()) else { return Effect::None };
+                return Effect::Restart(pid, name);
+            }
+            (Action::StartStop, _) => {
+                let Some(playbook) = self.selected() else { return Effect::None };
+                let running = State::from(&serde_json::to_value(playbook).unwrap_or_default()).status == "running";
+                let pid = playbook.id.clone();
+                return if running { Effect::Stop(pid) } else { Effect::Start(pid) };
+            }
+            (Action::Delete, _) => {
+                let Some(playbook) = self.selected() else { return Effect::None };
+                let pid = playbook.id.clone();
+                self.note(format!("Delete the playbook {} ({})? Press y to confirm", playbook.title, pid));
+                self.confirm = Some(pid);
+            }
+        }
+        Effect::None
+    }
+
+    /// The playbook whose actors are shown.
+    fn selected(&self) -> Option<&PlaybookSpec> {
+        self.playbooks.iter().find(|p| Some(&p.id) == self.pid.as_ref())
+    }
+
+    fn actor_name(&self) -> Option<String> {
+        self.actors.get(self.actor).and_then(|a| a["name"].as_str()).map(String::from)
+    }
+
+    /// Keep the cursors on the lists, and show the first playbook by default.
+    fn set_playbooks(&mut self, playbooks: Vec<PlaybookSpec>) {
+        self.playbooks = playbooks;
+        match self.pid.as_deref().and_then(|pid| self.playbooks.iter().position(|p| p.id == pid)) {
+            Some(position) => self.playbook = position,
+            None => self.playbook = self.playbook.min(self.playbooks.len().saturating_sub(1)),
+        }
+        if self.pid.is_none() {
+            self.pid = self.playbooks.first().map(|p| p.id.clone());
+        }
+    }
+
+    fn set_actors(&mut self, actors: Vec<Value>) {
+        self.actors = actors;
+        self.actor = self.actor.min(self.actors.len().saturating_sub(1));
+    }
+
+    /// Forget the deleted playbook, showing the next one.
+    fn deleted(&mut self, pid: &str) {
+        self.playbooks.retain(|p| p.id != pid);
+        if self.pid.as_deref() == Some(pid) {
+            self.pid = None;
+            (self.actors, self.actor, self.bottom) = (vec![], 0, Bottom::Empty);
+        }
+        self.set_playbooks(std::mem::take(&mut self.playbooks));
+    }
+
+    fn stream(&mut self, line: String) {
+        if self.lines.len() == STREAM_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    /// Record what happened in the activity panel.
+    fn note(&mut self, message: impl Into<String>) {
+        if self.activity.len() == ACTIVITY_LINES {
+            self.activity.pop_front();
+        }
+        self.activity.push_back(format!("{} {}", Local::now().format("%H:%M:%S"), message.into()));
+    }
+}
+
+/// The index after the cursor, staying on the last item.
+fn next(cursor: usize, len: usize) -> usize {
+    (cursor + 1).min(len.saturating_sub(1))
+}
+
+/// The color of the status cells.
+fn status_style(status: &str) -> Style {
+    match status {
+        "running" | "ready" | "succeeded" => Style::new().fg(Color::Green),
+        "pending" | "building" | "initializing" => Style::new().fg(Color::Yellow),
+        "failed" | "error" | "crashloopbackoff" => Style::new().fg(Color::Red),
+        _ => Style::new(),
+    }
+}
+
+/// Restores the terminal when dropped, even if the dashboard failed.
+struct Screen {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+}
+
+impl Screen {
+    fn enter() -> Result<Self> {
+        let failed = |e: std::io::Error| Errors::TuiInitFailed(e.to_string());
+        terminal::enable_raw_mode().map_err(failed)?;
+        crossterm::execute!(std::io::stdout(), EnterAlternateScreen).map_err(failed)?;
+        let terminal = Terminal::new(CrosstermBackend::new(std::io::stdout())).map_err(failed)?;
+        Ok(Self { terminal })
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+        let _ = crossterm::execute!(std::io::stdout(), LeaveAlternateScreen);
+        let _ = self.terminal.show_cursor();
+    }
+}
+
+/// Read the terminal events in a blocking task, until the receiver is dropped.
+fn read_events() -> mpsc::UnboundedReceiver<Event> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::task::spawn_blocking(move || loop {
+        match event::poll(Duration::from_millis(250)) {
+            Ok(true) => match event::read() {
+                Ok(event) if tx.send(event).is_ok() => {}
+                _ => break,
+            },
+            Ok(false) if !tx.is_closed() => {}
+            _ => break,
+        }
+    });
+    rx
+}
+
+/// Run the dashboard until quit, starting on the given playbook.
+pub async fn run(ctx: Arc<Context>, pid: Option<String>) -> Result<()> {
+    let keymap = Keymap::configured();
+    let mut screen = Screen::enter()?;
+    let mut app = App::new(pid);
+    let mut poller = Poller::new(Default::default());
+    let mut keys = read_events();
+    let mut ticker = interval(REFRESH);
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut stream: Option<JoinHandle<()>> = None;
+
+    loop {
+        screen.terminal.draw(|frame| draw(frame, &app, &keymap)).map_err(Errors::FailedWriteOutput)?;
+
+        let effect = tokio::select! {
+            _ = ticker.tick() => Effect::Refresh,
+            Some(line) = rx.recv() => {
+                app.stream(line);
+                Effect::None
+            }
+            event = keys.recv() => match event {
+                Some(Event::Key(key)) if key.kind == KeyEventKind::Press => app.key(key, &keymap),
+                Some(_) => Effect::None,
+                None => Effect::Quit,
+            },
+            _ = ctx.shutdown.triggered() => Effect::Quit,
+        };
+
+        let result = match effect {
+            Effect::None => Ok(()),
+            Effect::Quit => break,
+            Effect::Refresh => {
+                ticker.reset();
+                refresh(&ctx, &mut poller, &mut app).await
+            }
+            Effect::Logs(pid, name) => {
+                let (ctx, tx) = (ctx.clone(), tx.clone());
+                replace(&mut stream, async move {
+                    let sink = |line| {
+                        let _ = tx.send(line);
+                        Ok(())
+                    };
+                    if let Err(e) = logger::tail_into(&ctx, &pid, &name, &Options::default(), sink).await {
+                        let _ = tx.send(e.to_string());
+                    }
+                });
+                Ok(())
+            }
+            Effect::Events(pid) => {
+                let (ctx, tx) = (ctx.clone(), tx.clone());
+                replace(&mut stream, async move {
+                    let sink = |data: &str| {
+                        let _ = tx.send(data.to_string());
+                        Ok(())
+                    };
+                    if let Err(e) = events::follow(&ctx, &pid, sink).await {
+                        let _ = tx.send(e.to_string());
+                    }
+                });
+                Ok(())
+            }
+            Effect::Restart(pid, name) => {
+                let result = restart::restart(&ctx, &ctx.api().await, &pid, &name).await;
+                result.map(|_| app.note(format!("Restarted the actor {}", name)))
+            }
+            Effect::Start(pid) => action(&ctx, "start", &pid).await.map(|_| app.note(format!("Started {}", pid))),
+            Effect::Stop(pid) => action(&ctx, "stop", &pid).await.map(|_| app.note(format!("Stopped {}", pid))),
+            Effect::Delete(pid) => action(&ctx, "delete", &pid).await.map(|_| {
+                app.deleted(&pid);
+                app.note(format!("Deleted the playbook {}", pid));
+            }),
+        };
+        if let Err(e) = result {
+            app.note(e.to_string());
+        }
+    }
+
+    if let Some(stream) = stream {
+        stream.abort();
+    }
+    Ok(())
+}
+
+/// Stream into the bottom panel, instead of the previous stream if any.
+fn replace<F>(stream: &mut Option<JoinHandle<()>>, task: F)
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    if let Some(stream) = stream.replace(tokio::spawn(task)) {
+        stream.abort();
+    }
+}
+
+/// Start, stop or delete the playbook, through the recorder.
+async fn action(ctx: &Context, action: &str, pid: &str) -> Result<()> {
+    let recorder = &ctx.recorder;
+    let (status, error): (u16, fn(String) -> Errors) = match action {
+        "start" => {
+            let path = format!("/playbooks/{}/actions/start", pid);
+            (
+                recorder.mutate_async("POST", &path, None, ctx.playbooks().start_async(pid)).await?,
+                Errors::FailedStartPlaybook,
+            )
+        }
+        "stop" => {
+            let path = format!("/playbooks/{}/actions/stop", pid);
+            (
+                recorder.mutate_async("POST", &path, None, ctx.playbooks().stop_async(pid)).await?,
+                Errors::FailedStopPlaybook,
+            )
+        }
+        _ => {
+            let path = format!("/playbooks/{}", pid);
+            (
+                recorder.mutate_async("DELETE", &path, None, ctx.playbooks().delete_async(pid)).await?,
+                Errors::FailedDeletePlaybook,
+            )
+        }
+    };
+    match status {
+        204 => Ok(()),
+        _ => Err(error(pid.to_string())),
+    }
+}
+
+/// Poll the playbooks, and the actors of the selected one.
+async fn refresh(ctx: &Context, poller: &mut Poller, app: &mut App) -> Result<()> {
+    let api = ctx.api().await;
+    if poller.poll(&api).await? {
+        app.set_playbooks(poller.playbooks().to_vec());
+    }
+    if let Some(pid) = &app.pid {
+        app.set_actors(actors::list(&api, pid, Status::All).await?);
+    }
+    Ok(())
+}
+
+fn draw(frame: &mut Frame, app: &App, keymap: &Keymap) {
+    let [top, bottom, activity, status] =
+        Layout::vertical([Constraint::Min(6), Constraint::Length(10), Constraint::Length(5), Constraint::Length(1)])
+            .areas(frame.area());
+    let [left, center] = Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)]).areas(top);
+    let focused = |focus: Focus| match app.focus == focus {
+        true => Style::new().fg(Color::Cyan),
+        false => Style::new(),
+    };
+    let highlight = Style::new().add_modifier(Modifier::REVERSED);
+
+    let items = app.playbooks.iter().map(|p| match app.pid.as_deref() == Some(&p.id) {
+        true => Line::styled(p.title.clone(), Style::new().add_modifier(Modifier::BOLD)),
+        false => Line::raw(p.title.clone()),
+    });
+    let list = List::new(items)
+        .block(Block::bordered().title(" Playbooks ").border_style(focused(Focus::Playbooks)))
+        .highlight_style(highlight);
+    let mut state = ListState::default().with_selected((!app.playbooks.is_empty()).then_some(app.playbook));
+    frame.render_stateful_widget(list, left, &mut state);
+
+    let rows = app.actors.iter().map(|actor| {
+        let state = State::from(actor);
+        let string = |key: &str| actor[key].as_str().unwrap_or_default().to_string();
+        let style = status_style(&state.status);
+        Row::new([
+            Cell::from(string("name")),
+            Cell::from(string("image")),
+            Cell::from(Line::styled(state.status, style)),
+        ])
+    });
+    let widths = [Constraint::Percentage(30), Constraint::Percentage(50), Constraint::Percentage(20)];
+    let table = Table::new(rows, widths)
+        .header(Row::new(["NAME", "IMAGE", "STATUS"]).style(Style::new().add_modifier(Modifier::BOLD)))
+        .block(Block::bordered().title(" Actors ").border_style(focused(Focus::Actors)))
+        .row_highlight_style(highlight);
+    let mut state = TableState::default().with_selected((!app.actors.is_empty()).then_some(app.actor));
+    frame.render_stateful_widget(table, center, &mut state);
+
+    draw_bottom(frame, app, bottom);
+
+    let height = activity.height.saturating_sub(2) as usize;
+    let lines = app.activity.iter().skip(app.activity.len().saturating_sub(height)).map(|l| Line::raw(l.as_str()));
+    frame.render_widget(
+        Paragraph::new(lines.collect::<Vec<_>>()).block(Block::bordered().title(" Activity ")),
+        activity,
+    );
+
+    let hint = format!("{} help  {} quit", keymap.keys(Action::Help), keymap.keys(Action::Quit));
+    frame.render_widget(Paragraph::new(Line::styled(hint, Style::new().fg(Color::DarkGray))), status);
+
+    if app.help {
+        draw_help(frame, keymap);
+    }
+}
+
+/// Draw the details of the actor, or the last lines of its log stream.
+fn draw_bottom(frame: &mut Frame, app: &App, area: Rect) {
+    let actor = |name: &str| app.actors.iter().find(|a| a["name"].as_str() == Some(name));
+    let height = area.height.saturating_sub(2) as usize;
+    let tail = || app.lines.iter().skip(app.lines.len().saturating_sub(height)).map(|l| Line::raw(l.as_str()));
+    let (title, lines): (String, Vec<Line>) = match &app.bottom {
+        Bottom::Empty => (String::from(" Logs "), vec![]),
+        Bottom::Details(name) => {
+            let lines = match actor(name) {
+                Some(actor) => {
+                    let state = State::from(actor);
+                    let mut lines = vec![
+                        Line::raw(format!("Image:  {}", actor["image"].as_str().unwrap_or_default())),
+                        Line::styled(format!("Status: {}", state.status), status_style(&state.status)),
+                    ];
+                    lines.extend(state.error.map(|e| Line::raw(format!("Error:  {}", e))));
+                    lines.extend(state.wait.map(|s| Line::raw(format!("Wait:   ~{}s", s))));
+                    lines
+                }
+                None => vec![Line::raw("The actor is gone")],
+            };
+            (format!(" {} ", name), lines)
+        }
+        Bottom::Logs(name) => (format!(" Logs of {} ", name), tail().collect()),
+        Bottom::Events(pid) => (format!(" Events of {} ", pid), tail().collect()),
+    };
+    frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(title)), area);
+}
+
+/// Draw the key bindings over the dashboard.
+fn draw_help(frame: &mut Frame, keymap: &Keymap) {
+    let lines: Vec<Line> =
+        Action::ALL.iter().map(|a| Line::raw(format!("{:>12}  {}", keymap.keys(*a), a.description()))).collect();
+    let [area] = Layout::vertical([Constraint::Length(lines.len() as u16 + 2)]).flex(Flex::Center).areas(frame.area());
+    let [area] = Layout::horizontal([Constraint::Length(56)]).flex(Flex::Center).areas(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" Keys ")), area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn dispatch_the_key_bindings() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.dispatch(key(KeyCode::Up)), Some(Action::Up));
+        assert_eq!(keymap.dispatch(key(KeyCode::Down)), Some(Action::Down));
+        assert_eq!(keymap.dispatch(key(KeyCode::Enter)), Some(Action::Enter));
+        assert_eq!(keymap.dispatch(key(KeyCode::Char('l'))), Some(Action::Logs));
+        assert_eq!(keymap.dispatch(key(KeyCode::Char('e'))), Some(Action::Events));
+        assert_eq!(keymap.dispatch(key(KeyCode::Char('r'))), Some(Action::Refresh));
+        assert_eq!(keymap.dispatch(key(KeyCode::Char('R'))), Some(Action::Restart));
+        assert_eq!(keymap.dispatch(key(KeyCode::Char('s'))), Some(Action::StartStop));
+        assert_eq!(keymap.dispatch(key(KeyCode::Char('d'))), Some(Action::Delete));
+        assert_eq!(keymap.dispatch(key(KeyCode::Char('?'))), Some(Action::Help));
+        assert_eq!(keymap.dispatch(key(KeyCode::Char('q'))), Some(Action::Quit));
+        assert_eq!(keymap.dispatch(key(KeyCode::Esc)), Some(Action::Quit));
+        assert_eq!(keymap.dispatch(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)), Some(Action::Quit));
+        assert_eq!(keymap.dispatch(key(KeyCode::Char('c'))), None);
+        assert_eq!(keymap.dispatch(key(KeyCode::Char('x'))), None);
+    }
+
+    #[test]
+    fn configure_the_key_bindings() {
+        let table: toml::Table =
+            toml::from_str("refresh = \"g\"\nquit = [\"x\", \"esc\"]\nfly = \"f\"\nlogs = \"F1\"").unwrap();
+        let keymap = Keymap::from_table(&table);
+        assert_eq!(keymap.dispatch(key(KeyCode::Char('g'))), Some(Action::Refresh));
+        assert_eq!(keymap.dispatch(key(KeyCode::Char('r'))), None, "the default key is unbound");
+        assert_eq!(keymap.dispatch(key(KeyCode::Char('x'))), Some(Action::Quit));
+        assert_eq!(keymap.dispatch(key(KeyCode::Esc)), Some(Action::Quit));
+        assert_eq!(keymap.dispatch(key(KeyCode::Char('q'))), None);
+        assert_eq!(keymap.dispatch(key(KeyCode::Char('l'))), Some(Action::Logs), "invalid keys are ignored");
+        assert_eq!(keymap.keys(Action::Quit), "x, esc");
+    }
+
+    fn playbook(id: &str, state: &str) -> PlaybookSpec {
+        let value = serde_json::json!({"id": id, "title": id, "description": "", "status": {"state": state}});
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn drill_into_the_actors() {
+        let mut app = App::new(None);
+        app.set_playbooks(vec![playbook("1", "Running"), playbook("2", "Stopped")]);
+        assert_eq!(app.pid.as_deref(), Some("1"));
+        assert_eq!(app.apply(Action::Restart), Effect::None);
+
+        assert_eq!(app.apply(Action::Down), Effect::None);
+        assert_eq!(app.apply(Action::Down), Effect::None);
+        assert_eq!(app.playbook, 1);
+        assert_eq!(app.apply(Action::Enter), Effect::Refresh);
+        assert_eq!((app.pid.as_deref(), app.focus), (Some("2"), Focus::Actors));
+
+        app.set_actors(vec![serde_json::json!({"name": "web"}), serde_json::json!({"name": "api"})]);
+        app.apply(Action::Down);
+        app.apply(Action::Enter);
+        assert_eq!(app.bottom, Bottom::Details("api".into()));
+        assert_eq!(app.apply(Action::Logs), Effect::Logs("2".into(), "api".into()));
+        assert_eq!(app.apply(Action::Events), Effect::Events("2".into()));
+        assert_eq!(app.apply(Action::Restart), Effect::Restart("2".into(), "api".into()));
+        assert_eq!(app.apply(Action::Quit), Effect::Quit);
+    }
+
+    #[test]
+    fn confirm_the_deletion() {
+        let keymap = Keymap::default();
+        let mut app = App::new(Some("1".into()));
+        app.set_playbooks(vec![playbook("1", "Running"), playbook("2", "Stopped")]);
+        assert_eq!(app.key(key(KeyCode::Char('s')), &keymap), Effect::Stop("1".into()));
+
+        assert_eq!(app.key(key(KeyCode::Char('d')), &keymap), Effect::None);
+        assert_eq!(app.key(key(KeyCode::Char('n')), &keymap), Effect::None);
+        assert!(app.activity.back().unwrap().ends_with("Kept the playbook 1"));
+
+        app.key(key(KeyCode::Char('d')), &keymap);
+        assert_eq!(app.key(key(KeyCode::Char('y')), &keymap), Effect::Delete("1".into()));
+        app.deleted("1");
+        assert_eq!(app.pid.as_deref(), Some("2"));
+        assert_eq!(app.key(key(KeyCode::Char('s')), &keymap), Effect::Start("2".into()));
+
+        app.key(key(KeyCode::Char('?')), &keymap);
+        assert!(app.help);
+        assert_eq!(app.key(key(KeyCode::Char('q')), &keymap), Effect::None, "the first key closes the help");
+        assert_eq!(app.key(key(KeyCode::Char('q')), &keymap), Effect::Quit);
+    }
+}
@@ -0,0 +1,269 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::errors::{Errors, Result};
+use crate::utils::{self, manifest};
+
+/// The index file of the cache, next to the cached repositories.
+const INDEX: &str = "index.toml";
+
+/// The origin and last access of the cached repositories, by directory name.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Index {
+    #[serde(default)]
+    entries: BTreeMap<String, Record>,
+}
+
+/// What a cached repository was cloned from, and when it was last read.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Record {
+    pub repository: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    pub accessed: DateTime<Utc>,
+}
+
+impl Index {
+    /// Load the index of the cache, empty if there is none yet.
+    pub fn load(dir: &Path) -> Result<Index> {
+        match fs::read_to_string(dir.join(INDEX)) {
+            Ok(content) => toml::from_str(&content).map_err(|e| Errors::InvalidCacheIndex(e.to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Index::default()),
+            Err(e) => Err(Errors::FailedUpdateCache(e)),
+        }
+    }
+
+    fn save(&self, dir: &Path) -> Result<()> {
+        let content = toml::to_string(self).map_err(|e| Errors::InvalidCacheIndex(e.to_string()))?;
+        utils::atomic_write(&dir.join(INDEX), content.as_bytes()).map_err(Errors::FailedUpdateCache)
+    }
+}
+
+/// Record the access to the cached repository.
+pub fn touch(dir: &Path, key: &str, repository: &str, reference: Option<&str>) -> Result<()> {
+    let mut index = Index::load(dir)?;
+    let record = Record { repository: repository.into(), reference: reference.map(String::from), accessed: Utc::now() };
+    index.entries.insert(key.into(), record);
+    index.save(dir)
+}
+
+/// A cached repository.
+#[derive(Debug)]
+pub struct Entry {
+    /// The directory name of the entry
+    pub key: String,
+    pub path: PathBuf,
+    /// The total size of the files, in bytes
+    pub size: u64,
+    /// The last access, else the last change of the directory
+    pub accessed: Option<DateTime<Utc>>,
+    /// The origin of the entry, unknown for the entries cached before the index
+    pub record: Option<Record>,
+}
+
+impl Entry {
+    /// Whether the entry was not accessed for the given duration, entries of
+    /// unknown age are kept.
+    pub fn older_than(&self, age: Duration, now: DateTime<Utc>) -> bool {
+        let age = TimeDelta::from_std(age).unwrap_or(TimeDelta::MAX);
+        self.accessed.is_some_and(|accessed| now.signed_duration_since(accessed) > age)
+    }
+}
+
+/// The cached repositories, by directory name.
+pub fn entries(dir: &Path) -> Result<Vec<Entry>> {
+    let index = Index::load(dir)?;
+    let read = match fs::read_dir(dir) {
+        Ok(read) => read,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(Errors::FailedUpdateCache(e)),
+    };
+
+    let mut entries = vec![];
+    for item in read {
+        let item = item.map_err(Errors::FailedUpdateCache)?;
+        let path = item.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let key = item.file_name().to_string_lossy().to_string();
+        let record = index.entries.get(&key).cloned();
+        let modified = item.metadata().and_then(|m| m.modified()).ok().map(DateTime::<Utc>::from);
+        let accessed = record.as_ref().map(|r| r.accessed).or(modified);
+        entries.push(Entry { key, size: size(&path), path, accessed, record });
+    }
+    entries.sort_by(|a, b| a.key.cmp(&b.key));
+
+    Ok(entries)
+}
+
+/// Remove the entries from the cache, and their records from the index along
+/// with the records of the entries already gone. Returns the bytes freed.
+pub fn remove(dir: &Path, entries: &[Entry]) -> Result<u64> {
+    let mut freed = 0;
+    for entry in entries {
+        fs::remove_dir_all(&entry.path).map_err(Errors::FailedUpdateCache)?;
+        freed += entry.size;
+    }
+
+    let mut index = Index::load(dir)?;
+    let before = index.entries.len();
+    index.entries.retain(|key, _| dir.join(key).is_dir());
+    if index.entries.len() != before {
+        index.save(dir)?;
+    }
+
+    Ok(freed)
+}
+
+/// Whether the entry is a sound git checkout.
+pub async fn check(entry: &Entry) -> bool {
+    if !entry.path.join(".git").exists() {
+        return false;
+    }
+    let mut command = Command::new("git");
+    command.args(["fsck", "--connectivity-only", "--no-dangling", "--no-progress"]).current_dir(&entry.path);
+    command.output().await.is_ok_and(|output| output.status.success())
+}
+
+/// Clone the entry again from the origin recorded in the index.
+pub async fn refetch(dir: &Path, key: &str, record: &Record) -> Result<()> {
+    let path = dir.join(key);
+    if path.exists() {
+        fs::remove_dir_all(&path).map_err(Errors::FailedUpdateCache)?;
+    }
+    manifest::clone(&record.repository, record.reference.as_deref(), &path).await?;
+    touch(dir, key, &record.repository, record.reference.as_deref())
+}
+
+/// The total size of the files in the directory, skipping what can't be read.
+fn size(path: &Path) -> u64 {
+    let Ok(read) = fs::read_dir(path) else {
+        return 0;
+    };
+    read.flatten()
+        .map(|item| match item.file_type() {
+            Ok(t) if t.is_dir() => size(&item.path()),
+            Ok(t) if t.is_file() => item.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("amp-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn fabricate(dir: &Path, key: &str, bytes: usize) {
+        fs::create_dir_all(dir.join(key).join(".git")).unwrap();
+        fs::write(dir.join(key).join(".amp.toml"), vec![b'#'; bytes]).unwrap();
+    }
+
+    fn days_ago(days: i64) -> DateTime<Utc> {
+        Utc::now() - TimeDelta::days(days)
+    }
+
+    #[test]
+    fn list_entries_with_their_index_records() {
+        let dir = cache("list");
+        fabricate(&dir, "https---github.com-org-web@HEAD", 100);
+        fabricate(&dir, "https---github.com-org-api@main", 20);
+        touch(&dir, "https---github.com-org-api@main", "https://github.com/org/api", Some("main")).unwrap();
+
+        let entries = entries(&dir).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].key, "https---github.com-org-api@main");
+        assert_eq!(entries[0].size, 20);
+        let record = entries[0].record.as_ref().unwrap();
+        assert_eq!(record.repository, "https://github.com/org/api");
+        assert_eq!(record.reference.as_deref(), Some("main"));
+
+        assert_eq!(entries[1].size, 100);
+        assert!(entries[1].record.is_none());
+        assert!(entries[1].accessed.is_some(), "falls back to the directory time");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn prune_old_entries() {
+        let dir = cache("prune");
+        fabricate(&dir, "old", 10);
+        fabricate(&dir, "new", 10);
+        let mut index = Index::default();
+        for (key, accessed) in [("old", days_ago(45)), ("new", days_ago(2)), ("gone", days_ago(90))] {
+            index.entries.insert(key.into(), Record { repository: key.into(), reference: None, accessed });
+        }
+        index.save(&dir).unwrap();
+
+        let stale: Vec<Entry> = entries(&dir)
+            .unwrap()
+            .into_iter()
+            .filter(|e| e.older_than(Duration::from_secs(30 * 86400), Utc::now()))
+            .collect();
+        assert_eq!(stale.iter().map(|e| e.key.as_str()).collect::<Vec<_>>(), vec!["old"]);
+        assert_eq!(remove(&dir, &stale).unwrap(), 10);
+
+        assert!(!dir.join("old").exists());
+        let index = Index::load(&dir).unwrap();
+        assert_eq!(index.entries.keys().collect::<Vec<_>>(), vec!["new"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reject_invalid_index() {
+        let dir = cache("invalid");
+        fs::write(dir.join(INDEX), "entries = 1").unwrap();
+        assert!(matches!(Index::load(&dir), Err(Errors::InvalidCacheIndex(_))));
+        assert!(entries(&dir).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn empty_without_cache() {
+        let dir = std::env::temp_dir().join(format!("amp-cache-missing-{}", std::process::id()));
+        assert!(entries(&dir).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn check_broken_entries() {
+        let dir = cache("check");
+        fabricate(&dir, "broken", 10);
+        fs::create_dir_all(dir.join("plain")).unwrap();
+
+        for entry in entries(&dir).unwrap() {
+            assert!(!check(&entry).await, "{} is not a sound checkout", entry.key);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
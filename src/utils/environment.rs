@@ -0,0 +1,154 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use toml::{Table, Value};
+
+use crate::errors::{Errors, Result};
+use crate::utils::profile::merge;
+use crate::utils::{manifest_edit, Manifest};
+
+/// The key of the environments table in the manifest.
+const ENVIRONMENTS: &str = "env";
+
+/// The key listing the contexts an environment is selected for.
+const CONTEXTS: &str = "contexts";
+
+/// Merge the environment over the manifest and return the effective content,
+/// along with the name of the environment. The environment is the selected
+/// one, else the one listing the current context in its `contexts`. It is
+/// applied after the profiles, so its values win.
+pub fn apply(manifest: &Manifest, selected: Option<&str>, context: Option<&str>) -> Result<(String, Option<String>)> {
+    if selected.is_none() && !manifest.content.contains(ENVIRONMENTS) {
+        return Ok((manifest.content.clone(), None));
+    }
+
+    let origin = manifest.origin.to_string();
+    let mut base: Table = toml::from_str(&manifest.content).map_err(|e| Errors::FailedParseManifest(origin, e))?;
+    let environments = match base.remove(ENVIRONMENTS) {
+        Some(Value::Table(environments)) => environments,
+        _ => Table::new(),
+    };
+
+    let name = match selected {
+        Some(name) if environments.contains_key(name) => Some(name.to_string()),
+        Some(name) => {
+            let available: Vec<&str> = environments.keys().map(String::as_str).collect();
+            return Err(Errors::NotFoundEnvironment(name.to_string(), available.join(", ")));
+        }
+        None => environments.iter().find(|(_, env)| listed(env, context)).map(|(name, _)| name.clone()),
+    };
+
+    if let Some(Value::Table(mut environment)) = name.as_ref().and_then(|n| environments.get(n)).cloned() {
+        environment.remove(CONTEXTS);
+        merge(&mut base, environment);
+    }
+
+    Ok((manifest_edit::serialize(&base)?, name))
+}
+
+/// Whether the environment lists the context in its `contexts`.
+fn listed(environment: &Value, context: Option<&str>) -> bool {
+    let Some(context) = context else {
+        return false;
+    };
+    let contexts = environment.get(CONTEXTS).and_then(Value::as_array);
+    contexts.is_some_and(|c| c.iter().any(|v| v.as_str() == Some(context)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{profile, Origin};
+
+    const CONTENT: &str = r#"
+[meta]
+name = "web"
+
+[deploy]
+env = { LOG = "info", PORT = "8080" }
+resources = { cpu = "100m", memory = "128Mi" }
+
+[[deploy.services]]
+kind = "http"
+port = 8080
+
+[profiles.debug.deploy]
+env = { LOG = "debug" }
+
+[env.staging]
+contexts = ["staging-eu"]
+deploy = { env = { LOG = "warn" }, resources = { memory = "256Mi" } }
+
+[env.production.deploy]
+env = { LOG = "error" }
+
+[[env.production.deploy.services]]
+kind = "https"
+port = 443
+"#;
+
+    fn manifest(content: &str) -> Manifest {
+        Manifest { origin: Origin::Url("https://a/.amp.toml".into()), content: content.into() }
+    }
+
+    fn effective(selected: Option<&str>, context: Option<&str>) -> (Table, Option<String>) {
+        let (content, name) = apply(&manifest(CONTENT), selected, context).unwrap();
+        (toml::from_str(&content).unwrap(), name)
+    }
+
+    #[test]
+    fn merge_nested_tables() {
+        let (table, name) = effective(Some("staging"), None);
+        assert_eq!(name.as_deref(), Some("staging"));
+        assert_eq!(table["deploy"]["env"]["LOG"].as_str(), Some("warn"));
+        assert_eq!(table["deploy"]["env"]["PORT"].as_str(), Some("8080"));
+        assert_eq!(table["deploy"]["resources"]["cpu"].as_str(), Some("100m"));
+        assert_eq!(table["deploy"]["resources"]["memory"].as_str(), Some("256Mi"));
+        assert!(!table.contains_key("env"));
+    }
+
+    #[test]
+    fn replace_arrays_of_tables() {
+        let (table, _) = effective(Some("production"), None);
+        let services = table["deploy"]["services"].as_array().unwrap();
+        assert_eq!(services.len(), 1);
+        assert_eq!(services[0]["port"].as_integer(), Some(443));
+    }
+
+    #[test]
+    fn select_by_context() {
+        assert_eq!(effective(None, Some("staging-eu")).1.as_deref(), Some("staging"));
+        assert_eq!(effective(Some("production"), Some("staging-eu")).1.as_deref(), Some("production"));
+
+        let (table, name) = effective(None, Some("dev"));
+        assert_eq!(name, None);
+        assert_eq!(table["deploy"]["env"]["LOG"].as_str(), Some("info"));
+        assert!(!table.contains_key("env"));
+    }
+
+    #[test]
+    fn applied_after_profiles() {
+        let content = profile::apply(&manifest(CONTENT), &["debug".into()], None, |_| None).unwrap();
+        let (content, _) = apply(&manifest(&content), Some("staging"), None).unwrap();
+        let table: Table = toml::from_str(&content).unwrap();
+        assert_eq!(table["deploy"]["env"]["LOG"].as_str(), Some("warn"));
+    }
+
+    #[test]
+    fn reject_undefined_environments() {
+        let result = apply(&manifest(CONTENT), Some("qa"), None);
+        assert!(matches!(result, Err(Errors::NotFoundEnvironment(name, available))
+            if name == "qa" && available == "production, staging"));
+    }
+}
@@ -0,0 +1,366 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::errors::{Errors, Result};
+use crate::prompt::Prompter;
+
+/// The git revision checked out in the workspace, each part missing if git
+/// isn't installed, the workspace isn't a repository, or HEAD is detached.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Revision {
+    pub branch: Option<String>,
+    pub sha: Option<String>,
+    /// Whether the working tree has uncommitted changes
+    pub dirty: bool,
+}
+
+impl Revision {
+    /// Detect the branch, the short SHA and the dirty flag of the directory.
+    pub fn detect(dir: &Path) -> Self {
+        let sha = git(dir, &["rev-parse", "--short", "HEAD"]);
+        if sha.is_none() {
+            return Self::default();
+        }
+        let branch = git(dir, &["rev-parse", "--abbrev-ref", "HEAD"]).filter(|b| b != "HEAD");
+        let dirty = git(dir, &["status", "--porcelain"]).is_some();
+        Self { branch, sha, dirty }
+    }
+
+    /// The attributes sent along with the syncs and the playbook, None if the
+    /// workspace isn't git-managed so that they are omitted entirely.
+    pub fn attributes(&self) -> Option<HashMap<String, String>> {
+        let sha = self.sha.as_ref()?;
+        let mut attributes =
+            HashMap::from([(String::from("commit"), sha.clone()), (String::from("dirty"), self.dirty.to_string())]);
+        if let Some(branch) = &self.branch {
+            attributes.insert(String::from("branch"), branch.clone());
+        }
+        Some(attributes)
+    }
+
+    /// Read the revision back from the attributes echoed by the server.
+    pub fn from_attributes(attributes: &Value) -> Option<Self> {
+        let string = |key: &str| attributes.get(key).and_then(Value::as_str).map(String::from);
+        Some(Self {
+            sha: Some(string("commit")?),
+            branch: string("branch"),
+            dirty: string("dirty").as_deref() == Some("true"),
+        })
+    }
+}
+
+impl std::fmt::Display for Revision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.sha.as_deref().unwrap_or("unknown"))?;
+        match (&self.branch, self.dirty) {
+            (Some(branch), true) => write!(f, " ({}, dirty)", branch),
+            (Some(branch), false) => write!(f, " ({})", branch),
+            (None, true) => write!(f, " (dirty)"),
+            (None, false) => Ok(()),
+        }
+    }
+}
+
+/// The root of the repository containing the directory, the closest
+/// ancestor with a `.git` directory or file (for worktrees and submodules).
+pub fn root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().find(|d| d.join(".git").exists()).map(Path::to_path_buf)
+}
+
+/// Check that the URL names a remote repository the server can clone: an
+/// `https`, `http`, `ssh` or `git` URL with a host and a path, or the scp-like
+/// `user@host:path`. The revision is given separately, so a fragment is rejected.
+pub fn validate_url(url: &str) -> Result<()> {
+    let invalid = || Errors::InvalidGitUrl(url.to_string());
+    if url.contains('#') || url.contains(char::is_whitespace) {
+        return Err(invalid());
+    }
+
+    if let Some((user_host, path)) = url.split_once(':').filter(|(head, _)| head.contains('@')) {
+        let host = user_host.rsplit('@').next().unwrap_or_default();
+        return match !host.is_empty() && !path.is_empty() && !path.starts_with("//") {
+            true => Ok(()),
+            false => Err(invalid()),
+        };
+    }
+
+    let url = reqwest::Url::parse(url).map_err(|_| invalid())?;
+    let path = url.path().trim_matches('/');
+    match ["https", "http", "ssh", "git"].contains(&url.scheme()) && url.host_str().is_some() && !path.is_empty() {
+        true => Ok(()),
+        false => Err(invalid()),
+    }
+}
+
+/// Where the server takes the sources from, which decides the local changes
+/// it misses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// A snapshot of the working tree, uncommitted changes included
+    Workspace,
+    /// The remote repository, without the uncommitted or unpushed changes
+    Remote,
+}
+
+/// The local changes of the working tree.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Status {
+    /// The number of changed and untracked files
+    pub uncommitted: usize,
+    /// The commits not pushed to the upstream yet
+    pub ahead: usize,
+    /// The upstream branch, if any
+    pub upstream: Option<String>,
+}
+
+impl Status {
+    /// The status of the directory, None if it isn't git-managed.
+    pub fn detect(dir: &Path) -> Option<Self> {
+        git(dir, &["status", "--porcelain=v2", "--branch"]).map(|output| Self::parse(&output))
+    }
+
+    /// Parse the output of `git status --porcelain=v2 --branch`.
+    fn parse(output: &str) -> Self {
+        let mut status = Self::default();
+        for line in output.lines() {
+            if let Some(upstream) = line.strip_prefix("# branch.upstream ") {
+                status.upstream = Some(upstream.to_string());
+            } else if let Some(counts) = line.strip_prefix("# branch.ab ") {
+                let ahead = counts.split_whitespace().next().and_then(|a| a.strip_prefix('+'));
+                status.ahead = ahead.and_then(|a| a.parse().ok()).unwrap_or_default();
+            } else if !line.starts_with('#') {
+                status.uncommitted += 1;
+            }
+        }
+        status
+    }
+
+    /// The warnings about the local changes the server is going to ship or miss.
+    pub fn warnings(&self, source: Source) -> Vec<String> {
+        let mut warnings = vec![];
+        match source {
+            Source::Workspace if self.uncommitted > 0 => warnings
+                .push(format!("You have {} that WILL be included", plural(self.uncommitted, "uncommitted change"))),
+            Source::Remote if self.uncommitted > 0 => warnings
+                .push(format!("You have {} that will NOT be included", plural(self.uncommitted, "uncommitted change"))),
+            _ => {}
+        }
+        if let (Source::Remote, Some(upstream)) = (source, &self.upstream) {
+            if self.ahead > 0 {
+                warnings.push(format!(
+                    "The branch is {} ahead of {}; the server will build without them",
+                    plural(self.ahead, "commit"),
+                    upstream
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+/// Warn about the local changes the server is going to ship or miss, and ask
+/// whether to continue. Non-git directories are skipped silently.
+pub fn check(prompter: &dyn Prompter, dir: &Path, source: Source) -> Result<()> {
+    let warnings = Status::detect(dir).map(|s| s.warnings(source)).unwrap_or_default();
+    if warnings.is_empty() {
+        return Ok(());
+    }
+
+    for warning in &warnings {
+        warn!("{}", warning);
+    }
+    match prompter.confirm("Continue anyway?", false)? {
+        true => Ok(()),
+        false => Err(Errors::Aborted(String::from("the local changes were not confirmed, see --no-git-checks"))),
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("1 {}", noun),
+        n => format!("{} {}s", n, noun),
+    }
+}
+
+/// The trimmed output of the git command, None if it failed or printed nothing.
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = match Command::new("git").args(args).current_dir(dir).output() {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            debug!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+            return None;
+        }
+        Err(e) => {
+            debug!("git is not available: {}", e);
+            return None;
+        }
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!stdout.is_empty()).then_some(stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn revision(branch: Option<&str>, dirty: bool) -> Revision {
+        Revision { branch: branch.map(String::from), sha: Some("1a2b3c4".into()), dirty }
+    }
+
+    #[test]
+    fn validate_remote_urls() {
+        for url in [
+            "https://github.com/amphitheatre-app/amp-example-go",
+            "https://github.com/amphitheatre-app/amp-example-go.git",
+            "ssh://git@github.com/org/repo.git",
+            "git@github.com:org/repo.git",
+        ] {
+            assert!(validate_url(url).is_ok(), "{}", url);
+        }
+        for url in
+            ["github.com/org/repo", "https://github.com", "ftp://host/repo", "https://github.com/org/repo#main", ""]
+        {
+            assert!(matches!(validate_url(url), Err(Errors::InvalidGitUrl(_))), "{}", url);
+        }
+    }
+
+    #[test]
+    fn build_attributes() {
+        let attributes = revision(Some("main"), true).attributes().unwrap();
+        assert_eq!(attributes["commit"], "1a2b3c4");
+        assert_eq!(attributes["branch"], "main");
+        assert_eq!(attributes["dirty"], "true");
+
+        let detached = revision(None, false).attributes().unwrap();
+        assert_eq!(detached.len(), 2);
+        assert!(!detached.contains_key("branch"));
+    }
+
+    #[test]
+    fn omit_attributes_outside_of_git() {
+        assert_eq!(Revision::default().attributes(), None);
+    }
+
+    #[test]
+    fn read_echoed_attributes() {
+        let value = json!({"commit": "1a2b3c4", "branch": "main", "dirty": "false"});
+        assert_eq!(Revision::from_attributes(&value), Some(revision(Some("main"), false)));
+        assert_eq!(Revision::from_attributes(&json!({"branch": "main"})), None);
+    }
+
+    #[test]
+    fn display_revisions() {
+        assert_eq!(revision(Some("main"), true).to_string(), "1a2b3c4 (main, dirty)");
+        assert_eq!(revision(Some("main"), false).to_string(), "1a2b3c4 (main)");
+        assert_eq!(revision(None, true).to_string(), "1a2b3c4 (dirty)");
+        assert_eq!(revision(None, false).to_string(), "1a2b3c4");
+    }
+
+    #[test]
+    fn degrade_without_repository() {
+        let missing = std::env::temp_dir().join("amp-git-missing");
+        assert_eq!(Revision::detect(&missing), Revision::default());
+        assert_eq!(Status::detect(&missing), None);
+    }
+
+    #[test]
+    fn parse_status() {
+        let output = "# branch.oid 1a2b3c4\n# branch.head main\n# branch.upstream origin/main\n# branch.ab +2 -1\n\
+                      1 .M N... 100644 100644 100644 a b src/main.rs\n? notes.txt";
+        let status = Status::parse(output);
+        assert_eq!(status, Status { uncommitted: 2, ahead: 2, upstream: Some("origin/main".into()) });
+    }
+
+    #[test]
+    fn warn_about_shipped_or_missed_changes() {
+        let status = Status { uncommitted: 4, ahead: 2, upstream: Some("origin/main".into()) };
+        assert_eq!(status.warnings(Source::Workspace), vec!["You have 4 uncommitted changes that WILL be included"]);
+        assert_eq!(
+            status.warnings(Source::Remote),
+            vec![
+                "You have 4 uncommitted changes that will NOT be included",
+                "The branch is 2 commits ahead of origin/main; the server will build without them"
+            ]
+        );
+
+        let status = Status { uncommitted: 1, ahead: 0, upstream: None };
+        assert_eq!(status.warnings(Source::Workspace), vec!["You have 1 uncommitted change that WILL be included"]);
+        assert!(Status::default().warnings(Source::Remote).is_empty());
+    }
+
+    /// A fresh directory under the temporary one, for the fabricated repositories.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("amp-git-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn run(dir: &Path, args: &[&str]) {
+        let identity = ["-c", "user.name=amp", "-c", "user.email=amp@example.com", "-c", "commit.gpgsign=false"];
+        let status = Command::new("git").args(identity).args(args).current_dir(dir).output().unwrap().status;
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn inspect_fabricated_repositories() {
+        let root = scratch("status");
+        let (remote, local) = (root.join("remote.git"), root.join("local"));
+        run(&root, &["init", "--bare", "remote.git"]);
+        run(&root, &["clone", remote.to_str().unwrap(), "local"]);
+
+        std::fs::write(local.join("README.md"), "web").unwrap();
+        run(&local, &["add", "."]);
+        run(&local, &["commit", "-m", "init"]);
+        run(&local, &["push", "-u", "origin", "HEAD"]);
+        assert_eq!(Status::detect(&local).unwrap().warnings(Source::Remote), Vec::<String>::new());
+
+        std::fs::write(local.join("README.md"), "api").unwrap();
+        run(&local, &["commit", "-am", "rename"]);
+        std::fs::write(local.join("main.rs"), "").unwrap();
+        let status = Status::detect(&local).unwrap();
+        assert_eq!((status.uncommitted, status.ahead), (1, 1));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn find_repository_roots() {
+        let root = scratch("root");
+        let nested = root.join("apps/web");
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(&nested).unwrap();
+
+        assert_eq!(super::root(&nested), Some(root.clone()));
+        assert_eq!(super::root(&root), Some(root.clone()));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn skip_non_git_directories() {
+        let dir = scratch("plain");
+        let prompter = crate::prompt::ScriptedPrompter::new(vec![]);
+        assert!(check(&prompter, &dir, Source::Workspace).is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
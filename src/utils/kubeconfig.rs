@@ -0,0 +1,269 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::errors::{Errors, Result};
+
+/// The parts of a Kubernetes kubeconfig needed to derive a context.
+#[derive(Debug, Default, Deserialize)]
+pub struct Kubeconfig {
+    #[serde(default)]
+    clusters: Vec<NamedCluster>,
+    #[serde(default)]
+    contexts: Vec<NamedContext>,
+    #[serde(default)]
+    users: Vec<NamedUser>,
+    #[serde(rename = "current-context", default)]
+    pub current_context: Option<String>,
+    /// The directory the relative file paths are resolved against
+    #[serde(skip)]
+    base: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct NamedCluster {
+    name: String,
+    cluster: Cluster,
+}
+
+#[derive(Debug, Deserialize)]
+struct Cluster {
+    server: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct NamedContext {
+    name: String,
+    context: ContextRef,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContextRef {
+    cluster: String,
+    #[serde(default)]
+    user: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NamedUser {
+    name: String,
+    #[serde(default)]
+    user: User,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct User {
+    token: Option<String>,
+    #[serde(rename = "tokenFile")]
+    token_file: Option<String>,
+    client_certificate: Option<String>,
+    client_certificate_data: Option<String>,
+    client_key: Option<String>,
+    client_key_data: Option<String>,
+}
+
+/// How the user of a kubeconfig context authenticates.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Credentials {
+    Token(String),
+    /// The PEM certificate and key, or the base64 data inlined in the kubeconfig
+    Certificate {
+        certificate: String,
+        key: String,
+    },
+    None,
+}
+
+/// The server and credentials of a kubeconfig context.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Resolved {
+    pub server: String,
+    pub credentials: Credentials,
+}
+
+impl Kubeconfig {
+    /// The kubeconfig path used by kubectl: the first of `KUBECONFIG`, else `~/.kube/config`.
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(paths) = std::env::var_os("KUBECONFIG") {
+            if let Some(path) = std::env::split_paths(&paths).next() {
+                return Some(path);
+            }
+        }
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".kube/config"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| Errors::KubeconfigParseFailed(format!("{}: {}", path.display(), e)))?;
+        let mut kubeconfig = Self::parse(&content)?;
+        kubeconfig.base = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(kubeconfig)
+    }
+
+    fn parse(content: &str) -> Result<Self> {
+        serde_yaml::from_str(content).map_err(|e| Errors::KubeconfigParseFailed(e.to_string()))
+    }
+
+    /// The names of the contexts, in the kubeconfig order.
+    pub fn contexts(&self) -> Vec<String> {
+        self.contexts.iter().map(|c| c.name.clone()).collect()
+    }
+
+    /// The server and credentials of the context.
+    pub fn resolve(&self, name: &str) -> Result<Resolved> {
+        let missing =
+            |kind: &str, name: &str| Errors::KubeconfigParseFailed(format!("{} {} is not defined", kind, name));
+
+        let context = self.contexts.iter().find(|c| c.name == name).ok_or_else(|| missing("context", name))?;
+        let cluster = &context.context.cluster;
+        let cluster = self.clusters.iter().find(|c| &c.name == cluster).ok_or_else(|| missing("cluster", cluster))?;
+
+        let credentials = match &context.context.user {
+            Some(user) => {
+                let user = self.users.iter().find(|u| &u.name == user).ok_or_else(|| missing("user", user))?;
+                self.credentials(&user.user)?
+            }
+            None => Credentials::None,
+        };
+
+        Ok(Resolved { server: cluster.cluster.server.clone(), credentials })
+    }
+
+    fn credentials(&self, user: &User) -> Result<Credentials> {
+        if let Some(token) = &user.token {
+            return Ok(Credentials::Token(token.clone()));
+        }
+        if let Some(path) = &user.token_file {
+            return Ok(Credentials::Token(self.read(path)?.trim().to_string()));
+        }
+
+        let certificate = match (&user.client_certificate_data, &user.client_certificate) {
+            (Some(data), _) => Some(data.clone()),
+            (None, Some(path)) => Some(self.read(path)?),
+            (None, None) => None,
+        };
+        let key = match (&user.client_key_data, &user.client_key) {
+            (Some(data), _) => Some(data.clone()),
+            (None, Some(path)) => Some(self.read(path)?),
+            (None, None) => None,
+        };
+        match (certificate, key) {
+            (Some(certificate), Some(key)) => Ok(Credentials::Certificate { certificate, key }),
+            (Some(_), None) | (None, Some(_)) => Err(Errors::KubeconfigParseFailed(String::from(
+                "the client certificate and key must be given together",
+            ))),
+            (None, None) => Ok(Credentials::None),
+        }
+    }
+
+    /// Read the file, relative to the kubeconfig.
+    fn read(&self, path: &str) -> Result<String> {
+        let path = self.base.join(path);
+        fs::read_to_string(&path).map_err(|e| Errors::KubeconfigParseFailed(format!("{}: {}", path.display(), e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KUBECONFIG: &str = r#"
+apiVersion: v1
+kind: Config
+current-context: staging
+clusters:
+  - name: staging
+    cluster:
+      server: https://staging.example.com:6443
+      certificate-authority-data: Y2E=
+  - name: production
+    cluster:
+      server: https://production.example.com:6443
+contexts:
+  - name: staging
+    context:
+      cluster: staging
+      user: ci
+  - name: production
+    context:
+      cluster: production
+      user: admin
+  - name: anonymous
+    context:
+      cluster: staging
+  - name: broken
+    context:
+      cluster: missing
+users:
+  - name: ci
+    user:
+      token: secret
+  - name: admin
+    user:
+      client-certificate-data: Y2VydA==
+      client-key-data: a2V5
+"#;
+
+    fn kubeconfig() -> Kubeconfig {
+        Kubeconfig::parse(KUBECONFIG).unwrap()
+    }
+
+    #[test]
+    fn list_contexts() {
+        let kubeconfig = kubeconfig();
+        assert_eq!(kubeconfig.contexts(), vec!["staging", "production", "anonymous", "broken"]);
+        assert_eq!(kubeconfig.current_context.as_deref(), Some("staging"));
+    }
+
+    #[test]
+    fn resolve_token_auth() {
+        let resolved = kubeconfig().resolve("staging").unwrap();
+        assert_eq!(resolved.server, "https://staging.example.com:6443");
+        assert_eq!(resolved.credentials, Credentials::Token("secret".into()));
+    }
+
+    #[test]
+    fn resolve_certificate_auth() {
+        let resolved = kubeconfig().resolve("production").unwrap();
+        let expected = Credentials::Certificate { certificate: "Y2VydA==".into(), key: "a2V5".into() };
+        assert_eq!(resolved.credentials, expected);
+    }
+
+    #[test]
+    fn resolve_files_relative_to_kubeconfig() {
+        let dir = std::env::temp_dir().join(format!("amp-kubeconfig-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("token"), "from-file\n").unwrap();
+        let content = KUBECONFIG.replace("token: secret", "tokenFile: token");
+        fs::write(dir.join("config"), content).unwrap();
+
+        let resolved = Kubeconfig::load(&dir.join("config")).unwrap().resolve("staging").unwrap();
+        assert_eq!(resolved.credentials, Credentials::Token("from-file".into()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reject_invalid_kubeconfigs() {
+        let kubeconfig = kubeconfig();
+        assert_eq!(kubeconfig.resolve("anonymous").unwrap().credentials, Credentials::None);
+        assert!(matches!(kubeconfig.resolve("broken"), Err(Errors::KubeconfigParseFailed(_))));
+        assert!(matches!(kubeconfig.resolve("unknown"), Err(Errors::KubeconfigParseFailed(_))));
+        assert!(matches!(Kubeconfig::parse("contexts: 42"), Err(Errors::KubeconfigParseFailed(_))));
+    }
+}
@@ -0,0 +1,385 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use amp_common::config::Configuration;
+use clap::ValueEnum;
+use tokio::process::Command;
+use tracing::{debug, info, warn};
+
+use crate::errors::{Errors, Result};
+use crate::utils::{cache, yaml};
+
+/// The manifest file name used when a git reference doesn't name one.
+const DEFAULT_MANIFEST: &str = ".amp.toml";
+
+/// The manifest file names searched for, in order of preference.
+pub const MANIFEST_NAMES: [&str; 3] = [DEFAULT_MANIFEST, ".amp.yaml", ".amp.yml"];
+
+/// Remote manifests larger than this are rejected.
+const MAX_REMOTE_BYTES: u64 = 1024 * 1024;
+
+/// Where a manifest was read from, so errors can cite it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Origin {
+    File(PathBuf),
+    Url(String),
+    Git { repository: String, reference: Option<String>, path: PathBuf, checkout: PathBuf },
+}
+
+impl Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Origin::File(path) => write!(f, "{}", path.display()),
+            Origin::Url(url) => write!(f, "{}", url),
+            Origin::Git { repository, reference, path, .. } => {
+                write!(f, "{}#{}:{}", repository, reference.as_deref().unwrap_or_default(), path.display())
+            }
+        }
+    }
+}
+
+impl Origin {
+    /// The path of the manifest, within the repository or the URL.
+    pub fn path(&self) -> PathBuf {
+        match self {
+            Origin::File(path) | Origin::Git { path, .. } => path.clone(),
+            Origin::Url(url) => {
+                PathBuf::from(reqwest::Url::parse(url).map(|u| u.path().to_string()).unwrap_or_default())
+            }
+        }
+    }
+}
+
+/// The formats a manifest can be written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Toml,
+    Yaml,
+}
+
+impl Format {
+    /// Detect the format from the extension of the path, TOML if it has none.
+    pub fn detect(path: &Path) -> Result<Format> {
+        let extension = path.extension().map(|e| e.to_string_lossy().to_ascii_lowercase());
+        match extension.as_deref() {
+            None | Some("toml") => Ok(Format::Toml),
+            Some("yaml" | "yml") => Ok(Format::Yaml),
+            Some(_) => Err(Errors::UnsupportedManifestFormat(path.display().to_string())),
+        }
+    }
+
+    /// Convert the content to TOML, the format every command and the server work with.
+    pub fn to_toml(self, origin: &str, content: String) -> Result<String> {
+        match self {
+            Format::Toml => Ok(content),
+            Format::Yaml => yaml::to_toml(origin, &content),
+        }
+    }
+}
+
+/// When to update the cached clones of remote git repositories.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum CachePolicy {
+    /// Clone the repository if missing, otherwise fetch the latest changes
+    #[default]
+    Always,
+    /// Only clone the repository if it is not cached yet
+    Missing,
+    /// Never clone or fetch, the repository must be cached already
+    Never,
+}
+
+/// The raw content of a manifest, along with its origin.
+#[derive(Clone, Debug)]
+pub struct Manifest {
+    pub origin: Origin,
+    pub content: String,
+}
+
+impl Manifest {
+    /// The local directory containing the manifest, remote URLs have none.
+    pub fn workspace(&self) -> Option<PathBuf> {
+        match &self.origin {
+            Origin::File(path) => path.parent().map(Path::to_path_buf),
+            Origin::Url(_) => None,
+            Origin::Git { path, checkout, .. } => checkout.join(path).parent().map(Path::to_path_buf),
+        }
+    }
+}
+
+/// Resolve where to read the manifest from: the given filename, else the
+/// `AMP_FILENAME` variable, else the closest `.amp.toml`, `.amp.yaml` or `.amp.yml`.
+/// Local paths are made absolute, relative to the workspace or the working directory.
+pub fn resolve_manifest(filename: Option<&str>, workspace: Option<&Path>) -> Result<String> {
+    let filename = filename.map(String::from).or_else(|| std::env::var("AMP_FILENAME").ok().filter(|f| !f.is_empty()));
+
+    let path = match filename {
+        Some(location) if is_remote(&location) => return Ok(location),
+        Some(location) => manifest_path(&location, workspace),
+        None => match workspace {
+            Some(_) => {
+                let dir = manifest_path("", workspace);
+                find_manifest(&dir, false).unwrap_or_else(|| dir.join(DEFAULT_MANIFEST))
+            }
+            None => {
+                let dir = std::env::current_dir().unwrap_or_default();
+                find_manifest(&dir, true).ok_or(Errors::NotFoundManifest(dir))?
+            }
+        },
+    };
+    if !path.is_file() {
+        return Err(Errors::ManifestNotFoundAt(path));
+    }
+
+    Ok(path.display().to_string())
+}
+
+/// The absolute path of the local manifest, relative to the workspace or the working directory.
+pub fn manifest_path(filename: &str, workspace: Option<&Path>) -> PathBuf {
+    let base = std::env::current_dir().unwrap_or_default();
+    let base = workspace.map(|w| base.join(w)).unwrap_or(base);
+    base.join(filename)
+}
+
+/// The first manifest found in the directory, or in its parents if `upwards`.
+/// Within a directory, TOML is preferred over YAML.
+fn find_manifest(dir: &Path, upwards: bool) -> Option<PathBuf> {
+    let dirs = dir.ancestors().take(if upwards { usize::MAX } else { 1 });
+    dirs.flat_map(|d| MANIFEST_NAMES.iter().map(move |name| d.join(name))).find(|path| path.is_file())
+}
+
+/// Whether the location is an URL or a git reference rather than a local path.
+fn is_remote(location: &str) -> bool {
+    location.starts_with("https://") || location.starts_with("http://") || parse_git_reference(location).is_some()
+}
+
+/// Read the manifest from a local path, an `https://` URL, or a git reference
+/// like `https://github.com/org/repo#main:.amp.toml`. YAML manifests, told
+/// apart by their extension, are converted to TOML.
+pub async fn read_manifest(http: &reqwest::Client, location: &str, policy: CachePolicy) -> Result<Manifest> {
+    let mut manifest = if let Some((repository, reference, path)) = parse_git_reference(location) {
+        read_git(repository, reference, path, policy).await?
+    } else if location.starts_with("https://") || location.starts_with("http://") {
+        read_url(http, location).await?
+    } else {
+        let path = PathBuf::from(location);
+        let content =
+            std::fs::read_to_string(&path).map_err(|e| Errors::FailedReadManifest(location.to_string(), e))?;
+        Manifest { origin: Origin::File(path), content }
+    };
+
+    let format = Format::detect(&manifest.origin.path())?;
+    manifest.content = format.to_toml(&manifest.origin.to_string(), manifest.content)?;
+    Ok(manifest)
+}
+
+/// Split a `<repository>#<reference>:<path>` location, both the reference
+/// and the path are optional. Returns None if the location is not a git reference.
+fn parse_git_reference(location: &str) -> Option<(String, Option<String>, PathBuf)> {
+    let (repository, rest) = location.split_once('#')?;
+    let (reference, path) = rest.split_once(':').unwrap_or((rest, ""));
+
+    let reference = (!reference.is_empty()).then(|| reference.to_string());
+    let path = PathBuf::from(if path.is_empty() { DEFAULT_MANIFEST } else { path });
+    Some((repository.to_string(), reference, path))
+}
+
+async fn read_url(http: &reqwest::Client, url: &str) -> Result<Manifest> {
+    info!("Fetching the manifest from {}", url);
+    let fetch = |e| Errors::FailedFetchManifest(url.to_string(), e);
+
+    let response = http.get(url).send().await.and_then(|r| r.error_for_status()).map_err(fetch)?;
+    if response.content_length().is_some_and(|len| len > MAX_REMOTE_BYTES) {
+        return Err(Errors::ManifestTooLarge(url.to_string(), MAX_REMOTE_BYTES));
+    }
+
+    let bytes = response.bytes().await.map_err(fetch)?;
+    if bytes.len() as u64 > MAX_REMOTE_BYTES {
+        return Err(Errors::ManifestTooLarge(url.to_string(), MAX_REMOTE_BYTES));
+    }
+
+    let content = String::from_utf8_lossy(&bytes).into_owned();
+    Ok(Manifest { origin: Origin::Url(url.to_string()), content })
+}
+
+async fn read_git(
+    repository: String,
+    reference: Option<String>,
+    path: PathBuf,
+    policy: CachePolicy,
+) -> Result<Manifest> {
+    let dir = remote_cache_dir()?;
+    let key = cache_key(&repository, reference.as_deref());
+    let checkout = dir.join(&key);
+    let cached = checkout.join(".git").exists();
+    match policy {
+        CachePolicy::Never if !cached => return Err(Errors::RemoteCacheMissing(repository)),
+        CachePolicy::Always if cached => sync(&repository, reference.as_deref(), &checkout).await?,
+        _ if !cached => clone(&repository, reference.as_deref(), &checkout).await?,
+        _ => debug!("Using the cached repository at {}", checkout.display()),
+    }
+    if let Err(err) = cache::touch(&dir, &key, &repository, reference.as_deref()) {
+        warn!("Failed to update the index of the remote cache: {}", err);
+    }
+
+    let file = checkout.join(&path);
+    let origin = Origin::Git { repository, reference, path, checkout };
+    let content = std::fs::read_to_string(file).map_err(|e| Errors::FailedReadManifest(origin.to_string(), e))?;
+    Ok(Manifest { origin, content })
+}
+
+/// The directory where remote git repositories are cloned into.
+pub fn remote_cache_dir() -> Result<PathBuf> {
+    let path = Configuration::path().map_err(Errors::InvalidConfigPath)?;
+    Ok(path.parent().unwrap_or(Path::new(".")).join("repos"))
+}
+
+/// A directory name for the given repository and reference.
+fn cache_key(repository: &str, reference: Option<&str>) -> String {
+    let name = format!("{}@{}", repository, reference.unwrap_or("HEAD"));
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '@' { c } else { '-' }).collect()
+}
+
+pub(super) async fn clone(repository: &str, reference: Option<&str>, checkout: &Path) -> Result<()> {
+    info!("Cloning {} into the remote cache", repository);
+    let mut args = vec!["clone", "--depth", "1"];
+    if let Some(reference) = reference {
+        args.extend(["--branch", reference]);
+    }
+    args.push(repository);
+    git(repository, &args, checkout.as_os_str().to_str().unwrap_or_default(), None).await
+}
+
+async fn sync(repository: &str, reference: Option<&str>, checkout: &Path) -> Result<()> {
+    info!("Syncing the cached repository {}", repository);
+    let reference = reference.unwrap_or("HEAD");
+    git(repository, &["fetch", "--depth", "1", "origin", reference], "", Some(checkout)).await?;
+    git(repository, &["reset", "--hard", "FETCH_HEAD"], "", Some(checkout)).await
+}
+
+async fn git(repository: &str, args: &[&str], target: &str, dir: Option<&Path>) -> Result<()> {
+    let mut command = Command::new("git");
+    command.args(args);
+    if !target.is_empty() {
+        command.arg(target);
+    }
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+
+    let output = command.output().await.map_err(|e| Errors::FailedCloneRepository(repository.into(), e.to_string()))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(Errors::FailedCloneRepository(repository.into(), stderr));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_git_references() {
+        let (repo, reference, path) = parse_git_reference("https://github.com/org/repo#main:apps/.amp.toml").unwrap();
+        assert_eq!(repo, "https://github.com/org/repo");
+        assert_eq!(reference.as_deref(), Some("main"));
+        assert_eq!(path, PathBuf::from("apps/.amp.toml"));
+
+        let (_, reference, path) = parse_git_reference("https://github.com/org/repo#v1.0").unwrap();
+        assert_eq!(reference.as_deref(), Some("v1.0"));
+        assert_eq!(path, PathBuf::from(DEFAULT_MANIFEST));
+
+        let (_, reference, _) = parse_git_reference("https://github.com/org/repo#:.amp.toml").unwrap();
+        assert_eq!(reference, None);
+
+        assert!(parse_git_reference("https://example.com/.amp.toml").is_none());
+    }
+
+    #[test]
+    fn resolve_relative_to_workspace() {
+        let dir = std::env::temp_dir().join(format!("amp-resolve-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("amp.staging.toml"), "").unwrap();
+
+        let location = resolve_manifest(Some("amp.staging.toml"), Some(&dir)).unwrap();
+        assert_eq!(PathBuf::from(location), dir.join("amp.staging.toml"));
+
+        let err = resolve_manifest(Some("amp.prod.toml"), Some(&dir)).unwrap_err();
+        assert!(err.to_string().contains(&dir.join("amp.prod.toml").display().to_string()));
+
+        let url = "https://example.com/.amp.toml";
+        assert_eq!(resolve_manifest(Some(url), Some(&dir)).unwrap(), url);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn find_toml_before_yaml() {
+        let dir = std::env::temp_dir().join(format!("amp-find-{}", std::process::id()));
+        let nested = dir.join("apps/web");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(dir.join(".amp.toml"), "").unwrap();
+        std::fs::write(nested.join(".amp.yml"), "").unwrap();
+
+        assert_eq!(find_manifest(&nested, true), Some(nested.join(".amp.yml")));
+        assert_eq!(find_manifest(&dir.join("apps"), false), None);
+        assert_eq!(find_manifest(&dir.join("apps"), true), Some(dir.join(".amp.toml")));
+
+        std::fs::write(nested.join(".amp.toml"), "").unwrap();
+        assert_eq!(find_manifest(&nested, false), Some(nested.join(".amp.toml")));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn detect_formats() {
+        assert_eq!(Format::detect(Path::new("apps/.amp.toml")).unwrap(), Format::Toml);
+        assert_eq!(Format::detect(Path::new("amp.staging.YAML")).unwrap(), Format::Yaml);
+        assert_eq!(Format::detect(Path::new(".amp.yml")).unwrap(), Format::Yaml);
+        assert_eq!(Format::detect(Path::new("Ampfile")).unwrap(), Format::Toml);
+
+        let err = Format::detect(Path::new("amp.json")).unwrap_err();
+        assert!(matches!(err, Errors::UnsupportedManifestFormat(path) if path == "amp.json"));
+
+        let origin = Origin::Url("https://example.com/web/.amp.yaml?ref=main".into());
+        assert_eq!(Format::detect(&origin.path()).unwrap(), Format::Yaml);
+    }
+
+    #[tokio::test]
+    async fn read_yaml_manifests() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/manifests");
+        let read = |name: &str| {
+            let location = dir.join(name).display().to_string();
+            async move { read_manifest(&reqwest::Client::new(), &location, CachePolicy::Never).await.unwrap() }
+        };
+
+        let yaml: toml::Table = toml::from_str(&read(".amp.yaml").await.content).unwrap();
+        let toml: toml::Table = toml::from_str(&read(".amp.toml").await.content).unwrap();
+        assert_eq!(yaml, toml);
+    }
+
+    #[test]
+    fn workspace_of_origins() {
+        let manifest = Manifest { origin: Origin::Url("https://example.com/.amp.toml".into()), content: "".into() };
+        assert_eq!(manifest.workspace(), None);
+
+        let manifest = Manifest { origin: Origin::File(PathBuf::from("/src/app/.amp.toml")), content: "".into() };
+        assert_eq!(manifest.workspace(), Some(PathBuf::from("/src/app")));
+    }
+}
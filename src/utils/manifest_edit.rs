@@ -0,0 +1,279 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::{self, Display};
+use std::path::Path;
+
+use serde::Serialize;
+use toml_edit::{Array, DocumentMut, InlineTable, Item, Table, TableLike, Value};
+
+use crate::errors::{Errors, Result};
+use crate::utils;
+
+/// A manifest document being edited. The edits keep its comments, key order
+/// and formatting, so the files users wrote by hand survive being touched by
+/// the CLI, and an unchanged document is written back byte for byte.
+pub struct ManifestEdit {
+    original: String,
+    document: DocumentMut,
+}
+
+impl ManifestEdit {
+    /// Parse the manifest content read from the given origin.
+    pub fn parse(origin: &str, content: &str) -> Result<Self> {
+        let document = content.parse().map_err(|e| Errors::FailedEditManifest(origin.to_string(), e))?;
+        Ok(Self { original: content.to_string(), document })
+    }
+
+    /// Set the value at the key path, creating the missing tables. A replaced
+    /// value keeps its surrounding whitespace and comments.
+    pub fn set(&mut self, path: &[&str], value: impl Into<Value>) -> Result<()> {
+        let (key, parent) = split(path);
+        let table = self.table_mut(parent)?;
+
+        let mut value = value.into();
+        match table.get_mut(key) {
+            Some(Item::Value(old)) => {
+                *value.decor_mut() = old.decor().clone();
+                *old = value;
+            }
+            Some(item) => *item = Item::Value(value),
+            None => {
+                table.insert(key, Item::Value(value));
+            }
+        }
+        Ok(())
+    }
+
+    /// Append the value to the array at the key path, creating it if missing.
+    /// The new element is laid out like the last one, so multi-line arrays stay
+    /// one element per line.
+    pub fn append(&mut self, path: &[&str], value: impl Into<Value>) -> Result<()> {
+        let (key, parent) = split(path);
+        let table = self.table_mut(parent)?;
+        if !table.contains_key(key) {
+            table.insert(key, Item::Value(Value::Array(Array::new())));
+        }
+
+        let name = path.join(".");
+        let array = table.get_mut(key).and_then(Item::as_array_mut).ok_or(Errors::ManifestKeyConflict(name))?;
+        let mut value = value.into();
+        let Some(last) = array.iter_mut().last() else {
+            array.push(value);
+            return Ok(());
+        };
+
+        let prefix = match last.decor().prefix().and_then(|p| p.as_str()) {
+            // Keep the indentation of the line, but not the comments above it.
+            Some(prefix) if prefix.contains('\n') => format!("\n{}", prefix.rsplit('\n').next().unwrap_or_default()),
+            _ => " ".to_string(),
+        };
+        let suffix = last.decor().suffix().and_then(|s| s.as_str()).unwrap_or_default().to_string();
+        last.decor_mut().set_suffix("");
+        value.decor_mut().set_prefix(prefix);
+        value.decor_mut().set_suffix(suffix);
+        array.push_formatted(value);
+        Ok(())
+    }
+
+    /// Insert an empty table at the key path, returns false if it already exists.
+    pub fn insert_table(&mut self, path: &[&str]) -> Result<bool> {
+        let (key, parent) = split(path);
+        let table = self.table_mut(parent)?;
+        if table.contains_key(key) {
+            return Ok(false);
+        }
+
+        table.insert(key, Item::Table(Table::new()));
+        Ok(true)
+    }
+
+    /// Remove the item at the key path, along with its comments.
+    pub fn remove(&mut self, path: &[&str]) -> Option<Item> {
+        let (key, parent) = split(path);
+        let mut item = self.document.as_item_mut();
+        for name in parent {
+            item = item.get_mut(name)?;
+        }
+        item.as_table_like_mut()?.remove(key)
+    }
+
+    /// Whether any edit changed the content.
+    pub fn is_changed(&self) -> bool {
+        self.document.to_string() != self.original
+    }
+
+    /// Write the edited manifest to the path atomically.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        write(path, &self.document.to_string())
+    }
+
+    /// The table at the key path, the missing ones are created implicitly, so
+    /// they are only written out once they hold a value.
+    fn table_mut(&mut self, path: &[&str]) -> Result<&mut dyn TableLike> {
+        let conflict = |i: usize| Errors::ManifestKeyConflict(path[..=i].join("."));
+
+        let mut item = self.document.as_item_mut();
+        for (i, key) in path.iter().enumerate() {
+            let inline = item.is_inline_table();
+            let table = item.as_table_like_mut().ok_or_else(|| conflict(i.saturating_sub(1)))?;
+            if !table.contains_key(key) {
+                table.insert(key, empty_table(inline));
+            }
+            item = table.get_mut(key).ok_or_else(|| conflict(i))?;
+        }
+
+        match item.as_table_like_mut() {
+            Some(table) => Ok(table),
+            None => Err(Errors::ManifestKeyConflict(path.join("."))),
+        }
+    }
+}
+
+impl Display for ManifestEdit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.document)
+    }
+}
+
+/// Serialize the value as a new document, there is no layout to preserve.
+pub fn serialize<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    toml::to_string(value).map_err(Errors::TomlSerializeError)
+}
+
+/// Write the manifest content to the path atomically.
+pub fn write(path: &Path, content: &str) -> Result<()> {
+    utils::atomic_write(path, content.as_bytes()).map_err(Errors::FailedSaveManifest)
+}
+
+fn split<'a, 'b>(path: &'a [&'b str]) -> (&'b str, &'a [&'b str]) {
+    let (key, parent) = path.split_last().expect("the key path must not be empty");
+    (key, parent)
+}
+
+fn empty_table(inline: bool) -> Item {
+    if inline {
+        return Item::Value(Value::InlineTable(InlineTable::new()));
+    }
+
+    let mut table = Table::new();
+    table.set_implicit(true);
+    Item::Table(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGIN: &str = "commented.toml";
+
+    fn fixture() -> String {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/manifest_edit").join(ORIGIN);
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    /// The lines removed from and added to the original content.
+    fn changes(original: &str, edit: &ManifestEdit) -> (Vec<String>, Vec<String>) {
+        let edited = edit.to_string();
+        let removed = original.lines().filter(|l| !edited.lines().any(|e| e == *l)).map(String::from).collect();
+        let added = edited.lines().filter(|l| !original.lines().any(|o| o == *l)).map(String::from).collect();
+        (removed, added)
+    }
+
+    #[test]
+    fn round_trip_is_byte_identical() {
+        let content = fixture();
+        let edit = ManifestEdit::parse(ORIGIN, &content).unwrap();
+
+        assert_eq!(edit.to_string(), content);
+        assert!(!edit.is_changed());
+    }
+
+    #[test]
+    fn set_keeps_trailing_comment() {
+        let content = fixture();
+        let mut edit = ManifestEdit::parse(ORIGIN, &content).unwrap();
+        edit.set(&["meta", "name"], "api").unwrap();
+
+        let (removed, added) = changes(&content, &edit);
+        assert_eq!(removed, vec![r#"name = "web"   # the name shown in `amp actor list`"#]);
+        assert_eq!(added, vec![r#"name = "api"   # the name shown in `amp actor list`"#]);
+        assert!(edit.is_changed());
+    }
+
+    #[test]
+    fn set_creates_missing_tables() {
+        let content = fixture();
+        let mut edit = ManifestEdit::parse(ORIGIN, &content).unwrap();
+        edit.set(&["deploy", "env", "DEBUG"], "1").unwrap();
+        edit.set(&["sync", "interval"], 5i64).unwrap();
+
+        let edited: toml::Table = toml::from_str(&edit.to_string()).unwrap();
+        assert_eq!(edited["deploy"]["env"]["DEBUG"].as_str(), Some("1"));
+        assert_eq!(edited["deploy"]["env"]["LOG"].as_str(), Some("info"));
+        assert_eq!(edited["sync"]["interval"].as_integer(), Some(5));
+        assert!(edit.to_string().starts_with(&content[..content.find("[deploy]").unwrap()]));
+    }
+
+    #[test]
+    fn append_follows_the_array_layout() {
+        let content = fixture();
+        let mut edit = ManifestEdit::parse(ORIGIN, &content).unwrap();
+        edit.append(&["deploy", "excludes"], "dist").unwrap();
+        edit.append(&["deploy", "ports"], 9000i64).unwrap();
+
+        let (removed, added) = changes(&content, &edit);
+        assert_eq!(removed, vec!["ports = [8080,   8443]"]);
+        assert_eq!(added, vec![r#"    "dist","#, "ports = [8080,   8443, 9000]"]);
+    }
+
+    #[test]
+    fn append_creates_missing_array() {
+        let mut edit = ManifestEdit::parse(ORIGIN, "[meta]\nname = \"web\"\n").unwrap();
+        edit.append(&["meta", "tags"], "frontend").unwrap();
+
+        assert_eq!(edit.to_string(), "[meta]\nname = \"web\"\ntags = [\"frontend\"]\n");
+    }
+
+    #[test]
+    fn insert_table_appends_once() {
+        let content = fixture();
+        let mut edit = ManifestEdit::parse(ORIGIN, &content).unwrap();
+        assert!(edit.insert_table(&["sync"]).unwrap());
+        assert!(!edit.insert_table(&["meta"]).unwrap());
+
+        assert_eq!(edit.to_string(), format!("{}[sync]\n", content));
+    }
+
+    #[test]
+    fn remove_drops_the_section_only() {
+        let content = fixture();
+        let mut edit = ManifestEdit::parse(ORIGIN, &content).unwrap();
+        assert!(edit.remove(&["partners"]).is_some());
+        assert!(edit.remove(&["missing", "key"]).is_none());
+
+        let end = content.find("\n# Partners").unwrap();
+        assert_eq!(edit.to_string(), content[..end]);
+    }
+
+    #[test]
+    fn conflicting_key_is_an_error() {
+        let mut edit = ManifestEdit::parse(ORIGIN, &fixture()).unwrap();
+
+        let err = edit.set(&["meta", "name", "first"], "web").unwrap_err();
+        assert!(matches!(err, Errors::ManifestKeyConflict(key) if key == "meta.name"));
+        assert!(edit.append(&["meta", "name"], "web").is_err());
+        assert!(!edit.is_changed());
+    }
+}
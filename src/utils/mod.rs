@@ -0,0 +1,226 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use amp_common::config::Configuration;
+use tar::Builder;
+use tracing::{debug, warn};
+
+pub mod cache;
+pub mod environment;
+pub mod git;
+pub mod kubeconfig;
+mod manifest;
+pub mod manifest_edit;
+pub mod naming;
+pub mod paths;
+pub mod profile;
+pub mod selector;
+mod substitute;
+pub mod time;
+pub mod yaml;
+
+pub use manifest::{
+    manifest_path, read_manifest, remote_cache_dir, resolve_manifest, CachePolicy, Format as ManifestFormat, Manifest,
+    Origin, MANIFEST_NAMES,
+};
+pub use substitute::{parse_key_value, Variables};
+
+use crate::errors::{Errors, Result};
+
+/// Archive the given directory into a tarball and return the bytes.
+pub fn archive(paths: &Vec<(PathBuf, PathBuf)>) -> Result<Vec<u8>> {
+    debug!("The given path for archive is {:?}", paths);
+    let mut tar = Builder::new(Vec::new());
+    for (path, name) in paths {
+        tar.append_path_with_name(path, name).map_err(Errors::FailedAppendPath)?;
+    }
+    tar.into_inner().map_err(Errors::FailedFinishTar)
+}
+
+/// Strip the given base path from the given path, see [`paths::relative`].
+#[inline]
+pub fn strip(base: &Path, path: &Path) -> Result<(PathBuf, PathBuf)> {
+    let striped_path = paths::relative(base, path)?;
+    debug!("the full path and striped path is: {:?}, {:?}", path, striped_path);
+    Ok((path.to_path_buf(), striped_path.to_path_buf()))
+}
+
+/// Write the given bytes to the path atomically: the bytes are written to a temporary
+/// file in the same directory, synced, and then renamed over the destination.
+pub fn atomic_write(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    atomic_write_with(path, bytes, |file, bytes| file.write_all(bytes))
+}
+
+fn atomic_write_with<F>(path: &Path, bytes: &[u8], write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut File, &[u8]) -> io::Result<()>,
+{
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)?;
+
+    let name = path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid file name"))?;
+    let temp = dir.join(format!(".{}.{}.tmp", name.to_string_lossy(), std::process::id()));
+
+    let result = File::create(&temp).and_then(|mut file| {
+        write(&mut file, bytes)?;
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+        return result;
+    }
+
+    // Persist the rename itself, directories can't be opened for syncing on Windows.
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+
+    Ok(())
+}
+
+/// The tables of the configuration file read by the CLI itself, unknown to its schema.
+const CLI_SETTINGS: [&str; 2] = ["output", "tui"];
+
+/// Save the configuration to its default path atomically, keeping the settings of the CLI.
+pub fn save_configuration(configuration: &Configuration) -> Result<()> {
+    let path = Configuration::path().map_err(Errors::InvalidConfigPath)?;
+    let mut content = manifest_edit::serialize(configuration)?;
+    if let Some(settings) = cli_settings(&path) {
+        content = format!("{}\n{}", content.trim_end(), manifest_edit::serialize(&settings)?);
+    }
+    atomic_write(&path, content.as_bytes()).map_err(|e| Errors::FailedSaveConfiguration(e.into()))
+}
+
+/// Read the setting of the CLI at the key path, such as `output.columns.playbooks`,
+/// from the configuration file.
+pub fn setting(path: &[&str]) -> Option<toml::Value> {
+    let settings = cli_settings(&Configuration::path().ok()?)?;
+    let (first, rest) = path.split_first()?;
+    rest.iter().try_fold(settings.get(*first)?, |value, key| value.get(key)).cloned()
+}
+
+fn cli_settings(path: &Path) -> Option<toml::Table> {
+    let content = fs::read_to_string(path).ok()?;
+    let mut table: toml::Table = toml::from_str(&content).ok()?;
+    table.retain(|key, _| CLI_SETTINGS.contains(&key.as_str()));
+    (!table.is_empty()).then_some(table)
+}
+
+/// Hide the token but its last 4 characters, enough to tell the tokens apart.
+pub fn mask_token(token: &str) -> String {
+    let chars: Vec<char> = token.chars().collect();
+    match chars.len() {
+        0 => String::new(),
+        len if len <= 8 => String::from("****"),
+        len => format!("****{}", chars[len - 4..].iter().collect::<String>()),
+    }
+}
+
+/// Move the corrupted file at the given path aside, so it can be recovered by hand
+/// and a fresh one written in its place. The backup is suffixed with the time, so
+/// the earlier backups are kept. Returns the path of the backup.
+pub fn backup_corrupted(path: &Path) -> io::Result<PathBuf> {
+    let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ");
+    let backup = (0..)
+        .map(|n| {
+            let mut backup = path.as_os_str().to_owned();
+            match n {
+                0 => backup.push(format!(".{}.bak", stamp)),
+                n => backup.push(format!(".{}-{}.bak", stamp, n)),
+            }
+            PathBuf::from(backup)
+        })
+        .find(|backup| !backup.exists())
+        .expect("a free backup path");
+
+    fs::rename(path, &backup)?;
+    warn!("The file {:?} is corrupted, it was moved to {:?}", path, backup);
+
+    Ok(backup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("amp-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn atomic_write_replaces_content() {
+        let path = temp_dir("atomic-write").join("config.toml");
+        fs::write(&path, "old").unwrap();
+
+        atomic_write(&path, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+    }
+
+    #[test]
+    fn read_the_cli_settings_only() {
+        let path = temp_dir("cli-settings").join("config.toml");
+        fs::write(&path, "[context]\ncurrent = \"dev\"\n\n[output.columns]\nplaybooks = [\"id\", \"status\"]\n")
+            .unwrap();
+
+        let settings = cli_settings(&path).unwrap();
+        assert_eq!(settings.keys().collect::<Vec<_>>(), vec!["output"]);
+        assert_eq!(settings["output"]["columns"]["playbooks"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn mask_all_but_the_last_characters() {
+        assert_eq!(mask_token("7c0e2bb1d4a54a1f"), "****4a1f");
+        assert_eq!(mask_token("short"), "****");
+        assert_eq!(mask_token(""), "");
+    }
+
+    #[test]
+    fn keep_the_earlier_backups() {
+        let dir = temp_dir("backup-corrupted");
+        let path = dir.join("config.toml");
+
+        fs::write(&path, "first").unwrap();
+        let first = backup_corrupted(&path).unwrap();
+        fs::write(&path, "second").unwrap();
+        let second = backup_corrupted(&path).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(fs::read_to_string(&first).unwrap(), "first");
+        assert_eq!(fs::read_to_string(&second).unwrap(), "second");
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn interrupted_write_keeps_original() {
+        let dir = temp_dir("interrupted-write");
+        let path = dir.join("config.toml");
+        fs::write(&path, "old").unwrap();
+
+        let result = atomic_write_with(&path, b"new content", |file, bytes| {
+            file.write_all(&bytes[..3])?;
+            Err(io::Error::new(io::ErrorKind::Other, "simulated crash"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1, "the temporary file should be removed");
+    }
+}
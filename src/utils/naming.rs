@@ -0,0 +1,108 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::utils::git::Revision;
+
+/// The longest slug suffixed onto the resource names.
+const MAX_SLUG: usize = 20;
+
+/// The longest resource name, as for a DNS label.
+const MAX_NAME: usize = 63;
+
+/// The playbook title, `<character> (<branch>)`, falling back to the short SHA
+/// on a detached HEAD, and to the character alone outside of a repository.
+pub fn title(character: &str, revision: &Revision) -> String {
+    let character = if character.is_empty() { "Untitled" } else { character };
+    match revision.branch.as_ref().or(revision.sha.as_ref()) {
+        Some(reference) => format!("{} ({})", character, reference),
+        None => character.to_string(),
+    }
+}
+
+/// The branch as a lowercase DNS label fragment: runs of other characters
+/// than ASCII letters and digits become a single dash, and it is cut to
+/// [`MAX_SLUG`] characters. None if nothing is left.
+pub fn slug(branch: &str) -> Option<String> {
+    let mut slug = String::new();
+    for c in branch.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.truncate(MAX_SLUG);
+    let slug = slug.trim_end_matches('-');
+    (!slug.is_empty()).then(|| slug.to_string())
+}
+
+/// The resource name suffixed with the slug, cutting the name so the whole
+/// stays a valid DNS label.
+pub fn suffixed(name: &str, slug: &str) -> String {
+    let keep = MAX_NAME.saturating_sub(slug.len() + 1);
+    let name = name.get(..keep.min(name.len())).unwrap_or(name).trim_end_matches('-');
+    format!("{}-{}", name, slug)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn revision(branch: Option<&str>, sha: Option<&str>) -> Revision {
+        Revision { branch: branch.map(String::from), sha: sha.map(String::from), dirty: false }
+    }
+
+    #[test]
+    fn compose_titles() {
+        assert_eq!(title("web", &revision(Some("feature/login"), Some("1a2b3c4"))), "web (feature/login)");
+        assert_eq!(title("web", &revision(None, Some("1a2b3c4"))), "web (1a2b3c4)");
+        assert_eq!(title("web", &Revision::default()), "web");
+        assert_eq!(title("", &Revision::default()), "Untitled");
+    }
+
+    #[test]
+    fn slugify_branches() {
+        let cases = [
+            ("main", Some("main")),
+            ("feature/login", Some("feature-login")),
+            ("Feature/Login_Page", Some("feature-login-page")),
+            ("fix//double--dash", Some("fix-double-dash")),
+            ("-leading-and-trailing-", Some("leading-and-trailing")),
+            ("release/1.2.3", Some("release-1-2-3")),
+            ("users/jane/üñíçødé", Some("users-jane")),
+            ("a-very-long-branch-name-for-a-feature", Some("a-very-long-branch-n")),
+            ("feature/abcdefghijklm-xyz", Some("feature-abcdefghijkl")),
+            ("feature/abcdefghijk-xyz", Some("feature-abcdefghijk")),
+            ("///", None),
+            ("ñ", None),
+            ("", None),
+        ];
+        for (branch, expected) in cases {
+            assert_eq!(slug(branch).as_deref(), expected, "slug of {:?}", branch);
+        }
+    }
+
+    #[test]
+    fn suffix_names() {
+        assert_eq!(suffixed("web", "feature-login"), "web-feature-login");
+
+        let long = "a".repeat(60);
+        let name = suffixed(&long, "main");
+        assert_eq!(name.len(), MAX_NAME);
+        assert!(name.ends_with("a-main"));
+
+        let dashed = format!("{}-{}", "a".repeat(57), "bcdef");
+        assert_eq!(suffixed(&dashed, "main"), format!("{}-main", "a".repeat(57)));
+    }
+}
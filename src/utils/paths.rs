@@ -0,0 +1,187 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+use crate::errors::{Errors, Result};
+
+/// Whether the paths of the platform are compared regardless of case.
+const CASE_INSENSITIVE: bool = cfg!(any(windows, target_os = "macos"));
+
+/// The path without its Windows verbatim prefix: `\\?\C:\src` is `C:\src`,
+/// and `\\?\UNC\server\share` is `\\server\share`.
+pub fn simplify(path: &str) -> Cow<'_, str> {
+    if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        return Cow::Owned(format!(r"\\{}", rest));
+    }
+    match path.strip_prefix(r"\\?\") {
+        Some(rest) => Cow::Borrowed(rest),
+        None => Cow::Borrowed(path),
+    }
+}
+
+/// The absolute path with the symlinks resolved, and without the verbatim
+/// prefix when possible. A path that doesn't exist anymore, such as a removed
+/// file, is only simplified.
+pub fn canonical(path: &Path) -> PathBuf {
+    match dunce::canonicalize(path) {
+        Ok(path) => path,
+        Err(_) => PathBuf::from(simplify(&path.to_string_lossy()).as_ref()),
+    }
+}
+
+/// The key to compare the path with others: simplified, with `/` separators,
+/// without empty or `.` components, and lowercased if the platform ignores
+/// case. Never send it to the server, it is only for comparing.
+pub fn comparable(path: &str, case_insensitive: bool) -> String {
+    let components: Vec<&str> = components(path).collect();
+    let key = format!("{}{}", if is_rooted(path) { "/" } else { "" }, components.join("/"));
+    match case_insensitive {
+        true => key.to_lowercase(),
+        false => key,
+    }
+}
+
+/// The path relative to the base, keeping the case of the path. Both are
+/// compared component by component, regardless of the verbatim prefixes and
+/// separators, and of case on the platforms ignoring it.
+pub fn relative(base: &Path, path: &Path) -> Result<PathBuf> {
+    relative_with(base, path, CASE_INSENSITIVE)
+}
+
+fn relative_with(base: &Path, path: &Path, case_insensitive: bool) -> Result<PathBuf> {
+    let outside = || Errors::FailedStripPrefix(path.to_path_buf(), base.to_path_buf());
+    let (base_str, path_str) = (base.to_string_lossy(), path.to_string_lossy());
+    let (base_str, path_str) = (simplify(&base_str), simplify(&path_str));
+    if is_rooted(&base_str) != is_rooted(&path_str) {
+        return Err(outside());
+    }
+
+    let mut rest = components(&path_str);
+    for expected in components(&base_str) {
+        match rest.next() {
+            Some(actual) if same(expected, actual, case_insensitive) => {}
+            _ => return Err(outside()),
+        }
+    }
+    Ok(rest.collect())
+}
+
+/// The path as sent to the server, relative and with `/` separators. Paths
+/// that are not valid UTF-8 can't be sent.
+pub fn to_remote(path: &Path) -> Result<String> {
+    let path = path.to_str().ok_or_else(|| Errors::NonUtf8Path(path.to_path_buf()))?;
+    Ok(components(path).collect::<Vec<&str>>().join("/"))
+}
+
+/// Remove the paths equal to an earlier one, as compared on the platform.
+pub fn dedup(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    dedup_with(paths, CASE_INSENSITIVE)
+}
+
+fn dedup_with(paths: Vec<PathBuf>, case_insensitive: bool) -> Vec<PathBuf> {
+    let mut seen = std::collections::HashSet::new();
+    paths.into_iter().filter(|p| seen.insert(comparable(&p.to_string_lossy(), case_insensitive))).collect()
+}
+
+fn components(path: &str) -> impl Iterator<Item = &str> {
+    path.split(['/', '\\']).filter(|c| !c.is_empty() && *c != ".")
+}
+
+fn is_rooted(path: &str) -> bool {
+    path.starts_with(['/', '\\'])
+}
+
+fn same(a: &str, b: &str, case_insensitive: bool) -> bool {
+    match case_insensitive {
+        true => a.to_lowercase() == b.to_lowercase(),
+        false => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simplify_verbatim_prefixes() {
+        assert_eq!(simplify(r"\\?\C:\src\app"), r"C:\src\app");
+        assert_eq!(simplify(r"\\?\UNC\server\share\app"), r"\\server\share\app");
+        assert_eq!(simplify(r"\\server\share\app"), r"\\server\share\app");
+        assert_eq!(simplify("/home/dev/app"), "/home/dev/app");
+    }
+
+    #[test]
+    fn compare_paths() {
+        assert_eq!(comparable(r"\\?\C:\Src\App\", true), "c:/src/app");
+        assert_eq!(comparable(r"C:\Src\.\App", false), "C:/Src/App");
+        assert_eq!(comparable(r"\\?\UNC\Server\Share\x", true), "/server/share/x");
+        assert_eq!(comparable(r"\\server\share\x", true), "/server/share/x");
+        assert_eq!(comparable("/home//dev/./app/", false), "/home/dev/app");
+    }
+
+    #[test]
+    fn relative_to_verbatim_and_case_insensitive_bases() {
+        let base = Path::new(r"C:\Users\Dev\App");
+        let path = Path::new(r"\\?\c:\users\dev\app\Src\Main.rs");
+        assert_eq!(relative_with(base, path, true).unwrap(), ["Src", "Main.rs"].iter().collect::<PathBuf>());
+        assert!(relative_with(base, path, false).is_err());
+
+        let share = Path::new(r"\\?\UNC\server\share\app\src\lib.rs");
+        let relative = relative_with(Path::new(r"\\server\share\app"), share, false).unwrap();
+        assert_eq!(relative, ["src", "lib.rs"].iter().collect::<PathBuf>());
+    }
+
+    #[test]
+    fn reject_paths_outside_of_the_base() {
+        let base = Path::new(r"C:\app");
+        for path in [r"C:\application\x", r"D:\app\x", r"C:\", r"\\server\share\app"] {
+            let err = relative_with(base, Path::new(path), true).unwrap_err();
+            assert!(err.to_string().contains(path), "{}", err);
+        }
+        assert!(relative_with(Path::new("/app"), Path::new("app/x"), false).is_err());
+    }
+
+    #[test]
+    fn relative_unix_paths() {
+        let relative =
+            relative_with(Path::new("/home/dev/app"), Path::new("/home/dev/app/src/main.rs"), false).unwrap();
+        assert_eq!(relative, PathBuf::from("src/main.rs"));
+        assert!(relative_with(Path::new("/home/dev/app"), Path::new("/home/dev/App/x"), false).is_err());
+    }
+
+    #[test]
+    fn remote_paths_keep_their_case() {
+        assert_eq!(to_remote(&["Src", "Main.rs"].iter().collect::<PathBuf>()).unwrap(), "Src/Main.rs");
+        assert_eq!(to_remote(Path::new(r"Src\Main.rs")).unwrap(), "Src/Main.rs");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reject_non_utf8_paths() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"src/\xff.rs"));
+        assert!(matches!(to_remote(path), Err(Errors::NonUtf8Path(_))));
+    }
+
+    #[test]
+    fn dedup_case_insensitive_paths() {
+        let paths = vec![PathBuf::from(r"C:\App\a.rs"), PathBuf::from(r"c:\app\A.rs"), PathBuf::from(r"C:\App\b.rs")];
+        assert_eq!(dedup_with(paths.clone(), true), vec![paths[0].clone(), paths[2].clone()]);
+        assert_eq!(dedup_with(paths.clone(), false), paths);
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use toml::{Table, Value};
+
+use crate::errors::{Errors, Result};
+use crate::utils::{manifest_edit, Manifest};
+
+/// The key of the profiles table in the manifest.
+const PROFILES: &str = "profiles";
+
+/// The key of the automatic activation conditions in a profile.
+const ACTIVATION: &str = "activation";
+
+/// Merge the active profiles over the base manifest and return the effective
+/// content. The profiles auto-activated by their `activation` block come first,
+/// followed by the ones selected on the command line, later profiles win.
+/// A selection prefixed with `-` disables an auto-activated profile.
+pub fn apply<F>(manifest: &Manifest, selected: &[String], context: Option<&str>, lookup: F) -> Result<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if selected.is_empty() && !manifest.content.contains(PROFILES) {
+        return Ok(manifest.content.clone());
+    }
+
+    let origin = manifest.origin.to_string();
+    let mut base: Table = toml::from_str(&manifest.content).map_err(|e| Errors::FailedParseManifest(origin, e))?;
+    let profiles = match base.remove(PROFILES) {
+        Some(Value::Table(profiles)) => profiles,
+        _ => Table::new(),
+    };
+
+    for name in active(&profiles, selected, context, lookup)? {
+        if let Some(Value::Table(mut profile)) = profiles.get(&name).cloned() {
+            profile.remove(ACTIVATION);
+            merge(&mut base, profile);
+        }
+    }
+
+    manifest_edit::serialize(&base)
+}
+
+/// The names of the active profiles, in the order they are merged.
+fn active<F>(profiles: &Table, selected: &[String], context: Option<&str>, lookup: F) -> Result<Vec<String>>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    for name in selected.iter().map(|s| s.trim_start_matches('-')) {
+        if !profiles.contains_key(name) {
+            return Err(Errors::NotFoundProfile(name.to_string()));
+        }
+    }
+
+    let disabled: Vec<&str> = selected.iter().filter_map(|s| s.strip_prefix('-')).collect();
+    let mut names: Vec<String> = profiles
+        .iter()
+        .filter(|(name, _)| !disabled.contains(&name.as_str()))
+        .filter(|(_, profile)| activated(profile, context, &lookup))
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    for name in selected.iter().filter(|s| !s.starts_with('-')) {
+        names.retain(|n| n != name);
+        names.push(name.clone());
+    }
+
+    Ok(names)
+}
+
+/// Whether all the conditions of the profile's activation block are met.
+fn activated<F: Fn(&str) -> Option<String>>(profile: &Value, context: Option<&str>, lookup: &F) -> bool {
+    let Some(Value::Table(activation)) = profile.get(ACTIVATION) else {
+        return false;
+    };
+
+    activation.iter().all(|(key, value)| match (key.as_str(), value.as_str()) {
+        ("env", Some(var)) => lookup(var).is_some(),
+        ("context", Some(name)) => context == Some(name),
+        _ => false,
+    })
+}
+
+/// Merge the overlay into the base, tables are merged key by key while
+/// scalars and arrays are replaced as a whole.
+pub(super) fn merge(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Origin;
+
+    const CONTENT: &str = r#"
+[meta]
+name = "web"
+replicas = 1
+
+[deploy]
+env = { LOG = "info", PORT = "8080" }
+excludes = ["target", "node_modules"]
+
+[profiles.debug.deploy]
+env = { LOG = "debug" }
+excludes = ["target"]
+
+[profiles.ci]
+activation = { env = "CI" }
+meta = { replicas = 2 }
+
+[profiles.prod]
+activation = { context = "prod" }
+meta = { replicas = 3 }
+"#;
+
+    fn effective(selected: &[&str], context: Option<&str>, ci: bool) -> Table {
+        let manifest = Manifest { origin: Origin::Url("https://a/.amp.toml".into()), content: CONTENT.into() };
+        let selected: Vec<String> = selected.iter().map(|s| s.to_string()).collect();
+        let lookup = |key: &str| (ci && key == "CI").then(|| "true".to_string());
+        toml::from_str(&apply(&manifest, &selected, context, lookup).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn merge_tables_and_replace_arrays() {
+        let table = effective(&["debug"], None, false);
+        assert_eq!(table["deploy"]["env"]["LOG"].as_str(), Some("debug"));
+        assert_eq!(table["deploy"]["env"]["PORT"].as_str(), Some("8080"));
+        assert_eq!(table["deploy"]["excludes"].as_array().unwrap().len(), 1);
+        assert!(!table.contains_key("profiles"));
+    }
+
+    #[test]
+    fn later_profiles_win() {
+        assert_eq!(effective(&[], Some("prod"), true)["meta"]["replicas"].as_integer(), Some(3));
+        assert_eq!(effective(&["ci"], Some("prod"), true)["meta"]["replicas"].as_integer(), Some(2));
+        assert_eq!(effective(&["prod", "ci"], None, false)["meta"]["replicas"].as_integer(), Some(2));
+    }
+
+    #[test]
+    fn disable_auto_activated_profiles() {
+        assert_eq!(effective(&[], None, true)["meta"]["replicas"].as_integer(), Some(2));
+        assert_eq!(effective(&["-ci"], None, true)["meta"]["replicas"].as_integer(), Some(1));
+    }
+
+    #[test]
+    fn reject_unknown_profiles() {
+        let manifest = Manifest { origin: Origin::Url("https://a/.amp.toml".into()), content: CONTENT.into() };
+        let result = apply(&manifest, &["missing".into()], None, |_| None);
+        assert!(matches!(result, Err(Errors::NotFoundProfile(name)) if name == "missing"));
+    }
+}
@@ -0,0 +1,218 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Label selectors in the Kubernetes syntax, such as `app=backend,version!=v1`
+//! or `tier in (web, api),!canary`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::errors::{Errors, Result};
+
+/// How a requirement compares the value of its label.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Equals,
+    NotEquals,
+    In,
+    NotIn,
+    Exists,
+    DoesNotExist,
+}
+
+/// A requirement on a single label of the selector.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Requirement {
+    pub key: String,
+    pub operator: Operator,
+    pub values: Vec<String>,
+}
+
+impl Requirement {
+    fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        let value = labels.get(&self.key);
+        match self.operator {
+            Operator::Equals | Operator::In => value.is_some_and(|v| self.values.contains(v)),
+            Operator::NotEquals | Operator::NotIn => !value.is_some_and(|v| self.values.contains(v)),
+            Operator::Exists => value.is_some(),
+            Operator::DoesNotExist => value.is_none(),
+        }
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values = self.values.join(",");
+        match self.operator {
+            Operator::Equals => write!(f, "{}={}", self.key, values),
+            Operator::NotEquals => write!(f, "{}!={}", self.key, values),
+            Operator::In => write!(f, "{} in ({})", self.key, values),
+            Operator::NotIn => write!(f, "{} notin ({})", self.key, values),
+            Operator::Exists => write!(f, "{}", self.key),
+            Operator::DoesNotExist => write!(f, "!{}", self.key),
+        }
+    }
+}
+
+/// The requirements a resource must all meet to be selected.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Selector {
+    pub requirements: Vec<Requirement>,
+}
+
+impl Selector {
+    /// Whether the labels meet every requirement.
+    pub fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        self.requirements.iter().all(|r| r.matches(labels))
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let requirements: Vec<String> = self.requirements.iter().map(Requirement::to_string).collect();
+        write!(f, "{}", requirements.join(","))
+    }
+}
+
+impl FromStr for Selector {
+    type Err = Errors;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let requirements = split(s)?.into_iter().map(requirement).collect::<Result<Vec<_>>>()?;
+        Ok(Self { requirements })
+    }
+}
+
+/// Parse the selector of a command line argument.
+pub fn parse_selector(s: &str) -> std::result::Result<Selector, String> {
+    s.parse().map_err(|e: Errors| e.to_string())
+}
+
+/// Split the selector on the commas outside of the value sets.
+fn split(selector: &str) -> Result<Vec<&str>> {
+    let invalid = || Errors::InvalidSelector(selector.to_string());
+    let (mut parts, mut start, mut depth) = (vec![], 0, 0);
+    for (i, c) in selector.char_indices() {
+        match c {
+            '(' if depth == 0 => depth += 1,
+            ')' if depth == 1 => depth -= 1,
+            '(' | ')' => return Err(invalid()),
+            ',' if depth == 0 => {
+                parts.push(selector[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err(invalid());
+    }
+    parts.push(selector[start..].trim());
+    match parts.iter().any(|p| p.is_empty()) {
+        true => Err(invalid()),
+        false => Ok(parts),
+    }
+}
+
+fn requirement(text: &str) -> Result<Requirement> {
+    let invalid = || Errors::InvalidSelector(text.to_string());
+    let key = |key: &str| {
+        let key = key.trim();
+        let valid = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || "-_./".contains(c));
+        valid.then(|| key.to_string()).ok_or_else(invalid)
+    };
+    let value = |value: &str| {
+        let value = value.trim();
+        let valid = value.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c));
+        valid.then(|| value.to_string()).ok_or_else(invalid)
+    };
+    let requirement = |key: String, operator, values| Requirement { key, operator, values };
+
+    if let Some((name, set)) = text.strip_suffix(')').and_then(|t| t.split_once('(')) {
+        let (name, operator) = match name.trim_end().rsplit_once(char::is_whitespace) {
+            Some((name, "in")) => (name, Operator::In),
+            Some((name, "notin")) => (name, Operator::NotIn),
+            _ => return Err(invalid()),
+        };
+        let values = set.split(',').map(value).collect::<Result<Vec<_>>>()?;
+        if values.iter().any(String::is_empty) {
+            return Err(invalid());
+        }
+        return Ok(requirement(key(name)?, operator, values));
+    }
+    if let Some((name, v)) = text.split_once("!=") {
+        return Ok(requirement(key(name)?, Operator::NotEquals, vec![value(v)?]));
+    }
+    if let Some((name, v)) = text.split_once("==").or_else(|| text.split_once('=')) {
+        return Ok(requirement(key(name)?, Operator::Equals, vec![value(v)?]));
+    }
+    match text.strip_prefix('!') {
+        Some(name) => Ok(requirement(key(name)?, Operator::DoesNotExist, vec![])),
+        None => Ok(requirement(key(text)?, Operator::Exists, vec![])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn parse(selector: &str) -> Selector {
+        selector.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_equality_and_inequality() {
+        let selector = parse("app=backend, version!=v1,tier==web");
+        let operators: Vec<&Operator> = selector.requirements.iter().map(|r| &r.operator).collect();
+        assert_eq!(operators, vec![&Operator::Equals, &Operator::NotEquals, &Operator::Equals]);
+        assert_eq!(selector.to_string(), "app=backend,version!=v1,tier=web");
+
+        assert!(selector.matches(&labels(&[("app", "backend"), ("version", "v2"), ("tier", "web")])));
+        assert!(selector.matches(&labels(&[("app", "backend"), ("tier", "web")])), "a missing label is not v1");
+        assert!(!selector.matches(&labels(&[("app", "backend"), ("version", "v1"), ("tier", "web")])));
+        assert!(!selector.matches(&labels(&[("app", "frontend"), ("tier", "web")])));
+    }
+
+    #[test]
+    fn parse_set_membership() {
+        let selector = parse("tier in (web, api),env notin (prod),!canary,team");
+        assert_eq!(
+            selector.requirements[0],
+            Requirement { key: "tier".into(), operator: Operator::In, values: vec!["web".into(), "api".into()] }
+        );
+        assert_eq!(selector.requirements[1].operator, Operator::NotIn);
+        assert_eq!(selector.requirements[2].operator, Operator::DoesNotExist);
+        assert_eq!(selector.requirements[3].operator, Operator::Exists);
+        assert_eq!(selector.to_string(), "tier in (web,api),env notin (prod),!canary,team");
+
+        assert!(selector.matches(&labels(&[("tier", "api"), ("env", "dev"), ("team", "shop")])));
+        assert!(!selector.matches(&labels(&[("tier", "db"), ("team", "shop")])));
+        assert!(!selector.matches(&labels(&[("tier", "web"), ("env", "prod"), ("team", "shop")])));
+        assert!(!selector.matches(&labels(&[("tier", "web"), ("canary", "true"), ("team", "shop")])));
+        assert!(!selector.matches(&labels(&[("tier", "web")])));
+    }
+
+    #[test]
+    fn reject_invalid_selectors() {
+        for invalid in ["", "app=backend,", "tier in (web", "tier in web)", "tier within (web)", "a b=c", "app=x y"] {
+            let error = invalid.parse::<Selector>().unwrap_err();
+            assert!(matches!(error, Errors::InvalidSelector(_)), "{}: {}", invalid, error);
+        }
+    }
+}
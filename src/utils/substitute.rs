@@ -0,0 +1,141 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use crate::errors::{Errors, Result};
+
+/// The values used to expand the `${VAR}` and `${VAR:-default}` placeholders
+/// of a manifest, `--set` values take precedence over the environment.
+#[derive(Clone, Debug, Default)]
+pub struct Variables {
+    values: HashMap<String, String>,
+    disabled: bool,
+}
+
+impl Variables {
+    pub fn new(values: Vec<(String, String)>, disabled: bool) -> Self {
+        Self { values: values.into_iter().collect(), disabled }
+    }
+
+    /// Expand the placeholders of the given content, the undefined variables
+    /// without a default are reported all at once.
+    pub fn substitute(&self, content: &str) -> Result<String> {
+        if self.disabled {
+            return Ok(content.to_string());
+        }
+
+        expand(content, |key| self.values.get(key).cloned().or_else(|| std::env::var(key).ok()))
+            .map_err(Errors::UndefinedVariables)
+    }
+}
+
+/// Parse a `KEY=VALUE` pair from the command line.
+pub fn parse_key_value(s: &str) -> std::result::Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("invalid KEY=VALUE: no `=` found in `{}`", s)),
+    }
+}
+
+/// Expand the placeholders using the given lookup, `$$` yields a literal dollar.
+fn expand<F: Fn(&str) -> Option<String>>(content: &str, lookup: F) -> std::result::Result<String, Vec<String>> {
+    let mut output = String::with_capacity(content.len());
+    let mut missing: Vec<String> = vec![];
+    let mut rest = content;
+
+    while let Some(pos) = rest.find('$') {
+        output.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+
+        if let Some(after) = rest.strip_prefix("$$") {
+            output.push('$');
+            rest = after;
+            continue;
+        }
+
+        let placeholder = rest.strip_prefix("${").and_then(|body| body.find('}').map(|end| &body[..end]));
+        let Some(placeholder) = placeholder else {
+            output.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+
+        let (key, default) = match placeholder.split_once(":-") {
+            Some((key, default)) => (key, Some(default)),
+            None => (placeholder, None),
+        };
+        match lookup(key).or_else(|| default.map(String::from)) {
+            Some(value) => output.push_str(&value),
+            None if !missing.iter().any(|m| m == key) => missing.push(key.to_string()),
+            None => {}
+        }
+        rest = &rest[placeholder.len() + 3..];
+    }
+    output.push_str(rest);
+
+    if !missing.is_empty() {
+        return Err(missing);
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(key: &str) -> Option<String> {
+        match key {
+            "REGISTRY" => Some("ghcr.io/amp".into()),
+            "EMPTY" => Some("".into()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expand_variables_and_defaults() {
+        let content = r#"image = "${REGISTRY}/web:${TAG:-latest}""#;
+        assert_eq!(expand(content, lookup).unwrap(), r#"image = "ghcr.io/amp/web:latest""#);
+        assert_eq!(expand("x=${EMPTY:-fallback}", lookup).unwrap(), "x=");
+    }
+
+    #[test]
+    fn escape_dollars() {
+        assert_eq!(expand("price = \"$$5\" and $$${REGISTRY}", lookup).unwrap(), "price = \"$5\" and $ghcr.io/amp");
+        assert_eq!(expand("cost $5 and ${unterminated", lookup).unwrap(), "cost $5 and ${unterminated");
+    }
+
+    #[test]
+    fn report_all_missing_variables() {
+        let err = expand("${A} ${B:-b} ${C} ${A}", lookup).unwrap_err();
+        assert_eq!(err, vec!["A", "C"]);
+    }
+
+    #[test]
+    fn overrides_take_precedence() {
+        let variables = Variables::new(vec![("AMP_TEST_SUBSTITUTE".into(), "set".into())], false);
+        assert_eq!(variables.substitute("${AMP_TEST_SUBSTITUTE}").unwrap(), "set");
+
+        let variables = Variables::new(vec![], true);
+        assert_eq!(variables.substitute("${AMP_TEST_UNDEFINED}").unwrap(), "${AMP_TEST_UNDEFINED}");
+    }
+
+    #[test]
+    fn parse_key_values() {
+        assert_eq!(parse_key_value("TAG=v1=2"), Ok(("TAG".into(), "v1=2".into())));
+        assert!(parse_key_value("TAG").is_err());
+        assert!(parse_key_value("=v").is_err());
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, TimeDelta, Utc};
+use tracing::warn;
+
+use crate::errors::{Errors, Result};
+
+/// Parse a time flag, either an RFC 3339 timestamp (`2024-01-15T10:30:00Z`),
+/// a duration ago (`5m`, `2h`, `1d`) or `now`.
+pub fn parse_time_flag(s: &str) -> Result<DateTime<Utc>> {
+    parse_time_flag_at(s, Utc::now())
+}
+
+fn parse_time_flag_at(s: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("now") {
+        return Ok(now);
+    }
+
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        let time = time.with_timezone(&Utc);
+        if time > now {
+            warn!("{} is in the future, nothing may be shown until then", s);
+        }
+        return Ok(time);
+    }
+
+    if s.starts_with('-') {
+        return Err(Errors::InvalidTimestamp(format!("{}, the duration must be positive", s)));
+    }
+    let duration = humantime::parse_duration(s).map_err(|e| Errors::InvalidTimestamp(format!("{}, {}", s, e)))?;
+    TimeDelta::from_std(duration)
+        .ok()
+        .and_then(|delta| now.checked_sub_signed(delta))
+        .ok_or_else(|| Errors::InvalidTimestamp(format!("{}, the duration is too long", s)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-01-15T12:00:00Z").unwrap().with_timezone(&Utc)
+    }
+
+    fn parse(s: &str) -> Result<String> {
+        parse_time_flag_at(s, now()).map(|t| t.to_rfc3339())
+    }
+
+    #[test]
+    fn parse_rfc3339_timestamps() {
+        assert_eq!(parse("2024-01-15T10:30:00Z").unwrap(), "2024-01-15T10:30:00+00:00");
+        assert_eq!(parse("2024-01-15T11:30:00+01:00").unwrap(), "2024-01-15T10:30:00+00:00");
+    }
+
+    #[test]
+    fn parse_relative_durations() {
+        assert_eq!(parse("5m").unwrap(), "2024-01-15T11:55:00+00:00");
+        assert_eq!(parse("2h").unwrap(), "2024-01-15T10:00:00+00:00");
+        assert_eq!(parse("1d").unwrap(), "2024-01-14T12:00:00+00:00");
+        assert_eq!(parse("1h 30m").unwrap(), "2024-01-15T10:30:00+00:00");
+    }
+
+    #[test]
+    fn parse_now() {
+        assert_eq!(parse("now").unwrap(), parse("NOW").unwrap());
+        assert_eq!(parse("now").unwrap(), now().to_rfc3339());
+    }
+
+    #[test]
+    fn accept_future_timestamps() {
+        assert_eq!(parse("2024-01-16T00:00:00Z").unwrap(), "2024-01-16T00:00:00+00:00");
+    }
+
+    #[test]
+    fn reject_invalid_times() {
+        for s in ["-5m", "5", "yesterday", "2024-01-15", ""] {
+            assert!(matches!(parse(s), Err(Errors::InvalidTimestamp(_))), "{:?} should be rejected", s);
+        }
+    }
+}
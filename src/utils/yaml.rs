@@ -0,0 +1,114 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::errors::{Errors, Result};
+use crate::utils::manifest_edit;
+
+/// The extensions of the manifests written in YAML.
+const EXTENSIONS: [&str; 2] = [".yaml", ".yml"];
+
+/// Whether the location names a YAML manifest.
+pub fn is_yaml(location: &str) -> bool {
+    EXTENSIONS.iter().any(|ext| location.ends_with(ext))
+}
+
+/// Convert the YAML manifest to TOML, the format every command and the server
+/// work with, so both formats produce the same playbooks.
+pub fn to_toml(origin: &str, content: &str) -> Result<String> {
+    let value: toml::Value = serde_yaml::from_str(content)
+        .map_err(|e| Errors::FailedParseYamlManifest(origin.to_string(), render(content, &e)))?;
+    manifest_edit::serialize(&value)
+}
+
+/// Render the error with the offending line, the way TOML parse errors are.
+fn render(content: &str, err: &serde_yaml::Error) -> String {
+    let message = err.to_string();
+    let Some(location) = err.location() else {
+        return message;
+    };
+
+    let message = message.split(" at line ").next().unwrap_or_default();
+    let line = content.lines().nth(location.line().saturating_sub(1)).unwrap_or_default();
+    let number = location.line().to_string();
+    let gutter = " ".repeat(number.len());
+    format!(
+        "YAML parse error at line {}, column {}\n{} |\n{} | {}\n{} | {}^\n{}",
+        location.line(),
+        location.column(),
+        gutter,
+        number,
+        line,
+        gutter,
+        " ".repeat(location.column().saturating_sub(1)),
+        message
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use amp_common::resource::CharacterSpec;
+    use amp_common::schema::Character;
+
+    use super::*;
+
+    const TOML: &str = r#"
+[meta]
+name = "web"
+version = "0.1.0"
+
+[deploy]
+env = { LOG = "info" }
+"#;
+
+    const YAML: &str = "
+meta:
+  name: web
+  version: 0.1.0
+deploy:
+  env:
+    LOG: info
+";
+
+    fn spec(content: &str) -> serde_json::Value {
+        let character: Character = toml::from_str(content).unwrap();
+        serde_json::to_value(CharacterSpec::from(&character)).unwrap()
+    }
+
+    #[test]
+    fn detect_yaml_locations() {
+        assert!(is_yaml("/app/.amp.yaml"));
+        assert!(is_yaml("https://github.com/org/web#main:.amp.yml"));
+        assert!(!is_yaml("/app/.amp.toml"));
+    }
+
+    #[test]
+    fn yaml_and_toml_produce_the_same_payload() {
+        let converted = to_toml(".amp.yaml", YAML).unwrap();
+        assert_eq!(spec(&converted), spec(TOML));
+    }
+
+    #[test]
+    fn render_error_location() {
+        let content = "meta:\n  name: web\n  version: [0.1\n";
+        let err = to_toml(".amp.yaml", content).unwrap_err();
+
+        let Errors::FailedParseYamlManifest(origin, rendered) = err else {
+            panic!("unexpected error: {}", err);
+        };
+        assert_eq!(origin, ".amp.yaml");
+        assert!(rendered.starts_with("YAML parse error at line "), "{}", rendered);
+        assert!(rendered.contains("  |\n"), "{}", rendered);
+        assert!(rendered.lines().any(|l| l.trim_end().ends_with('^')), "{}", rendered);
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+use std::process::Output;
+
+use tokio::process::Command;
+use wiremock::MockServer;
+
+/// Run the amp binary against the mock server, isolated from the user's configuration.
+pub async fn amp(server: &MockServer, args: &[&str]) -> Output {
+    let home = std::env::temp_dir().join(format!("amp-test-{}", std::process::id()));
+    std::fs::create_dir_all(&home).unwrap();
+
+    Command::new(PathBuf::from(env!("CARGO_BIN_EXE_amp")))
+        .args(args)
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("AMP_SERVER", server.uri())
+        .env("AMP_TOKEN", "7c0e2bb1d4a54a1f")
+        .env("AMP_CONTEXT_TITLE", "Test")
+        .output()
+        .await
+        .unwrap()
+}
@@ -0,0 +1,125 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use std::process::Output;
+
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Run the amp binary with `--dry-run` against the mock server, see [`common::amp`].
+async fn amp(server: &MockServer, args: &[&str]) -> Output {
+    common::amp(server, &[&["--dry-run"][..], args].concat()).await
+}
+
+/// A server failing the test on any mutating call it receives.
+async fn server() -> MockServer {
+    let server = MockServer::start().await;
+    for verb in ["POST", "PUT", "PATCH", "DELETE"] {
+        Mock::given(method(verb)).respond_with(ResponseTemplate::new(204)).expect(0).mount(&server).await;
+    }
+    server
+}
+
+/// The mutating commands runnable without a workspace, and the first call each of them plans.
+const MUTATIONS: &[(&[&str], &str)] = &[
+    (&["playbook", "start", "42"], "POST /playbooks/42/actions/start"),
+    (&["playbook", "stop", "42"], "POST /playbooks/42/actions/stop"),
+    (&["playbook", "rename", "42", "Storefront", "--confirm"], "PATCH /playbooks/42"),
+    (&["clean", "42"], "DELETE /playbooks/42"),
+    (&["actor", "restart", "web", "--pid", "42"], "POST /playbooks/42/actors/web/actions/restart"),
+    (&["actor", "exec", "web", "--pid", "42", "--", "ls"], "POST /playbooks/42/actors/web/exec"),
+];
+
+#[tokio::test]
+async fn plan_every_mutating_command() {
+    let server = server().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/playbooks/42"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"id": "42", "title": "web", "description": ""})))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/playbooks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .mount(&server)
+        .await;
+
+    for (args, planned) in MUTATIONS {
+        let output = amp(&server, args).await;
+        assert!(output.status.success(), "{:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(stdout.lines().next(), Some(*planned), "{:?}", args);
+    }
+}
+
+#[tokio::test]
+async fn look_up_before_planning() {
+    let server = server().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/actors/42"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{"name": "web"}, {"name": "api"}])))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = amp(&server, &["actor", "restart", "--all", "--pid", "42"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut planned: Vec<&str> = stdout.lines().collect();
+    planned.sort();
+    assert_eq!(
+        planned,
+        vec!["POST /playbooks/42/actors/api/actions/restart", "POST /playbooks/42/actors/web/actions/restart"]
+    );
+}
+
+#[tokio::test]
+async fn plan_a_bulk_stop() {
+    let server = server().await;
+    let playbooks =
+        json!([{"id": "1", "title": "web", "description": ""}, {"id": "2", "title": "api", "description": ""}]);
+    Mock::given(method("GET"))
+        .and(path("/v1/playbooks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(playbooks))
+        .mount(&server)
+        .await;
+
+    let output = amp(&server, &["playbook", "stop", "--all"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut planned: Vec<&str> = stdout.lines().collect();
+    planned.sort();
+    assert_eq!(planned, vec!["POST /playbooks/1/actions/stop", "POST /playbooks/2/actions/stop"]);
+}
+
+#[tokio::test]
+async fn plan_every_deletion() {
+    let server = server().await;
+    let playbooks =
+        json!([{"id": "1", "title": "web", "description": ""}, {"id": "2", "title": "api", "description": ""}]);
+    Mock::given(method("GET"))
+        .and(path("/v1/playbooks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(playbooks))
+        .mount(&server)
+        .await;
+
+    let output = amp(&server, &["clean", "--all"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "DELETE /playbooks/1\nDELETE /playbooks/2\n");
+}
@@ -0,0 +1,3 @@
+module github.com/example/api/v2
+
+go 1.23
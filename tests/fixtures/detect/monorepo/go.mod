@@ -0,0 +1,5 @@
+module github.com/example/monorepo
+
+go 1.23
+
+require github.com/gin-gonic/gin v1.10.0
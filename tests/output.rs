@@ -0,0 +1,297 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::amp;
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/playbooks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+async fn list_json_is_pipeable() {
+    let server = server().await;
+    let output = amp(&server, &["list", "-o", "json"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout: Value = serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(stdout, json!([]));
+
+    let output = amp(&server, &["list", "-o", "json", "-v", "--debug-http"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let stdout: Value = serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(stdout["items"], json!([]), "the list is wrapped along with the timings");
+    assert_eq!(stdout["timings"]["calls"], 1);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("[http] GET /playbooks"));
+    assert!(stderr.contains("API: 1 calls"));
+}
+
+#[tokio::test]
+async fn list_csv_is_pipeable() {
+    let server = server().await;
+    let output = amp(&server, &["list", "-o", "csv", "--debug-http"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "id,title,description\n");
+}
+
+#[tokio::test]
+async fn empty_table_message_goes_to_stderr() {
+    let server = server().await;
+    let output = amp(&server, &["list"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No playbooks found"));
+}
+
+async fn server_with_playbooks() -> MockServer {
+    let server = MockServer::start().await;
+    let playbooks = json!([
+        {"id": "1", "title": "web", "description": "The storefront"},
+        {"id": "2", "title": "api-gateway-with-a-rather-long-title-for-narrow-terminals", "description": ""}
+    ]);
+    Mock::given(method("GET"))
+        .and(path("/v1/playbooks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(playbooks))
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+async fn list_names_one_per_line() {
+    let server = server_with_playbooks().await;
+    let output = amp(&server, &["list", "-o", "name"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n2\n");
+}
+
+#[tokio::test]
+async fn list_csv_without_headers() {
+    let server = server_with_playbooks().await;
+    let output = amp(&server, &["list", "-o", "csv", "--no-headers"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "1,web,The storefront\n2,api-gateway-with-a-rather-long-title-for-narrow-terminals,\n"
+    );
+}
+
+#[tokio::test]
+async fn redirected_tables_are_not_truncated() {
+    let server = server_with_playbooks().await;
+    let output = amp(&server, &["list", "--no-headers"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", stdout);
+    assert!(lines[0].trim_start().starts_with("1 "), "{}", stdout);
+    assert!(lines[1].contains("api-gateway-with-a-rather-long-title-for-narrow-terminals"), "{}", stdout);
+    assert!(!stdout.contains('…') && !stdout.contains("title"), "{}", stdout);
+
+    let output = amp(&server, &["list", "--no-headers", "--wide"]).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains('…') && !stdout.contains("owner"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn list_count_is_a_bare_integer() {
+    let server = server_with_playbooks().await;
+    let output = amp(&server, &["list", "-o", "count"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let count = stdout.strip_suffix('\n').unwrap();
+    assert_eq!(count.trim(), count);
+    assert_eq!(count.parse::<usize>().unwrap(), 2);
+}
+
+#[tokio::test]
+async fn list_count_trusts_the_total_count() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/playbooks"))
+        .respond_with(ResponseTemplate::new(200).insert_header("X-Total-Count", "42").set_body_json(json!([])))
+        .mount(&server)
+        .await;
+    let output = amp(&server, &["list", "-o", "count"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n");
+}
+
+#[tokio::test]
+async fn list_me_filters_on_the_current_user() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/me"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"email": "jane@example.com"})))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/playbooks"))
+        .and(query_param("created_by", "jane@example.com"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{"id": "1", "title": "web", "description": ""}])))
+        .mount(&server)
+        .await;
+
+    let output = amp(&server, &["list", "--me", "-o", "name"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+}
+
+#[tokio::test]
+async fn list_none_only_sets_the_exit_code() {
+    let output = amp(&server_with_playbooks().await, &["list", "-o", "none"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.stdout.is_empty());
+
+    let output = amp(&server().await, &["list", "-o", "none", "--debug-http"]).await;
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("API: 1 calls"), "the summary is still printed");
+
+    let output = amp(&server().await, &["list", "-o", "none", "--wide"]).await;
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[tokio::test]
+async fn list_count_follows_the_pages_when_searching() {
+    let server = MockServer::start().await;
+    let next = format!("<{}/v1/playbooks?q=web&page=2>; rel=\"next\"", server.uri());
+    Mock::given(method("GET"))
+        .and(path("/v1/playbooks"))
+        .and(query_param("page", "2"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!([{"id": "3", "title": "web-admin", "description": ""}])),
+        )
+        .mount(&server)
+        .await;
+    let first = json!([{"id": "1", "title": "web", "description": ""}, {"id": "2", "title": "api", "description": ""}]);
+    Mock::given(method("GET"))
+        .and(path("/v1/playbooks"))
+        .respond_with(ResponseTemplate::new(200).insert_header("Link", next.as_str()).set_body_json(first))
+        .mount(&server)
+        .await;
+
+    let output = amp(&server, &["playbook", "list", "-o", "count", "--search", "web"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "2\n");
+}
+
+#[tokio::test]
+async fn context_token_is_masked_unless_asked() {
+    let server = server().await;
+    let output = amp(&server, &["context", "show", "-o", "json"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let context: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(context["token"], "****4a1f");
+
+    let output = amp(&server, &["context", "show", "-o", "json", "--unmask-token"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let context: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(context["token"], "7c0e2bb1d4a54a1f");
+}
+
+#[tokio::test]
+async fn list_ids_and_titles_only() {
+    let server = server_with_playbooks().await;
+    for (format, expected) in [
+        ("id-only", vec!["1", "2"]),
+        ("name-only", vec!["1", "2"]),
+        ("title-only", vec!["web", "api-gateway-with-a-rather-long-title-for-narrow-terminals"]),
+    ] {
+        let output = amp(&server, &["list", "--output", format]).await;
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.ends_with('\n'), "{}: {:?}", format, stdout);
+        assert!(stdout.lines().all(|line| line == line.trim() && !line.is_empty()), "{}: {:?}", format, stdout);
+        assert_eq!(stdout.lines().collect::<Vec<_>>(), expected, "{}", format);
+    }
+}
+
+#[tokio::test]
+async fn list_sparse_fields_of_full_playbooks() {
+    let server = MockServer::start().await;
+    let playbooks = json!([{"id": "1", "title": "web", "description": "The storefront", "characters": []}]);
+    Mock::given(method("GET"))
+        .and(path("/v1/playbooks"))
+        .and(query_param("fields", "id,title"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(playbooks))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let output = amp(&server, &["list", "--fields", "id,title", "-o", "json"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(stdout, json!([{"id": "1", "title": "web"}]));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("ignored the sparse fieldset"));
+
+    let output = amp(&server, &["list", "--fields", "id,owner_name"]).await;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid field owner_name"));
+}
+
+#[tokio::test]
+async fn import_dry_run_validates_and_plans() {
+    let server = server().await;
+    let dir = std::env::temp_dir().join(format!("amp-import-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let preface = amp_common::resource::Preface::registry("web", "hub", "latest");
+    let write = |name: &str, exported: Value| {
+        let file = dir.join(name);
+        std::fs::write(&file, exported.to_string()).unwrap();
+        file.display().to_string()
+    };
+
+    let valid = write("valid.json", json!({"id": "1", "title": "web", "preface": preface}));
+    let output = amp(&server, &["playbook", "import", &valid, "--dry-run"]).await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.lines().any(|line| line == "title = \"web\""), "{}", stdout);
+    assert!(stdout.lines().any(|line| line == "POST /playbooks"), "{}", stdout);
+    let requests = server.received_requests().await.unwrap();
+    assert!(requests.iter().all(|r| r.method.as_str() == "GET"), "the dry run mutates through the API");
+
+    let untitled = write("untitled.json", json!({"id": "1", "title": "", "preface": preface}));
+    let output = amp(&server, &["playbook", "import", &untitled, "--dry-run"]).await;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("the title is empty"));
+
+    let unreadable = write("unreadable.json", json!({"id": "1", "title": "web"}));
+    let output = amp(&server, &["playbook", "import", &unreadable, "--dry-run"]).await;
+    assert!(!output.status.success());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn init_rejects_the_root_path() {
+    let server = MockServer::start().await;
+    let output = amp(&server, &["init", "--filename", "/"]).await;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("has no parent directory"));
+}